@@ -1,10 +1,20 @@
 use super::unwind_rule::UnwindRuleAarch64;
 use super::unwindregs::UnwindRegsAarch64;
+use crate::any_unwinder::CpuArch;
 use crate::arch::Arch;
+use crate::exidx::ExidxUnwinding;
+use crate::orc::OrcUnwinding;
 
 /// The Aarch64 CPU architecture.
 pub struct ArchAarch64;
 impl Arch for ArchAarch64 {
+    const CPU_ARCH: CpuArch = CpuArch::Aarch64;
     type UnwindRule = UnwindRuleAarch64;
     type UnwindRegs = UnwindRegsAarch64;
 }
+
+// .ARM.exidx is only used on 32-bit ARM.
+impl ExidxUnwinding for ArchAarch64 {}
+
+// ORC is only used by the x86_64 Linux kernel.
+impl OrcUnwinding for ArchAarch64 {}
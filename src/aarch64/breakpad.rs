@@ -0,0 +1,119 @@
+use super::{arch::ArchAarch64, unwind_rule::UnwindRuleAarch64, unwindregs::UnwindRegsAarch64};
+use crate::breakpad::{
+    BreakpadRecord, BreakpadUnwinderError, BreakpadUnwinding, CfiRegisterNames, SimpleCfaBase,
+    SimpleCfiRow, Variables,
+};
+use crate::unwind_result::UnwindResult;
+
+const REGISTER_NAMES: CfiRegisterNames = CfiRegisterNames {
+    sp: "sp",
+    fp: "x29",
+    ra: Some("x30"),
+};
+
+impl BreakpadUnwinding for ArchAarch64 {
+    fn unwind_frame<F>(
+        record: BreakpadRecord,
+        regs: &mut UnwindRegsAarch64,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleAarch64>, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let BreakpadRecord::Cfi(rules) = record else {
+            return Err(BreakpadUnwinderError::UnsupportedWinRecord);
+        };
+
+        if let Some(unwind_rule) = rules
+            .simple_row(&REGISTER_NAMES)
+            .and_then(|row| translate_into_unwind_rule(&row))
+        {
+            return Ok(UnwindResult::ExecRule(unwind_rule));
+        }
+
+        let lr = regs.lr();
+        let sp = regs.sp();
+        let fp = regs.fp();
+        let mut variables = Variables::new();
+        variables.set("x30", lr)?;
+        variables.set("sp", sp)?;
+        variables.set("x29", fp)?;
+
+        let cfa = rules.evaluate_cfa(&mut variables, read_stack)?;
+        let new_sp = rules
+            .evaluate_register("sp", &mut variables, read_stack)?
+            .unwrap_or(cfa);
+        if !is_first_frame && new_sp <= sp {
+            return Err(BreakpadUnwinderError::StackPointerMovedBackwards);
+        }
+        let new_lr = match rules.evaluate_register(".ra", &mut variables, read_stack)? {
+            Some(ra) => ra,
+            None => rules
+                .evaluate_register("x30", &mut variables, read_stack)?
+                .unwrap_or(lr),
+        };
+        if !is_first_frame && new_lr == lr && new_sp == sp {
+            return Err(BreakpadUnwinderError::DidNotAdvance);
+        }
+        let new_fp = rules
+            .evaluate_register("x29", &mut variables, read_stack)?
+            .unwrap_or(fp);
+        let new_lr = regs.lr_mask().strip_ptr_auth(new_lr);
+
+        regs.set_fp(new_fp);
+        regs.set_sp(new_sp);
+        regs.set_lr(new_lr);
+
+        Ok(UnwindResult::Uncacheable(new_lr))
+    }
+}
+
+fn translate_into_unwind_rule(row: &SimpleCfiRow) -> Option<UnwindRuleAarch64> {
+    let by_8 = |offset: i64| {
+        if offset % 8 != 0 {
+            return None;
+        }
+        i16::try_from(offset / 8).ok()
+    };
+    match row.cfa_base {
+        SimpleCfaBase::Sp => {
+            if row.cfa_offset % 16 != 0 {
+                return None;
+            }
+            let sp_offset_by_16 = u16::try_from(row.cfa_offset / 16).ok()?;
+            match (row.ra_offset, row.fp_offset) {
+                (None, None) if sp_offset_by_16 == 0 => Some(UnwindRuleAarch64::NoOp),
+                (None, None) => Some(UnwindRuleAarch64::OffsetSp { sp_offset_by_16 }),
+                (None, Some(_)) => None,
+                (Some(lr_cfa_offset), None) => Some(UnwindRuleAarch64::OffsetSpAndRestoreLr {
+                    sp_offset_by_16,
+                    lr_storage_offset_from_sp_by_8: by_8(
+                        row.cfa_offset.checked_add(lr_cfa_offset)?,
+                    )?,
+                }),
+                (Some(lr_cfa_offset), Some(fp_cfa_offset)) => {
+                    UnwindRuleAarch64::offset_sp_and_restore_fp_and_lr(
+                        sp_offset_by_16,
+                        by_8(row.cfa_offset.checked_add(fp_cfa_offset)?)?,
+                        by_8(row.cfa_offset.checked_add(lr_cfa_offset)?)?,
+                    )
+                }
+            }
+        }
+        SimpleCfaBase::Fp => {
+            let lr_cfa_offset = row.ra_offset?;
+            let fp_cfa_offset = row.fp_offset?;
+            if row.cfa_offset == 16 && fp_cfa_offset == -16 && lr_cfa_offset == -8 {
+                Some(UnwindRuleAarch64::UseFramePointer)
+            } else {
+                let sp_offset_from_fp_by_8 = u16::try_from(by_8(row.cfa_offset)?).ok()?;
+                UnwindRuleAarch64::use_frame_pointer_with_offsets(
+                    sp_offset_from_fp_by_8,
+                    by_8(row.cfa_offset.checked_add(fp_cfa_offset)?)?,
+                    by_8(row.cfa_offset.checked_add(lr_cfa_offset)?)?,
+                )
+            }
+        }
+    }
+}
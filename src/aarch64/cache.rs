@@ -11,6 +11,16 @@ impl CacheAarch64<MayAllocateDuringUnwind> {
     pub fn new() -> Self {
         Self(Cache::new())
     }
+
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared(shared: &SharedCacheAarch64) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
 }
 
 impl<P: AllocationPolicy> CacheAarch64<P> {
@@ -19,6 +29,16 @@ impl<P: AllocationPolicy> CacheAarch64<P> {
         Self(Cache::new())
     }
 
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config_in(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared_in(shared: &SharedCacheAarch64) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
+
     /// Returns a snapshot of the cache usage statistics.
     pub fn stats(&self) -> CacheStats {
         self.0.rule_cache.stats()
@@ -30,3 +50,31 @@ impl<P: AllocationPolicy> Default for CacheAarch64<P> {
         Self::new_in()
     }
 }
+
+/// Unwind rule storage for [`UnwinderAarch64`](super::UnwinderAarch64) which can be shared by the
+/// caches of multiple threads, so that an unwind rule which was found on one thread is a
+/// cache hit on all other threads. Create the per-thread caches with
+/// [`CacheAarch64::new_shared`] or [`CacheAarch64::new_shared_in`].
+///
+/// Lookups and insertions are lock-free and don't allocate, so the sharing caches can be
+/// used from signal handlers. Cloning gives you another handle to the same storage.
+#[derive(Clone, Default)]
+pub struct SharedCacheAarch64(pub SharedCache<UnwindRuleAarch64>);
+
+impl SharedCacheAarch64 {
+    /// Create a new shared cache.
+    pub fn new() -> Self {
+        Self(SharedCache::new())
+    }
+
+    /// Create a new shared cache with the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(SharedCache::with_config(config))
+    }
+
+    /// Returns a snapshot of the cache usage statistics, summed up over all caches
+    /// which share these rules.
+    pub fn stats(&self) -> CacheStats {
+        self.0.stats()
+    }
+}
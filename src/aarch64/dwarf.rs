@@ -24,6 +24,19 @@ impl DwarfUnwindRegs for UnwindRegsAarch64 {
 }
 
 impl DwarfUnwinding for ArchAarch64 {
+    fn rule_for_row<O, UCS>(
+        unwind_info: &UnwindTableRow<O, UCS>,
+    ) -> Result<Self::UnwindRule, ConversionError>
+    where
+        O: ReaderOffset,
+        UCS: UnwindContextStorage<O>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let fp_rule = unwind_info.register(AArch64::X29);
+        let lr_rule = unwind_info.register(AArch64::X30);
+        translate_into_unwind_rule(cfa_rule, &fp_rule, &lr_rule)
+    }
+
     fn unwind_frame<F, R, UCS, ES>(
         section: &impl UnwindSection<R>,
         unwind_info: &UnwindTableRow<R::Offset, UCS>,
@@ -42,14 +55,6 @@ impl DwarfUnwinding for ArchAarch64 {
         let fp_rule = unwind_info.register(AArch64::X29);
         let lr_rule = unwind_info.register(AArch64::X30);
 
-        match translate_into_unwind_rule(cfa_rule, &fp_rule, &lr_rule) {
-            Ok(unwind_rule) => return Ok(UnwindResult::ExecRule(unwind_rule)),
-            Err(_err) => {
-                // Could not translate into a cacheable unwind rule. Fall back to the generic path.
-                // eprintln!("Unwind rule translation failed: {:?}", err);
-            }
-        }
-
         let cfa = eval_cfa_rule::<R, _, ES>(section, cfa_rule, encoding, regs)
             .ok_or(DwarfUnwinderError::CouldNotRecoverCfa)?;
 
@@ -157,11 +162,12 @@ fn translate_into_unwind_rule<RO: ReaderOffset>(
                         let fp_storage_offset_from_sp_by_8 =
                             i16::try_from((offset + fp_cfa_offset) / 8)
                                 .map_err(|_| ConversionError::FpStorageOffsetDoesNotFit)?;
-                        Ok(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                        UnwindRuleAarch64::offset_sp_and_restore_fp_and_lr(
                             sp_offset_by_16,
                             fp_storage_offset_from_sp_by_8,
                             lr_storage_offset_from_sp_by_8,
-                        })
+                        )
+                        .ok_or(ConversionError::LrStorageOffsetDoesNotFit)
                     }
                 }
             }
@@ -181,11 +187,12 @@ fn translate_into_unwind_rule<RO: ReaderOffset>(
                     let fp_storage_offset_from_fp_by_8 =
                         i16::try_from((offset + fp_cfa_offset) / 8)
                             .map_err(|_| ConversionError::FpStorageOffsetDoesNotFit)?;
-                    Ok(UnwindRuleAarch64::UseFramepointerWithOffsets {
+                    UnwindRuleAarch64::use_frame_pointer_with_offsets(
                         sp_offset_from_fp_by_8,
                         fp_storage_offset_from_fp_by_8,
                         lr_storage_offset_from_fp_by_8,
-                    })
+                    )
+                    .ok_or(ConversionError::LrStorageOffsetDoesNotFit)
                 }
             }
             _ => Err(ConversionError::CfaIsOffsetFromUnknownRegister),
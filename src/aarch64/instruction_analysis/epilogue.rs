@@ -420,11 +420,11 @@ pub fn unwind_rule_from_detected_epilogue(
                 },
                 (Some(_), None) => return None,
                 (Some(fp_offset), Some(lr_offset)) => {
-                    UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                    UnwindRuleAarch64::offset_sp_and_restore_fp_and_lr(
                         sp_offset_by_16,
-                        fp_storage_offset_from_sp_by_8: i16::try_from(fp_offset / 8).ok()?,
-                        lr_storage_offset_from_sp_by_8: i16::try_from(lr_offset / 8).ok()?,
-                    }
+                        i16::try_from(fp_offset / 8).ok()?,
+                        i16::try_from(lr_offset / 8).ok()?,
+                    )?
                 }
             };
             Some(rule)
@@ -453,7 +453,7 @@ mod test {
             Some(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
                 sp_offset_by_16: 5,
                 fp_storage_offset_from_sp_by_8: 8,
-                lr_storage_offset_from_sp_by_8: 9,
+                lr_storage_offset_from_fp_storage_by_8: 1,
             })
         );
         assert_eq!(
@@ -498,7 +498,7 @@ mod test {
             Some(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
                 sp_offset_by_16: 4,
                 fp_storage_offset_from_sp_by_8: 6,
-                lr_storage_offset_from_sp_by_8: 7
+                lr_storage_offset_from_fp_storage_by_8: 1
             })
         );
         assert_eq!(
@@ -541,7 +541,7 @@ mod test {
             Some(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
                 sp_offset_by_16: 1,
                 fp_storage_offset_from_sp_by_8: 0,
-                lr_storage_offset_from_sp_by_8: 1
+                lr_storage_offset_from_fp_storage_by_8: 1
             })
         );
         assert_eq!(
@@ -616,7 +616,7 @@ mod test {
             Some(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
                 sp_offset_by_16: 3,
                 fp_storage_offset_from_sp_by_8: 4,
-                lr_storage_offset_from_sp_by_8: 5
+                lr_storage_offset_from_fp_storage_by_8: 1
             })
         );
         assert_eq!(
@@ -671,7 +671,7 @@ mod test {
             Some(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
                 sp_offset_by_16: 2,
                 fp_storage_offset_from_sp_by_8: 2,
-                lr_storage_offset_from_sp_by_8: 3
+                lr_storage_offset_from_fp_storage_by_8: 1
             })
         );
         assert_eq!(
@@ -1,10 +1,34 @@
 use super::arch::ArchAarch64;
 use super::unwind_rule::UnwindRuleAarch64;
+use super::unwindregs::UnwindRegsAarch64;
 use crate::instruction_analysis::InstructionAnalysis;
-use crate::macho::{CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding, CuiUnwindResult};
+use crate::macho::{
+    read_sigtramp_context, CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding,
+    CuiUnwindResult, SIGINFO_SIZE, UCONTEXT_MCONTEXT, UCONTEXT_MCSIZE,
+};
+use crate::unwind_result::UnwindResult;
 use macho_unwind_info::opcodes::OpcodeArm64;
 use macho_unwind_info::Function;
 
+/// `mov w1, #UC_FLAVOR`, the second argument for `__sigreturn`.
+const SIGTRAMP_MOV_W1_UC_FLAVOR: u32 = 0x528003c1;
+/// How many instructions after the signal handler call we look for the `__sigreturn`
+/// arguments.
+const SIGTRAMP_MAX_INSTRUCTIONS_BEFORE_SIGRETURN: usize = 8;
+
+/// The size of the mcontext up to the end of the thread state.
+const MCONTEXT_THREAD_STATE_END: u64 = 16 + 272;
+/// Offsets of the registers we need in the mcontext, after the 16 byte exception state.
+const MCONTEXT_FP: u64 = 16 + 29 * 8;
+const MCONTEXT_LR: u64 = 16 + 30 * 8;
+const MCONTEXT_SP: u64 = 16 + 31 * 8;
+const MCONTEXT_PC: u64 = 16 + 32 * 8;
+
+fn is_blr(insn: u32) -> bool {
+    // blr, and blraa / blrab / blraaz / blrabz
+    insn & 0xfefff000 == 0xd63f0000
+}
+
 impl CompactUnwindInfoUnwinding for ArchAarch64 {
     fn unwind_frame(
         function: Function,
@@ -27,7 +51,7 @@ impl CompactUnwindInfoUnwinding for ArchAarch64 {
                 ) {
                     // We are inside a prologue / epilogue. Ignore the opcode and use the rule from
                     // instruction analysis.
-                    return Ok(CuiUnwindResult::ExecRule(rule));
+                    return Ok(CuiUnwindResult::ExecRuleFromInstructionAnalysis(rule));
                 }
             }
         }
@@ -93,4 +117,97 @@ impl CompactUnwindInfoUnwinding for ArchAarch64 {
         };
         Ok(CuiUnwindResult::ExecRule(rule))
     }
+
+    fn is_sigtramp_return_address(function_bytes: &[u8], return_address_offset: usize) -> bool {
+        let instruction_at = |offset: usize| {
+            let bytes = function_bytes.get(offset..offset.checked_add(4)?)?;
+            Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let call = return_address_offset
+            .checked_sub(4)
+            .and_then(instruction_at);
+        if !call.is_some_and(is_blr) {
+            return false;
+        }
+        (0..SIGTRAMP_MAX_INSTRUCTIONS_BEFORE_SIGRETURN).any(|i| {
+            instruction_at(return_address_offset + i * 4) == Some(SIGTRAMP_MOV_W1_UC_FLAVOR)
+        })
+    }
+
+    fn unwind_sigtramp_frame<F>(
+        regs: &mut UnwindRegsAarch64,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleAarch64>, CompactUnwindInfoUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        // The kernel calls _sigtramp with sp pointing at a frame which starts with the
+        // siginfo, followed by the ucontext. _sigtramp stores its frame record at the top
+        // of its stack frame, so its sp on entry was fp + 16.
+        let ucontext = regs
+            .fp()
+            .checked_add(16 + SIGINFO_SIZE)
+            .ok_or(CompactUnwindInfoUnwinderError::SigtrampContextNotFound)?;
+        let mcontext = read_sigtramp_context(ucontext, UCONTEXT_MCONTEXT, read_stack)?;
+        let mcsize = read_sigtramp_context(ucontext, UCONTEXT_MCSIZE, read_stack)?;
+        if mcontext <= ucontext || mcsize < MCONTEXT_THREAD_STATE_END {
+            return Err(CompactUnwindInfoUnwinderError::SigtrampContextNotFound);
+        }
+
+        let new_fp = read_sigtramp_context(mcontext, MCONTEXT_FP, read_stack)?;
+        let new_lr = read_sigtramp_context(mcontext, MCONTEXT_LR, read_stack)?;
+        let new_sp = read_sigtramp_context(mcontext, MCONTEXT_SP, read_stack)?;
+        let new_pc = read_sigtramp_context(mcontext, MCONTEXT_PC, read_stack)?;
+        // On arm64e, pc and lr in the thread state are signed.
+        let ptr_auth_mask = regs.lr_mask();
+        regs.set_fp(new_fp);
+        regs.set_lr(ptr_auth_mask.strip_ptr_auth(new_lr));
+        regs.set_sp(new_sp);
+        Ok(UnwindResult::InstructionPointer(
+            ptr_auth_mask.strip_ptr_auth(new_pc),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sigtramp_return_address() {
+        let function_bytes = [
+            0x00, 0x01, 0x3f, 0xd6, // blr x8
+            0xe0, 0x03, 0x13, 0xaa, // mov x0, x19
+            0xc1, 0x03, 0x80, 0x52, // mov w1, #0x1e
+            0xe2, 0x03, 0x14, 0xaa, // mov x2, x20
+        ];
+        assert!(ArchAarch64::is_sigtramp_return_address(&function_bytes, 4));
+        assert!(!ArchAarch64::is_sigtramp_return_address(&function_bytes, 8));
+        assert!(!ArchAarch64::is_sigtramp_return_address(&function_bytes, 0));
+    }
+
+    #[test]
+    fn test_unwind_sigtramp_frame() {
+        // fp is 0x100, so the siginfo is at 0x110, the ucontext at 0x178 and the mcontext
+        // at 0x1b0.
+        let mut stack = [0u64; 0x80];
+        stack[(0x178 + UCONTEXT_MCSIZE as usize) / 8] = 808;
+        stack[(0x178 + UCONTEXT_MCONTEXT as usize) / 8] = 0x1b0;
+        stack[(0x1b0 + MCONTEXT_FP as usize) / 8] = 0x2000;
+        stack[(0x1b0 + MCONTEXT_LR as usize) / 8] = 0x1000_0000_0123_4500;
+        stack[(0x1b0 + MCONTEXT_SP as usize) / 8] = 0x1800;
+        stack[(0x1b0 + MCONTEXT_PC as usize) / 8] = 0x2000_0000_0123_4567;
+        let mut read_stack = |addr: u64| stack.get((addr / 8) as usize).copied().ok_or(());
+        let mut regs = UnwindRegsAarch64::new_with_ptr_auth_mask(
+            crate::aarch64::PtrAuthMask::from_max_known_address(0x1_0000_0000),
+            0x7fff0000,
+            0xe0,
+            0x100,
+        );
+        let res = ArchAarch64::unwind_sigtramp_frame(&mut regs, &mut read_stack);
+        assert_eq!(res, Ok(UnwindResult::InstructionPointer(0x1234567)));
+        assert_eq!(regs.lr(), 0x1234500);
+        assert_eq!(regs.sp(), 0x1800);
+        assert_eq!(regs.fp(), 0x2000);
+    }
 }
@@ -1,4 +1,5 @@
 mod arch;
+mod breakpad;
 mod cache;
 mod dwarf;
 mod instruction_analysis;
@@ -6,6 +7,8 @@ mod instruction_analysis;
 mod macho;
 #[cfg(feature = "pe")]
 mod pe;
+mod sframe;
+mod signal_frame;
 mod unwind_rule;
 mod unwinder;
 mod unwindregs;
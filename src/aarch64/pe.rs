@@ -1,19 +1,1047 @@
 use super::arch::ArchAarch64;
+use super::unwind_rule::UnwindRuleAarch64;
+use super::unwindregs::UnwindRegsAarch64;
 use crate::pe::{PeSections, PeUnwinderError, PeUnwinding};
+use crate::rule_table::{RuleTable, RuleTableBuilder};
 use crate::unwind_result::UnwindResult;
+use crate::unwind_rule::UnwindRule;
+
+use arrayvec::ArrayVec;
+
+/// An entry in the ARM64 .pdata section. Each entry is 8 bytes: the RVA of the
+/// function start, followed by either the RVA of the function's .xdata record or,
+/// if the low two bits are non-zero, packed unwind data.
+#[derive(Clone, Copy, Debug)]
+struct RuntimeFunction {
+    begin_address: u32,
+    unwind_data: u32,
+}
+
+impl RuntimeFunction {
+    fn parse(bytes: &[u8]) -> Self {
+        Self {
+            begin_address: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            unwind_data: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        }
+    }
+
+    /// Find the entry with the highest begin address that is <= `address`.
+    fn lookup(pdata: &[u8], address: u32) -> Option<Self> {
+        let entry = |index: usize| Self::parse(&pdata[index * 8..][..8]);
+        let (mut low, mut high) = (0, pdata.len() / 8);
+        while low < high {
+            let mid = (low + high) / 2;
+            if entry(mid).begin_address <= address {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low.checked_sub(1).map(entry)
+    }
+}
+
+/// The register class of saved registers. We only track x29 (fp) and x30 (lr), but
+/// saves of other registers still need to be decoded because they can move sp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RegClass {
+    General,
+    Vector,
+}
+
+/// A single ARM64 unwind code, either decoded from .xdata or synthesized from packed
+/// unwind data. Every code other than `End` and `EndC` corresponds to exactly one
+/// prologue / epilogue instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnwindCode {
+    /// sp += size
+    AllocStack(u32),
+    /// `count` consecutive registers starting at `first` were stored at sp + offset. If
+    /// `pre_indexed` is set, they were stored at sp, and sp was decremented by offset.
+    SaveRegs {
+        class: RegClass,
+        first: u8,
+        count: u8,
+        offset: u16,
+        pre_indexed: bool,
+    },
+    /// x(reg) and lr were stored at sp + offset, with the same pre-indexing rules as
+    /// `SaveRegs`.
+    SaveRegAndLr {
+        reg: u8,
+        offset: u16,
+        pre_indexed: bool,
+    },
+    /// sp = fp - offset
+    SetSpFromFp(u16),
+    /// The following register pair save also covers the next pair of registers.
+    SaveNext,
+    PacSignLr,
+    Nop,
+    End,
+    EndC,
+    Unsupported(u8),
+}
+
+impl UnwindCode {
+    fn byte_len(opcode: u8) -> usize {
+        match opcode {
+            0x00..=0xbf => 1,
+            0xc0..=0xdf => 2,
+            0xe0 => 4,
+            0xe2 => 2,
+            0xe7 => 3,
+            0xf8 => 2,
+            0xf9 => 3,
+            0xfa => 4,
+            0xfb => 5,
+            _ => 1,
+        }
+    }
+
+    fn parse(bytes: &[u8]) -> Self {
+        let op = bytes[0];
+        let val = || u16::from_be_bytes([bytes[0], bytes[1]]);
+        let general = |first: u16, count, offset: u16, pre_indexed| UnwindCode::SaveRegs {
+            class: RegClass::General,
+            first: first as u8,
+            count,
+            offset,
+            pre_indexed,
+        };
+        let vector = |first: u16, count, offset: u16, pre_indexed| UnwindCode::SaveRegs {
+            class: RegClass::Vector,
+            first: first as u8,
+            count,
+            offset,
+            pre_indexed,
+        };
+        match op {
+            // alloc_s
+            0x00..=0x1f => UnwindCode::AllocStack(u32::from(op & 0x1f) * 16),
+            // save_r19r20_x
+            0x20..=0x3f => general(19, 2, u16::from(op & 0x1f) * 8, true),
+            // save_fplr
+            0x40..=0x7f => general(29, 2, u16::from(op & 0x3f) * 8, false),
+            // save_fplr_x
+            0x80..=0xbf => general(29, 2, (u16::from(op & 0x3f) + 1) * 8, true),
+            // alloc_m
+            0xc0..=0xc7 => UnwindCode::AllocStack(u32::from(val() & 0x7ff) * 16),
+            // save_regp
+            0xc8..=0xcb => general(19 + ((val() >> 6) & 0xf), 2, (val() & 0x3f) * 8, false),
+            // save_regp_x
+            0xcc..=0xcf => general(19 + ((val() >> 6) & 0xf), 2, ((val() & 0x3f) + 1) * 8, true),
+            // save_reg
+            0xd0..=0xd3 => general(19 + ((val() >> 6) & 0xf), 1, (val() & 0x3f) * 8, false),
+            // save_reg_x
+            0xd4..=0xd5 => general(19 + ((val() >> 5) & 0xf), 1, ((val() & 0x1f) + 1) * 8, true),
+            // save_lrpair
+            0xd6..=0xd7 => UnwindCode::SaveRegAndLr {
+                reg: (19 + 2 * ((val() >> 6) & 0x7)) as u8,
+                offset: (val() & 0x3f) * 8,
+                pre_indexed: false,
+            },
+            // save_fregp
+            0xd8..=0xd9 => vector(8 + ((val() >> 6) & 0x7), 2, (val() & 0x3f) * 8, false),
+            // save_fregp_x
+            0xda..=0xdb => vector(8 + ((val() >> 6) & 0x7), 2, ((val() & 0x3f) + 1) * 8, true),
+            // save_freg
+            0xdc..=0xdd => vector(8 + ((val() >> 6) & 0x7), 1, (val() & 0x3f) * 8, false),
+            // save_freg_x
+            0xde => vector(8 + ((val() >> 5) & 0x7), 1, ((val() & 0x1f) + 1) * 8, true),
+            // alloc_l
+            0xe0 => {
+                UnwindCode::AllocStack(u32::from_be_bytes([0, bytes[1], bytes[2], bytes[3]]) * 16)
+            }
+            // set_fp
+            0xe1 => UnwindCode::SetSpFromFp(0),
+            // add_fp
+            0xe2 => UnwindCode::SetSpFromFp(u16::from(bytes[1]) * 8),
+            0xe3 => UnwindCode::Nop,
+            0xe4 => UnwindCode::End,
+            0xe5 => UnwindCode::EndC,
+            0xe6 => UnwindCode::SaveNext,
+            // save_any_reg: 11100111'0pxrrrrr'ffoooooo
+            0xe7 => {
+                let (pair, pre_indexed) = (bytes[1] & 0x40 != 0, bytes[1] & 0x20 != 0);
+                let (reg, class, o) = (bytes[1] & 0x1f, bytes[2] >> 6, u16::from(bytes[2] & 0x3f));
+                if bytes[1] & 0x80 != 0 || class == 3 {
+                    return UnwindCode::Unsupported(op);
+                }
+                let offset = if pre_indexed {
+                    (o + 1) * 16
+                } else if pair || class == 2 {
+                    o * 16
+                } else {
+                    o * 8
+                };
+                UnwindCode::SaveRegs {
+                    class: if class == 0 {
+                        RegClass::General
+                    } else {
+                        RegClass::Vector
+                    },
+                    first: reg,
+                    count: if pair { 2 } else { 1 },
+                    offset,
+                    pre_indexed,
+                }
+            }
+            0xfc => UnwindCode::PacSignLr,
+            // alloc_z (SVE), custom stack codes (trap frames, machine frames, contexts)
+            // and reserved codes.
+            _ => UnwindCode::Unsupported(op),
+        }
+    }
+
+    fn is_custom_stack_code(&self) -> bool {
+        matches!(self, UnwindCode::Unsupported(0xe8..=0xef))
+    }
+}
+
+/// Iterates over the unwind codes in a byte sequence from an .xdata record.
+#[derive(Clone)]
+struct UnwindCodeIter<'a> {
+    bytes: &'a [u8],
+}
+
+impl Iterator for UnwindCodeIter<'_> {
+    type Item = UnwindCode;
+
+    fn next(&mut self) -> Option<UnwindCode> {
+        let len = UnwindCode::byte_len(*self.bytes.first()?);
+        if self.bytes.len() < len {
+            self.bytes = &[];
+            return None;
+        }
+        let (code, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(UnwindCode::parse(code))
+    }
+}
+
+/// The number of instructions described by the code sequence, i.e. the number of
+/// codes up to the terminating `end` / `end_c`.
+fn sequence_len(codes: impl Iterator<Item = UnwindCode>) -> u32 {
+    codes
+        .take_while(|code| !matches!(code, UnwindCode::End | UnwindCode::EndC))
+        .filter(|code| !code.is_custom_stack_code())
+        .count() as u32
+}
+
+/// The effect of an unwind code on sp, fp and lr.
+enum Step {
+    /// fp = *(sp + fp_offset), lr = *(sp + lr_offset), then sp += sp_offset
+    Restore {
+        fp_offset: Option<u32>,
+        lr_offset: Option<u32>,
+        sp_offset: u32,
+    },
+    /// sp = fp - offset
+    SetSpFromFp(u32),
+}
+
+/// Calls `f` with the effect of every code in `codes`, until the end of the sequence.
+fn for_each_step<I, F>(codes: I, mut f: F) -> Result<(), PeUnwinderError>
+where
+    I: Iterator<Item = UnwindCode>,
+    F: FnMut(Step) -> Result<(), PeUnwinderError>,
+{
+    let mut save_next_count = 0;
+    for code in codes {
+        let step = match code {
+            UnwindCode::AllocStack(size) => Step::Restore {
+                fp_offset: None,
+                lr_offset: None,
+                sp_offset: size,
+            },
+            UnwindCode::SaveRegs {
+                class,
+                first,
+                count,
+                offset,
+                pre_indexed,
+            } => {
+                let count = if count == 2 {
+                    2 + 2 * save_next_count
+                } else {
+                    u32::from(count)
+                };
+                let (base, sp_offset) = match pre_indexed {
+                    true => (0, u32::from(offset)),
+                    false => (u32::from(offset), 0),
+                };
+                let location = |reg: u32| match class {
+                    RegClass::General => (u32::from(first)..u32::from(first) + count)
+                        .contains(&reg)
+                        .then(|| base + (reg - u32::from(first)) * 8),
+                    RegClass::Vector => None,
+                };
+                Step::Restore {
+                    fp_offset: location(29),
+                    lr_offset: location(30),
+                    sp_offset,
+                }
+            }
+            UnwindCode::SaveRegAndLr {
+                reg: _,
+                offset,
+                pre_indexed,
+            } => {
+                let (base, sp_offset) = match pre_indexed {
+                    true => (0, u32::from(offset)),
+                    false => (u32::from(offset), 0),
+                };
+                Step::Restore {
+                    fp_offset: None,
+                    lr_offset: Some(base + 8),
+                    sp_offset,
+                }
+            }
+            UnwindCode::SetSpFromFp(offset) => Step::SetSpFromFp(u32::from(offset)),
+            UnwindCode::SaveNext => {
+                save_next_count += 1;
+                continue;
+            }
+            UnwindCode::PacSignLr | UnwindCode::Nop | UnwindCode::EndC => {
+                // Return addresses are stripped of their pointer authentication bits
+                // using the lr mask in UnwindRegsAarch64, so pac_sign_lr needs no work.
+                save_next_count = 0;
+                continue;
+            }
+            UnwindCode::End => break,
+            UnwindCode::Unsupported(op) => return Err(PeUnwinderError::UnsupportedUnwindCode(op)),
+        };
+        save_next_count = 0;
+        f(step)?;
+    }
+    Ok(())
+}
+
+/// Translate the codes into a cacheable unwind rule, if possible.
+fn rule_for_codes(codes: impl Iterator<Item = UnwindCode>) -> Option<UnwindRuleAarch64> {
+    // Offsets are relative to the incoming sp, or to fp once sp has been restored from fp.
+    let mut relative_to_fp = false;
+    let mut sp_offset: i64 = 0;
+    let mut fp_storage_offset = None;
+    let mut lr_storage_offset = None;
+    for_each_step(codes, |step| {
+        match step {
+            Step::Restore {
+                fp_offset,
+                lr_offset,
+                sp_offset: offset,
+            } => {
+                if let Some(fp_offset) = fp_offset {
+                    fp_storage_offset = Some(sp_offset + i64::from(fp_offset));
+                }
+                if let Some(lr_offset) = lr_offset {
+                    lr_storage_offset = Some(sp_offset + i64::from(lr_offset));
+                }
+                sp_offset += i64::from(offset);
+            }
+            Step::SetSpFromFp(offset) => {
+                if relative_to_fp || fp_storage_offset.is_some() || lr_storage_offset.is_some() {
+                    // Not expressible as a rule; fall back to the interpreter.
+                    return Err(PeUnwinderError::UnwindInfoParseError);
+                }
+                relative_to_fp = true;
+                sp_offset = -i64::from(offset);
+            }
+        }
+        Ok(())
+    })
+    .ok()?;
+
+    if relative_to_fp {
+        let (fp_storage_offset, lr_storage_offset) = (fp_storage_offset?, lr_storage_offset?);
+        if sp_offset == 16 && fp_storage_offset == 0 && lr_storage_offset == 8 {
+            return Some(UnwindRuleAarch64::UseFramePointer);
+        }
+        return UnwindRuleAarch64::use_frame_pointer_with_offsets(
+            u16::try_from(sp_offset / 8).ok()?,
+            i16::try_from(fp_storage_offset / 8).ok()?,
+            i16::try_from(lr_storage_offset / 8).ok()?,
+        );
+    }
+
+    if sp_offset % 16 != 0 {
+        return None;
+    }
+    let sp_offset_by_16 = u16::try_from(sp_offset / 16).ok()?;
+    match (fp_storage_offset, lr_storage_offset) {
+        (None, None) if sp_offset_by_16 == 0 => Some(UnwindRuleAarch64::NoOp),
+        (None, None) => Some(UnwindRuleAarch64::OffsetSp { sp_offset_by_16 }),
+        (None, Some(lr_storage_offset)) => Some(UnwindRuleAarch64::OffsetSpAndRestoreLr {
+            sp_offset_by_16,
+            lr_storage_offset_from_sp_by_8: i16::try_from(lr_storage_offset / 8).ok()?,
+        }),
+        (Some(fp_storage_offset), Some(lr_storage_offset)) => {
+            UnwindRuleAarch64::offset_sp_and_restore_fp_and_lr(
+                sp_offset_by_16,
+                i16::try_from(fp_storage_offset / 8).ok()?,
+                i16::try_from(lr_storage_offset / 8).ok()?,
+            )
+        }
+        (Some(_), None) => None,
+    }
+}
+
+/// Unwind using the given codes, returning a cacheable rule if the codes can be
+/// expressed as one.
+fn unwind_with_codes<I, F>(
+    codes: I,
+    regs: &mut UnwindRegsAarch64,
+    read_stack: &mut F,
+) -> Result<UnwindResult<UnwindRuleAarch64>, PeUnwinderError>
+where
+    I: Iterator<Item = UnwindCode> + Clone,
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    if let Some(rule) = rule_for_codes(codes.clone()) {
+        return Ok(UnwindResult::ExecRule(rule));
+    }
+
+    let (mut sp, mut fp, mut lr) = (regs.sp(), regs.fp(), regs.lr());
+    let mut read =
+        |addr: u64| read_stack(addr).map_err(|()| PeUnwinderError::MissingStackData(Some(addr)));
+    for_each_step(codes, |step| {
+        match step {
+            Step::Restore {
+                fp_offset,
+                lr_offset,
+                sp_offset,
+            } => {
+                if let Some(fp_offset) = fp_offset {
+                    fp = read(sp.wrapping_add(u64::from(fp_offset)))?;
+                }
+                if let Some(lr_offset) = lr_offset {
+                    lr = read(sp.wrapping_add(u64::from(lr_offset)))?;
+                }
+                sp = sp.wrapping_add(u64::from(sp_offset));
+            }
+            Step::SetSpFromFp(offset) => sp = fp.wrapping_sub(u64::from(offset)),
+        }
+        Ok(())
+    })?;
+
+    regs.set_sp(sp);
+    regs.set_fp(fp);
+    regs.set_lr(lr);
+    Ok(UnwindResult::Uncacheable(regs.lr_mask().strip_ptr_auth(lr)))
+}
+
+/// Pushes the rules for return addresses in a function whose prologue is described by
+/// `codes`, followed by `leaf_rule` for the addresses after the function. The lengths
+/// are in 4-byte instructions.
+fn push_function_rules<I>(
+    builder: &mut RuleTableBuilder,
+    begin_address: u32,
+    codes: I,
+    prologue_len: u32,
+    function_length: u32,
+    leaf_rule: Option<u64>,
+) where
+    I: Iterator<Item = UnwindCode> + Clone,
+{
+    for offset in 0..=prologue_len.min(function_length) {
+        let skip = (prologue_len - offset) as usize;
+        let rule = rule_for_codes(codes.clone().skip(skip));
+        builder.push(
+            begin_address.saturating_add(offset * 4),
+            rule.map(UnwindRule::pack),
+        );
+    }
+    builder.push(begin_address.saturating_add(function_length * 4), leaf_rule);
+}
+
+/// The packed unwind data format, used for functions whose prologue and epilogue
+/// follow a canonical form.
+struct PackedUnwindData {
+    flag: u8,
+    function_length: u32,
+    reg_f: u8,
+    reg_i: u8,
+    h: bool,
+    cr: u8,
+    frame_size: u32,
+}
+
+impl PackedUnwindData {
+    fn parse(data: u32) -> Self {
+        Self {
+            flag: (data & 0x3) as u8,
+            function_length: (data >> 2) & 0x7ff,
+            reg_f: ((data >> 13) & 0x7) as u8,
+            reg_i: ((data >> 16) & 0xf) as u8,
+            h: (data >> 20) & 0x1 != 0,
+            cr: ((data >> 21) & 0x3) as u8,
+            frame_size: ((data >> 23) & 0x1ff) * 16,
+        }
+    }
+
+    /// Synthesize the unwind codes for the canonical prologue, in unwind order.
+    fn prologue_codes(&self) -> Result<ArrayVec<UnwindCode, 24>, PeUnwinderError> {
+        if self.reg_i > 10 {
+            return Err(PeUnwinderError::UnwindInfoParseError);
+        }
+        let chained = self.cr == 2 || self.cr == 3;
+        let int_size = u32::from(self.reg_i) * 8 + if self.cr == 1 { 8 } else { 0 };
+        let fp_count = if self.reg_f > 0 { self.reg_f + 1 } else { 0 };
+        let fp_size = u32::from(fp_count) * 8;
+        let save_size = (int_size + fp_size + if self.h { 64 } else { 0 } + 0xf) & !0xf;
+        let local_size = self
+            .frame_size
+            .checked_sub(save_size)
+            .ok_or(PeUnwinderError::UnwindInfoParseError)?;
+        let save_size = save_size as u16;
+
+        // Build the codes in prologue order first, then reverse them.
+        let mut codes = ArrayVec::<UnwindCode, 24>::new();
+        let general = |first: u8, count, offset, pre_indexed| UnwindCode::SaveRegs {
+            class: RegClass::General,
+            first,
+            count,
+            offset,
+            pre_indexed,
+        };
+        if self.cr == 2 {
+            codes.push(UnwindCode::PacSignLr);
+        }
+        for i in 0..self.reg_i / 2 {
+            codes.push(match i {
+                0 => general(19, 2, save_size, true),
+                _ => general(19 + 2 * i, 2, u16::from(i) * 16, false),
+            });
+        }
+        let first_save_is_pre_indexed = self.reg_i <= 1;
+        let (offset, pre_indexed) = match first_save_is_pre_indexed {
+            true => (save_size, true),
+            false => ((int_size - 8) as u16, false),
+        };
+        if self.reg_i % 2 == 1 {
+            let reg = 19 + self.reg_i - 1;
+            codes.push(match self.cr {
+                1 => UnwindCode::SaveRegAndLr {
+                    reg,
+                    offset: match pre_indexed {
+                        true => offset,
+                        false => offset - 8,
+                    },
+                    pre_indexed,
+                },
+                _ => general(reg, 1, offset, pre_indexed),
+            });
+        } else if self.cr == 1 {
+            codes.push(general(30, 1, offset, pre_indexed));
+        }
+        for i in 0..fp_count / 2 {
+            let (offset, pre_indexed) = match (i, int_size) {
+                (0, 0) => (save_size, true),
+                _ => (int_size as u16 + u16::from(i) * 16, false),
+            };
+            codes.push(UnwindCode::SaveRegs {
+                class: RegClass::Vector,
+                first: 8 + 2 * i,
+                count: 2,
+                offset,
+                pre_indexed,
+            });
+        }
+        if fp_count % 2 == 1 {
+            codes.push(UnwindCode::SaveRegs {
+                class: RegClass::Vector,
+                first: 8 + fp_count - 1,
+                count: 1,
+                offset: (int_size + fp_size - 8) as u16,
+                pre_indexed: false,
+            });
+        }
+        if self.h {
+            // Homing the argument registers x0-x7 takes four stp instructions. If nothing
+            // else has been saved, the save area is allocated separately.
+            if int_size == 0 && fp_size == 0 {
+                codes.push(UnwindCode::AllocStack(u32::from(save_size)));
+            }
+            for _ in 0..4 {
+                codes.push(UnwindCode::Nop);
+            }
+        }
+        let alloc_local = |codes: &mut ArrayVec<UnwindCode, 24>| {
+            if local_size > 4080 {
+                codes.push(UnwindCode::AllocStack(4080));
+                codes.push(UnwindCode::AllocStack(local_size - 4080));
+            } else if local_size > 0 {
+                codes.push(UnwindCode::AllocStack(local_size));
+            }
+        };
+        if chained && local_size <= 512 {
+            codes.push(general(29, 2, local_size as u16, true));
+            codes.push(UnwindCode::SetSpFromFp(0));
+        } else if chained {
+            alloc_local(&mut codes);
+            codes.push(general(29, 2, 0, false));
+            codes.push(UnwindCode::SetSpFromFp(0));
+        } else {
+            alloc_local(&mut codes);
+        }
+
+        codes.reverse();
+        Ok(codes)
+    }
+}
+
+/// The header of an .xdata record, followed by the epilog scopes and the unwind codes.
+struct XdataRecord<'a> {
+    function_length: u32,
+    /// If the E bit is set, the single epilog is at the end of the function and its
+    /// codes start at this index.
+    single_epilog_index: Option<usize>,
+    epilog_scopes: &'a [u8],
+    codes: &'a [u8],
+}
+
+impl<'a> XdataRecord<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let word = |pos: usize| {
+            let b = data.get(pos..pos + 4)?;
+            Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let header = word(0)?;
+        let function_length = header & 0x3ffff;
+        if (header >> 18) & 0x3 != 0 {
+            // Unknown version.
+            return None;
+        }
+        let single_epilog = (header >> 21) & 0x1 != 0;
+        let mut epilog_count = ((header >> 22) & 0x1f) as usize;
+        let mut code_words = ((header >> 27) & 0x1f) as usize;
+        let mut pos = 4;
+        if epilog_count == 0 && code_words == 0 {
+            let extended = word(4)?;
+            epilog_count = (extended & 0xffff) as usize;
+            code_words = ((extended >> 16) & 0xff) as usize;
+            pos = 8;
+        }
+        let (single_epilog_index, epilog_scopes) = if single_epilog {
+            (Some(epilog_count), &[][..])
+        } else {
+            let scopes = data.get(pos..pos + epilog_count * 4)?;
+            pos += epilog_count * 4;
+            (None, scopes)
+        };
+        let codes = data.get(pos..pos + code_words * 4)?;
+        Some(Self {
+            function_length,
+            single_epilog_index,
+            epilog_scopes,
+            codes,
+        })
+    }
+
+    fn codes_at(&self, index: usize) -> Result<UnwindCodeIter<'a>, PeUnwinderError> {
+        let bytes = self
+            .codes
+            .get(index..)
+            .ok_or(PeUnwinderError::UnwindInfoParseError)?;
+        Ok(UnwindCodeIter { bytes })
+    }
+
+    /// Returns the epilog which contains the instruction at `offset`, as the index of
+    /// its first unwind code and the number of epilog instructions that have already
+    /// been executed.
+    fn epilog_at(&self, offset: u32) -> Result<Option<(usize, u32)>, PeUnwinderError> {
+        if let Some(index) = self.single_epilog_index {
+            // The epilog is at the end of the function and ends with a ret.
+            let len = sequence_len(self.codes_at(index)?) + 1;
+            let start = self.function_length.saturating_sub(len);
+            return Ok((offset >= start).then_some((index, offset - start)));
+        }
+        for scope in self.epilog_scopes.chunks_exact(4) {
+            let scope = u32::from_le_bytes([scope[0], scope[1], scope[2], scope[3]]);
+            let start = scope & 0x3ffff;
+            let index = (scope >> 22) as usize;
+            if offset < start {
+                break;
+            }
+            // The epilog's last instruction, the ret, is not described by a code.
+            if offset - start <= sequence_len(self.codes_at(index)?) {
+                return Ok(Some((index, offset - start)));
+            }
+        }
+        Ok(None)
+    }
+}
 
 impl PeUnwinding for ArchAarch64 {
     fn unwind_frame<F, D>(
-        _sections: PeSections<D>,
-        _address: u32,
-        _regs: &mut Self::UnwindRegs,
-        _is_first_frame: bool,
-        _read_stack: &mut F,
+        sections: PeSections<D>,
+        address: u32,
+        regs: &mut Self::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
     ) -> Result<UnwindResult<Self::UnwindRule>, PeUnwinderError>
     where
         F: FnMut(u64) -> Result<u64, ()>,
         D: core::ops::Deref<Target = [u8]>,
     {
-        Err(PeUnwinderError::Aarch64Unsupported)
+        // Leaf functions which don't touch the stack don't need .pdata entries.
+        let leaf_rule = UnwindResult::ExecRule(UnwindRuleAarch64::NoOpIfFirstFrameOtherwiseFp);
+        let Some(function) = RuntimeFunction::lookup(sections.pdata, address) else {
+            return Ok(leaf_rule);
+        };
+        // The offset of the instruction in the function, in 4-byte instructions.
+        let offset = (address - function.begin_address) / 4;
+
+        match function.unwind_data & 0x3 {
+            0 => {
+                let xdata = sections.unwind_info_memory_at_rva(function.unwind_data)?;
+                let record =
+                    XdataRecord::parse(xdata).ok_or(PeUnwinderError::UnwindInfoParseError)?;
+                if offset >= record.function_length {
+                    return Ok(leaf_rule);
+                }
+
+                // In the prologue, skip the codes for instructions which haven't executed
+                // yet. The first code describes the last prologue instruction.
+                let prologue_len = sequence_len(record.codes_at(0)?);
+                if offset < prologue_len {
+                    let skip = (prologue_len - offset) as usize;
+                    return unwind_with_codes(record.codes_at(0)?.skip(skip), regs, read_stack);
+                }
+
+                // In an epilog, skip the codes for the instructions which have already
+                // executed. Epilog codes are listed in execution order.
+                if is_first_frame {
+                    if let Some((index, skip)) = record.epilog_at(offset)? {
+                        let codes = record.codes_at(index)?.skip(skip as usize);
+                        return unwind_with_codes(codes, regs, read_stack);
+                    }
+                }
+
+                unwind_with_codes(record.codes_at(0)?, regs, read_stack)
+            }
+            1 | 2 => {
+                let packed = PackedUnwindData::parse(function.unwind_data);
+                if offset >= packed.function_length {
+                    return Ok(leaf_rule);
+                }
+                let codes = packed.prologue_codes()?;
+                if packed.flag == 2 {
+                    // A function fragment without prologue and epilogue.
+                    return unwind_with_codes(codes.iter().copied(), regs, read_stack);
+                }
+
+                let prologue_len = codes.len() as u32;
+                if offset < prologue_len {
+                    let skip = (prologue_len - offset) as usize;
+                    return unwind_with_codes(codes.iter().copied().skip(skip), regs, read_stack);
+                }
+
+                // The epilog mirrors the prologue, but doesn't restore the homed argument
+                // registers or set up fp, and ends with a ret.
+                let is_epilog_code = |code: &UnwindCode| {
+                    !matches!(code, UnwindCode::Nop | UnwindCode::SetSpFromFp(_))
+                };
+                let epilog_len = codes.iter().filter(|c| is_epilog_code(c)).count() as u32 + 1;
+                let epilog_start = packed.function_length.saturating_sub(epilog_len);
+                if is_first_frame && offset >= epilog_start {
+                    let skip = (offset - epilog_start) as usize;
+                    let codes = codes.iter().copied().filter(is_epilog_code).skip(skip);
+                    return unwind_with_codes(codes, regs, read_stack);
+                }
+
+                unwind_with_codes(codes.iter().copied(), regs, read_stack)
+            }
+            _ => Err(PeUnwinderError::UnwindInfoParseError),
+        }
+    }
+
+    fn compile_rule_table<D>(sections: PeSections<D>) -> Result<RuleTable, PeUnwinderError>
+    where
+        D: core::ops::Deref<Target = [u8]>,
+    {
+        let leaf_rule = Some(UnwindRuleAarch64::NoOpIfFirstFrameOtherwiseFp.pack());
+        let mut builder = RuleTableBuilder::new();
+        builder.push(0, leaf_rule);
+        for entry in sections.pdata.chunks_exact(8) {
+            let function = RuntimeFunction::parse(entry);
+            let begin = function.begin_address;
+            // Functions whose unwind data can't be parsed get no rules.
+            builder.push(begin, None);
+            match function.unwind_data & 0x3 {
+                0 => {
+                    let Some(record) = sections
+                        .unwind_info_memory_at_rva(function.unwind_data)
+                        .ok()
+                        .and_then(XdataRecord::parse)
+                    else {
+                        continue;
+                    };
+                    let Ok(codes) = record.codes_at(0) else {
+                        continue;
+                    };
+                    let prologue_len = sequence_len(codes.clone());
+                    push_function_rules(
+                        &mut builder,
+                        begin,
+                        codes,
+                        prologue_len,
+                        record.function_length,
+                        leaf_rule,
+                    );
+                }
+                1 | 2 => {
+                    let packed = PackedUnwindData::parse(function.unwind_data);
+                    let Ok(codes) = packed.prologue_codes() else {
+                        continue;
+                    };
+                    // Function fragments have no prologue.
+                    let prologue_len = match packed.flag {
+                        2 => 0,
+                        _ => codes.len() as u32,
+                    };
+                    push_function_rules(
+                        &mut builder,
+                        begin,
+                        codes.iter().copied(),
+                        prologue_len,
+                        packed.function_length,
+                        leaf_rule,
+                    );
+                }
+                _ => {}
+            }
+        }
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pe::DataAtRvaRange;
+
+    fn pdata(entries: &[(u32, u32)]) -> Vec<u8> {
+        entries
+            .iter()
+            .flat_map(|(begin, data)| [begin.to_le_bytes(), data.to_le_bytes()])
+            .flatten()
+            .collect()
+    }
+
+    fn unwind(
+        pdata: &[u8],
+        xdata: Option<&DataAtRvaRange<&[u8]>>,
+        address: u32,
+        is_first_frame: bool,
+    ) -> Result<UnwindResult<UnwindRuleAarch64>, PeUnwinderError> {
+        let sections = PeSections {
+            pdata: &pdata,
+            rdata: None,
+            xdata,
+            text: None,
+        };
+        let mut regs = UnwindRegsAarch64::new(0x1234, 0x1000, 0x1100);
+        <ArchAarch64 as PeUnwinding>::unwind_frame(
+            sections,
+            address,
+            &mut regs,
+            is_first_frame,
+            &mut |_| Err(()),
+        )
+    }
+
+    #[test]
+    fn test_packed() {
+        // Flag=1, FunctionLength=20, RegF=0, RegI=2, H=0, CR=3, FrameSize=4 (64 bytes):
+        // stp x19, x20, [sp, #-16]!
+        // stp x29, lr, [sp, #-48]!
+        // mov x29, sp
+        // ...
+        // ldp x29, lr, [sp], #48
+        // ldp x19, x20, [sp], #16
+        // ret
+        let packed = 1 | (20 << 2) | (2 << 16) | (3 << 21) | (4 << 23);
+        let pdata = pdata(&[(0x1000, packed)]);
+        let expected = [
+            (0x1000, true, UnwindRuleAarch64::NoOp),
+            (
+                0x1004,
+                true,
+                UnwindRuleAarch64::OffsetSp { sp_offset_by_16: 1 },
+            ),
+            (
+                0x1008,
+                true,
+                UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                    sp_offset_by_16: 4,
+                    fp_storage_offset_from_sp_by_8: 0,
+                    lr_storage_offset_from_fp_storage_by_8: 1,
+                },
+            ),
+            (
+                0x1020,
+                false,
+                UnwindRuleAarch64::UseFramepointerWithOffsets {
+                    sp_offset_from_fp_by_8: 8,
+                    fp_storage_offset_from_fp_by_8: 0,
+                    lr_storage_offset_from_fp_storage_by_8: 1,
+                },
+            ),
+            (
+                0x1044,
+                true,
+                UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                    sp_offset_by_16: 4,
+                    fp_storage_offset_from_sp_by_8: 0,
+                    lr_storage_offset_from_fp_storage_by_8: 1,
+                },
+            ),
+            (
+                0x1048,
+                true,
+                UnwindRuleAarch64::OffsetSp { sp_offset_by_16: 1 },
+            ),
+            (0x104c, true, UnwindRuleAarch64::NoOp),
+            (0x1050, true, UnwindRuleAarch64::NoOpIfFirstFrameOtherwiseFp),
+        ];
+        for (address, is_first_frame, rule) in expected {
+            assert_eq!(
+                unwind(&pdata, None, address, is_first_frame),
+                Ok(UnwindResult::ExecRule(rule)),
+                "address {address:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_xdata() {
+        // stp x19, x20, [sp, #-32]!   ; save_r19r20_x
+        // stp x29, lr, [sp, #16]      ; save_fplr
+        // add x29, sp, #16            ; add_fp
+        // sub sp, sp, #48             ; alloc_s
+        // ...
+        // add sp, sp, #48             ; epilog at instruction 16
+        // ldp x29, lr, [sp, #16]
+        // ldp x19, x20, [sp], #32
+        // ret
+        let codes = [
+            0x03, 0xe2, 0x02, 0x42, 0x24, 0xe4, 0x03, 0x42, 0x24, 0xe4, 0xe4, 0xe4,
+        ];
+        let header: u32 = 20 | (1 << 22) | (3 << 27);
+        let scope: u32 = 16 | (6 << 22);
+        let mut data = Vec::new();
+        data.extend_from_slice(&header.to_le_bytes());
+        data.extend_from_slice(&scope.to_le_bytes());
+        data.extend_from_slice(&codes);
+        let xdata = DataAtRvaRange {
+            rva_range: 0x2000..0x2000 + data.len() as u32,
+            data: &data[..],
+        };
+        let pdata = pdata(&[(0x1000, 0x2000)]);
+        let fp_and_lr_stored = UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+            sp_offset_by_16: 2,
+            fp_storage_offset_from_sp_by_8: 2,
+            lr_storage_offset_from_fp_storage_by_8: 1,
+        };
+        let expected = [
+            (0x1000, true, UnwindRuleAarch64::NoOp),
+            (0x1008, true, fp_and_lr_stored),
+            (0x1020, false, UnwindRuleAarch64::UseFramePointer),
+            (
+                0x1040,
+                true,
+                UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                    sp_offset_by_16: 5,
+                    fp_storage_offset_from_sp_by_8: 8,
+                    lr_storage_offset_from_fp_storage_by_8: 1,
+                },
+            ),
+            (0x1044, true, fp_and_lr_stored),
+            (
+                0x1048,
+                true,
+                UnwindRuleAarch64::OffsetSp { sp_offset_by_16: 2 },
+            ),
+            (0x104c, true, UnwindRuleAarch64::NoOp),
+        ];
+        for (address, is_first_frame, rule) in expected {
+            assert_eq!(
+                unwind(&pdata, Some(&xdata), address, is_first_frame),
+                Ok(UnwindResult::ExecRule(rule)),
+                "address {address:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_uncacheable() {
+        // sub sp, sp, #0x200000       ; alloc_l, too large for a rule
+        // stp x27, x28, [sp]          ; save_regp
+        // stp x29, lr, [sp, #16]      ; save_next
+        let codes = [0xe6, 0xca, 0x00, 0xe0, 0x02, 0x00, 0x00, 0xe4];
+        let header: u32 = 20 | (1 << 21) | (7 << 22) | (2 << 27);
+        let mut data = Vec::new();
+        data.extend_from_slice(&header.to_le_bytes());
+        data.extend_from_slice(&codes);
+        let xdata = DataAtRvaRange {
+            rva_range: 0x2000..0x2000 + data.len() as u32,
+            data: &data[..],
+        };
+        let pdata = pdata(&[(0x1000, 0x2000)]);
+        let sections = PeSections {
+            pdata: &&pdata[..],
+            rdata: None,
+            xdata: Some(&xdata),
+            text: None,
+        };
+        let stack = [1, 2, 0x3000, 0x456789];
+        let mut read_stack =
+            |addr: u64| stack.get(((addr - 0x1000) / 8) as usize).copied().ok_or(());
+        let mut regs = UnwindRegsAarch64::new(0x1234, 0x1000, 0x1100);
+        let res = <ArchAarch64 as PeUnwinding>::unwind_frame(
+            sections,
+            0x1020,
+            &mut regs,
+            false,
+            &mut read_stack,
+        );
+        assert_eq!(res, Ok(UnwindResult::Uncacheable(0x456789)));
+        assert_eq!(regs.sp(), 0x201000);
+        assert_eq!(regs.fp(), 0x3000);
+        assert_eq!(regs.lr(), 0x456789);
+    }
+
+    #[test]
+    fn test_rule_table() {
+        // A packed function at 0x1000, as in test_packed, and a function at 0x1100 whose
+        // stack allocation is too large for a rule, as in test_uncacheable.
+        let packed = 1 | (20 << 2) | (2 << 16) | (3 << 21) | (4 << 23);
+        let codes = [0xe6, 0xca, 0x00, 0xe0, 0x02, 0x00, 0x00, 0xe4];
+        let header: u32 = 20 | (1 << 21) | (7 << 22) | (2 << 27);
+        let mut data = Vec::new();
+        data.extend_from_slice(&header.to_le_bytes());
+        data.extend_from_slice(&codes);
+        let xdata = DataAtRvaRange {
+            rva_range: 0x2000..0x2000 + data.len() as u32,
+            data: &data[..],
+        };
+        let pdata = pdata(&[(0x1000, packed), (0x1100, 0x2000)]);
+        let table = <ArchAarch64 as PeUnwinding>::compile_rule_table(PeSections {
+            pdata: &&pdata[..],
+            rdata: None,
+            xdata: Some(&xdata),
+            text: None,
+        })
+        .unwrap();
+        for address in (0xff8..0x1180).step_by(4) {
+            let rule = match unwind(&pdata, Some(&xdata), address, false) {
+                Ok(UnwindResult::ExecRule(rule)) => Some(rule),
+                _ => None,
+            };
+            assert_eq!(table.lookup(address), rule, "address {address:#x}");
+        }
+        assert_eq!(table.lookup::<UnwindRuleAarch64>(0x1120), None);
     }
 }
@@ -0,0 +1,121 @@
+use super::{arch::ArchAarch64, unwind_rule::UnwindRuleAarch64, unwindregs::UnwindRegsAarch64};
+use crate::add_signed::checked_add_signed;
+use crate::sframe::{SFrameAbi, SFrameCfaBase, SFrameRow, SFrameUnwinderError, SFrameUnwinding};
+use crate::unwind_result::UnwindResult;
+
+impl SFrameUnwinding for ArchAarch64 {
+    fn rule_for_row(
+        row: &SFrameRow,
+        abi: SFrameAbi,
+    ) -> Result<Option<UnwindRuleAarch64>, SFrameUnwinderError> {
+        if abi == SFrameAbi::Amd64Le {
+            return Err(SFrameUnwinderError::UnsupportedAbi(abi as u8));
+        }
+        // Signed return addresses don't need special treatment here, the pointer
+        // authentication bits are stripped from all return addresses using the lr mask.
+        Ok(translate_into_unwind_rule(row))
+    }
+
+    fn unwind_frame<F>(
+        row: SFrameRow,
+        abi: SFrameAbi,
+        regs: &mut UnwindRegsAarch64,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleAarch64>, SFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        if let Some(unwind_rule) = Self::rule_for_row(&row, abi)? {
+            return Ok(UnwindResult::ExecRule(unwind_rule));
+        }
+
+        let lr = regs.lr();
+        let sp = regs.sp();
+        let fp = regs.fp();
+
+        let cfa_base = match row.cfa_base {
+            SFrameCfaBase::Sp => sp,
+            SFrameCfaBase::Fp => fp,
+        };
+        let cfa = checked_add_signed(cfa_base, i64::from(row.cfa_offset))
+            .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+        if !is_first_frame {
+            if cfa <= sp {
+                return Err(SFrameUnwinderError::StackPointerMovedBackwards);
+            }
+            if row.ra_offset.is_none() {
+                // The return address is still in lr, which is only valid for the first frame.
+                return Err(SFrameUnwinderError::DidNotAdvance);
+            }
+        }
+        let mut read_at_cfa_offset = |offset: i32| {
+            let location = checked_add_signed(cfa, i64::from(offset))
+                .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+            read_stack(location).map_err(|_| SFrameUnwinderError::CouldNotReadStack(location))
+        };
+        let new_fp = match row.fp_offset {
+            Some(offset) => read_at_cfa_offset(offset)?,
+            None => fp,
+        };
+        let new_lr = match row.ra_offset {
+            Some(offset) => read_at_cfa_offset(offset)?,
+            None => lr,
+        };
+        let new_lr = regs.lr_mask().strip_ptr_auth(new_lr);
+
+        regs.set_fp(new_fp);
+        regs.set_sp(cfa);
+        regs.set_lr(new_lr);
+
+        Ok(UnwindResult::Uncacheable(new_lr))
+    }
+}
+
+fn translate_into_unwind_rule(row: &SFrameRow) -> Option<UnwindRuleAarch64> {
+    let by_8 = |offset: i32| {
+        if offset % 8 != 0 {
+            return None;
+        }
+        i16::try_from(offset / 8).ok()
+    };
+    match row.cfa_base {
+        SFrameCfaBase::Sp => {
+            if row.cfa_offset % 16 != 0 {
+                return None;
+            }
+            let sp_offset_by_16 = u16::try_from(row.cfa_offset / 16).ok()?;
+            match (row.ra_offset, row.fp_offset) {
+                (None, None) => Some(UnwindRuleAarch64::OffsetSp { sp_offset_by_16 }),
+                (None, Some(_)) => None,
+                (Some(lr_cfa_offset), None) => Some(UnwindRuleAarch64::OffsetSpAndRestoreLr {
+                    sp_offset_by_16,
+                    lr_storage_offset_from_sp_by_8: by_8(
+                        row.cfa_offset.checked_add(lr_cfa_offset)?,
+                    )?,
+                }),
+                (Some(lr_cfa_offset), Some(fp_cfa_offset)) => {
+                    UnwindRuleAarch64::offset_sp_and_restore_fp_and_lr(
+                        sp_offset_by_16,
+                        by_8(row.cfa_offset.checked_add(fp_cfa_offset)?)?,
+                        by_8(row.cfa_offset.checked_add(lr_cfa_offset)?)?,
+                    )
+                }
+            }
+        }
+        SFrameCfaBase::Fp => {
+            let lr_cfa_offset = row.ra_offset?;
+            let fp_cfa_offset = row.fp_offset?;
+            if row.cfa_offset == 16 && fp_cfa_offset == -16 && lr_cfa_offset == -8 {
+                Some(UnwindRuleAarch64::UseFramePointer)
+            } else {
+                let sp_offset_from_fp_by_8 = u16::try_from(by_8(row.cfa_offset)?).ok()?;
+                UnwindRuleAarch64::use_frame_pointer_with_offsets(
+                    sp_offset_from_fp_by_8,
+                    by_8(row.cfa_offset.checked_add(fp_cfa_offset)?)?,
+                    by_8(row.cfa_offset.checked_add(lr_cfa_offset)?)?,
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::{arch::ArchAarch64, unwind_rule::UnwindRuleAarch64, unwindregs::UnwindRegsAarch64};
+use crate::signal_frame::{read_at_offset, SignalFrameUnwinderError, SignalFrameUnwinding};
+use crate::unwind_result::UnwindResult;
+
+/// The offset of `uc` in `struct rt_sigframe`, after the 128 byte `siginfo_t`.
+const RT_SIGFRAME_UCONTEXT: u64 = 128;
+/// The offset of `uc_mcontext` in `ucontext_t`. `uc_sigmask` is padded to 128 bytes and
+/// `uc_mcontext` is 16-byte aligned.
+const UCONTEXT_MCONTEXT: u64 = 176;
+/// Offsets of the registers we need in `struct sigcontext`, after `fault_address`.
+const SIGCONTEXT_X29: u64 = 8 + 29 * 8;
+const SIGCONTEXT_X30: u64 = 8 + 30 * 8;
+const SIGCONTEXT_SP: u64 = 8 + 31 * 8;
+const SIGCONTEXT_PC: u64 = 8 + 32 * 8;
+
+impl SignalFrameUnwinding for ArchAarch64 {
+    /// `mov x8, #139; svc #0`
+    const LINUX_SIGRETURN_TRAMPOLINE: &'static [u8] =
+        &[0x68, 0x11, 0x80, 0xd2, 0x01, 0x00, 0x00, 0xd4];
+    const LINUX_SIGRETURN_SYSCALL_OFFSET: usize = 4;
+
+    fn unwind_linux_signal_frame<F>(
+        regs: &mut UnwindRegsAarch64,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleAarch64>, SignalFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        // sp points at the `struct rt_sigframe`.
+        let sp = regs.sp();
+        let mcontext = RT_SIGFRAME_UCONTEXT + UCONTEXT_MCONTEXT;
+        let new_fp = read_at_offset(sp, mcontext + SIGCONTEXT_X29, read_stack)?;
+        let new_lr = read_at_offset(sp, mcontext + SIGCONTEXT_X30, read_stack)?;
+        let new_sp = read_at_offset(sp, mcontext + SIGCONTEXT_SP, read_stack)?;
+        let new_pc = read_at_offset(sp, mcontext + SIGCONTEXT_PC, read_stack)?;
+        if new_sp == sp && new_pc == regs.lr() {
+            return Err(SignalFrameUnwinderError::DidNotAdvance);
+        }
+
+        // The interrupted lr can be signed if it was spilled by the interrupted function's
+        // prologue but not yet overwritten.
+        regs.set_lr(regs.lr_mask().strip_ptr_auth(new_lr));
+        regs.set_sp(new_sp);
+        regs.set_fp(new_fp);
+
+        Ok(UnwindResult::InstructionPointer(new_pc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aarch64::PtrAuthMask;
+    use crate::signal_frame::is_linux_sigreturn_trampoline;
+
+    #[test]
+    fn test_trampoline() {
+        // __kernel_rt_sigreturn in the vDSO
+        let text = [
+            0x1f, 0x20, 0x03, 0xd5, 0x68, 0x11, 0x80, 0xd2, 0x01, 0x00, 0x00, 0xd4,
+        ];
+        let is_trampoline = |offset| is_linux_sigreturn_trampoline::<ArchAarch64>(&text, offset);
+        assert!(!is_trampoline(0));
+        assert!(is_trampoline(4));
+        assert!(is_trampoline(8));
+        assert!(!is_trampoline(12));
+    }
+
+    #[test]
+    fn test_unwind_signal_frame() {
+        let mut stack = [0u64; 128];
+        let regs_index = (304 + 8) / 8;
+        stack[regs_index + 29] = 0x1230; // x29
+        stack[regs_index + 30] = 0xffff_0000_0040_2000; // x30, signed
+        stack[regs_index + 31] = 0x8000; // sp
+        stack[regs_index + 32] = 0x401000; // pc
+        let mut read_stack = |addr: u64| stack.get((addr / 8) as usize).copied().ok_or(());
+        let mut regs = UnwindRegsAarch64::new_with_ptr_auth_mask(
+            PtrAuthMask::from_max_known_address(0x80_0000_0000),
+            0x7777,
+            0x0,
+            0x50,
+        );
+        let res = ArchAarch64::unwind_linux_signal_frame(&mut regs, &mut read_stack);
+        assert_eq!(res, Ok(UnwindResult::InstructionPointer(0x401000)));
+        assert_eq!(regs.lr(), 0x402000);
+        assert_eq!(regs.sp(), 0x8000);
+        assert_eq!(regs.fp(), 0x1230);
+    }
+}
@@ -2,8 +2,13 @@ use super::unwindregs::UnwindRegsAarch64;
 use crate::add_signed::checked_add_signed;
 use crate::error::Error;
 
-use crate::unwind_rule::UnwindRule;
+use crate::unwind_rule::{pack_rule, unpack_rule, UnwindRule};
 
+/// An unwind rule for aarch64.
+///
+/// The rule fits in 6 bytes, so that a cache entry fits in 16. To get there, rules which
+/// restore both fp and lr store the location of lr relative to the location of fp. The two
+/// are almost always saved next to each other, with a single `stp`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UnwindRuleAarch64 {
     /// (sp, fp, lr) = (sp, fp, lr)
@@ -29,22 +34,83 @@ pub enum UnwindRuleAarch64 {
         sp_offset_by_16: u16,
         lr_storage_offset_from_sp_by_8: i16,
     },
-    /// (sp, fp, lr) = (sp + 16x, *(sp + 8y), *(sp + 8z))
+    /// (sp, fp, lr) = (sp + 16x, *(sp + 8y), *(sp + 8y + 8z))
+    /// Use [`offset_sp_and_restore_fp_and_lr`](Self::offset_sp_and_restore_fp_and_lr)
+    /// to create it from the sp-relative location of lr.
     OffsetSpAndRestoreFpAndLr {
         sp_offset_by_16: u16,
         fp_storage_offset_from_sp_by_8: i16,
-        lr_storage_offset_from_sp_by_8: i16,
+        lr_storage_offset_from_fp_storage_by_8: i8,
     },
     /// (sp, fp, lr) = (fp + 16, *fp, *(fp + 8))
     UseFramePointer,
-    /// (sp, fp, lr) = (fp + 8x, *(fp + 8y), *(fp + 8z))
+    /// (sp, fp, lr) = (fp + 8x, *(fp + 8y), *(fp + 8y + 8z))
+    /// Use [`use_frame_pointer_with_offsets`](Self::use_frame_pointer_with_offsets) to
+    /// create it from the fp-relative location of lr.
     UseFramepointerWithOffsets {
         sp_offset_from_fp_by_8: u16,
         fp_storage_offset_from_fp_by_8: i16,
-        lr_storage_offset_from_fp_by_8: i16,
+        lr_storage_offset_from_fp_storage_by_8: i8,
     },
 }
 
+impl UnwindRuleAarch64 {
+    /// Creates an [`OffsetSpAndRestoreFpAndLr`](Self::OffsetSpAndRestoreFpAndLr) rule.
+    /// Returns `None` if lr is stored too far away from fp.
+    pub fn offset_sp_and_restore_fp_and_lr(
+        sp_offset_by_16: u16,
+        fp_storage_offset_from_sp_by_8: i16,
+        lr_storage_offset_from_sp_by_8: i16,
+    ) -> Option<Self> {
+        Some(UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+            sp_offset_by_16,
+            fp_storage_offset_from_sp_by_8,
+            lr_storage_offset_from_fp_storage_by_8: storage_distance(
+                fp_storage_offset_from_sp_by_8,
+                lr_storage_offset_from_sp_by_8,
+            )?,
+        })
+    }
+
+    /// Creates a [`UseFramepointerWithOffsets`](Self::UseFramepointerWithOffsets) rule.
+    /// Returns `None` if lr is stored too far away from fp.
+    pub fn use_frame_pointer_with_offsets(
+        sp_offset_from_fp_by_8: u16,
+        fp_storage_offset_from_fp_by_8: i16,
+        lr_storage_offset_from_fp_by_8: i16,
+    ) -> Option<Self> {
+        Some(UnwindRuleAarch64::UseFramepointerWithOffsets {
+            sp_offset_from_fp_by_8,
+            fp_storage_offset_from_fp_by_8,
+            lr_storage_offset_from_fp_storage_by_8: storage_distance(
+                fp_storage_offset_from_fp_by_8,
+                lr_storage_offset_from_fp_by_8,
+            )?,
+        })
+    }
+}
+
+fn storage_distance(fp_storage_offset_by_8: i16, lr_storage_offset_by_8: i16) -> Option<i8> {
+    i8::try_from(i32::from(lr_storage_offset_by_8) - i32::from(fp_storage_offset_by_8)).ok()
+}
+
+/// Packs the fp and lr storage offsets into two fields. The lr field holds the offset of
+/// lr from the same base as fp, wrapping around if it does not fit into an `i16`, so that
+/// the packed representation does not depend on how the rule stores it.
+fn pack_fp_and_lr(
+    fp_storage_offset_by_8: i16,
+    lr_storage_offset_from_fp_storage_by_8: i8,
+) -> [u16; 2] {
+    let lr_storage_offset_by_8 =
+        fp_storage_offset_by_8.wrapping_add(i16::from(lr_storage_offset_from_fp_storage_by_8));
+    [fp_storage_offset_by_8 as u16, lr_storage_offset_by_8 as u16]
+}
+
+fn unpack_fp_and_lr(fp: u16, lr: u16) -> Option<(i16, i8)> {
+    let distance = i8::try_from(lr.wrapping_sub(fp) as i16).ok()?;
+    Some((fp as i16, distance))
+}
+
 impl UnwindRule for UnwindRuleAarch64 {
     type UnwindRegs = UnwindRegsAarch64;
 
@@ -58,6 +124,84 @@ impl UnwindRule for UnwindRuleAarch64 {
         UnwindRuleAarch64::UseFramePointer
     }
 
+    fn pack(self) -> u64 {
+        match self {
+            UnwindRuleAarch64::NoOp => pack_rule(0, [0, 0, 0]),
+            UnwindRuleAarch64::NoOpIfFirstFrameOtherwiseFp => pack_rule(1, [0, 0, 0]),
+            UnwindRuleAarch64::OffsetSp { sp_offset_by_16 } => {
+                pack_rule(2, [sp_offset_by_16, 0, 0])
+            }
+            UnwindRuleAarch64::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_16 } => {
+                pack_rule(3, [sp_offset_by_16, 0, 0])
+            }
+            UnwindRuleAarch64::OffsetSpAndRestoreLr {
+                sp_offset_by_16,
+                lr_storage_offset_from_sp_by_8,
+            } => pack_rule(
+                4,
+                [sp_offset_by_16, lr_storage_offset_from_sp_by_8 as u16, 0],
+            ),
+            UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                sp_offset_by_16,
+                fp_storage_offset_from_sp_by_8,
+                lr_storage_offset_from_fp_storage_by_8,
+            } => {
+                let [fp, lr] = pack_fp_and_lr(
+                    fp_storage_offset_from_sp_by_8,
+                    lr_storage_offset_from_fp_storage_by_8,
+                );
+                pack_rule(5, [sp_offset_by_16, fp, lr])
+            }
+            UnwindRuleAarch64::UseFramePointer => pack_rule(6, [0, 0, 0]),
+            UnwindRuleAarch64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8,
+                fp_storage_offset_from_fp_by_8,
+                lr_storage_offset_from_fp_storage_by_8,
+            } => {
+                let [fp, lr] = pack_fp_and_lr(
+                    fp_storage_offset_from_fp_by_8,
+                    lr_storage_offset_from_fp_storage_by_8,
+                );
+                pack_rule(7, [sp_offset_from_fp_by_8, fp, lr])
+            }
+        }
+    }
+
+    fn unpack(bits: u64) -> Option<Self> {
+        let (tag, [a, b, c]) = unpack_rule(bits);
+        let rule = match tag {
+            0 => UnwindRuleAarch64::NoOp,
+            1 => UnwindRuleAarch64::NoOpIfFirstFrameOtherwiseFp,
+            2 => UnwindRuleAarch64::OffsetSp { sp_offset_by_16: a },
+            3 => {
+                UnwindRuleAarch64::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_16: a }
+            }
+            4 => UnwindRuleAarch64::OffsetSpAndRestoreLr {
+                sp_offset_by_16: a,
+                lr_storage_offset_from_sp_by_8: b as i16,
+            },
+            5 => {
+                let (fp, lr) = unpack_fp_and_lr(b, c)?;
+                UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                    sp_offset_by_16: a,
+                    fp_storage_offset_from_sp_by_8: fp,
+                    lr_storage_offset_from_fp_storage_by_8: lr,
+                }
+            }
+            6 => UnwindRuleAarch64::UseFramePointer,
+            7 => {
+                let (fp, lr) = unpack_fp_and_lr(b, c)?;
+                UnwindRuleAarch64::UseFramepointerWithOffsets {
+                    sp_offset_from_fp_by_8: a,
+                    fp_storage_offset_from_fp_by_8: fp,
+                    lr_storage_offset_from_fp_storage_by_8: lr,
+                }
+            }
+            _ => return None,
+        };
+        Some(rule)
+    }
+
     fn exec<F>(
         self,
         is_first_frame: bool,
@@ -125,18 +269,18 @@ impl UnwindRule for UnwindRuleAarch64 {
             UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
                 sp_offset_by_16,
                 fp_storage_offset_from_sp_by_8,
-                lr_storage_offset_from_sp_by_8,
+                lr_storage_offset_from_fp_storage_by_8,
             } => {
                 let sp_offset = u64::from(sp_offset_by_16) * 16;
                 let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
-                let lr_storage_offset = i64::from(lr_storage_offset_from_sp_by_8) * 8;
-                let lr_location =
-                    checked_add_signed(sp, lr_storage_offset).ok_or(Error::IntegerOverflow)?;
-                let new_lr =
-                    read_stack(lr_location).map_err(|_| Error::CouldNotReadStack(lr_location))?;
                 let fp_storage_offset = i64::from(fp_storage_offset_from_sp_by_8) * 8;
                 let fp_location =
                     checked_add_signed(sp, fp_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let lr_storage_offset = i64::from(lr_storage_offset_from_fp_storage_by_8) * 8;
+                let lr_location = checked_add_signed(fp_location, lr_storage_offset)
+                    .ok_or(Error::IntegerOverflow)?;
+                let new_lr =
+                    read_stack(lr_location).map_err(|_| Error::CouldNotReadStack(lr_location))?;
                 let new_fp =
                     read_stack(fp_location).map_err(|_| Error::CouldNotReadStack(fp_location))?;
                 (new_lr, new_sp, new_fp)
@@ -195,20 +339,20 @@ impl UnwindRule for UnwindRuleAarch64 {
             UnwindRuleAarch64::UseFramepointerWithOffsets {
                 sp_offset_from_fp_by_8,
                 fp_storage_offset_from_fp_by_8,
-                lr_storage_offset_from_fp_by_8,
+                lr_storage_offset_from_fp_storage_by_8,
             } => {
                 let sp_offset_from_fp = u64::from(sp_offset_from_fp_by_8) * 8;
                 let new_sp = fp
                     .checked_add(sp_offset_from_fp)
                     .ok_or(Error::IntegerOverflow)?;
-                let lr_storage_offset = i64::from(lr_storage_offset_from_fp_by_8) * 8;
-                let lr_location =
-                    checked_add_signed(fp, lr_storage_offset).ok_or(Error::IntegerOverflow)?;
-                let new_lr =
-                    read_stack(lr_location).map_err(|_| Error::CouldNotReadStack(lr_location))?;
                 let fp_storage_offset = i64::from(fp_storage_offset_from_fp_by_8) * 8;
                 let fp_location =
                     checked_add_signed(fp, fp_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let lr_storage_offset = i64::from(lr_storage_offset_from_fp_storage_by_8) * 8;
+                let lr_location = checked_add_signed(fp_location, lr_storage_offset)
+                    .ok_or(Error::IntegerOverflow)?;
+                let new_lr =
+                    read_stack(lr_location).map_err(|_| Error::CouldNotReadStack(lr_location))?;
                 let new_fp =
                     read_stack(fp_location).map_err(|_| Error::CouldNotReadStack(fp_location))?;
 
@@ -1,8 +1,8 @@
 use core::ops::Deref;
 
 use crate::{
-    unwinder::UnwinderInternal, AllocationPolicy, Error, FrameAddress, MayAllocateDuringUnwind,
-    Module, Unwinder,
+    unwinder::UnwinderInternal, AllocationPolicy, Error, FrameAddress, FrameDiagnostics,
+    MayAllocateDuringUnwind, Module, ModuleAddress, ReadStack, UnwindMethod, Unwinder,
 };
 
 use super::{ArchAarch64, CacheAarch64, UnwindRegsAarch64};
@@ -51,6 +51,14 @@ impl<D: Deref<Target = [u8]>, P: AllocationPolicy> Unwinder for UnwinderAarch64<
         self.0.max_known_code_address()
     }
 
+    fn module_for_address(&self, address: u64) -> Option<ModuleAddress<'_, Module<D>>> {
+        self.0.module_for_address(address)
+    }
+
+    fn modules(&self) -> core::slice::Iter<'_, Module<D>> {
+        self.0.modules()
+    }
+
     fn unwind_frame<F>(
         &self,
         address: FrameAddress,
@@ -59,8 +67,36 @@ impl<D: Deref<Target = [u8]>, P: AllocationPolicy> Unwinder for UnwinderAarch64<
         read_stack: &mut F,
     ) -> Result<Option<u64>, Error>
     where
-        F: FnMut(u64) -> Result<u64, ()>,
+        F: ReadStack,
     {
         self.0.unwind_frame(address, regs, &mut cache.0, read_stack)
     }
+
+    fn unwind_frame_with_method<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsAarch64,
+        cache: &mut CacheAarch64<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, UnwindMethod)
+    where
+        F: ReadStack,
+    {
+        self.0
+            .unwind_frame_with_method(address, regs, &mut cache.0, read_stack)
+    }
+
+    fn unwind_frame_with_diagnostics<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsAarch64,
+        cache: &mut CacheAarch64<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, FrameDiagnostics)
+    where
+        F: ReadStack,
+    {
+        self.0
+            .unwind_frame_with_diagnostics(address, regs, &mut cache.0, read_stack)
+    }
 }
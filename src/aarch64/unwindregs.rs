@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 
 use crate::display_utils::HexNum;
+use crate::frame_record::FrameRegs;
 
 /// The registers used for unwinding on Aarch64. We only need lr (x30), sp (x31),
 /// and fp (x29).
@@ -136,6 +137,16 @@ impl UnwindRegsAarch64 {
     }
 }
 
+impl FrameRegs for UnwindRegsAarch64 {
+    fn stack_pointer(&self) -> u64 {
+        self.sp()
+    }
+
+    fn frame_pointer(&self) -> u64 {
+        self.fp()
+    }
+}
+
 impl Debug for UnwindRegsAarch64 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("UnwindRegsAarch64")
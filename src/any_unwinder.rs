@@ -0,0 +1,373 @@
+use core::ops::Deref;
+
+use crate::aarch64::{CacheAarch64, UnwindRegsAarch64, UnwinderAarch64};
+use crate::arm::{CacheArm, UnwindRegsArm, UnwinderArm};
+use crate::riscv64::{CacheRiscv64, UnwindRegsRiscv64, UnwinderRiscv64};
+use crate::x86::{CacheX86, UnwindRegsX86, UnwinderX86};
+use crate::x86_64::{CacheX86_64, UnwindRegsX86_64, UnwinderX86_64};
+use crate::{
+    AllocationPolicy, CacheConfig, CacheStats, Error, FrameAddress, FrameDiagnostics, FrameRegs,
+    MayAllocateDuringUnwind, Module, ModuleAddress, ReadStack, UnwindMethod, UnwindSource,
+    Unwinder,
+};
+
+/// A CPU architecture, for picking the architecture of an [`AnyUnwinder`] at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CpuArch {
+    /// 64-bit x86, also known as amd64. See [`UnwinderX86_64`].
+    X86_64,
+    /// 64-bit ARM, also known as arm64. See [`UnwinderAarch64`].
+    Aarch64,
+    /// 32-bit ARM. See [`UnwinderArm`].
+    Arm,
+    /// 32-bit x86, also known as i386 or i686. See [`UnwinderX86`].
+    X86,
+    /// 64-bit RISC-V. See [`UnwinderRiscv64`].
+    Riscv64,
+}
+
+impl CpuArch {
+    /// The size of a stack word, in bytes.
+    pub(crate) const fn word_size(self) -> u8 {
+        match self {
+            CpuArch::X86_64 | CpuArch::Aarch64 | CpuArch::Riscv64 => 8,
+            CpuArch::Arm | CpuArch::X86 => 4,
+        }
+    }
+}
+
+/// An unwinder whose CPU architecture is picked at runtime. It dispatches to the
+/// unwinder for that architecture. Use the [`Unwinder`] trait for unwinding.
+///
+/// The unwind registers and the cache which are passed to it need to be for the same
+/// architecture, otherwise unwinding fails with [`Error::ArchitectureMismatch`].
+///
+/// Type arguments:
+///
+///  - `D`: The type for unwind section data in the modules. See [`Module`].
+/// -  `P`: The [`AllocationPolicy`].
+pub enum AnyUnwinder<D, P = MayAllocateDuringUnwind> {
+    X86_64(UnwinderX86_64<D, P>),
+    Aarch64(UnwinderAarch64<D, P>),
+    Arm(UnwinderArm<D, P>),
+    X86(UnwinderX86<D, P>),
+    Riscv64(UnwinderRiscv64<D, P>),
+}
+
+impl<D, P> Clone for AnyUnwinder<D, P> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::X86_64(unwinder) => Self::X86_64(unwinder.clone()),
+            Self::Aarch64(unwinder) => Self::Aarch64(unwinder.clone()),
+            Self::Arm(unwinder) => Self::Arm(unwinder.clone()),
+            Self::X86(unwinder) => Self::X86(unwinder.clone()),
+            Self::Riscv64(unwinder) => Self::Riscv64(unwinder.clone()),
+        }
+    }
+}
+
+impl<D, P> AnyUnwinder<D, P> {
+    /// Create an unwinder for a process with the given CPU architecture.
+    pub fn new(arch: CpuArch) -> Self {
+        match arch {
+            CpuArch::X86_64 => Self::X86_64(UnwinderX86_64::new()),
+            CpuArch::Aarch64 => Self::Aarch64(UnwinderAarch64::new()),
+            CpuArch::Arm => Self::Arm(UnwinderArm::new()),
+            CpuArch::X86 => Self::X86(UnwinderX86::new()),
+            CpuArch::Riscv64 => Self::Riscv64(UnwinderRiscv64::new()),
+        }
+    }
+
+    /// The CPU architecture of this unwinder.
+    pub fn arch(&self) -> CpuArch {
+        match self {
+            Self::X86_64(_) => CpuArch::X86_64,
+            Self::Aarch64(_) => CpuArch::Aarch64,
+            Self::Arm(_) => CpuArch::Arm,
+            Self::X86(_) => CpuArch::X86,
+            Self::Riscv64(_) => CpuArch::Riscv64,
+        }
+    }
+}
+
+impl<D: Deref<Target = [u8]>, P: AllocationPolicy> Unwinder for AnyUnwinder<D, P> {
+    type UnwindRegs = AnyUnwindRegs;
+    type Cache = AnyCache<P>;
+    type Module = Module<D>;
+
+    fn add_module(&mut self, module: Module<D>) {
+        match self {
+            Self::X86_64(unwinder) => unwinder.add_module(module),
+            Self::Aarch64(unwinder) => unwinder.add_module(module),
+            Self::Arm(unwinder) => unwinder.add_module(module),
+            Self::X86(unwinder) => unwinder.add_module(module),
+            Self::Riscv64(unwinder) => unwinder.add_module(module),
+        }
+    }
+
+    fn remove_module(&mut self, module_avma_range_start: u64) {
+        match self {
+            Self::X86_64(unwinder) => unwinder.remove_module(module_avma_range_start),
+            Self::Aarch64(unwinder) => unwinder.remove_module(module_avma_range_start),
+            Self::Arm(unwinder) => unwinder.remove_module(module_avma_range_start),
+            Self::X86(unwinder) => unwinder.remove_module(module_avma_range_start),
+            Self::Riscv64(unwinder) => unwinder.remove_module(module_avma_range_start),
+        }
+    }
+
+    fn max_known_code_address(&self) -> u64 {
+        match self {
+            Self::X86_64(unwinder) => unwinder.max_known_code_address(),
+            Self::Aarch64(unwinder) => unwinder.max_known_code_address(),
+            Self::Arm(unwinder) => unwinder.max_known_code_address(),
+            Self::X86(unwinder) => unwinder.max_known_code_address(),
+            Self::Riscv64(unwinder) => unwinder.max_known_code_address(),
+        }
+    }
+
+    fn module_for_address(&self, address: u64) -> Option<ModuleAddress<'_, Module<D>>> {
+        match self {
+            Self::X86_64(unwinder) => unwinder.module_for_address(address),
+            Self::Aarch64(unwinder) => unwinder.module_for_address(address),
+            Self::Arm(unwinder) => unwinder.module_for_address(address),
+            Self::X86(unwinder) => unwinder.module_for_address(address),
+            Self::Riscv64(unwinder) => unwinder.module_for_address(address),
+        }
+    }
+
+    fn modules(&self) -> core::slice::Iter<'_, Module<D>> {
+        match self {
+            Self::X86_64(unwinder) => unwinder.modules(),
+            Self::Aarch64(unwinder) => unwinder.modules(),
+            Self::Arm(unwinder) => unwinder.modules(),
+            Self::X86(unwinder) => unwinder.modules(),
+            Self::Riscv64(unwinder) => unwinder.modules(),
+        }
+    }
+
+    fn unwind_frame<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut AnyUnwindRegs,
+        cache: &mut AnyCache<P>,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, Error>
+    where
+        F: ReadStack,
+    {
+        self.unwind_frame_with_method(address, regs, cache, read_stack)
+            .0
+    }
+
+    fn unwind_frame_with_method<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut AnyUnwindRegs,
+        cache: &mut AnyCache<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, UnwindMethod)
+    where
+        F: ReadStack,
+    {
+        match (self, regs, cache) {
+            (Self::X86_64(u), AnyUnwindRegs::X86_64(r), AnyCache::X86_64(c)) => {
+                u.unwind_frame_with_method(address, r, c, read_stack)
+            }
+            (Self::Aarch64(u), AnyUnwindRegs::Aarch64(r), AnyCache::Aarch64(c)) => {
+                u.unwind_frame_with_method(address, r, c, read_stack)
+            }
+            (Self::Arm(u), AnyUnwindRegs::Arm(r), AnyCache::Arm(c)) => {
+                u.unwind_frame_with_method(address, r, c, read_stack)
+            }
+            (Self::X86(u), AnyUnwindRegs::X86(r), AnyCache::X86(c)) => {
+                u.unwind_frame_with_method(address, r, c, read_stack)
+            }
+            (Self::Riscv64(u), AnyUnwindRegs::Riscv64(r), AnyCache::Riscv64(c)) => {
+                u.unwind_frame_with_method(address, r, c, read_stack)
+            }
+            _ => (
+                Err(Error::ArchitectureMismatch),
+                UnwindMethod::Uncached(UnwindSource::ArchitectureMismatch),
+            ),
+        }
+    }
+
+    fn unwind_frame_with_diagnostics<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut AnyUnwindRegs,
+        cache: &mut AnyCache<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, FrameDiagnostics)
+    where
+        F: ReadStack,
+    {
+        match (self, regs, cache) {
+            (Self::X86_64(u), AnyUnwindRegs::X86_64(r), AnyCache::X86_64(c)) => {
+                u.unwind_frame_with_diagnostics(address, r, c, read_stack)
+            }
+            (Self::Aarch64(u), AnyUnwindRegs::Aarch64(r), AnyCache::Aarch64(c)) => {
+                u.unwind_frame_with_diagnostics(address, r, c, read_stack)
+            }
+            (Self::Arm(u), AnyUnwindRegs::Arm(r), AnyCache::Arm(c)) => {
+                u.unwind_frame_with_diagnostics(address, r, c, read_stack)
+            }
+            (Self::X86(u), AnyUnwindRegs::X86(r), AnyCache::X86(c)) => {
+                u.unwind_frame_with_diagnostics(address, r, c, read_stack)
+            }
+            (Self::Riscv64(u), AnyUnwindRegs::Riscv64(r), AnyCache::Riscv64(c)) => {
+                u.unwind_frame_with_diagnostics(address, r, c, read_stack)
+            }
+            _ => (
+                Err(Error::ArchitectureMismatch),
+                FrameDiagnostics::with_source(UnwindSource::ArchitectureMismatch),
+            ),
+        }
+    }
+}
+
+/// The unwinder cache type for [`AnyUnwinder`]. It must have the same CPU architecture
+/// as the unwinder.
+pub enum AnyCache<P: AllocationPolicy = MayAllocateDuringUnwind> {
+    X86_64(CacheX86_64<P>),
+    Aarch64(CacheAarch64<P>),
+    Arm(CacheArm<P>),
+    X86(CacheX86<P>),
+    Riscv64(CacheRiscv64<P>),
+}
+
+impl AnyCache<MayAllocateDuringUnwind> {
+    /// Create a new cache for the given CPU architecture.
+    pub fn new(arch: CpuArch) -> Self {
+        Self::new_in(arch)
+    }
+
+    /// Create a new cache for the given CPU architecture, whose rule cache has the given
+    /// size and associativity.
+    pub fn with_config(arch: CpuArch, config: CacheConfig) -> Self {
+        Self::with_config_in(arch, config)
+    }
+}
+
+impl<P: AllocationPolicy> AnyCache<P> {
+    /// Create a new cache for the given CPU architecture.
+    pub fn new_in(arch: CpuArch) -> Self {
+        match arch {
+            CpuArch::X86_64 => Self::X86_64(CacheX86_64::new_in()),
+            CpuArch::Aarch64 => Self::Aarch64(CacheAarch64::new_in()),
+            CpuArch::Arm => Self::Arm(CacheArm::new_in()),
+            CpuArch::X86 => Self::X86(CacheX86::new_in()),
+            CpuArch::Riscv64 => Self::Riscv64(CacheRiscv64::new_in()),
+        }
+    }
+
+    /// Create a new cache for the given CPU architecture, whose rule cache has the given
+    /// size and associativity.
+    pub fn with_config_in(arch: CpuArch, config: CacheConfig) -> Self {
+        match arch {
+            CpuArch::X86_64 => Self::X86_64(CacheX86_64::with_config_in(config)),
+            CpuArch::Aarch64 => Self::Aarch64(CacheAarch64::with_config_in(config)),
+            CpuArch::Arm => Self::Arm(CacheArm::with_config_in(config)),
+            CpuArch::X86 => Self::X86(CacheX86::with_config_in(config)),
+            CpuArch::Riscv64 => Self::Riscv64(CacheRiscv64::with_config_in(config)),
+        }
+    }
+
+    /// The CPU architecture of this cache.
+    pub fn arch(&self) -> CpuArch {
+        match self {
+            Self::X86_64(_) => CpuArch::X86_64,
+            Self::Aarch64(_) => CpuArch::Aarch64,
+            Self::Arm(_) => CpuArch::Arm,
+            Self::X86(_) => CpuArch::X86,
+            Self::Riscv64(_) => CpuArch::Riscv64,
+        }
+    }
+
+    /// Returns a snapshot of the cache usage statistics.
+    pub fn stats(&self) -> CacheStats {
+        match self {
+            Self::X86_64(cache) => cache.stats(),
+            Self::Aarch64(cache) => cache.stats(),
+            Self::Arm(cache) => cache.stats(),
+            Self::X86(cache) => cache.stats(),
+            Self::Riscv64(cache) => cache.stats(),
+        }
+    }
+}
+
+/// The unwind registers type for [`AnyUnwinder`]. It must have the same CPU
+/// architecture as the unwinder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnyUnwindRegs {
+    X86_64(UnwindRegsX86_64),
+    Aarch64(UnwindRegsAarch64),
+    Arm(UnwindRegsArm),
+    X86(UnwindRegsX86),
+    Riscv64(UnwindRegsRiscv64),
+}
+
+impl AnyUnwindRegs {
+    /// The CPU architecture of these registers.
+    pub fn arch(&self) -> CpuArch {
+        match self {
+            Self::X86_64(_) => CpuArch::X86_64,
+            Self::Aarch64(_) => CpuArch::Aarch64,
+            Self::Arm(_) => CpuArch::Arm,
+            Self::X86(_) => CpuArch::X86,
+            Self::Riscv64(_) => CpuArch::Riscv64,
+        }
+    }
+}
+
+impl FrameRegs for AnyUnwindRegs {
+    fn stack_pointer(&self) -> u64 {
+        match self {
+            Self::X86_64(regs) => regs.stack_pointer(),
+            Self::Aarch64(regs) => regs.stack_pointer(),
+            Self::Arm(regs) => regs.stack_pointer(),
+            Self::X86(regs) => regs.stack_pointer(),
+            Self::Riscv64(regs) => regs.stack_pointer(),
+        }
+    }
+
+    fn frame_pointer(&self) -> u64 {
+        match self {
+            Self::X86_64(regs) => regs.frame_pointer(),
+            Self::Aarch64(regs) => regs.frame_pointer(),
+            Self::Arm(regs) => regs.frame_pointer(),
+            Self::X86(regs) => regs.frame_pointer(),
+            Self::Riscv64(regs) => regs.frame_pointer(),
+        }
+    }
+}
+
+impl From<UnwindRegsX86_64> for AnyUnwindRegs {
+    fn from(regs: UnwindRegsX86_64) -> Self {
+        Self::X86_64(regs)
+    }
+}
+
+impl From<UnwindRegsAarch64> for AnyUnwindRegs {
+    fn from(regs: UnwindRegsAarch64) -> Self {
+        Self::Aarch64(regs)
+    }
+}
+
+impl From<UnwindRegsArm> for AnyUnwindRegs {
+    fn from(regs: UnwindRegsArm) -> Self {
+        Self::Arm(regs)
+    }
+}
+
+impl From<UnwindRegsX86> for AnyUnwindRegs {
+    fn from(regs: UnwindRegsX86) -> Self {
+        Self::X86(regs)
+    }
+}
+
+impl From<UnwindRegsRiscv64> for AnyUnwindRegs {
+    fn from(regs: UnwindRegsRiscv64) -> Self {
+        Self::Riscv64(regs)
+    }
+}
@@ -1,6 +1,10 @@
+use crate::any_unwinder::CpuArch;
 use crate::unwind_rule::UnwindRule;
 
 pub trait Arch {
+    const CPU_ARCH: CpuArch;
+    /// The size of a stack word, in bytes.
+    const WORD_SIZE: u8 = Self::CPU_ARCH.word_size();
     type UnwindRegs;
     type UnwindRule: UnwindRule<UnwindRegs = Self::UnwindRegs>;
 }
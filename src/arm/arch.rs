@@ -0,0 +1,28 @@
+use super::unwind_rule::UnwindRuleArm;
+use super::unwindregs::UnwindRegsArm;
+use crate::any_unwinder::CpuArch;
+use crate::arch::Arch;
+use crate::breakpad::BreakpadUnwinding;
+use crate::orc::OrcUnwinding;
+use crate::sframe::SFrameUnwinding;
+use crate::signal_frame::SignalFrameUnwinding;
+
+/// The 32-bit ARM CPU architecture.
+pub struct ArchArm;
+impl Arch for ArchArm {
+    const CPU_ARCH: CpuArch = CpuArch::Arm;
+    type UnwindRule = UnwindRuleArm;
+    type UnwindRegs = UnwindRegsArm;
+}
+
+// .sframe is only emitted for x86_64 and aarch64.
+impl SFrameUnwinding for ArchArm {}
+
+// ORC is only used by the x86_64 Linux kernel.
+impl OrcUnwinding for ArchArm {}
+
+// Breakpad STACK CFI isn't supported on this architecture yet.
+impl BreakpadUnwinding for ArchArm {}
+
+// Signal frames are only recognized on x86_64 and aarch64 for now.
+impl SignalFrameUnwinding for ArchArm {}
@@ -0,0 +1,78 @@
+use super::unwind_rule::*;
+use crate::cache::*;
+
+/// The unwinder cache type for [`UnwinderArm`](super::UnwinderArm).
+pub struct CacheArm<P: AllocationPolicy = MayAllocateDuringUnwind>(pub Cache<UnwindRuleArm, P>);
+
+impl CacheArm<MayAllocateDuringUnwind> {
+    /// Create a new cache.
+    pub fn new() -> Self {
+        Self(Cache::new())
+    }
+
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared(shared: &SharedCacheArm) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
+}
+
+impl<P: AllocationPolicy> CacheArm<P> {
+    /// Create a new cache.
+    pub fn new_in() -> Self {
+        Self(Cache::new())
+    }
+
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config_in(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared_in(shared: &SharedCacheArm) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
+
+    /// Returns a snapshot of the cache usage statistics.
+    pub fn stats(&self) -> CacheStats {
+        self.0.rule_cache.stats()
+    }
+}
+
+impl<P: AllocationPolicy> Default for CacheArm<P> {
+    fn default() -> Self {
+        Self::new_in()
+    }
+}
+
+/// Unwind rule storage for [`UnwinderArm`](super::UnwinderArm) which can be shared by the
+/// caches of multiple threads, so that an unwind rule which was found on one thread is a
+/// cache hit on all other threads. Create the per-thread caches with
+/// [`CacheArm::new_shared`] or [`CacheArm::new_shared_in`].
+///
+/// Lookups and insertions are lock-free and don't allocate, so the sharing caches can be
+/// used from signal handlers. Cloning gives you another handle to the same storage.
+#[derive(Clone, Default)]
+pub struct SharedCacheArm(pub SharedCache<UnwindRuleArm>);
+
+impl SharedCacheArm {
+    /// Create a new shared cache.
+    pub fn new() -> Self {
+        Self(SharedCache::new())
+    }
+
+    /// Create a new shared cache with the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(SharedCache::with_config(config))
+    }
+
+    /// Returns a snapshot of the cache usage statistics, summed up over all caches
+    /// which share these rules.
+    pub fn stats(&self) -> CacheStats {
+        self.0.stats()
+    }
+}
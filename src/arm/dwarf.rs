@@ -0,0 +1,210 @@
+use gimli::{
+    Arm, CfaRule, Encoding, EvaluationStorage, Reader, ReaderOffset, Register, RegisterRule,
+    UnwindContextStorage, UnwindSection, UnwindTableRow,
+};
+
+use super::{arch::ArchArm, unwind_rule::UnwindRuleArm, unwindregs::UnwindRegsArm};
+
+use crate::unwind_result::UnwindResult;
+
+use crate::dwarf::{
+    eval_cfa_rule, eval_register_rule, ConversionError, DwarfUnwindRegs, DwarfUnwinderError,
+    DwarfUnwinding,
+};
+
+impl DwarfUnwindRegs for UnwindRegsArm {
+    fn get(&self, register: Register) -> Option<u64> {
+        match register {
+            Arm::R7 => Some(self.r7().into()),
+            Arm::R11 => Some(self.r11().into()),
+            Arm::SP => Some(self.sp().into()),
+            Arm::LR => Some(self.lr().into()),
+            _ => None,
+        }
+    }
+}
+
+impl DwarfUnwinding for ArchArm {
+    fn rule_for_row<O, UCS>(
+        unwind_info: &UnwindTableRow<O, UCS>,
+    ) -> Result<Self::UnwindRule, ConversionError>
+    where
+        O: ReaderOffset,
+        UCS: UnwindContextStorage<O>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let r7_rule = unwind_info.register(Arm::R7);
+        let r11_rule = unwind_info.register(Arm::R11);
+        let lr_rule = unwind_info.register(Arm::LR);
+        translate_into_unwind_rule(cfa_rule, &r7_rule, &r11_rule, &lr_rule)
+    }
+
+    fn unwind_frame<F, R, UCS, ES>(
+        section: &impl UnwindSection<R>,
+        unwind_info: &UnwindTableRow<R::Offset, UCS>,
+        encoding: Encoding,
+        regs: &mut Self::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, DwarfUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+        R: Reader,
+        UCS: UnwindContextStorage<R::Offset>,
+        ES: EvaluationStorage<R>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let r7_rule = unwind_info.register(Arm::R7);
+        let r11_rule = unwind_info.register(Arm::R11);
+        let lr_rule = unwind_info.register(Arm::LR);
+
+        let cfa = eval_cfa_rule::<R, _, ES>(section, cfa_rule, encoding, regs)
+            .ok_or(DwarfUnwinderError::CouldNotRecoverCfa)?;
+
+        let lr = u64::from(regs.lr());
+        let r7 = u64::from(regs.r7());
+        let r11 = u64::from(regs.r11());
+        let sp = u64::from(regs.sp());
+
+        let (r7, r11, lr) = if !is_first_frame {
+            if cfa <= sp {
+                return Err(DwarfUnwinderError::StackPointerMovedBackwards);
+            }
+            let r7 = eval_register_rule::<R, F, _, ES>(
+                section, r7_rule, cfa, encoding, r7, regs, read_stack,
+            )
+            .ok_or(DwarfUnwinderError::CouldNotRecoverFramePointer)?;
+            let r11 = eval_register_rule::<R, F, _, ES>(
+                section, r11_rule, cfa, encoding, r11, regs, read_stack,
+            )
+            .ok_or(DwarfUnwinderError::CouldNotRecoverFramePointer)?;
+            let lr = eval_register_rule::<R, F, _, ES>(
+                section, lr_rule, cfa, encoding, lr, regs, read_stack,
+            )
+            .ok_or(DwarfUnwinderError::CouldNotRecoverReturnAddress)?;
+            (r7, r11, lr)
+        } else {
+            // For the first frame, be more lenient when encountering errors.
+            let r7 = eval_register_rule::<R, F, _, ES>(
+                section, r7_rule, cfa, encoding, r7, regs, read_stack,
+            )
+            .unwrap_or(r7);
+            let r11 = eval_register_rule::<R, F, _, ES>(
+                section, r11_rule, cfa, encoding, r11, regs, read_stack,
+            )
+            .unwrap_or(r11);
+            let lr = eval_register_rule::<R, F, _, ES>(
+                section, lr_rule, cfa, encoding, lr, regs, read_stack,
+            )
+            .unwrap_or(lr);
+            (r7, r11, lr)
+        };
+
+        regs.set_r7(r7 as u32);
+        regs.set_r11(r11 as u32);
+        regs.set_sp(cfa as u32);
+        regs.set_lr(lr as u32);
+
+        // The lowest bit of a return address is set if the caller is Thumb code.
+        Ok(UnwindResult::Uncacheable(lr & !1))
+    }
+
+    fn rule_if_uncovered_by_fde() -> Self::UnwindRule {
+        UnwindRuleArm::NoOpIfFirstFrameOtherwiseFp
+    }
+}
+
+fn register_rule_to_cfa_offset<RO: ReaderOffset>(
+    rule: &RegisterRule<RO>,
+) -> Result<Option<i64>, ConversionError> {
+    match *rule {
+        RegisterRule::Undefined | RegisterRule::SameValue => Ok(None),
+        RegisterRule::Offset(offset) => Ok(Some(offset)),
+        _ => Err(ConversionError::RegisterNotStoredRelativeToCfa),
+    }
+}
+
+/// Converts a storage offset relative to the CFA into the representation used in
+/// [`UnwindRuleArm`], where zero means "not saved".
+fn storage_offset_by_4(
+    cfa_offset: Option<i64>,
+    err: ConversionError,
+) -> Result<i8, ConversionError> {
+    match cfa_offset {
+        None => Ok(0),
+        Some(offset) => match i8::try_from(offset / 4) {
+            Ok(offset_by_4) if offset_by_4 != 0 => Ok(offset_by_4),
+            _ => Err(err),
+        },
+    }
+}
+
+fn translate_into_unwind_rule<RO: ReaderOffset>(
+    cfa_rule: &CfaRule<RO>,
+    r7_rule: &RegisterRule<RO>,
+    r11_rule: &RegisterRule<RO>,
+    lr_rule: &RegisterRule<RO>,
+) -> Result<UnwindRuleArm, ConversionError> {
+    let (register, offset) = match cfa_rule {
+        CfaRule::RegisterAndOffset { register, offset } => (*register, *offset),
+        CfaRule::Expression(_) => return Err(ConversionError::CfaIsExpression),
+    };
+    let lr_cfa_offset = register_rule_to_cfa_offset(lr_rule)?;
+    let r7_cfa_offset = register_rule_to_cfa_offset(r7_rule)?;
+    let r11_cfa_offset = register_rule_to_cfa_offset(r11_rule)?;
+    let lr_storage_offset_from_new_sp_by_4 =
+        storage_offset_by_4(lr_cfa_offset, ConversionError::LrStorageOffsetDoesNotFit)?;
+    let r7_storage_offset_from_new_sp_by_4 =
+        storage_offset_by_4(r7_cfa_offset, ConversionError::FpStorageOffsetDoesNotFit)?;
+    let r11_storage_offset_from_new_sp_by_4 =
+        storage_offset_by_4(r11_cfa_offset, ConversionError::FpStorageOffsetDoesNotFit)?;
+
+    match register {
+        Arm::SP => {
+            let sp_offset_by_4 =
+                u16::try_from(offset / 4).map_err(|_| ConversionError::SpOffsetDoesNotFit)?;
+            match (lr_cfa_offset, r7_cfa_offset, r11_cfa_offset) {
+                (None, None, None) => {
+                    if let RegisterRule::Undefined = lr_rule {
+                        // See the comment in the aarch64 implementation: An undefined return
+                        // address can mean "root function" or "omitted from the table".
+                        Ok(UnwindRuleArm::OffsetSpIfFirstFrameOtherwiseStackEndsHere {
+                            sp_offset_by_4,
+                        })
+                    } else {
+                        Ok(UnwindRuleArm::OffsetSp { sp_offset_by_4 })
+                    }
+                }
+                _ => Ok(UnwindRuleArm::OffsetSpAndRestore {
+                    sp_offset_by_4,
+                    lr_storage_offset_from_new_sp_by_4,
+                    r7_storage_offset_from_new_sp_by_4,
+                    r11_storage_offset_from_new_sp_by_4,
+                }),
+            }
+        }
+        Arm::R7 | Arm::R11 => {
+            if lr_cfa_offset.is_none() {
+                return Err(ConversionError::FramePointerRuleDoesNotRestoreLr);
+            }
+            let sp_offset_from_fp_by_4 =
+                i16::try_from(offset / 4).map_err(|_| ConversionError::SpOffsetFromFpDoesNotFit)?;
+            if register == Arm::R7 {
+                Ok(UnwindRuleArm::UseR7WithOffsets {
+                    sp_offset_from_r7_by_4: sp_offset_from_fp_by_4,
+                    lr_storage_offset_from_new_sp_by_4,
+                    r7_storage_offset_from_new_sp_by_4,
+                    r11_storage_offset_from_new_sp_by_4,
+                })
+            } else {
+                Ok(UnwindRuleArm::UseR11WithOffsets {
+                    sp_offset_from_r11_by_4: sp_offset_from_fp_by_4,
+                    lr_storage_offset_from_new_sp_by_4,
+                    r7_storage_offset_from_new_sp_by_4,
+                    r11_storage_offset_from_new_sp_by_4,
+                })
+            }
+        }
+        _ => Err(ConversionError::CfaIsOffsetFromUnknownRegister),
+    }
+}
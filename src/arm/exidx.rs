@@ -0,0 +1,286 @@
+use super::arch::ArchArm;
+use super::unwind_rule::UnwindRuleArm;
+use super::unwindregs::UnwindRegsArm;
+use crate::exidx::{EhabiInstruction, EhabiInstructions, ExidxUnwinderError, ExidxUnwinding};
+use crate::unwind_result::UnwindResult;
+
+impl ExidxUnwinding for ArchArm {
+    fn unwind_frame<F>(
+        instructions: EhabiInstructions,
+        regs: &mut UnwindRegsArm,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleArm>, ExidxUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        if let Some(rule) = translate_into_unwind_rule(instructions)? {
+            return Ok(UnwindResult::ExecRule(rule));
+        }
+
+        // The instructions do something that can't be expressed as an UnwindRuleArm,
+        // for example popping sp. Execute them directly on the register values.
+        let return_address = execute_instructions(instructions, regs, is_first_frame, read_stack)?;
+        Ok(UnwindResult::Uncacheable(return_address))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VspBase {
+    Sp,
+    R7,
+    R11,
+}
+
+/// Run the instructions symbolically, tracking vsp and the storage locations of the
+/// registers we care about as offsets from the register which vsp was initially based on.
+///
+/// Returns `Ok(None)` if the instructions cannot be expressed as an [`UnwindRuleArm`].
+fn translate_into_unwind_rule(
+    instructions: EhabiInstructions,
+) -> Result<Option<UnwindRuleArm>, ExidxUnwinderError> {
+    let mut base = VspBase::Sp;
+    let mut vsp_offset: i64 = 0;
+    let mut lr_offset = None;
+    let mut pc_offset = None;
+    let mut r7_offset = None;
+    let mut r11_offset = None;
+    let mut is_first_instruction = true;
+
+    for instruction in instructions {
+        match instruction {
+            EhabiInstruction::IncrementVsp(x) => vsp_offset += i64::from(x),
+            EhabiInstruction::DecrementVsp(x) => vsp_offset -= i64::from(x),
+            EhabiInstruction::PopRegisters(mask) => {
+                if mask & (1 << 13) != 0 {
+                    return Ok(None);
+                }
+                for reg in 0..16 {
+                    if mask & (1 << reg) == 0 {
+                        continue;
+                    }
+                    match reg {
+                        7 => r7_offset = Some(vsp_offset),
+                        11 => r11_offset = Some(vsp_offset),
+                        14 => lr_offset = Some(vsp_offset),
+                        15 => pc_offset = Some(vsp_offset),
+                        _ => {}
+                    }
+                    vsp_offset += 4;
+                }
+            }
+            EhabiInstruction::SetVspFromRegister(reg) => {
+                if !is_first_instruction {
+                    return Ok(None);
+                }
+                base = match reg {
+                    7 => VspBase::R7,
+                    11 => VspBase::R11,
+                    13 => VspBase::Sp,
+                    _ => return Ok(None),
+                };
+            }
+            EhabiInstruction::Finish => break,
+            EhabiInstruction::RefuseToUnwind => return Err(ExidxUnwinderError::RefuseToUnwind),
+            EhabiInstruction::Spare(op) => return Err(ExidxUnwinderError::SpareOpcode(op)),
+        }
+        is_first_instruction = false;
+    }
+
+    // If pc was popped, it contains the return address. Otherwise, lr does.
+    let return_address_offset = pc_offset.or(lr_offset);
+    let storage_offset_by_4 = |offset: Option<i64>| -> Option<i8> {
+        match offset {
+            None => Some(0),
+            Some(offset) => {
+                let offset_from_new_sp = offset - vsp_offset;
+                match i8::try_from(offset_from_new_sp / 4) {
+                    Ok(offset_by_4) if offset_by_4 != 0 => Some(offset_by_4),
+                    _ => None,
+                }
+            }
+        }
+    };
+    let (
+        Some(lr_storage_offset_from_new_sp_by_4),
+        Some(r7_storage_offset_from_new_sp_by_4),
+        Some(r11_storage_offset_from_new_sp_by_4),
+    ) = (
+        storage_offset_by_4(return_address_offset),
+        storage_offset_by_4(r7_offset),
+        storage_offset_by_4(r11_offset),
+    )
+    else {
+        return Ok(None);
+    };
+
+    let rule = match base {
+        VspBase::Sp => {
+            let Ok(sp_offset_by_4) = u16::try_from(vsp_offset / 4) else {
+                return Ok(None);
+            };
+            if return_address_offset.is_none() && r7_offset.is_none() && r11_offset.is_none() {
+                UnwindRuleArm::OffsetSp { sp_offset_by_4 }
+            } else {
+                UnwindRuleArm::OffsetSpAndRestore {
+                    sp_offset_by_4,
+                    lr_storage_offset_from_new_sp_by_4,
+                    r7_storage_offset_from_new_sp_by_4,
+                    r11_storage_offset_from_new_sp_by_4,
+                }
+            }
+        }
+        VspBase::R7 | VspBase::R11 => {
+            let Ok(sp_offset_from_fp_by_4) = i16::try_from(vsp_offset / 4) else {
+                return Ok(None);
+            };
+            if base == VspBase::R7 {
+                UnwindRuleArm::UseR7WithOffsets {
+                    sp_offset_from_r7_by_4: sp_offset_from_fp_by_4,
+                    lr_storage_offset_from_new_sp_by_4,
+                    r7_storage_offset_from_new_sp_by_4,
+                    r11_storage_offset_from_new_sp_by_4,
+                }
+            } else {
+                UnwindRuleArm::UseR11WithOffsets {
+                    sp_offset_from_r11_by_4: sp_offset_from_fp_by_4,
+                    lr_storage_offset_from_new_sp_by_4,
+                    r7_storage_offset_from_new_sp_by_4,
+                    r11_storage_offset_from_new_sp_by_4,
+                }
+            }
+        }
+    };
+    Ok(Some(rule))
+}
+
+/// Execute the instructions on the actual register values, following the "virtual
+/// register set" model from the EHABI specification. Returns the return address.
+fn execute_instructions<F>(
+    instructions: EhabiInstructions,
+    regs: &mut UnwindRegsArm,
+    is_first_frame: bool,
+    read_stack: &mut F,
+) -> Result<u64, ExidxUnwinderError>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let sp = regs.sp();
+    let mut vsp = sp;
+    let mut r7 = regs.r7();
+    let mut r11 = regs.r11();
+    let mut r13 = sp;
+    let mut lr = regs.lr();
+    let mut pc = None;
+
+    for instruction in instructions {
+        match instruction {
+            EhabiInstruction::IncrementVsp(x) => {
+                vsp = vsp
+                    .checked_add(x)
+                    .ok_or(ExidxUnwinderError::IntegerOverflow)?
+            }
+            EhabiInstruction::DecrementVsp(x) => {
+                vsp = vsp
+                    .checked_sub(x)
+                    .ok_or(ExidxUnwinderError::IntegerOverflow)?
+            }
+            EhabiInstruction::PopRegisters(mask) => {
+                for reg in 0..16 {
+                    if mask & (1 << reg) == 0 {
+                        continue;
+                    }
+                    if matches!(reg, 7 | 11 | 13 | 14 | 15) {
+                        let location = u64::from(vsp);
+                        let value = read_stack(location)
+                            .map_err(|_| ExidxUnwinderError::CouldNotReadStack(location))?
+                            as u32;
+                        match reg {
+                            7 => r7 = value,
+                            11 => r11 = value,
+                            13 => r13 = value,
+                            14 => lr = value,
+                            _ => pc = Some(value),
+                        }
+                    }
+                    vsp = vsp
+                        .checked_add(4)
+                        .ok_or(ExidxUnwinderError::IntegerOverflow)?;
+                }
+                if mask & (1 << 13) != 0 {
+                    // Popping sp changes vsp.
+                    vsp = r13;
+                }
+            }
+            EhabiInstruction::SetVspFromRegister(reg) => {
+                vsp = match reg {
+                    7 => r7,
+                    11 => r11,
+                    13 => r13,
+                    _ => return Err(ExidxUnwinderError::UnsupportedVspRegister(reg)),
+                };
+            }
+            EhabiInstruction::Finish => break,
+            EhabiInstruction::RefuseToUnwind => return Err(ExidxUnwinderError::RefuseToUnwind),
+            EhabiInstruction::Spare(op) => return Err(ExidxUnwinderError::SpareOpcode(op)),
+        }
+    }
+
+    let new_sp = vsp;
+    let return_address = pc.unwrap_or(lr);
+    if !is_first_frame {
+        if new_sp < sp {
+            return Err(ExidxUnwinderError::StackPointerMovedBackwards);
+        }
+        if new_sp == sp && return_address == regs.lr() {
+            return Err(ExidxUnwinderError::DidNotAdvance);
+        }
+    }
+    regs.set_sp(new_sp);
+    regs.set_r7(r7);
+    regs.set_r11(r11);
+    regs.set_lr(return_address);
+
+    // The lowest bit of a return address is set if the caller is Thumb code.
+    Ok(u64::from(return_address & !1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        // 0x97 vsp = r7; 0x41 vsp = vsp - 8; 0x84 0x0b pop {r4, r5, r7, r14};
+        // 0x00 vsp = vsp + 4; 0xb0 finish
+        let words = [0x41, 0x97, 0x01, 0x81, 0xb0, 0x00, 0x0b, 0x84];
+        let rule = translate_into_unwind_rule(EhabiInstructions::new(&words, 2));
+        assert_eq!(
+            rule,
+            Ok(Some(UnwindRuleArm::UseR7WithOffsets {
+                sp_offset_from_r7_by_4: 3,
+                lr_storage_offset_from_new_sp_by_4: -2,
+                r7_storage_offset_from_new_sp_by_4: -3,
+                r11_storage_offset_from_new_sp_by_4: 0,
+            }))
+        );
+
+        // 0xa8 pop {r4, r14}; 0xb0 finish; 0xb0 finish
+        let words = [0xb0, 0xb0, 0xa8, 0x80];
+        let rule = translate_into_unwind_rule(EhabiInstructions::new(&words, 1));
+        assert_eq!(
+            rule,
+            Ok(Some(UnwindRuleArm::OffsetSpAndRestore {
+                sp_offset_by_4: 2,
+                lr_storage_offset_from_new_sp_by_4: -1,
+                r7_storage_offset_from_new_sp_by_4: 0,
+                r11_storage_offset_from_new_sp_by_4: 0,
+            }))
+        );
+
+        // 0x80 0x00 refuse to unwind
+        let words = [0xb0, 0x00, 0x80, 0x80];
+        let rule = translate_into_unwind_rule(EhabiInstructions::new(&words, 1));
+        assert_eq!(rule, Err(ExidxUnwinderError::RefuseToUnwind));
+    }
+}
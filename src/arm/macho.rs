@@ -0,0 +1,37 @@
+use super::arch::ArchArm;
+use super::unwind_rule::UnwindRuleArm;
+use super::unwindregs::UnwindRegsArm;
+use crate::macho::{CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding, CuiUnwindResult};
+use crate::unwind_result::UnwindResult;
+use macho_unwind_info::Function;
+
+impl CompactUnwindInfoUnwinding for ArchArm {
+    fn unwind_frame(
+        _function: Function,
+        _is_first_frame: bool,
+        _address_offset_within_function: usize,
+        _function_bytes: Option<&[u8]>,
+    ) -> Result<CuiUnwindResult<UnwindRuleArm>, CompactUnwindInfoUnwinderError> {
+        Err(CompactUnwindInfoUnwinderError::ArmUnsupported)
+    }
+
+    fn rule_for_stub_helper(
+        _offset: u32,
+    ) -> Result<CuiUnwindResult<UnwindRuleArm>, CompactUnwindInfoUnwinderError> {
+        Err(CompactUnwindInfoUnwinderError::ArmUnsupported)
+    }
+
+    fn is_sigtramp_return_address(_function_bytes: &[u8], _return_address_offset: usize) -> bool {
+        false
+    }
+
+    fn unwind_sigtramp_frame<F>(
+        _regs: &mut UnwindRegsArm,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleArm>, CompactUnwindInfoUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(CompactUnwindInfoUnwinderError::ArmUnsupported)
+    }
+}
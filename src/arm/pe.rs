@@ -0,0 +1,27 @@
+use super::arch::ArchArm;
+use crate::pe::{PeSections, PeUnwinderError, PeUnwinding};
+use crate::rule_table::RuleTable;
+use crate::unwind_result::UnwindResult;
+
+impl PeUnwinding for ArchArm {
+    fn unwind_frame<F, D>(
+        _sections: PeSections<D>,
+        _address: u32,
+        _regs: &mut Self::UnwindRegs,
+        _is_first_frame: bool,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, PeUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+        D: core::ops::Deref<Target = [u8]>,
+    {
+        Err(PeUnwinderError::ArmUnsupported)
+    }
+
+    fn compile_rule_table<D>(_sections: PeSections<D>) -> Result<RuleTable, PeUnwinderError>
+    where
+        D: core::ops::Deref<Target = [u8]>,
+    {
+        Err(PeUnwinderError::ArmUnsupported)
+    }
+}
@@ -0,0 +1,402 @@
+use super::unwindregs::UnwindRegsArm;
+use crate::add_signed::checked_add_signed;
+use crate::error::Error;
+
+use crate::unwind_rule::{pack_rule, unpack_rule, UnwindRule};
+
+/// An unwind rule for 32-bit ARM.
+///
+/// The `*_storage_offset_from_new_sp_by_4` fields describe where a register was saved,
+/// relative to the recovered stack pointer, in units of 4 bytes. Registers are always
+/// saved below the caller's stack pointer, so these offsets are negative. An offset of
+/// zero means that the register has not been saved and keeps its current value; for lr,
+/// this means that the return address is still in the lr register, which is only
+/// possible for the first frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnwindRuleArm {
+    /// (sp, r7, r11, lr) = (sp, r7, r11, lr)
+    /// Only possible for the first frame. Subsequent frames must get the
+    /// return address from somewhere other than the lr register to avoid
+    /// infinite loops.
+    NoOp,
+    /// (sp, r7, r11, lr) = if is_first_frame (sp, r7, r11, lr) else UseFramePointer
+    /// Used as a fallback rule.
+    NoOpIfFirstFrameOtherwiseFp,
+    /// (sp, r7, r11, lr) = (sp + 4x, r7, r11, lr)
+    /// Only possible for the first frame. Subsequent frames must get the
+    /// return address from somewhere other than the lr register to avoid
+    /// infinite loops.
+    OffsetSp { sp_offset_by_4: u16 },
+    /// (sp, r7, r11, lr) = (sp + 4x, r7, r11, lr) if is_first_frame
+    /// This rule reflects an ambiguity in DWARF CFI information. When the
+    /// return address is "undefined" because it was omitted, it could mean
+    /// "same value", but this is only allowed for the first frame.
+    OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_4: u16 },
+    /// new_sp = sp + 4x
+    /// (sp, r7, r11, lr) = (new_sp, *(new_sp + 4a), *(new_sp + 4b), *(new_sp + 4c))
+    OffsetSpAndRestore {
+        sp_offset_by_4: u16,
+        lr_storage_offset_from_new_sp_by_4: i8,
+        r7_storage_offset_from_new_sp_by_4: i8,
+        r11_storage_offset_from_new_sp_by_4: i8,
+    },
+    /// new_sp = r7 + 4x
+    /// (sp, r7, r11, lr) = (new_sp, *(new_sp + 4a), *(new_sp + 4b), *(new_sp + 4c))
+    UseR7WithOffsets {
+        sp_offset_from_r7_by_4: i16,
+        lr_storage_offset_from_new_sp_by_4: i8,
+        r7_storage_offset_from_new_sp_by_4: i8,
+        r11_storage_offset_from_new_sp_by_4: i8,
+    },
+    /// new_sp = r11 + 4x
+    /// (sp, r7, r11, lr) = (new_sp, *(new_sp + 4a), *(new_sp + 4b), *(new_sp + 4c))
+    UseR11WithOffsets {
+        sp_offset_from_r11_by_4: i16,
+        lr_storage_offset_from_new_sp_by_4: i8,
+        r7_storage_offset_from_new_sp_by_4: i8,
+        r11_storage_offset_from_new_sp_by_4: i8,
+    },
+    /// (sp, fp, lr) = (fp + 8, *fp, *(fp + 4)), where fp is r7 if r7 looks like a
+    /// valid frame pointer, and r11 otherwise.
+    UseFramePointer,
+}
+
+impl UnwindRule for UnwindRuleArm {
+    type UnwindRegs = UnwindRegsArm;
+
+    fn rule_for_stub_functions() -> Self {
+        UnwindRuleArm::NoOp
+    }
+    fn rule_for_function_start() -> Self {
+        UnwindRuleArm::NoOp
+    }
+    fn fallback_rule() -> Self {
+        UnwindRuleArm::UseFramePointer
+    }
+
+    fn pack(self) -> u64 {
+        // The three 8-bit storage offsets are packed into the second and third field.
+        let offsets = |lr: i8, r7: i8, r11: i8| {
+            [
+                u16::from_le_bytes([lr as u8, r7 as u8]),
+                u16::from(r11 as u8),
+            ]
+        };
+        match self {
+            UnwindRuleArm::NoOp => pack_rule(0, [0, 0, 0]),
+            UnwindRuleArm::NoOpIfFirstFrameOtherwiseFp => pack_rule(1, [0, 0, 0]),
+            UnwindRuleArm::OffsetSp { sp_offset_by_4 } => pack_rule(2, [sp_offset_by_4, 0, 0]),
+            UnwindRuleArm::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_4 } => {
+                pack_rule(3, [sp_offset_by_4, 0, 0])
+            }
+            UnwindRuleArm::OffsetSpAndRestore {
+                sp_offset_by_4,
+                lr_storage_offset_from_new_sp_by_4: lr,
+                r7_storage_offset_from_new_sp_by_4: r7,
+                r11_storage_offset_from_new_sp_by_4: r11,
+            } => {
+                let [b, c] = offsets(lr, r7, r11);
+                pack_rule(4, [sp_offset_by_4, b, c])
+            }
+            UnwindRuleArm::UseR7WithOffsets {
+                sp_offset_from_r7_by_4,
+                lr_storage_offset_from_new_sp_by_4: lr,
+                r7_storage_offset_from_new_sp_by_4: r7,
+                r11_storage_offset_from_new_sp_by_4: r11,
+            } => {
+                let [b, c] = offsets(lr, r7, r11);
+                pack_rule(5, [sp_offset_from_r7_by_4 as u16, b, c])
+            }
+            UnwindRuleArm::UseR11WithOffsets {
+                sp_offset_from_r11_by_4,
+                lr_storage_offset_from_new_sp_by_4: lr,
+                r7_storage_offset_from_new_sp_by_4: r7,
+                r11_storage_offset_from_new_sp_by_4: r11,
+            } => {
+                let [b, c] = offsets(lr, r7, r11);
+                pack_rule(6, [sp_offset_from_r11_by_4 as u16, b, c])
+            }
+            UnwindRuleArm::UseFramePointer => pack_rule(7, [0, 0, 0]),
+        }
+    }
+
+    fn unpack(bits: u64) -> Option<Self> {
+        let (tag, [a, b, c]) = unpack_rule(bits);
+        let [lr, r7] = b.to_le_bytes().map(|offset| offset as i8);
+        let r11 = c as u8 as i8;
+        let rule = match tag {
+            0 => UnwindRuleArm::NoOp,
+            1 => UnwindRuleArm::NoOpIfFirstFrameOtherwiseFp,
+            2 => UnwindRuleArm::OffsetSp { sp_offset_by_4: a },
+            3 => UnwindRuleArm::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_4: a },
+            4 => UnwindRuleArm::OffsetSpAndRestore {
+                sp_offset_by_4: a,
+                lr_storage_offset_from_new_sp_by_4: lr,
+                r7_storage_offset_from_new_sp_by_4: r7,
+                r11_storage_offset_from_new_sp_by_4: r11,
+            },
+            5 => UnwindRuleArm::UseR7WithOffsets {
+                sp_offset_from_r7_by_4: a as i16,
+                lr_storage_offset_from_new_sp_by_4: lr,
+                r7_storage_offset_from_new_sp_by_4: r7,
+                r11_storage_offset_from_new_sp_by_4: r11,
+            },
+            6 => UnwindRuleArm::UseR11WithOffsets {
+                sp_offset_from_r11_by_4: a as i16,
+                lr_storage_offset_from_new_sp_by_4: lr,
+                r7_storage_offset_from_new_sp_by_4: r7,
+                r11_storage_offset_from_new_sp_by_4: r11,
+            },
+            7 => UnwindRuleArm::UseFramePointer,
+            _ => return None,
+        };
+        Some(rule)
+    }
+
+    fn exec<F>(
+        self,
+        is_first_frame: bool,
+        regs: &mut UnwindRegsArm,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, Error>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let lr = regs.lr();
+        let sp = regs.sp();
+        let r7 = regs.r7();
+        let r11 = regs.r11();
+
+        let (new_lr, new_sp, new_r7, new_r11) = match self {
+            UnwindRuleArm::NoOp => {
+                if !is_first_frame {
+                    return Err(Error::DidNotAdvance);
+                }
+                (lr, sp, r7, r11)
+            }
+            UnwindRuleArm::NoOpIfFirstFrameOtherwiseFp => {
+                if is_first_frame {
+                    (lr, sp, r7, r11)
+                } else {
+                    match unwind_with_frame_pointer(regs, read_stack)? {
+                        Some(new_regs) => new_regs,
+                        None => return Ok(None),
+                    }
+                }
+            }
+            UnwindRuleArm::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_4 } => {
+                if !is_first_frame {
+                    return Ok(None);
+                }
+                let sp_offset = u32::from(sp_offset_by_4) * 4;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                (lr, new_sp, r7, r11)
+            }
+            UnwindRuleArm::OffsetSp { sp_offset_by_4 } => {
+                if !is_first_frame {
+                    return Err(Error::DidNotAdvance);
+                }
+                let sp_offset = u32::from(sp_offset_by_4) * 4;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                (lr, new_sp, r7, r11)
+            }
+            UnwindRuleArm::OffsetSpAndRestore {
+                sp_offset_by_4,
+                lr_storage_offset_from_new_sp_by_4,
+                r7_storage_offset_from_new_sp_by_4,
+                r11_storage_offset_from_new_sp_by_4,
+            } => {
+                let sp_offset = u32::from(sp_offset_by_4) * 4;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                restore_registers(
+                    regs,
+                    new_sp,
+                    [
+                        lr_storage_offset_from_new_sp_by_4,
+                        r7_storage_offset_from_new_sp_by_4,
+                        r11_storage_offset_from_new_sp_by_4,
+                    ],
+                    is_first_frame,
+                    read_stack,
+                )?
+            }
+            UnwindRuleArm::UseR7WithOffsets {
+                sp_offset_from_r7_by_4,
+                lr_storage_offset_from_new_sp_by_4,
+                r7_storage_offset_from_new_sp_by_4,
+                r11_storage_offset_from_new_sp_by_4,
+            } => {
+                let sp_offset = i32::from(sp_offset_from_r7_by_4) * 4;
+                let new_sp = checked_add_signed(r7, sp_offset).ok_or(Error::IntegerOverflow)?;
+                if new_sp <= sp {
+                    return Err(Error::FramepointerUnwindingMovedBackwards);
+                }
+                restore_registers(
+                    regs,
+                    new_sp,
+                    [
+                        lr_storage_offset_from_new_sp_by_4,
+                        r7_storage_offset_from_new_sp_by_4,
+                        r11_storage_offset_from_new_sp_by_4,
+                    ],
+                    is_first_frame,
+                    read_stack,
+                )?
+            }
+            UnwindRuleArm::UseR11WithOffsets {
+                sp_offset_from_r11_by_4,
+                lr_storage_offset_from_new_sp_by_4,
+                r7_storage_offset_from_new_sp_by_4,
+                r11_storage_offset_from_new_sp_by_4,
+            } => {
+                let sp_offset = i32::from(sp_offset_from_r11_by_4) * 4;
+                let new_sp = checked_add_signed(r11, sp_offset).ok_or(Error::IntegerOverflow)?;
+                if new_sp <= sp {
+                    return Err(Error::FramepointerUnwindingMovedBackwards);
+                }
+                restore_registers(
+                    regs,
+                    new_sp,
+                    [
+                        lr_storage_offset_from_new_sp_by_4,
+                        r7_storage_offset_from_new_sp_by_4,
+                        r11_storage_offset_from_new_sp_by_4,
+                    ],
+                    is_first_frame,
+                    read_stack,
+                )?
+            }
+            UnwindRuleArm::UseFramePointer => match unwind_with_frame_pointer(regs, read_stack)? {
+                Some(new_regs) => new_regs,
+                None => return Ok(None),
+            },
+        };
+        // The lowest bit of a return address is set if the caller is Thumb code.
+        let return_address = new_lr & !1;
+        if return_address == 0 {
+            return Ok(None);
+        }
+        if !is_first_frame && new_sp == sp {
+            return Err(Error::DidNotAdvance);
+        }
+        regs.set_lr(new_lr);
+        regs.set_sp(new_sp);
+        regs.set_r7(new_r7);
+        regs.set_r11(new_r11);
+
+        Ok(Some(u64::from(return_address)))
+    }
+}
+
+/// Reads lr, r7 and r11 from the stack, for each register whose storage offset is non-zero.
+/// Returns the new (lr, sp, r7, r11).
+fn restore_registers<F>(
+    regs: &UnwindRegsArm,
+    new_sp: u32,
+    [lr_offset_by_4, r7_offset_by_4, r11_offset_by_4]: [i8; 3],
+    is_first_frame: bool,
+    read_stack: &mut F,
+) -> Result<(u32, u32, u32, u32), Error>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    if lr_offset_by_4 == 0 && !is_first_frame {
+        return Err(Error::DidNotAdvance);
+    }
+    let mut restore = |offset_by_4: i8, value: u32| -> Result<u32, Error> {
+        if offset_by_4 == 0 {
+            return Ok(value);
+        }
+        let location =
+            checked_add_signed(new_sp, i32::from(offset_by_4) * 4).ok_or(Error::IntegerOverflow)?;
+        let location = u64::from(location);
+        let value = read_stack(location).map_err(|_| Error::CouldNotReadStack(location))?;
+        Ok(value as u32)
+    };
+    let new_lr = restore(lr_offset_by_4, regs.lr())?;
+    let new_r7 = restore(r7_offset_by_4, regs.r7())?;
+    let new_r11 = restore(r11_offset_by_4, regs.r11())?;
+    Ok((new_lr, new_sp, new_r7, new_r11))
+}
+
+/// Do one step of a frame pointer stack walk. Returns the new (lr, sp, r7, r11), or
+/// `None` if the end of the frame pointer chain has been reached.
+///
+/// Frame-based ARM functions push the caller's frame pointer and lr next to each other
+/// and then point the frame pointer at the saved frame pointer:
+///
+/// ```text
+/// push  {r7, lr}      ; Thumb code; ARM code uses r11 instead of r7
+/// mov   r7, sp
+/// ```
+///
+/// So *fp is the caller's frame pointer and *(fp + 4) is the return address.
+///
+/// Thumb code uses r7 as the frame pointer and ARM code uses r11. We don't know which
+/// kind of code we're in, so we use r7 if it looks like it points into the stack, and
+/// r11 otherwise.
+fn unwind_with_frame_pointer<F>(
+    regs: &UnwindRegsArm,
+    read_stack: &mut F,
+) -> Result<Option<(u32, u32, u32, u32)>, Error>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let sp = regs.sp();
+    let r7 = regs.r7();
+    let r11 = regs.r11();
+    let r7_is_plausible = r7 != 0 && r7.is_multiple_of(4) && r7 >= sp;
+    let fp = if r7_is_plausible { r7 } else { r11 };
+
+    let new_sp = fp.checked_add(8).ok_or(Error::IntegerOverflow)?;
+    let lr_location = u64::from(fp) + 4;
+    let new_lr = read_stack(lr_location).map_err(|_| Error::CouldNotReadStack(lr_location))?;
+    let fp_location = u64::from(fp);
+    let new_fp = read_stack(fp_location).map_err(|_| Error::CouldNotReadStack(fp_location))?;
+    let (new_lr, new_fp) = (new_lr as u32, new_fp as u32);
+    if new_fp == 0 {
+        return Ok(None);
+    }
+    if new_fp <= fp || new_sp <= sp {
+        return Err(Error::FramepointerUnwindingMovedBackwards);
+    }
+    if r7_is_plausible {
+        Ok(Some((new_lr, new_sp, new_fp, r11)))
+    } else {
+        Ok(Some((new_lr, new_sp, r7, new_fp)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let stack = [
+            1, 2, 3, 4, 0x40, 0x100201, 5, 6, 0x38, 0x100100, 7, 8, 9, 10, 0x0, 0x0,
+        ];
+        let mut read_stack = |addr| Ok(stack[(addr / 4) as usize]);
+        let mut regs = UnwindRegsArm::new(0x100300, 0x8, 0x10, 0);
+        let res = UnwindRuleArm::NoOp.exec(true, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100300)));
+        assert_eq!(regs.sp(), 0x8);
+        let res = UnwindRuleArm::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100200)));
+        assert_eq!(regs.sp(), 0x18);
+        assert_eq!(regs.r7(), 0x40);
+        // The caller restores sp from r7 and pops {r4, r5, r7, lr}.
+        let rule = UnwindRuleArm::UseR7WithOffsets {
+            sp_offset_from_r7_by_4: -6,
+            lr_storage_offset_from_new_sp_by_4: -1,
+            r7_storage_offset_from_new_sp_by_4: -2,
+            r11_storage_offset_from_new_sp_by_4: 0,
+        };
+        let res = rule.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100100)));
+        assert_eq!(regs.sp(), 0x28);
+        assert_eq!(regs.r7(), 0x38);
+        let res = UnwindRuleArm::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(None));
+    }
+}
@@ -0,0 +1,98 @@
+use core::fmt::Debug;
+
+use crate::display_utils::HexNum;
+use crate::frame_record::FrameRegs;
+
+/// The registers used for unwinding on 32-bit ARM. We need lr (r14) and sp (r13),
+/// as well as the two registers which are used as frame pointers: r7 in Thumb code
+/// and r11 in ARM code.
+///
+/// The stack is read in 32-bit words: the `read_stack` callback is called with
+/// 4-byte aligned addresses and should return the 32-bit value at that address,
+/// zero-extended to `u64`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct UnwindRegsArm {
+    lr: u32,
+    sp: u32,
+    r7: u32,
+    r11: u32,
+}
+
+impl UnwindRegsArm {
+    /// Create a set of unwind register values.
+    pub fn new(lr: u32, sp: u32, r7: u32, r11: u32) -> Self {
+        Self { lr, sp, r7, r11 }
+    }
+
+    /// Get the stack pointer value (r13).
+    #[inline(always)]
+    pub fn sp(&self) -> u32 {
+        self.sp
+    }
+
+    /// Set the stack pointer value (r13).
+    #[inline(always)]
+    pub fn set_sp(&mut self, sp: u32) {
+        self.sp = sp
+    }
+
+    /// Get the r7 register value, the frame pointer in Thumb code.
+    #[inline(always)]
+    pub fn r7(&self) -> u32 {
+        self.r7
+    }
+
+    /// Set the r7 register value, the frame pointer in Thumb code.
+    #[inline(always)]
+    pub fn set_r7(&mut self, r7: u32) {
+        self.r7 = r7
+    }
+
+    /// Get the r11 register value, the frame pointer in ARM code.
+    #[inline(always)]
+    pub fn r11(&self) -> u32 {
+        self.r11
+    }
+
+    /// Set the r11 register value, the frame pointer in ARM code.
+    #[inline(always)]
+    pub fn set_r11(&mut self, r11: u32) {
+        self.r11 = r11
+    }
+
+    /// Get the lr register value (r14).
+    #[inline(always)]
+    pub fn lr(&self) -> u32 {
+        self.lr
+    }
+
+    /// Set the lr register value (r14).
+    #[inline(always)]
+    pub fn set_lr(&mut self, lr: u32) {
+        self.lr = lr
+    }
+}
+
+impl FrameRegs for UnwindRegsArm {
+    fn stack_pointer(&self) -> u64 {
+        u64::from(self.sp())
+    }
+
+    /// r7 if it looks like a frame pointer, otherwise r11. This is the same guess that
+    /// frame pointer unwinding makes.
+    fn frame_pointer(&self) -> u64 {
+        let r7_is_plausible = self.r7 != 0 && self.r7.is_multiple_of(4) && self.r7 >= self.sp;
+        u64::from(if r7_is_plausible { self.r7 } else { self.r11 })
+    }
+}
+
+impl Debug for UnwindRegsArm {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnwindRegsArm")
+            .field("lr", &HexNum(self.lr))
+            .field("sp", &HexNum(self.sp))
+            .field("r7", &HexNum(self.r7))
+            .field("r11", &HexNum(self.r11))
+            .finish()
+    }
+}
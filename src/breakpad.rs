@@ -0,0 +1,785 @@
+use alloc::vec::Vec;
+use arrayvec::ArrayVec;
+use core::marker::PhantomData;
+
+use crate::{arch::Arch, unwind_result::UnwindResult};
+
+/// The maximum number of register rules we track for a `STACK CFI` address.
+const MAX_CFI_RULES: usize = 24;
+/// The maximum number of variables (registers, `.cfa`, `$T0` etc.) during evaluation.
+const MAX_VARIABLES: usize = 32;
+/// The maximum depth of the postfix expression stack.
+const MAX_STACK_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpadUnwinderError {
+    AddressNotCovered(u32),
+    InvalidRecord(u32),
+    TooManyRules,
+    MissingCfaRule,
+    MissingReturnAddressRule,
+    InvalidExpression,
+    UndefinedVariable,
+    ExpressionTooComplex,
+    DivisionByZero,
+    CouldNotReadStack(u64),
+    DidNotAdvance,
+    StackPointerMovedBackwards,
+    UnsupportedWinRecord,
+    UnsupportedArch,
+}
+
+impl core::fmt::Display for BreakpadUnwinderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::AddressNotCovered(addr) => write!(
+                f,
+                "No STACK CFI or STACK WIN record covers the address 0x{addr:x}"
+            ),
+            Self::InvalidRecord(offset) => {
+                write!(f, "Invalid STACK record at offset 0x{offset:x}")
+            }
+            Self::TooManyRules => write!(f, "The STACK CFI record has too many register rules"),
+            Self::MissingCfaRule => write!(f, "The STACK CFI record has no .cfa rule"),
+            Self::MissingReturnAddressRule => {
+                write!(f, "The STACK record has no rule for the return address")
+            }
+            Self::InvalidExpression => write!(f, "Invalid postfix expression"),
+            Self::UndefinedVariable => {
+                write!(f, "The postfix expression uses an undefined variable")
+            }
+            Self::ExpressionTooComplex => write!(f, "The postfix expression is too complex"),
+            Self::DivisionByZero => write!(f, "Division by zero in postfix expression"),
+            Self::CouldNotReadStack(addr) => {
+                write!(f, "Could not read stack memory at 0x{addr:x}")
+            }
+            Self::DidNotAdvance => write!(f, "Did not advance"),
+            Self::StackPointerMovedBackwards => write!(f, "Stack pointer moved backwards"),
+            Self::UnsupportedWinRecord => {
+                write!(f, "STACK WIN records are only supported on 32-bit x86")
+            }
+            Self::UnsupportedArch => write!(
+                f,
+                "Breakpad unwinding is only supported on x86_64, aarch64 and 32-bit x86"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BreakpadUnwinderError {}
+
+pub trait BreakpadUnwinding: Arch {
+    fn unwind_frame<F>(
+        _record: BreakpadRecord,
+        _regs: &mut Self::UnwindRegs,
+        _is_first_frame: bool,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(BreakpadUnwinderError::UnsupportedArch)
+    }
+}
+
+/// The kinds of records we index. For records which start at the same address, the
+/// later kind wins, which matches the preference of Breakpad's own stack walker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RecordKind {
+    Cfi,
+    WinFpo,
+    WinFrameData,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IndexEntry {
+    address: u32,
+    size: u32,
+    kind: RecordKind,
+    /// The byte offset of the record's line in the `.sym` file.
+    offset: u32,
+}
+
+/// A sorted index of the `STACK CFI INIT` and `STACK WIN` records in a Breakpad `.sym`
+/// file. It is built when the module is added; the records themselves are only parsed
+/// during unwinding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakpadIndex {
+    entries: Vec<IndexEntry>,
+    address_size: u8,
+}
+
+impl BreakpadIndex {
+    pub fn new(sym: &[u8]) -> Self {
+        let mut entries = Vec::new();
+        let mut address_size = 8;
+        let mut offset = 0;
+        while offset < sym.len() {
+            let (line, next_offset) = line_at(sym, offset);
+            if let Some(line) = line {
+                if let Some(rest) = line.strip_prefix("STACK CFI INIT ") {
+                    if let Some((address, size)) = parse_cfi_init_range(rest) {
+                        entries.push(IndexEntry {
+                            address,
+                            size,
+                            kind: RecordKind::Cfi,
+                            offset: offset as u32,
+                        });
+                    }
+                } else if let Some(rest) = line.strip_prefix("STACK WIN ") {
+                    if let Some(record) = WinRecord::parse(rest) {
+                        let kind = match record.frame_type {
+                            WinFrameType::Fpo => RecordKind::WinFpo,
+                            WinFrameType::FrameData => RecordKind::WinFrameData,
+                        };
+                        entries.push(IndexEntry {
+                            address: record.address,
+                            size: record.code_size,
+                            kind,
+                            offset: offset as u32,
+                        });
+                    }
+                } else if let Some(rest) = line.strip_prefix("MODULE ") {
+                    // MODULE <os> <arch> <id> <name>
+                    if let Some(arch) = rest.split_ascii_whitespace().nth(1) {
+                        if matches!(arch, "x86" | "arm" | "ppc" | "mips") {
+                            address_size = 4;
+                        }
+                    }
+                }
+            }
+            offset = next_offset;
+        }
+        entries.sort_by_key(|entry| (entry.address, entry.kind));
+        Self {
+            entries,
+            address_size,
+        }
+    }
+
+    /// The size of a code address in the module described by the `MODULE` line.
+    pub fn address_size(&self) -> u8 {
+        self.address_size
+    }
+
+    fn entry_for_address(&self, address: u32) -> Option<&IndexEntry> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.address <= address)
+            .checked_sub(1)?;
+        let entry = &self.entries[index];
+        if address - entry.address < entry.size {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the line at `offset` (without the line terminator) and the offset of the
+/// next line. The line is `None` if it's not valid UTF-8.
+fn line_at(sym: &[u8], offset: usize) -> (Option<&str>, usize) {
+    let rest = &sym[offset..];
+    let (line, next_offset) = match rest.iter().position(|b| *b == b'\n') {
+        Some(len) => (&rest[..len], offset + len + 1),
+        None => (rest, sym.len()),
+    };
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    (core::str::from_utf8(line).ok(), next_offset)
+}
+
+/// Splits off the first whitespace-separated token.
+fn next_token(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if s.is_empty() {
+        return None;
+    }
+    match s.find(|c: char| c.is_ascii_whitespace()) {
+        Some(end) => Some((&s[..end], &s[end..])),
+        None => Some((s, "")),
+    }
+}
+
+fn parse_hex_token(s: &str) -> Option<(u32, &str)> {
+    let (token, rest) = next_token(s)?;
+    Some((u32::from_str_radix(token, 16).ok()?, rest))
+}
+
+fn parse_cfi_init_range(s: &str) -> Option<(u32, u32)> {
+    let (address, rest) = parse_hex_token(s)?;
+    let (size, _) = parse_hex_token(rest)?;
+    Some((address, size))
+}
+
+/// The register rules which apply at an address covered by `STACK CFI` records: the
+/// rules from the `STACK CFI INIT` record, updated by all the `STACK CFI` records up to
+/// the address.
+///
+/// Each rule is a postfix expression which computes the caller's value of a register
+/// (or `.cfa` / `.ra`) from the callee's register values and from `.cfa`.
+#[derive(Debug, Clone, Default)]
+pub struct CfiRules<'a> {
+    rules: ArrayVec<(&'a str, &'a str), MAX_CFI_RULES>,
+}
+
+impl<'a> CfiRules<'a> {
+    /// Returns the expression for `name`, e.g. `".cfa"` or `"$rbp"`.
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.rules
+            .iter()
+            .find(|(rule_name, _)| *rule_name == name)
+            .map(|(_, expr)| *expr)
+    }
+
+    /// Applies a rule string like `.cfa: $rsp 16 + $rbp: .cfa -16 + ^`. `offset` is the
+    /// offset of the record in the `.sym` file, for error reporting.
+    fn apply(&mut self, s: &'a str, offset: u32) -> Result<(), BreakpadUnwinderError> {
+        let invalid = BreakpadUnwinderError::InvalidRecord(offset);
+        let mut current: Option<(&'a str, usize)> = None;
+        let mut rest = s;
+        while let Some((token, remainder)) = next_token(rest) {
+            let token_start = token.as_ptr() as usize - s.as_ptr() as usize;
+            if let Some(name) = token.strip_suffix(':') {
+                if let Some((name, expr_start)) = current {
+                    self.set(name, s[expr_start..token_start].trim(), invalid)?;
+                }
+                current = Some((name, token_start + token.len()));
+            } else if current.is_none() {
+                return Err(invalid);
+            }
+            rest = remainder;
+        }
+        match current {
+            Some((name, expr_start)) => self.set(name, s[expr_start..].trim(), invalid),
+            None => Err(invalid),
+        }
+    }
+
+    fn set(
+        &mut self,
+        name: &'a str,
+        expr: &'a str,
+        invalid: BreakpadUnwinderError,
+    ) -> Result<(), BreakpadUnwinderError> {
+        if expr.is_empty() {
+            return Err(invalid);
+        }
+        match self
+            .rules
+            .iter_mut()
+            .find(|(rule_name, _)| *rule_name == name)
+        {
+            Some(rule) => rule.1 = expr,
+            None => self
+                .rules
+                .try_push((name, expr))
+                .map_err(|_| BreakpadUnwinderError::TooManyRules)?,
+        }
+        Ok(())
+    }
+
+    /// Recognizes the rule shapes which compilers emit for most functions: the CFA is
+    /// the stack pointer or the frame pointer plus a constant, and the return address
+    /// and the frame pointer are either unchanged or saved at a constant offset from
+    /// the CFA.
+    pub fn simple_row(&self, names: &CfiRegisterNames) -> Option<SimpleCfiRow> {
+        let (cfa_reg, cfa_offset) = match tokens::<3>(self.get(".cfa")?)?[..] {
+            [reg] => (reg, 0),
+            [reg, offset, "+"] => (reg, offset.parse::<i64>().ok()?),
+            [reg, offset, "-"] => (reg, offset.parse::<i64>().ok()?.checked_neg()?),
+            _ => return None,
+        };
+        let cfa_base = if cfa_reg == names.sp {
+            SimpleCfaBase::Sp
+        } else if cfa_reg == names.fp {
+            SimpleCfaBase::Fp
+        } else {
+            return None;
+        };
+        let ra_offset = match (self.get(".ra"), names.ra) {
+            (Some(expr), Some(ra)) if expr == ra => None,
+            (None, Some(_)) => None,
+            (Some(expr), _) => Some(parse_cfa_relative_load(expr)?),
+            (None, None) => return None,
+        };
+        let fp_offset = match self.get(names.fp) {
+            Some(expr) if expr == names.fp => None,
+            Some(expr) => Some(parse_cfa_relative_load(expr)?),
+            None => None,
+        };
+        if self.get(names.sp).is_some_and(|expr| expr != ".cfa") {
+            return None;
+        }
+        Some(SimpleCfiRow {
+            cfa_base,
+            cfa_offset,
+            ra_offset,
+            fp_offset,
+        })
+    }
+
+    /// Evaluates the `.cfa` rule and makes `.cfa` available to subsequent evaluations.
+    pub fn evaluate_cfa<F>(
+        &self,
+        variables: &mut Variables<'a>,
+        read_stack: &mut F,
+    ) -> Result<u64, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let expr = self
+            .get(".cfa")
+            .ok_or(BreakpadUnwinderError::MissingCfaRule)?;
+        let cfa = evaluate(expr, variables, read_stack)?
+            .ok_or(BreakpadUnwinderError::InvalidExpression)?;
+        variables.set(".cfa", cfa)?;
+        Ok(cfa)
+    }
+
+    /// Evaluates the rule for `name`. Returns `None` if there is no rule for it.
+    pub fn evaluate_register<F>(
+        &self,
+        name: &str,
+        variables: &mut Variables<'a>,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let Some(expr) = self.get(name) else {
+            return Ok(None);
+        };
+        let value = evaluate(expr, variables, read_stack)?
+            .ok_or(BreakpadUnwinderError::InvalidExpression)?;
+        Ok(Some(value))
+    }
+}
+
+/// Parses `.cfa N + ^`, `.cfa N - ^` and `.cfa ^`.
+fn parse_cfa_relative_load(expr: &str) -> Option<i64> {
+    match tokens::<4>(expr)?[..] {
+        [".cfa", "^"] => Some(0),
+        [".cfa", offset, "+", "^"] => offset.parse().ok(),
+        [".cfa", offset, "-", "^"] => offset.parse::<i64>().ok()?.checked_neg(),
+        _ => None,
+    }
+}
+
+/// Splits the expression into its tokens, or returns `None` if it has more than `N`.
+fn tokens<const N: usize>(expr: &str) -> Option<ArrayVec<&str, N>> {
+    let mut tokens = ArrayVec::new();
+    for token in expr.split_ascii_whitespace() {
+        tokens.try_push(token).ok()?;
+    }
+    Some(tokens)
+}
+
+/// The Breakpad names of the registers which are relevant for unwinding.
+pub struct CfiRegisterNames {
+    pub sp: &'static str,
+    pub fp: &'static str,
+    /// The register which holds the return address on entry, if the architecture
+    /// has one (the link register).
+    pub ra: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimpleCfaBase {
+    Sp,
+    Fp,
+}
+
+/// A set of `STACK CFI` rules which can be described with constant offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimpleCfiRow {
+    pub cfa_base: SimpleCfaBase,
+    pub cfa_offset: i64,
+    /// The offset from the CFA where the return address is saved. `None` if the return
+    /// address is still in the link register.
+    pub ra_offset: Option<i64>,
+    /// The offset from the CFA where the frame pointer is saved. `None` if the frame
+    /// pointer is unchanged.
+    pub fp_offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinFrameType {
+    /// `FRAME_FPO` (type 0): described by the frame sizes only.
+    Fpo,
+    /// `FRAME_FRAMEDATA` (type 4): has a program string.
+    FrameData,
+}
+
+/// A parsed `STACK WIN` record, as emitted for 32-bit x86 PDBs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WinRecord<'a> {
+    pub frame_type: WinFrameType,
+    pub address: u32,
+    pub code_size: u32,
+    pub prologue_size: u32,
+    pub epilogue_size: u32,
+    pub parameter_size: u32,
+    pub saved_register_size: u32,
+    pub local_size: u32,
+    pub max_stack_size: u32,
+    pub program_string: Option<&'a str>,
+    pub allocates_base_pointer: bool,
+}
+
+impl<'a> WinRecord<'a> {
+    /// Parses the part of the record after `STACK WIN `.
+    fn parse(s: &'a str) -> Option<Self> {
+        let (frame_type, rest) = parse_hex_token(s)?;
+        let frame_type = match frame_type {
+            0 => WinFrameType::Fpo,
+            4 => WinFrameType::FrameData,
+            _ => return None,
+        };
+        let (address, rest) = parse_hex_token(rest)?;
+        let (code_size, rest) = parse_hex_token(rest)?;
+        let (prologue_size, rest) = parse_hex_token(rest)?;
+        let (epilogue_size, rest) = parse_hex_token(rest)?;
+        let (parameter_size, rest) = parse_hex_token(rest)?;
+        let (saved_register_size, rest) = parse_hex_token(rest)?;
+        let (local_size, rest) = parse_hex_token(rest)?;
+        let (max_stack_size, rest) = parse_hex_token(rest)?;
+        let (has_program_string, rest) = next_token(rest)?;
+        let (program_string, allocates_base_pointer) = if has_program_string == "1" {
+            (Some(rest.trim()), false)
+        } else {
+            (None, next_token(rest)?.0 == "1")
+        };
+        Some(Self {
+            frame_type,
+            address,
+            code_size,
+            prologue_size,
+            epilogue_size,
+            parameter_size,
+            saved_register_size,
+            local_size,
+            max_stack_size,
+            program_string,
+            allocates_base_pointer,
+        })
+    }
+}
+
+/// The unwind information for an address in a Breakpad `.sym` file.
+// The CFI rules are not boxed because unwinding must not allocate.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum BreakpadRecord<'a> {
+    Cfi(CfiRules<'a>),
+    Win(WinRecord<'a>),
+}
+
+/// Variables for postfix expression evaluation: register values, `.cfa`, and the
+/// temporaries and outputs of `STACK WIN` program strings.
+#[derive(Debug, Clone, Default)]
+pub struct Variables<'a> {
+    values: ArrayVec<(&'a str, u64), MAX_VARIABLES>,
+}
+
+impl<'a> Variables<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.values
+            .iter()
+            .find(|(var_name, _)| *var_name == name)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn set(&mut self, name: &'a str, value: u64) -> Result<(), BreakpadUnwinderError> {
+        match self
+            .values
+            .iter_mut()
+            .find(|(var_name, _)| *var_name == name)
+        {
+            Some(var) => var.1 = value,
+            None => self
+                .values
+                .try_push((name, value))
+                .map_err(|_| BreakpadUnwinderError::ExpressionTooComplex)?,
+        }
+        Ok(())
+    }
+}
+
+enum StackItem<'a> {
+    Value(u64),
+    Name(&'a str),
+}
+
+/// Evaluates a Breakpad postfix expression.
+///
+/// Supports the binary operators `+ - * / % @` (`@` aligns down), the dereference
+/// operator `^`, and the assignment operator `=` which is used in `STACK WIN` program
+/// strings. Returns the value which is left on the stack, or `None` if the stack is
+/// empty, which is the case for program strings that only consist of assignments.
+pub fn evaluate<'a, F>(
+    expr: &'a str,
+    variables: &mut Variables<'a>,
+    read_stack: &mut F,
+) -> Result<Option<u64>, BreakpadUnwinderError>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let mut stack: ArrayVec<StackItem<'a>, MAX_STACK_DEPTH> = ArrayVec::new();
+    let pop_value = |stack: &mut ArrayVec<StackItem<'a>, MAX_STACK_DEPTH>,
+                     variables: &Variables<'a>| {
+        match stack.pop() {
+            Some(StackItem::Value(value)) => Ok(value),
+            Some(StackItem::Name(name)) => variables
+                .get(name)
+                .ok_or(BreakpadUnwinderError::UndefinedVariable),
+            None => Err(BreakpadUnwinderError::InvalidExpression),
+        }
+    };
+
+    for token in expr.split_ascii_whitespace() {
+        let item = match token {
+            "+" | "-" | "*" | "/" | "%" | "@" => {
+                let b = pop_value(&mut stack, variables)?;
+                let a = pop_value(&mut stack, variables)?;
+                let value = match token {
+                    "+" => a.wrapping_add(b),
+                    "-" => a.wrapping_sub(b),
+                    "*" => a.wrapping_mul(b),
+                    "/" => a
+                        .checked_div(b)
+                        .ok_or(BreakpadUnwinderError::DivisionByZero)?,
+                    "%" => a
+                        .checked_rem(b)
+                        .ok_or(BreakpadUnwinderError::DivisionByZero)?,
+                    _ => a & !b.wrapping_sub(1),
+                };
+                StackItem::Value(value)
+            }
+            "^" => {
+                let address = pop_value(&mut stack, variables)?;
+                let value = read_stack(address)
+                    .map_err(|_| BreakpadUnwinderError::CouldNotReadStack(address))?;
+                StackItem::Value(value)
+            }
+            "=" => {
+                let value = pop_value(&mut stack, variables)?;
+                let Some(StackItem::Name(name)) = stack.pop() else {
+                    return Err(BreakpadUnwinderError::InvalidExpression);
+                };
+                variables.set(name, value)?;
+                continue;
+            }
+            _ => match token.parse::<i64>() {
+                Ok(number) => StackItem::Value(number as u64),
+                Err(_) => StackItem::Name(token),
+            },
+        };
+        stack
+            .try_push(item)
+            .map_err(|_| BreakpadUnwinderError::ExpressionTooComplex)?;
+    }
+
+    match stack.len() {
+        0 => Ok(None),
+        1 => pop_value(&mut stack, variables).map(Some),
+        _ => Err(BreakpadUnwinderError::InvalidExpression),
+    }
+}
+
+pub struct BreakpadUnwinder<'a, A: BreakpadUnwinding> {
+    sym: &'a [u8],
+    index: &'a BreakpadIndex,
+    _arch: PhantomData<A>,
+}
+
+impl<'a, A: BreakpadUnwinding> BreakpadUnwinder<'a, A> {
+    pub fn new(sym: &'a [u8], index: &'a BreakpadIndex) -> Self {
+        Self {
+            sym,
+            index,
+            _arch: PhantomData,
+        }
+    }
+
+    pub fn record_for_address(
+        &self,
+        address: u32,
+    ) -> Result<BreakpadRecord<'a>, BreakpadUnwinderError> {
+        let entry = self
+            .index
+            .entry_for_address(address)
+            .ok_or(BreakpadUnwinderError::AddressNotCovered(address))?;
+        let invalid = || BreakpadUnwinderError::InvalidRecord(entry.offset);
+        let (line, mut next_offset) = line_at(self.sym, entry.offset as usize);
+        let line = line.ok_or_else(invalid)?;
+
+        if entry.kind != RecordKind::Cfi {
+            let record = line
+                .strip_prefix("STACK WIN ")
+                .and_then(WinRecord::parse)
+                .ok_or_else(invalid)?;
+            return Ok(BreakpadRecord::Win(record));
+        }
+
+        // STACK CFI INIT <address> <size> <rules>
+        let (_, rest) = line
+            .strip_prefix("STACK CFI INIT ")
+            .and_then(parse_hex_token)
+            .and_then(|(_, rest)| parse_hex_token(rest))
+            .ok_or_else(invalid)?;
+        let mut rules = CfiRules::default();
+        rules.apply(rest, entry.offset)?;
+
+        // The following STACK CFI <address> <rules> records are sorted by address and
+        // describe how the rules change within the function.
+        while next_offset < self.sym.len() {
+            let (line, following_offset) = line_at(self.sym, next_offset);
+            let Some(rest) = line.and_then(|line| line.strip_prefix("STACK CFI ")) else {
+                break;
+            };
+            if rest.starts_with("INIT ") {
+                break;
+            }
+            let offset = next_offset as u32;
+            let (row_address, rest) =
+                parse_hex_token(rest).ok_or(BreakpadUnwinderError::InvalidRecord(offset))?;
+            if row_address > address {
+                break;
+            }
+            rules.apply(rest, offset)?;
+            next_offset = following_offset;
+        }
+        Ok(BreakpadRecord::Cfi(rules))
+    }
+
+    pub fn unwind_frame<F>(
+        &self,
+        address: u32,
+        regs: &mut A::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<A::UnwindRule>, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let record = self.record_for_address(address)?;
+        A::unwind_frame(record, regs, is_first_frame, read_stack)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::x86_64::ArchX86_64;
+
+    const SYM: &str = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so\r
+FILE 0 test.c\r
+FUNC 1000 30 0 test_function\r
+1000 30 3 0\r
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^\r
+STACK CFI 1001 .cfa: $rsp 16 + $rbp: .cfa -16 + ^\r
+STACK CFI 1004 .cfa: $rbp 16 +\r
+STACK CFI 102e .cfa: $rsp 8 +\r
+STACK CFI INIT 2000 10 .cfa: $rsp 8 + .ra: .cfa -8 + ^\r
+STACK WIN 4 3000 20 3 0 8 0 4 0 1 $T0 $ebp = $eip $T0 4 + ^ = $ebp $T0 ^ = $esp $T0 8 + =\r
+STACK WIN 0 3000 20 3 0 8 0 4 0 0 1\r
+";
+
+    fn names() -> CfiRegisterNames {
+        CfiRegisterNames {
+            sp: "$rsp",
+            fp: "$rbp",
+            ra: None,
+        }
+    }
+
+    #[test]
+    fn test_cfi_rules() {
+        let index = BreakpadIndex::new(SYM.as_bytes());
+        assert_eq!(index.address_size(), 8);
+        let unwinder = BreakpadUnwinder::<ArchX86_64>::new(SYM.as_bytes(), &index);
+        let row = |address| match unwinder.record_for_address(address) {
+            Ok(BreakpadRecord::Cfi(rules)) => rules.simple_row(&names()),
+            _ => None,
+        };
+        let row_at_cfa = |cfa_base, cfa_offset, fp_offset| {
+            Some(SimpleCfiRow {
+                cfa_base,
+                cfa_offset,
+                ra_offset: Some(-8),
+                fp_offset,
+            })
+        };
+        assert_eq!(row(0x1000), row_at_cfa(SimpleCfaBase::Sp, 8, None));
+        assert_eq!(row(0x1003), row_at_cfa(SimpleCfaBase::Sp, 16, Some(-16)));
+        assert_eq!(row(0x1010), row_at_cfa(SimpleCfaBase::Fp, 16, Some(-16)));
+        assert_eq!(row(0x102f), row_at_cfa(SimpleCfaBase::Sp, 8, Some(-16)));
+        assert_eq!(row(0x2000), row_at_cfa(SimpleCfaBase::Sp, 8, None));
+        assert!(matches!(
+            unwinder.record_for_address(0x1030),
+            Err(BreakpadUnwinderError::AddressNotCovered(0x1030))
+        ));
+
+        // The FRAME_DATA record is preferred over the FPO record.
+        match unwinder.record_for_address(0x3010) {
+            Ok(BreakpadRecord::Win(record)) => {
+                assert_eq!(record.frame_type, WinFrameType::FrameData);
+                assert_eq!(record.parameter_size, 8);
+                assert_eq!(record.local_size, 4);
+                assert_eq!(
+                    record.program_string,
+                    Some("$T0 $ebp = $eip $T0 4 + ^ = $ebp $T0 ^ = $esp $T0 8 + =")
+                );
+            }
+            _ => panic!("expected a STACK WIN record"),
+        };
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut read_stack = |addr: u64| if addr < 0x1000 { Ok(addr * 2) } else { Err(()) };
+        let mut variables = Variables::new();
+        variables.set("$esp", 0x100).unwrap();
+        variables.set("$ebp", 0x200).unwrap();
+
+        assert_eq!(
+            evaluate("$esp 8 + ^", &mut variables, &mut read_stack),
+            Ok(Some(0x210))
+        );
+        assert_eq!(
+            evaluate("$ebp 7 - 16 @", &mut variables, &mut read_stack),
+            Ok(Some(0x1f0))
+        );
+        assert_eq!(
+            evaluate(
+                "$T0 $ebp = $eip $T0 4 + ^ = $esp $T0 8 + =",
+                &mut variables,
+                &mut read_stack
+            ),
+            Ok(None)
+        );
+        assert_eq!(variables.get("$eip"), Some(0x408));
+        assert_eq!(variables.get("$esp"), Some(0x208));
+        assert_eq!(
+            evaluate("$eax 4 +", &mut variables, &mut read_stack),
+            Err(BreakpadUnwinderError::UndefinedVariable)
+        );
+        assert_eq!(
+            evaluate("$esp 0 /", &mut variables, &mut read_stack),
+            Err(BreakpadUnwinderError::DivisionByZero)
+        );
+        assert_eq!(
+            evaluate("0x2000 ^", &mut variables, &mut read_stack),
+            Err(BreakpadUnwinderError::UndefinedVariable)
+        );
+        assert_eq!(
+            evaluate("$ebp 4096 + ^", &mut variables, &mut read_stack),
+            Err(BreakpadUnwinderError::CouldNotReadStack(0x1200))
+        );
+    }
+}
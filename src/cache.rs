@@ -1,8 +1,10 @@
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 
-use crate::{rule_cache::RuleCache, unwind_rule::UnwindRule};
+use crate::rule_cache::{RuleCache, SharedRuleCache};
+use crate::unwind_rule::UnwindRule;
 
-pub use crate::rule_cache::CacheStats;
+pub use crate::rule_cache::{CacheAssociativity, CacheConfig, CacheKeyMode, CacheStats};
 
 /// A trait which lets you opt into allocation-free unwinding. The two implementations of
 /// this trait are [`MustNotAllocateDuringUnwind`] and [`MayAllocateDuringUnwind`].
@@ -58,7 +60,9 @@ impl AllocationPolicy for MayAllocateDuringUnwind {
 /// A single unwinder cache can be used with multiple unwinders alternatingly.
 ///
 /// The cache stores unwind rules for addresses it has seen before, and it stores the
-/// unwind context which gimli needs for DWARF CFI evaluation.
+/// unwind context which gimli needs for DWARF CFI evaluation. If you unwind on multiple
+/// threads, each thread needs its own cache, but the unwind rules can be shared between
+/// them with a [`SharedCache`].
 pub struct Cache<R: UnwindRule, P: AllocationPolicy = MayAllocateDuringUnwind> {
     pub(crate) gimli_unwind_context:
         Box<gimli::UnwindContext<usize, P::GimliUnwindContextStorage<usize>>>,
@@ -67,9 +71,22 @@ pub struct Cache<R: UnwindRule, P: AllocationPolicy = MayAllocateDuringUnwind> {
 
 impl<R: UnwindRule, P: AllocationPolicy> Cache<R, P> {
     pub fn new() -> Self {
+        Self::with_config(CacheConfig::default())
+    }
+
+    /// Create a cache whose rule cache has the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self {
+            gimli_unwind_context: Box::new(gimli::UnwindContext::new_in()),
+            rule_cache: RuleCache::new(config),
+        }
+    }
+
+    /// Create a cache which stores its unwind rules in `shared`.
+    pub fn new_shared(shared: &SharedCache<R>) -> Self {
         Self {
             gimli_unwind_context: Box::new(gimli::UnwindContext::new_in()),
-            rule_cache: RuleCache::new(),
+            rule_cache: RuleCache::new_shared(shared.0.clone()),
         }
     }
 }
@@ -79,3 +96,40 @@ impl<R: UnwindRule, P: AllocationPolicy> Default for Cache<R, P> {
         Self::new()
     }
 }
+
+/// Unwind rule storage which can be shared by the caches of multiple threads, so that
+/// an unwind rule which was found on one thread is a cache hit on all other threads.
+///
+/// Lookups and insertions are lock-free and don't allocate, so the sharing caches can be
+/// used from signal handlers. Cloning a `SharedCache` gives you another handle to the
+/// same storage.
+pub struct SharedCache<R: UnwindRule>(Arc<SharedRuleCache<R>>);
+
+impl<R: UnwindRule> SharedCache<R> {
+    pub fn new() -> Self {
+        Self::with_config(CacheConfig::default())
+    }
+
+    /// Create shared rule storage with the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(Arc::new(SharedRuleCache::new(config)))
+    }
+
+    /// Returns a snapshot of the cache usage statistics, summed up over all caches
+    /// which share these rules.
+    pub fn stats(&self) -> CacheStats {
+        self.0.stats()
+    }
+}
+
+impl<R: UnwindRule> Clone for SharedCache<R> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<R: UnwindRule> Default for SharedCache<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
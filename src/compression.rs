@@ -0,0 +1,187 @@
+use core::ops::Deref;
+
+#[cfg(feature = "compression")]
+use alloc::vec::Vec;
+
+use crate::ModuleSectionInfo;
+
+/// The bytes of a section which may have been stored compressed in the module.
+///
+/// Uncompressed sections keep using the original section data. Compressed sections are
+/// decompressed when the module is added, and the decompressed bytes are owned by this
+/// type.
+pub enum MaybeDecompressed<D> {
+    Original(D),
+    #[cfg(feature = "compression")]
+    Decompressed(Vec<u8>),
+}
+
+impl<D: Deref<Target = [u8]>> Deref for MaybeDecompressed<D> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            MaybeDecompressed::Original(data) => data,
+            #[cfg(feature = "compression")]
+            MaybeDecompressed::Decompressed(data) => data,
+        }
+    }
+}
+
+/// Returns the `.debug_frame` data of the module.
+///
+/// With the `compression` feature, this also looks for the legacy `.zdebug_frame` section
+/// and decompresses sections with an ELF compression header (`SHF_COMPRESSED`) or with a
+/// `ZLIB` prefix. Returns `None` if the section is missing or can't be decompressed.
+pub fn debug_frame_data<D: Deref<Target = [u8]>>(
+    section_info: &mut impl ModuleSectionInfo<D>,
+) -> Option<MaybeDecompressed<D>> {
+    #[cfg(feature = "compression")]
+    {
+        let data = section_info
+            .section_data(b".debug_frame")
+            .or_else(|| section_info.section_data(b".zdebug_frame"))
+            .or_else(|| section_info.section_data(b"__zdebug_frame"))?;
+        match decompress_if_compressed(&data, section_info.address_size()) {
+            Ok(Some(decompressed)) => Some(MaybeDecompressed::Decompressed(decompressed)),
+            Ok(None) => Some(MaybeDecompressed::Original(data)),
+            Err(()) => None,
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    section_info
+        .section_data(b".debug_frame")
+        .map(MaybeDecompressed::Original)
+}
+
+#[cfg(feature = "compression")]
+const ELFCOMPRESS_ZLIB: u32 = 1;
+#[cfg(feature = "compression")]
+const ELFCOMPRESS_ZSTD: u32 = 2;
+
+/// Decompresses the section data if it starts with an ELF compression header (`Elf32_Chdr`
+/// or `Elf64_Chdr`, depending on `address_size`) or with the `ZLIB` prefix used by
+/// `.zdebug_*` sections.
+///
+/// Returns `Ok(None)` if the data isn't compressed. DWARF CFI can't be mistaken for a
+/// compression header: it starts with the length of a CIE, which is never 1 or 2.
+#[cfg(feature = "compression")]
+fn decompress_if_compressed(data: &[u8], address_size: u8) -> Result<Option<Vec<u8>>, ()> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            data.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    if let Some(header) = data.strip_prefix(b"ZLIB") {
+        let size = u64::from_be_bytes(header.get(..8).ok_or(())?.try_into().map_err(|_| ())?);
+        return decompress_zlib(&header[8..], size).map(Some);
+    }
+
+    let Some(ch_type) = read_u32(0) else {
+        return Ok(None);
+    };
+    if ch_type != ELFCOMPRESS_ZLIB && ch_type != ELFCOMPRESS_ZSTD {
+        return Ok(None);
+    }
+    let (size, compressed) = if address_size == 8 {
+        // Elf64_Chdr: ch_type, ch_reserved, ch_size (u64), ch_addralign (u64)
+        if read_u32(4) != Some(0) {
+            return Ok(None);
+        }
+        let size = u64::from_le_bytes(data.get(8..16).ok_or(())?.try_into().map_err(|_| ())?);
+        (size, data.get(24..).ok_or(())?)
+    } else {
+        // Elf32_Chdr: ch_type, ch_size, ch_addralign
+        let size = read_u32(4).ok_or(())?;
+        (u64::from(size), data.get(12..).ok_or(())?)
+    };
+    match ch_type {
+        ELFCOMPRESS_ZLIB => decompress_zlib(compressed, size).map(Some),
+        _ => decompress_zstd(compressed, size).map(Some),
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decompress_zlib(compressed: &[u8], size: u64) -> Result<Vec<u8>, ()> {
+    let size = usize::try_from(size).map_err(|_| ())?;
+    let decompressed = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, size)
+        .map_err(|_| ())?;
+    if decompressed.len() != size {
+        return Err(());
+    }
+    Ok(decompressed)
+}
+
+#[cfg(feature = "compression")]
+fn decompress_zstd(compressed: &[u8], size: u64) -> Result<Vec<u8>, ()> {
+    let size = usize::try_from(size).map_err(|_| ())?;
+    // The size comes from the file, so don't abort if it's bogus and can't be allocated.
+    let mut decompressed = Vec::new();
+    decompressed.try_reserve_exact(size).map_err(|_| ())?;
+    ruzstd::decoding::FrameDecoder::new()
+        .decode_all_to_vec(compressed, &mut decompressed)
+        .map_err(|_| ())?;
+    if decompressed.len() != size {
+        return Err(());
+    }
+    Ok(decompressed)
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod test {
+    use super::*;
+
+    const DEBUG_FRAME: &[u8] = &[
+        0x14, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 1, 0, 4, 0x78, 0x10, 0x0c, 0x07, 0x08, 0x90, 1, 0,
+        0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn test_decompress() {
+        let size = DEBUG_FRAME.len() as u64;
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(DEBUG_FRAME, 6);
+        let zstd = ruzstd::encoding::compress_to_vec(
+            DEBUG_FRAME,
+            ruzstd::encoding::CompressionLevel::Fastest,
+        );
+
+        assert_eq!(decompress_if_compressed(DEBUG_FRAME, 8), Ok(None));
+        assert_eq!(decompress_if_compressed(DEBUG_FRAME, 4), Ok(None));
+
+        let chdr64 = |ch_type: u32, compressed: &[u8]| {
+            [
+                &ch_type.to_le_bytes()[..],
+                &[0; 4],
+                &size.to_le_bytes(),
+                &8u64.to_le_bytes(),
+                compressed,
+            ]
+            .concat()
+        };
+        let chdr32 = |ch_type: u32, compressed: &[u8]| {
+            [
+                &ch_type.to_le_bytes()[..],
+                &(size as u32).to_le_bytes(),
+                &4u32.to_le_bytes(),
+                compressed,
+            ]
+            .concat()
+        };
+        let expected = Ok(Some(DEBUG_FRAME.to_vec()));
+        assert_eq!(decompress_if_compressed(&chdr64(1, &zlib), 8), expected);
+        assert_eq!(decompress_if_compressed(&chdr64(2, &zstd), 8), expected);
+        assert_eq!(decompress_if_compressed(&chdr32(1, &zlib), 4), expected);
+        assert_eq!(decompress_if_compressed(&chdr32(2, &zstd), 4), expected);
+
+        let zdebug = [&b"ZLIB"[..], &size.to_be_bytes(), &zlib].concat();
+        assert_eq!(decompress_if_compressed(&zdebug, 8), expected);
+
+        // Truncated compressed data must not be used.
+        assert_eq!(
+            decompress_if_compressed(&chdr64(1, &zlib[..zlib.len() - 4]), 8),
+            Err(())
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use crate::error::Error;
+use crate::stack_memory::ReadStack;
+use crate::unwinder::Unwinder;
+use crate::FrameAddress;
+
+/// A handle around an [`Unwinder`] whose modules can be added and removed through `&self`,
+/// while other threads are unwinding with it.
+///
+/// This is meant for live profiling, where modules are loaded and unloaded on one thread
+/// while sampler threads unwind. Each change is made on a copy of the current unwinder,
+/// which is then published atomically. Readers get a [`UnwinderSnapshot`] of the unwinder
+/// as it was when the snapshot was taken, and keep using it until they drop it.
+///
+/// Taking a snapshot and dropping it only does a few atomic operations: it never blocks,
+/// allocates or frees memory. So it's fine to unwind from a signal handler, as long as the
+/// cache uses [`MustNotAllocateDuringUnwind`](crate::MustNotAllocateDuringUnwind). It is the
+/// writer which frees the old unwinder, once all snapshots of it have been dropped. This
+/// means that changing the modules waits for the snapshots which are alive at that time,
+/// so don't hold on to a snapshot on the thread which changes the modules.
+pub struct ConcurrentUnwinder<U> {
+    current: AtomicPtr<U>,
+    /// Readers register in the reader count slot for the current epoch. Writers advance
+    /// the epoch after publishing a new unwinder, and then wait for the slot of the old
+    /// epoch to drain.
+    epoch: AtomicUsize,
+    readers: [AtomicUsize; 2],
+    /// Serializes writers.
+    writing: AtomicBool,
+    _marker: PhantomData<*const U>,
+}
+
+// The unwinder is shared with readers on other threads, and dropped by whichever thread
+// replaces it.
+unsafe impl<U: Send> Send for ConcurrentUnwinder<U> {}
+unsafe impl<U: Send + Sync> Sync for ConcurrentUnwinder<U> {}
+
+impl<U: Default> Default for ConcurrentUnwinder<U> {
+    fn default() -> Self {
+        Self::new(U::default())
+    }
+}
+
+impl<U> Drop for ConcurrentUnwinder<U> {
+    fn drop(&mut self) {
+        // We have exclusive access, so there can't be any snapshots.
+        drop(unsafe { Box::from_raw(*self.current.get_mut()) });
+    }
+}
+
+impl<U> ConcurrentUnwinder<U> {
+    /// Create a handle around `unwinder`.
+    pub fn new(unwinder: U) -> Self {
+        Self {
+            current: AtomicPtr::new(Box::into_raw(Box::new(unwinder))),
+            epoch: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writing: AtomicBool::new(false),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a snapshot of the current unwinder. Changes to the modules which are
+    /// made while the snapshot is alive don't affect it.
+    pub fn snapshot(&self) -> UnwinderSnapshot<'_, U> {
+        let slot = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let slot = &self.readers[epoch & 1];
+            slot.fetch_add(1, Ordering::SeqCst);
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break slot;
+            }
+            // A writer advanced the epoch in the meantime and might not wait for us.
+            slot.fetch_sub(1, Ordering::SeqCst);
+        };
+        // The writer which replaces this unwinder waits for our slot to drain before
+        // freeing it.
+        let unwinder = unsafe { &*self.current.load(Ordering::SeqCst) };
+        UnwinderSnapshot { unwinder, slot }
+    }
+
+    /// Makes a copy of the current unwinder, calls `f` on it and publishes the result.
+    /// Waits until the old unwinder is no longer used by any snapshot.
+    pub fn update(&self, f: impl FnOnce(&mut U))
+    where
+        U: Clone,
+    {
+        let _lock = WriteLock::acquire(&self.writing);
+        // Only writers free unwinders, and we're the only writer.
+        let mut unwinder = unsafe { &*self.current.load(Ordering::SeqCst) }.clone();
+        f(&mut unwinder);
+        let old = self
+            .current
+            .swap(Box::into_raw(Box::new(unwinder)), Ordering::SeqCst);
+
+        // Readers which registered in the new epoch's slot will see the new unwinder.
+        // Readers in the old epoch's slot may still be using the old one. The slot of
+        // the epoch before that was drained by the previous writer.
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+        while self.readers[epoch & 1].load(Ordering::SeqCst) != 0 {
+            wait();
+        }
+        drop(unsafe { Box::from_raw(old) });
+    }
+}
+
+impl<U: Unwinder> ConcurrentUnwinder<U> {
+    /// Add a module to the unwinder. See [`Unwinder::add_module`].
+    pub fn add_module(&self, module: U::Module) {
+        self.update(|unwinder| unwinder.add_module(module));
+    }
+
+    /// Remove a module from the unwinder. See [`Unwinder::remove_module`].
+    pub fn remove_module(&self, module_avma_range_start: u64) {
+        self.update(|unwinder| unwinder.remove_module(module_avma_range_start));
+    }
+
+    /// Unwind a single frame with the current unwinder. See [`Unwinder::unwind_frame`].
+    ///
+    /// To unwind an entire stack with the same modules, take a [`snapshot`](Self::snapshot)
+    /// and call [`Unwinder::iter_frames`] on it.
+    pub fn unwind_frame<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut U::UnwindRegs,
+        cache: &mut U::Cache,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, Error>
+    where
+        F: ReadStack,
+    {
+        self.snapshot()
+            .unwind_frame(address, regs, cache, read_stack)
+    }
+}
+
+/// A snapshot of the unwinder in a [`ConcurrentUnwinder`]. Derefs to the unwinder.
+pub struct UnwinderSnapshot<'a, U> {
+    unwinder: &'a U,
+    slot: &'a AtomicUsize,
+}
+
+impl<U> Deref for UnwinderSnapshot<'_, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        self.unwinder
+    }
+}
+
+impl<U> Drop for UnwinderSnapshot<'_, U> {
+    fn drop(&mut self) {
+        self.slot.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct WriteLock<'a>(&'a AtomicBool);
+
+impl<'a> WriteLock<'a> {
+    fn acquire(writing: &'a AtomicBool) -> Self {
+        while writing
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            wait();
+        }
+        Self(writing)
+    }
+}
+
+impl Drop for WriteLock<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+fn wait() {
+    #[cfg(feature = "std")]
+    std::thread::yield_now();
+    #[cfg(not(feature = "std"))]
+    core::hint::spin_loop();
+}
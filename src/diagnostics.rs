@@ -0,0 +1,89 @@
+use crate::dwarf::ConversionError;
+use crate::error::UnwinderError;
+
+/// The kind of unwind information that was used to unwind a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnwindSource {
+    /// An opcode from the `__unwind_info` section.
+    #[cfg(feature = "macho")]
+    CompactUnwindInfo,
+    /// The `__eh_frame` FDE which an `__unwind_info` opcode referred to.
+    #[cfg(feature = "macho")]
+    CompactUnwindInfoEhFrame,
+    /// A rule derived from the function's instructions, because the frame's address was
+    /// in a prologue or an epilogue.
+    #[cfg(feature = "macho")]
+    InstructionAnalysis,
+    /// `.eh_frame`, looked up via `.eh_frame_hdr`.
+    EhFrameHdr,
+    /// `.eh_frame`, looked up via an index which was built when the module was added.
+    EhFrameDwarfCfiIndex,
+    /// `.debug_frame`.
+    DebugFrame,
+    /// The `.debug_frame` of the MiniDebugInfo in `.gnu_debugdata`.
+    #[cfg(feature = "gnu-debugdata")]
+    GnuDebugdata,
+    /// PE unwind information, from the `.pdata` and `.xdata` sections.
+    #[cfg(feature = "pe")]
+    Pe,
+    /// `.ARM.exidx` and `.ARM.extab`.
+    ArmExidx,
+    /// Linux kernel ORC unwind tables.
+    Orc,
+    /// `.sframe`.
+    SFrame,
+    /// A Breakpad symbol file.
+    Breakpad,
+    /// The module's [precompiled rule table](crate::Module::with_precompiled_rules),
+    /// which was compiled from the module's unwind information.
+    PrecompiledRules,
+    /// A [`SerializedRuleTable`](crate::SerializedRuleTable).
+    SerializedRuleTable,
+    /// The address is in a signal return trampoline which was recognized by its
+    /// instructions or by its symbol, and the registers were recovered from the signal
+    /// frame on the stack. Trampolines which are marked as such in their DWARF CFI are
+    /// reported with the source of the CFI.
+    SignalFrame,
+    /// No unwind information could be used, and the frame was unwound with the arch's
+    /// fallback rule, which uses the frame pointer. This happens if no module covers the
+    /// address, or if the module's unwind information failed; see
+    /// [`FrameDiagnostics::error`].
+    Fallback,
+    /// The frame was not unwound, because the unwinder, the registers and the cache of an
+    /// [`AnyUnwinder`](crate::AnyUnwinder) are for different CPU architectures. Unwinding
+    /// failed with [`Error::ArchitectureMismatch`](crate::Error::ArchitectureMismatch).
+    ArchitectureMismatch,
+}
+
+/// Describes how a single frame was unwound. Returned by
+/// [`Unwinder::unwind_frame_with_diagnostics`](crate::Unwinder::unwind_frame_with_diagnostics)
+/// and [`UnwindIterator::next_with_diagnostics`](crate::UnwindIterator::next_with_diagnostics).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameDiagnostics {
+    /// The unwind information that was used for this frame.
+    pub source: UnwindSource,
+    /// The error which made the unwinder give up on the module's unwind information.
+    /// This is only set if `source` is [`UnwindSource::Fallback`]. It is `None` if
+    /// no module covers the address.
+    pub error: Option<UnwinderError>,
+    /// Set if the DWARF CFI row for this frame could not be translated into a cacheable
+    /// unwind rule. The row was then evaluated directly.
+    pub conversion_error: Option<ConversionError>,
+}
+
+impl FrameDiagnostics {
+    pub(crate) fn new() -> Self {
+        Self {
+            source: UnwindSource::Fallback,
+            error: None,
+            conversion_error: None,
+        }
+    }
+
+    pub(crate) fn with_source(source: UnwindSource) -> Self {
+        Self {
+            source,
+            ..Self::new()
+        }
+    }
+}
@@ -4,12 +4,15 @@ use alloc::vec::Vec;
 use gimli::{
     CfaRule, CieOrFde, DebugFrame, EhFrame, EhFrameHdr, Encoding, EndianSlice, Evaluation,
     EvaluationResult, EvaluationStorage, Expression, LittleEndian, Location, ParsedEhFrameHdr,
-    Reader, ReaderOffset, Register, RegisterRule, UnwindContext, UnwindContextStorage,
+    Reader, ReaderOffset, Register, RegisterRule, StoreOnHeap, UnwindContext, UnwindContextStorage,
     UnwindOffset, UnwindSection, UnwindTableRow, Value,
 };
 
 pub(crate) use gimli::BaseAddresses;
 
+use crate::rule_table::{RuleTable, RuleTableBuilder};
+use crate::signal_frame::{SignalFrameUnwinderError, SignalFrameUnwinding};
+use crate::unwind_rule::UnwindRule;
 use crate::{arch::Arch, unwind_result::UnwindResult, ModuleSectionInfo};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,6 +24,7 @@ pub enum DwarfUnwinderError {
     CouldNotRecoverCfa,
     CouldNotRecoverReturnAddress,
     CouldNotRecoverFramePointer,
+    SignalFrame(SignalFrameUnwinderError),
 }
 
 impl core::fmt::Display for DwarfUnwinderError {
@@ -38,6 +42,7 @@ impl core::fmt::Display for DwarfUnwinderError {
             Self::CouldNotRecoverCfa => write!(f, "Could not recover the CFA"),
             Self::CouldNotRecoverReturnAddress => write!(f, "Could not recover the return address"),
             Self::CouldNotRecoverFramePointer => write!(f, "Could not recover the frame pointer"),
+            Self::SignalFrame(err) => write!(f, "Could not unwind the signal frame: {err}"),
         }
     }
 }
@@ -48,12 +53,15 @@ impl std::error::Error for DwarfUnwinderError {
         match self {
             Self::FdeFromOffsetFailed(e) => Some(e),
             Self::UnwindInfoForAddressFailed(e) => Some(e),
+            Self::SignalFrame(e) => Some(e),
             _ => None,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// The reason why a DWARF CFI row could not be translated into a cacheable unwind rule.
+/// Such rows are evaluated with the generic DWARF path, which is slower and uncacheable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConversionError {
     CfaIsExpression,
     CfaIsOffsetFromUnknownRegister,
@@ -71,7 +79,84 @@ pub enum ConversionError {
     FramePointerRuleHasStrangeBpOffset,
 }
 
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CfaIsExpression => write!(f, "The CFA is a DWARF expression"),
+            Self::CfaIsOffsetFromUnknownRegister => {
+                write!(f, "The CFA is an offset from an unsupported register")
+            }
+            Self::ReturnAddressRuleWithUnexpectedOffset => {
+                write!(f, "The return address is stored at an unexpected offset")
+            }
+            Self::ReturnAddressRuleWasWeird => {
+                write!(f, "The return address rule is not an offset from the CFA")
+            }
+            Self::SpOffsetDoesNotFit => write!(f, "The stack pointer offset does not fit"),
+            Self::RegisterNotStoredRelativeToCfa => {
+                write!(f, "A register is not stored relative to the CFA")
+            }
+            Self::RestoringFpButNotLr => {
+                write!(
+                    f,
+                    "The frame pointer is restored but the link register is not"
+                )
+            }
+            Self::LrStorageOffsetDoesNotFit => {
+                write!(f, "The link register storage offset does not fit")
+            }
+            Self::FpStorageOffsetDoesNotFit => {
+                write!(f, "The frame pointer storage offset does not fit")
+            }
+            Self::SpOffsetFromFpDoesNotFit => {
+                write!(
+                    f,
+                    "The stack pointer offset from the frame pointer does not fit"
+                )
+            }
+            Self::FramePointerRuleDoesNotRestoreLr => {
+                write!(
+                    f,
+                    "The frame pointer rule does not restore the link register"
+                )
+            }
+            Self::FramePointerRuleDoesNotRestoreFp => {
+                write!(
+                    f,
+                    "The frame pointer rule does not restore the frame pointer"
+                )
+            }
+            Self::FramePointerRuleDoesNotRestoreBp => {
+                write!(
+                    f,
+                    "The frame pointer rule does not restore the base pointer"
+                )
+            }
+            Self::FramePointerRuleHasStrangeBpOffset => {
+                write!(
+                    f,
+                    "The frame pointer rule has an unexpected base pointer offset"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {}
+
 pub trait DwarfUnwinding: Arch {
+    /// Translates the CFI row into a cacheable unwind rule, if it has a shape that
+    /// the arch's unwind rule type can express.
+    fn rule_for_row<O, UCS>(
+        unwind_info: &UnwindTableRow<O, UCS>,
+    ) -> Result<Self::UnwindRule, ConversionError>
+    where
+        O: ReaderOffset,
+        UCS: UnwindContextStorage<O>;
+
+    /// Evaluates the CFI row directly. This is used for rows which `rule_for_row`
+    /// could not translate.
     fn unwind_frame<F, R, UCS, ES>(
         section: &impl UnwindSection<R>,
         unwind_info: &UnwindTableRow<R::Offset, UCS>,
@@ -97,7 +182,7 @@ pub enum UnwindSectionType {
 pub struct DwarfUnwinder<'a, R, A, UCS>
 where
     R: Reader,
-    A: DwarfUnwinding,
+    A: DwarfUnwinding + SignalFrameUnwinding,
     UCS: UnwindContextStorage<R::Offset>,
 {
     unwind_section_data: R,
@@ -106,13 +191,14 @@ where
     unwind_context: &'a mut UnwindContext<R::Offset, UCS>,
     base_svma: u64,
     bases: BaseAddresses,
+    address_size: u8,
     _arch: PhantomData<A>,
 }
 
 impl<'a, R, A, UCS> DwarfUnwinder<'a, R, A, UCS>
 where
     R: Reader,
-    A: DwarfUnwinding,
+    A: DwarfUnwinding + SignalFrameUnwinding,
     UCS: UnwindContextStorage<R::Offset>,
 {
     pub fn new(
@@ -122,11 +208,12 @@ where
         unwind_context: &'a mut UnwindContext<R::Offset, UCS>,
         bases: BaseAddresses,
         base_svma: u64,
+        address_size: u8,
     ) -> Self {
         let eh_frame_hdr = match eh_frame_hdr_data {
             Some(eh_frame_hdr_data) => {
                 let hdr = EhFrameHdr::new(eh_frame_hdr_data, unwind_section_data.endian());
-                hdr.parse(&bases, 8).ok()
+                hdr.parse(&bases, address_size).ok()
             }
             None => None,
         };
@@ -137,6 +224,7 @@ where
             unwind_context,
             bases,
             base_svma,
+            address_size,
             _arch: PhantomData,
         }
     }
@@ -150,6 +238,7 @@ where
         fde_offset.0.into_u64().try_into().ok()
     }
 
+    #[cfg(feature = "macho")]
     pub fn unwind_frame_with_fde<F, ES>(
         &mut self,
         regs: &mut A::UnwindRegs,
@@ -157,7 +246,37 @@ where
         rel_lookup_address: u32,
         fde_offset: u32,
         read_stack: &mut F,
+        conversion_error: &mut Option<ConversionError>,
     ) -> Result<UnwindResult<A::UnwindRule>, DwarfUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+        ES: EvaluationStorage<R>,
+    {
+        let unwind_result = self.try_unwind_frame_with_fde::<F, ES>(
+            regs,
+            is_first_frame,
+            rel_lookup_address,
+            fde_offset,
+            read_stack,
+            conversion_error,
+        )?;
+        Ok(unwind_result.unwrap_or(UnwindResult::ExecRule(A::rule_if_uncovered_by_fde())))
+    }
+
+    /// Like `unwind_frame_with_fde`, but returns `Ok(None)` if the FDE does not cover the
+    /// address.
+    ///
+    /// If the CFI row could not be translated into an unwind rule, the reason is stored
+    /// in `conversion_error` and the row is evaluated directly.
+    pub fn try_unwind_frame_with_fde<F, ES>(
+        &mut self,
+        regs: &mut A::UnwindRegs,
+        is_first_frame: bool,
+        rel_lookup_address: u32,
+        fde_offset: u32,
+        read_stack: &mut F,
+        conversion_error: &mut Option<ConversionError>,
+    ) -> Result<Option<UnwindResult<A::UnwindRule>>, DwarfUnwinderError>
     where
         F: FnMut(u64) -> Result<u64, ()>,
         ES: EvaluationStorage<R>,
@@ -167,12 +286,25 @@ where
         match self.unwind_section_type {
             UnwindSectionType::EhFrame => {
                 let mut eh_frame = EhFrame::from(unwind_section_data);
-                eh_frame.set_address_size(8);
+                eh_frame.set_address_size(self.address_size);
                 let unwind_info = self.unwind_info_for_fde(&eh_frame, lookup_svma, fde_offset);
                 if let Err(DwarfUnwinderError::UnwindInfoForAddressFailed(_)) = unwind_info {
-                    return Ok(UnwindResult::ExecRule(A::rule_if_uncovered_by_fde()));
+                    return Ok(None);
+                }
+                let FdeUnwindInfo {
+                    row: unwind_info,
+                    encoding,
+                    is_signal_trampoline,
+                } = unwind_info?;
+                if is_signal_trampoline {
+                    return A::unwind_linux_signal_frame(regs, read_stack)
+                        .map(Some)
+                        .map_err(DwarfUnwinderError::SignalFrame);
+                }
+                match A::rule_for_row(unwind_info) {
+                    Ok(rule) => return Ok(Some(UnwindResult::ExecRule(rule))),
+                    Err(err) => *conversion_error = Some(err),
                 }
-                let (unwind_info, encoding) = unwind_info?;
                 A::unwind_frame::<F, R, UCS, ES>(
                     &eh_frame,
                     unwind_info,
@@ -181,15 +313,29 @@ where
                     is_first_frame,
                     read_stack,
                 )
+                .map(Some)
             }
             UnwindSectionType::DebugFrame => {
                 let mut debug_frame = DebugFrame::from(unwind_section_data);
-                debug_frame.set_address_size(8);
+                debug_frame.set_address_size(self.address_size);
                 let unwind_info = self.unwind_info_for_fde(&debug_frame, lookup_svma, fde_offset);
                 if let Err(DwarfUnwinderError::UnwindInfoForAddressFailed(_)) = unwind_info {
-                    return Ok(UnwindResult::ExecRule(A::rule_if_uncovered_by_fde()));
+                    return Ok(None);
+                }
+                let FdeUnwindInfo {
+                    row: unwind_info,
+                    encoding,
+                    is_signal_trampoline,
+                } = unwind_info?;
+                if is_signal_trampoline {
+                    return A::unwind_linux_signal_frame(regs, read_stack)
+                        .map(Some)
+                        .map_err(DwarfUnwinderError::SignalFrame);
+                }
+                match A::rule_for_row(unwind_info) {
+                    Ok(rule) => return Ok(Some(UnwindResult::ExecRule(rule))),
+                    Err(err) => *conversion_error = Some(err),
                 }
-                let (unwind_info, encoding) = unwind_info?;
                 A::unwind_frame::<F, R, UCS, ES>(
                     &debug_frame,
                     unwind_info,
@@ -198,6 +344,7 @@ where
                     is_first_frame,
                     read_stack,
                 )
+                .map(Some)
             }
         }
     }
@@ -207,7 +354,7 @@ where
         unwind_section: &US,
         lookup_svma: u64,
         fde_offset: u32,
-    ) -> Result<(&UnwindTableRow<R::Offset, UCS>, Encoding), DwarfUnwinderError> {
+    ) -> Result<FdeUnwindInfo<'_, R::Offset, UCS>, DwarfUnwinderError> {
         let fde = unwind_section.fde_from_offset(
             &self.bases,
             US::Offset::from(R::Offset::from_u32(fde_offset)),
@@ -215,6 +362,7 @@ where
         );
         let fde = fde.map_err(DwarfUnwinderError::FdeFromOffsetFailed)?;
         let encoding = fde.cie().encoding();
+        let is_signal_trampoline = fde.cie().is_signal_trampoline();
         let unwind_info: &UnwindTableRow<_, _> = fde
             .unwind_info_for_address(
                 unwind_section,
@@ -223,10 +371,22 @@ where
                 lookup_svma,
             )
             .map_err(DwarfUnwinderError::UnwindInfoForAddressFailed)?;
-        Ok((unwind_info, encoding))
+        Ok(FdeUnwindInfo {
+            row: unwind_info,
+            encoding,
+            is_signal_trampoline,
+        })
     }
 }
 
+struct FdeUnwindInfo<'a, O: ReaderOffset, UCS: UnwindContextStorage<O>> {
+    row: &'a UnwindTableRow<O, UCS>,
+    encoding: Encoding,
+    /// Whether the CIE has the `S` augmentation, which marks the FDEs of signal return
+    /// trampolines. Their CFA is not a normal call frame.
+    is_signal_trampoline: bool,
+}
+
 pub(crate) fn base_addresses_for_sections<D>(
     section_info: &mut impl ModuleSectionInfo<D>,
 ) -> BaseAddresses {
@@ -356,7 +516,7 @@ impl DwarfCfiIndex {
     ) -> Result<Self, DwarfCfiIndexError> {
         let bases = base_addresses_for_sections(section_info);
         let mut eh_frame = EhFrame::from(EndianSlice::new(eh_frame_data, LittleEndian));
-        eh_frame.set_address_size(8);
+        eh_frame.set_address_size(section_info.address_size());
 
         Self::try_new(eh_frame, bases, section_info.base_svma())
     }
@@ -367,7 +527,7 @@ impl DwarfCfiIndex {
     ) -> Result<Self, DwarfCfiIndexError> {
         let bases = base_addresses_for_sections(section_info);
         let mut debug_frame = DebugFrame::from(EndianSlice::new(debug_frame_data, LittleEndian));
-        debug_frame.set_address_size(8);
+        debug_frame.set_address_size(section_info.address_size());
 
         Self::try_new(debug_frame, bases, section_info.base_svma())
     }
@@ -382,6 +542,63 @@ impl DwarfCfiIndex {
     }
 }
 
+/// Translates the rows of all FDEs in the section into unwind rules, for a module's
+/// [`RuleTable`]. Rows which can't be translated, and the FDEs of signal return
+/// trampolines, are left to the regular DWARF path.
+///
+/// `uncovered_rule` is stored for the addresses before the first FDE and after the end
+/// of each FDE, which the `.eh_frame_hdr` path unwinds with
+/// [`DwarfUnwinding::rule_if_uncovered_by_fde`]. If it is `None`, they are left to the
+/// regular DWARF path too.
+pub fn compile_rule_table<A, R, US>(
+    unwind_section: US,
+    bases: &BaseAddresses,
+    base_svma: u64,
+    uncovered_rule: Option<A::UnwindRule>,
+) -> Result<RuleTable, DwarfCfiIndexError>
+where
+    A: DwarfUnwinding,
+    R: Reader,
+    US: UnwindSection<R>,
+{
+    let mut fdes = Vec::new();
+    let mut entries_iter = unwind_section.entries(bases);
+    while let Some(entry) = entries_iter.next()? {
+        if let CieOrFde::Fde(partial_fde) = entry {
+            fdes.push(partial_fde.parse(US::cie_from_offset)?);
+        }
+    }
+    fdes.sort_by_key(|fde| fde.initial_address());
+
+    let relative_address = |svma: u64| {
+        let relative_address = svma
+            .checked_sub(base_svma)
+            .ok_or(DwarfCfiIndexError::CouldNotSubtractBaseAddress)?;
+        u32::try_from(relative_address).map_err(|_| DwarfCfiIndexError::RelativeAddressTooBig)
+    };
+    let mut unwind_context = UnwindContext::<R::Offset, StoreOnHeap>::new();
+    let mut builder = RuleTableBuilder::new();
+    if let Some(rule) = uncovered_rule {
+        builder.push(0, Some(rule.pack()));
+    }
+    for fde in &fdes {
+        if fde.cie().is_signal_trampoline() {
+            builder.push(relative_address(fde.initial_address())?, None);
+        } else {
+            let mut rows = fde.rows(&unwind_section, bases, &mut unwind_context)?;
+            while let Some(row) = rows.next_row()? {
+                let rule = A::rule_for_row(row).ok().map(UnwindRule::pack);
+                builder.push(relative_address(row.start_address())?, rule);
+            }
+        }
+        builder.push(
+            relative_address(fde.end_address())?,
+            uncovered_rule.map(UnwindRule::pack),
+        );
+    }
+    Ok(builder.finish())
+}
+
 pub trait DwarfUnwindRegs {
     fn get(&self, register: Register) -> Option<u64>;
 }
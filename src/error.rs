@@ -1,8 +1,15 @@
+use crate::any_unwinder::CpuArch;
+use crate::breakpad::BreakpadUnwinderError;
 use crate::dwarf::DwarfUnwinderError;
+use crate::exidx::ExidxUnwinderError;
 #[cfg(feature = "macho")]
 use crate::macho::CompactUnwindInfoUnwinderError;
+use crate::orc::OrcUnwinderError;
 #[cfg(feature = "pe")]
 use crate::pe::PeUnwinderError;
+use crate::rule_table::RuleTableError;
+use crate::sframe::SFrameUnwinderError;
+use crate::signal_frame::SignalFrameUnwinderError;
 
 /// The error type used in this crate.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,6 +19,7 @@ pub enum Error {
     DidNotAdvance,
     IntegerOverflow,
     ReturnAddressIsNull,
+    ArchitectureMismatch,
 }
 
 impl core::fmt::Display for Error {
@@ -27,6 +35,10 @@ impl core::fmt::Display for Error {
             ),
             Self::IntegerOverflow => write!(f, "Unwinding caused integer overflow"),
             Self::ReturnAddressIsNull => write!(f, "Return address is null"),
+            Self::ArchitectureMismatch => write!(
+                f,
+                "The unwinder, the unwind registers and the cache are for different CPU architectures"
+            ),
         }
     }
 }
@@ -34,6 +46,8 @@ impl core::fmt::Display for Error {
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
+/// The reason why a module's unwind information could not be used for a frame. See
+/// [`FrameDiagnostics::error`](crate::FrameDiagnostics::error).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnwinderError {
     #[cfg(feature = "macho")]
@@ -41,11 +55,19 @@ pub enum UnwinderError {
     Dwarf(DwarfUnwinderError),
     #[cfg(feature = "pe")]
     Pe(PeUnwinderError),
+    Exidx(ExidxUnwinderError),
+    SFrame(SFrameUnwinderError),
+    Orc(OrcUnwinderError),
+    Breakpad(BreakpadUnwinderError),
+    SignalFrame(SignalFrameUnwinderError),
     #[cfg(feature = "macho")]
     NoDwarfData,
     NoModuleUnwindData,
     EhFrameHdrCouldNotFindAddress,
     DwarfCfiIndexCouldNotFindAddress,
+    RuleTable(RuleTableError),
+    RuleTableArchMismatch(CpuArch),
+    RuleTableCouldNotFindAddress,
 }
 
 impl core::fmt::Display for UnwinderError {
@@ -58,6 +80,11 @@ impl core::fmt::Display for UnwinderError {
             Self::Dwarf(err) => write!(f, "DWARF unwinding failed: {err}"),
             #[cfg(feature = "pe")]
             Self::Pe(err) => write!(f, "PE unwinding failed: {err}"),
+            Self::Exidx(err) => write!(f, ".ARM.exidx unwinding failed: {err}"),
+            Self::SFrame(err) => write!(f, ".sframe unwinding failed: {err}"),
+            Self::Orc(err) => write!(f, "ORC unwinding failed: {err}"),
+            Self::Breakpad(err) => write!(f, "Breakpad unwinding failed: {err}"),
+            Self::SignalFrame(err) => write!(f, "Signal frame unwinding failed: {err}"),
             #[cfg(feature = "macho")]
             Self::NoDwarfData => write!(
                 f,
@@ -74,6 +101,16 @@ impl core::fmt::Display for UnwinderError {
                 f,
                 "Failed to look up the address in the DwarfCfiIndex search table"
             ),
+            Self::RuleTable(err) => write!(f, "Could not read the rule table: {err}"),
+            Self::RuleTableArchMismatch(arch) => {
+                write!(
+                    f,
+                    "The rule table is for a different CPU architecture: {arch:?}"
+                )
+            }
+            Self::RuleTableCouldNotFindAddress => {
+                write!(f, "The rule table has no rule for the address")
+            }
         }
     }
 }
@@ -91,6 +128,36 @@ impl From<PeUnwinderError> for UnwinderError {
     }
 }
 
+impl From<ExidxUnwinderError> for UnwinderError {
+    fn from(e: ExidxUnwinderError) -> Self {
+        Self::Exidx(e)
+    }
+}
+
+impl From<SFrameUnwinderError> for UnwinderError {
+    fn from(e: SFrameUnwinderError) -> Self {
+        Self::SFrame(e)
+    }
+}
+
+impl From<OrcUnwinderError> for UnwinderError {
+    fn from(e: OrcUnwinderError) -> Self {
+        Self::Orc(e)
+    }
+}
+
+impl From<BreakpadUnwinderError> for UnwinderError {
+    fn from(e: BreakpadUnwinderError) -> Self {
+        Self::Breakpad(e)
+    }
+}
+
+impl From<SignalFrameUnwinderError> for UnwinderError {
+    fn from(e: SignalFrameUnwinderError) -> Self {
+        Self::SignalFrame(e)
+    }
+}
+
 #[cfg(feature = "macho")]
 impl From<CompactUnwindInfoUnwinderError> for UnwinderError {
     fn from(e: CompactUnwindInfoUnwinderError) -> Self {
@@ -110,6 +177,12 @@ impl std::error::Error for UnwinderError {
             Self::Dwarf(e) => Some(e),
             #[cfg(feature = "pe")]
             Self::Pe(e) => Some(e),
+            Self::Exidx(e) => Some(e),
+            Self::SFrame(e) => Some(e),
+            Self::Orc(e) => Some(e),
+            Self::Breakpad(e) => Some(e),
+            Self::SignalFrame(e) => Some(e),
+            Self::RuleTable(e) => Some(e),
             _ => None,
         }
     }
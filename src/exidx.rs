@@ -0,0 +1,434 @@
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use crate::add_signed::checked_add_signed;
+use crate::{arch::Arch, unwind_result::UnwindResult, unwind_rule::UnwindRule};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExidxUnwinderError {
+    AddressOutsideRange(u32),
+    CantUnwind,
+    RefuseToUnwind,
+    ExtabOutOfBounds(u64),
+    MissingExtabData(u64),
+    UnsupportedPersonality(u8),
+    SpareOpcode(u8),
+    UnsupportedVspRegister(u8),
+    CouldNotReadStack(u64),
+    IntegerOverflow,
+    DidNotAdvance,
+    StackPointerMovedBackwards,
+    UnsupportedArch,
+}
+
+impl core::fmt::Display for ExidxUnwinderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::AddressOutsideRange(addr) => write!(
+                f,
+                "Address 0x{addr:x} outside of the range covered by .ARM.exidx"
+            ),
+            Self::CantUnwind => write!(f, "The .ARM.exidx entry was marked as EXIDX_CANTUNWIND"),
+            Self::RefuseToUnwind => write!(f, "Encountered the \"refuse to unwind\" opcode"),
+            Self::ExtabOutOfBounds(svma) => {
+                write!(
+                    f,
+                    ".ARM.extab entry at 0x{svma:x} extends past the section end"
+                )
+            }
+            Self::MissingExtabData(svma) => write!(
+                f,
+                "Don't have the .ARM.extab data for the entry at 0x{svma:x}"
+            ),
+            Self::UnsupportedPersonality(index) => {
+                write!(f, "Unsupported compact personality routine index {index}")
+            }
+            Self::SpareOpcode(opcode) => write!(f, "Encountered spare opcode 0x{opcode:x}"),
+            Self::UnsupportedVspRegister(reg) => {
+                write!(
+                    f,
+                    "Cannot set vsp from register r{reg}, its value is not tracked"
+                )
+            }
+            Self::CouldNotReadStack(addr) => {
+                write!(f, "Could not read stack memory at 0x{addr:x}")
+            }
+            Self::IntegerOverflow => write!(f, "vsp computation overflowed"),
+            Self::DidNotAdvance => write!(f, "Did not advance"),
+            Self::StackPointerMovedBackwards => write!(f, "Stack pointer moved backwards"),
+            Self::UnsupportedArch => {
+                write!(f, ".ARM.exidx unwinding is only supported on 32-bit ARM")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExidxUnwinderError {}
+
+/// Data and the related SVMA range within the binary.
+///
+/// This is used for the `.ARM.exidx` and `.ARM.extab` sections, whose entries refer to
+/// each other and to code using "prel31" offsets, i.e. offsets relative to the address
+/// of the referring word.
+///
+/// Type arguments:
+///  - `D`: The type for unwind section data. This allows carrying owned data on the
+///    module, e.g. `Vec<u8>`. But it could also be a wrapper around mapped memory from
+///    a file or a different process, for example. It just needs to provide a slice of
+///    bytes via its `Deref` implementation.
+pub struct DataAtSvmaRange<D> {
+    pub data: D,
+    pub svma_range: Range<u64>,
+}
+
+pub trait ExidxUnwinding: Arch {
+    fn unwind_frame<F>(
+        _instructions: EhabiInstructions,
+        _regs: &mut Self::UnwindRegs,
+        _is_first_frame: bool,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, ExidxUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(ExidxUnwinderError::UnsupportedArch)
+    }
+}
+
+/// A single decoded ARM EHABI unwinding instruction, see section 10.3 "Frame unwinding
+/// instructions" of the "Exception Handling ABI for the Arm Architecture".
+///
+/// Pops of VFP and iWMMXt registers are reported as plain vsp increments, because we
+/// don't track the values of those registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EhabiInstruction {
+    /// vsp = vsp + x
+    IncrementVsp(u32),
+    /// vsp = vsp - x
+    DecrementVsp(u32),
+    /// Pop the core registers whose bits are set in the mask (bit n = rn), lowest
+    /// register first.
+    PopRegisters(u16),
+    /// vsp = r[n]
+    SetVspFromRegister(u8),
+    /// The function cannot be unwound.
+    RefuseToUnwind,
+    /// Finish; if r15 has not been popped, pc = lr.
+    Finish,
+    /// An opcode which the specification marks as "spare" or "reserved".
+    Spare(u8),
+}
+
+/// An iterator over the EHABI instructions of a function. The instruction bytes are
+/// packed into 32-bit words, most significant byte first.
+#[derive(Clone, Copy)]
+pub struct EhabiInstructions<'a> {
+    words: &'a [u8],
+    pos: usize,
+    len: usize,
+}
+
+impl<'a> EhabiInstructions<'a> {
+    /// `words` contains the (little-endian) words holding the opcode bytes, and `pos` is the
+    /// index of the first opcode byte within the big-endian byte sequence of these words.
+    pub fn new(words: &'a [u8], pos: usize) -> Self {
+        let len = words.len() / 4 * 4;
+        Self { words, pos, len }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        if self.pos >= self.len {
+            return None;
+        }
+        let byte = self.words[self.pos / 4 * 4 + 3 - self.pos % 4];
+        self.pos += 1;
+        Some(byte)
+    }
+}
+
+impl Iterator for EhabiInstructions<'_> {
+    type Item = EhabiInstruction;
+
+    fn next(&mut self) -> Option<EhabiInstruction> {
+        let op = self.next_byte()?;
+        let instruction = match op {
+            0x00..=0x3f => EhabiInstruction::IncrementVsp((u32::from(op & 0x3f) << 2) + 4),
+            0x40..=0x7f => EhabiInstruction::DecrementVsp((u32::from(op & 0x3f) << 2) + 4),
+            0x80..=0x8f => {
+                let op2 = self.next_byte()?;
+                let mask = (u16::from(op & 0x0f) << 8) | u16::from(op2);
+                if mask == 0 {
+                    EhabiInstruction::RefuseToUnwind
+                } else {
+                    EhabiInstruction::PopRegisters(mask << 4)
+                }
+            }
+            0x9d | 0x9f => EhabiInstruction::Spare(op),
+            0x90..=0x9f => EhabiInstruction::SetVspFromRegister(op & 0x0f),
+            0xa0..=0xa7 => EhabiInstruction::PopRegisters(pop_r4_to(op & 0x07)),
+            0xa8..=0xaf => EhabiInstruction::PopRegisters(pop_r4_to(op & 0x07) | (1 << 14)),
+            0xb0 => EhabiInstruction::Finish,
+            0xb1 => {
+                let op2 = self.next_byte()?;
+                if op2 == 0 || op2 & 0xf0 != 0 {
+                    EhabiInstruction::Spare(op)
+                } else {
+                    EhabiInstruction::PopRegisters(u16::from(op2))
+                }
+            }
+            0xb2 => {
+                let mut value: u32 = 0;
+                let mut shift = 0;
+                loop {
+                    let byte = self.next_byte()?;
+                    value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                    shift += 7;
+                }
+                EhabiInstruction::IncrementVsp(0x204u32.checked_add(value.checked_mul(4)?)?)
+            }
+            0xb3 => {
+                // VFP registers saved with FSTMFDX: one extra word.
+                let op2 = self.next_byte()?;
+                EhabiInstruction::IncrementVsp(u32::from(op2 & 0x0f) * 8 + 12)
+            }
+            0xb4..=0xb7 => EhabiInstruction::Spare(op),
+            0xb8..=0xbf => EhabiInstruction::IncrementVsp(u32::from(op & 0x07) * 8 + 12),
+            0xc0..=0xc5 => EhabiInstruction::IncrementVsp(u32::from(op & 0x07) * 8 + 8),
+            0xc6 | 0xc8 | 0xc9 => {
+                let op2 = self.next_byte()?;
+                EhabiInstruction::IncrementVsp(u32::from(op2 & 0x0f) * 8 + 8)
+            }
+            0xc7 => {
+                let op2 = self.next_byte()?;
+                if op2 == 0 || op2 & 0xf0 != 0 {
+                    EhabiInstruction::Spare(op)
+                } else {
+                    EhabiInstruction::IncrementVsp(op2.count_ones() * 4)
+                }
+            }
+            0xd0..=0xd7 => EhabiInstruction::IncrementVsp(u32::from(op & 0x07) * 8 + 8),
+            0xca..=0xcf | 0xd8..=0xff => EhabiInstruction::Spare(op),
+        };
+        Some(instruction)
+    }
+}
+
+/// The mask for popping r4 up to and including r[4+n].
+fn pop_r4_to(n: u8) -> u16 {
+    ((1u16 << (n + 1)) - 1) << 4
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decode a "prel31" value, a 31 bit signed offset relative to the word's address.
+fn prel31(word: u32) -> i64 {
+    i64::from(((word << 1) as i32) >> 1)
+}
+
+const EXIDX_CANTUNWIND: u32 = 1;
+
+pub struct ExidxUnwinder<'a, A: ExidxUnwinding> {
+    exidx: &'a [u8],
+    exidx_svma: u64,
+    extab: Option<(&'a [u8], Range<u64>)>,
+    base_svma: u64,
+    _arch: PhantomData<A>,
+}
+
+impl<'a, A: ExidxUnwinding> ExidxUnwinder<'a, A> {
+    pub fn new<D: core::ops::Deref<Target = [u8]>>(
+        exidx: &'a DataAtSvmaRange<D>,
+        extab: Option<&'a DataAtSvmaRange<D>>,
+        base_svma: u64,
+    ) -> Self {
+        Self {
+            exidx: &exidx.data[..],
+            exidx_svma: exidx.svma_range.start,
+            extab: extab.map(|extab| (&extab.data[..], extab.svma_range.clone())),
+            base_svma,
+            _arch: PhantomData,
+        }
+    }
+
+    fn entry_count(&self) -> usize {
+        self.exidx.len() / 8
+    }
+
+    /// Returns the function start SVMA and the SVMA of the second word of the entry.
+    fn entry(&self, index: usize) -> Option<(u64, u64, u32)> {
+        let entry_svma = self.exidx_svma + index as u64 * 8;
+        let function_word = read_u32(self.exidx, index * 8)?;
+        let data_word = read_u32(self.exidx, index * 8 + 4)?;
+        let function_svma = checked_add_signed(entry_svma, prel31(function_word))? & !1;
+        Some((function_svma, entry_svma + 4, data_word))
+    }
+
+    /// Find the index of the last entry whose function start is at or before the address.
+    fn entry_index_for_svma(&self, lookup_svma: u64) -> Option<usize> {
+        let mut low = 0;
+        let mut high = self.entry_count();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (function_svma, _, _) = self.entry(mid)?;
+            if function_svma <= lookup_svma {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low.checked_sub(1)
+    }
+
+    /// Returns the instructions for the function covering the address, and the relative
+    /// address of the function start.
+    pub fn instructions_for_address(
+        &self,
+        rel_lookup_address: u32,
+    ) -> Result<(EhabiInstructions<'a>, u32), ExidxUnwinderError> {
+        let outside = ExidxUnwinderError::AddressOutsideRange(rel_lookup_address);
+        let lookup_svma = self.base_svma + u64::from(rel_lookup_address);
+        let index = self.entry_index_for_svma(lookup_svma).ok_or(outside)?;
+        let (function_svma, data_word_svma, data_word) = self.entry(index).ok_or(outside)?;
+        let function_start = function_svma
+            .checked_sub(self.base_svma)
+            .and_then(|f| u32::try_from(f).ok())
+            .ok_or(outside)?;
+
+        if data_word == EXIDX_CANTUNWIND {
+            return Err(ExidxUnwinderError::CantUnwind);
+        }
+        if data_word & 0x8000_0000 != 0 {
+            // The instructions are stored inline, in the compact model with personality
+            // routine index 0: three bytes of opcodes.
+            let personality = ((data_word >> 24) & 0x0f) as u8;
+            if personality != 0 {
+                return Err(ExidxUnwinderError::UnsupportedPersonality(personality));
+            }
+            let words = self
+                .exidx
+                .get(index * 8 + 4..index * 8 + 8)
+                .ok_or(outside)?;
+            return Ok((EhabiInstructions::new(words, 1), function_start));
+        }
+
+        let extab_entry_svma = checked_add_signed(data_word_svma, prel31(data_word))
+            .ok_or(ExidxUnwinderError::IntegerOverflow)?;
+        let (extab, extab_svma_range) = self
+            .extab
+            .as_ref()
+            .filter(|(_, range)| range.contains(&extab_entry_svma))
+            .ok_or(ExidxUnwinderError::MissingExtabData(extab_entry_svma))?;
+        let out_of_bounds = ExidxUnwinderError::ExtabOutOfBounds(extab_entry_svma);
+        let entry = usize::try_from(extab_entry_svma - extab_svma_range.start)
+            .ok()
+            .and_then(|offset| extab.get(offset..))
+            .ok_or(out_of_bounds)?;
+        let first_word = read_u32(entry, 0).ok_or(out_of_bounds)?;
+        let (words, pos) = if first_word & 0x8000_0000 != 0 {
+            match ((first_word >> 24) & 0x0f) as u8 {
+                // Su16: three bytes of opcodes in this word.
+                0 => (&entry[..4], 1),
+                // Lu16 / Lu32: a count of additional words, then two bytes of opcodes.
+                1 | 2 => {
+                    let additional_words = ((first_word >> 16) & 0xff) as usize;
+                    let len = (1 + additional_words) * 4;
+                    (entry.get(..len).ok_or(out_of_bounds)?, 2)
+                }
+                personality => return Err(ExidxUnwinderError::UnsupportedPersonality(personality)),
+            }
+        } else {
+            // The first word is the prel31 address of a generic personality routine,
+            // e.g. __gxx_personality_v0. It is followed by a count of additional words
+            // and three bytes of opcodes.
+            let second_word = read_u32(entry, 4).ok_or(out_of_bounds)?;
+            let additional_words = (second_word >> 24) as usize;
+            let len = (1 + additional_words) * 4;
+            (entry.get(4..4 + len).ok_or(out_of_bounds)?, 1)
+        };
+        Ok((EhabiInstructions::new(words, pos), function_start))
+    }
+
+    pub fn unwind_frame<F>(
+        &self,
+        rel_lookup_address: u32,
+        regs: &mut A::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<A::UnwindRule>, ExidxUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let (instructions, function_start) = self.instructions_for_address(rel_lookup_address)?;
+        if is_first_frame && rel_lookup_address == function_start {
+            // The unwind instructions describe the function body, not the prologue.
+            return Ok(UnwindResult::ExecRule(
+                A::UnwindRule::rule_for_function_start(),
+            ));
+        }
+        A::unwind_frame(instructions, regs, is_first_frame, read_stack)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // 0x97 vsp = r7; 0x41 vsp = vsp - 8; 0x84 0x0b pop {r4, r5, r7, r14};
+        // 0x00 vsp = vsp + 4; 0xb0 finish
+        let words = [0x41, 0x97, 0x01, 0x81, 0xb0, 0x00, 0x0b, 0x84];
+        let instructions: alloc::vec::Vec<_> = EhabiInstructions::new(&words, 2).collect();
+        assert_eq!(
+            instructions,
+            [
+                EhabiInstruction::SetVspFromRegister(7),
+                EhabiInstruction::DecrementVsp(8),
+                EhabiInstruction::PopRegisters(0b0100_0000_1011_0000),
+                EhabiInstruction::IncrementVsp(4),
+                EhabiInstruction::Finish,
+            ]
+        );
+
+        // 0xab pop {r4-r7, r14}; 0xb2 0x81 0x01 vsp = vsp + 0x204 + (0x81 << 2); 0xc9 0x81 pop {d8-d9}
+        let words = [0x81, 0xb2, 0xab, 0x80, 0x00, 0x81, 0xc9, 0x01];
+        let instructions: alloc::vec::Vec<_> = EhabiInstructions::new(&words, 1).collect();
+        assert_eq!(
+            instructions,
+            [
+                EhabiInstruction::PopRegisters(0b0100_0000_1111_0000),
+                EhabiInstruction::IncrementVsp(0x204 + (0x81 << 2)),
+                EhabiInstruction::IncrementVsp(16),
+                EhabiInstruction::IncrementVsp(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extab_out_of_bounds() {
+        // One entry for a function at 0x800, whose extab entry at 0x2080 is inside the
+        // svma range of .ARM.extab but beyond the end of its data.
+        let function_word = (0x800u32.wrapping_sub(0x1000)) & 0x7fff_ffff;
+        let data_word = 0x2080 - 0x1004;
+        let exidx = DataAtSvmaRange {
+            data: [function_word.to_le_bytes(), u32::to_le_bytes(data_word)].concat(),
+            svma_range: 0x1000..0x1008,
+        };
+        let extab = DataAtSvmaRange {
+            data: alloc::vec![0; 8],
+            svma_range: 0x2000..0x2100,
+        };
+        let unwinder = ExidxUnwinder::<crate::arm::ArchArm>::new(&exidx, Some(&extab), 0);
+        assert!(matches!(
+            unwinder.instructions_for_address(0x900),
+            Err(ExidxUnwinderError::ExtabOutOfBounds(0x2080))
+        ));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::code_address::FrameAddress;
+use crate::diagnostics::UnwindSource;
+
+/// Access to the stack pointer and the frame pointer of each architecture's unwind
+/// registers, widened to `u64`. This is used to fill in [`FrameRecord`]s.
+pub trait FrameRegs {
+    /// The stack pointer.
+    fn stack_pointer(&self) -> u64;
+
+    /// The frame pointer.
+    fn frame_pointer(&self) -> u64;
+}
+
+/// How the registers of a frame in a [`FrameRecord`] were found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnwindMethod {
+    /// This is the first frame, whose registers were passed to the unwinder.
+    InitialRegisters,
+    /// The frame was unwound with an unwind rule from the cache. The cache does not
+    /// remember where the rule came from.
+    Cached,
+    /// The frame was unwound with the module's unwind information, or with the fallback
+    /// rule if the unwind information could not be used.
+    Uncached(UnwindSource),
+}
+
+impl UnwindMethod {
+    /// Whether the unwound frame was a signal handler frame. The unwinder then returned
+    /// the instruction pointer of the interrupted code instead of a return address, which
+    /// should be unwound as a [`FrameAddress::InstructionPointer`].
+    pub fn is_signal_frame(&self) -> bool {
+        *self == UnwindMethod::Uncached(UnwindSource::SignalFrame)
+    }
+}
+
+/// A frame yielded by [`UnwindIterator::next_record`](crate::UnwindIterator::next_record).
+///
+/// Unlike a bare [`FrameAddress`], this includes the stack pointer, which lets you tell
+/// recursive frames apart, and compare the frames of two stacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRecord {
+    /// The code address of this frame.
+    pub address: FrameAddress,
+    /// The stack pointer in this frame. For all frames except the first one and the
+    /// frames which were interrupted by a signal, this is the CFA of the frame that was
+    /// unwound to find this frame, i.e. the stack pointer right before the call
+    /// instruction.
+    pub sp: u64,
+    /// The frame pointer in this frame, if it was recovered during unwinding. Unwind
+    /// rules which don't restore the frame pointer leave it unchanged, so for frames
+    /// which don't use a frame pointer this is the value from the frame below.
+    pub fp: u64,
+    /// How this frame was found.
+    pub method: UnwindMethod,
+}
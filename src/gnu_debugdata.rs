@@ -0,0 +1,21 @@
+use alloc::vec::Vec;
+
+use object::{Object, ObjectSection};
+
+/// Extracts the `.debug_frame` section from the MiniDebugInfo in a `.gnu_debugdata`
+/// section.
+///
+/// MiniDebugInfo is an xz-compressed ELF image which contains a subset of the module's
+/// debug sections, usually `.symtab` and sometimes `.debug_frame`. It is used by Fedora
+/// and by Android system libraries, whose `.eh_frame` doesn't always cover all functions.
+///
+/// Returns `None` if the data can't be decompressed, isn't an ELF image, or if the image
+/// has no `.debug_frame` section. Only the `.debug_frame` bytes are kept; the rest of the
+/// decompressed image is dropped.
+pub fn debug_frame_from_gnu_debugdata(gnu_debugdata: &[u8]) -> Option<Vec<u8>> {
+    let mut image = Vec::new();
+    lzma_rs::xz_decompress(&mut &gnu_debugdata[..], &mut image).ok()?;
+    let file = object::File::parse(&image[..]).ok()?;
+    let debug_frame = file.section_by_name(".debug_frame")?;
+    Some(debug_frame.data().ok()?.to_vec())
+}
@@ -2,21 +2,21 @@
 //!
 //! Framehop is a stack frame unwinder written in 100% Rust. It produces high quality stacks at high speed, on multiple platforms and architectures, without an expensive pre-processing step for unwind information. This makes it suitable for sampling profilers.
 //!
-//! It currently supports unwinding x86_64 and aarch64, with unwind information formats commonly used on Windows, macOS, Linux and Android.
+//! It currently supports unwinding x86_64, i686, aarch64, 32-bit ARM and riscv64, with unwind information formats commonly used on Windows, macOS, Linux and Android.
 //!
 //! You give framehop register values, stack memory and unwind data, and framehop produces a list of return addresses.
 //!
 //! Framehop can be used in the following scenarios:
 //!
 //!  - Live unwinding of a remote process. This is how [`samply`](https://github.com/mstange/samply/) uses it.
-//!  - Offline unwinding from saved registers and stack bytes, even on a different machine, a different OS, or a different CPU architecture.
-//!  - Live unwinding inside the same process. This is currently unproven, but should work as long as you can do heap allocation before sampling, in order to allocate a cache and to update the list of modules. The actual unwinding does not require any heap allocation and should work even inside a signal handler, as long as you use `MustNotAllocateDuringUnwind`.
+//!  - Offline unwinding from saved registers and stack bytes, even on a different machine, a different OS, or a different CPU architecture. If the binaries aren't available there, `Module::serialize_rule_table` saves the unwind rules from their unwind information in a compact format which can be loaded with `SerializedRuleTable`.
+//!  - Live unwinding inside the same process. This is currently unproven, but should work as long as you can do heap allocation before sampling, in order to allocate a cache and to update the list of modules. The actual unwinding does not require any heap allocation and should work even inside a signal handler, as long as you use `MustNotAllocateDuringUnwind`. To add and remove modules while other threads are unwinding, wrap the unwinder in a `ConcurrentUnwinder`.
 //!
 //! As a user of framehop, your responsibilities are the following:
 //!
 //!  - You need to enumerate the modules (libraries) that are loaded in the sampled process ahead of time, or ideally maintain a live list which is updated whenever modules are loaded / unloaded.
 //!  - You need to provide address ranges and unwind section data for those modules.
-//!  - When sampling, you provide the register values and a callback  to read arbitrary stack memory without segfaulting.
+//!  - When sampling, you provide the register values and a callback  to read arbitrary stack memory without segfaulting. If you have a copy of the stack instead, for example from a `perf` sample, you can pass it as `StackRegions`.
 //!  - On aarch64, picking the right bitmask to strip pointer authentication bits from return addresses is up to you.
 //!  - You will need to do symbol resolution yourself, if you want function names. Framehop only produces addresses, it does not do any symbolication.
 //!
@@ -25,13 +25,21 @@
 //!  - It parses a number of different unwind information formats. At the moment, it supports the following:
 //!    - Apple's Compact Unwinding Format, in `__unwind_info` (macOS)
 //!    - DWARF CFI in `.eh_frame` (using `.eh_frame_hdr` as an index, if available)
-//!    - DWARF CFI in `.debug_frame`
-//!    - PE unwind info in `.pdata`, `.rdata` and `.xdata` (for Windows x86_64)
+//!    - DWARF CFI in `.debug_frame`, also if it is compressed (with the `compression` cargo feature)
+//!    - DWARF CFI in the xz-compressed MiniDebugInfo in `.gnu_debugdata` (with the `gnu-debugdata` cargo feature)
+//!    - PE unwind info in `.pdata`, `.rdata` and `.xdata` (for Windows x86_64 and aarch64)
+//!    - ARM EHABI unwind tables in `.ARM.exidx` and `.ARM.extab` (32-bit ARM)
+//!    - SFrame stack trace information in `.sframe` (Linux x86_64 and aarch64)
+//!    - Linux kernel ORC unwind tables in `.orc_unwind_ip` and `.orc_unwind` (x86_64), from `vmlinux` or from relocated kernel modules
+//!    - `STACK CFI` and `STACK WIN` records in Breakpad symbol files (x86_64, i686 and aarch64), see `Module::new_from_breakpad_sym`
 //!  - It supports correct unwinding even when the program is interrupted inside a function prologue or epilogue. On macOS, it has to analyze assembly instructions in order to do this.
-//!  - On x86_64 and aarch64, it falls back to frame pointer unwinding if it cannot find unwind information for an address.
-//!  - It caches the unwind rule for each address in a fixed-size cache, so that repeated unwinding from the same address is even faster.
+//!  - It unwinds through signal handler frames on x86_64 and aarch64, by recovering the interrupted registers from the signal frame. On Linux, signal return trampolines without CFI are recognized by their instructions, in modules which report the trampoline's symbol range, see `ExplicitModuleSectionInfo::rt_sigreturn_svma`. On macOS, `_sigtramp` is recognized by its instructions or by its symbol range, see `ExplicitModuleSectionInfo::sigtramp_svma`.
+//!  - On x86_64, i686, aarch64, 32-bit ARM and riscv64, it falls back to frame pointer unwinding if it cannot find unwind information for an address.
+//!  - If you need to know why a stack went wrong, `Unwinder::unwind_frame_with_diagnostics` and `UnwindIterator::next_with_diagnostics` report which unwind information was used for each frame, and the error that caused any fallback to frame pointers.
+//!  - If the CPU architecture is only known at runtime, for example when unwinding saved samples from a different machine, `AnyUnwinder` dispatches to the unwinder for that architecture, with `AnyUnwindRegs` and `AnyCache`.
+//!  - It caches the unwind rule for each address in a fixed-size cache, so that repeated unwinding from the same address is even faster. The size and the associativity of the cache can be configured with `CacheConfig`. When unwinding on multiple threads, the cached rules can be shared between the threads' caches, e.g. with `SharedCacheX86_64`. With `CacheKeyMode::ModuleRelative`, rules are cached by module-relative address, so that they can be reused for the same library in other processes.
 //!  - It generates binary search indexes for unwind information formats which don't have them. Specifically, for `.debug_frame` and for `.eh_frame` without `.eh_frame_hdr`.
-//!  - It does a reasonable job of detecting the end of the stack, so that you can differentiate between properly terminated stacks and prematurely truncated stacks.
+//!  - It does a reasonable job of detecting the end of the stack, so that you can differentiate between properly terminated stacks and prematurely truncated stacks. If you know the bounds of the thread's stack, `UnwindIterator::with_stack_bounds` keeps the unwinder from reading outside of them, and `UnwindIterator::stack_end` tells you whether the stack was complete, cut off, or corrupted.
 //!
 //! Framehop is not suitable for debuggers or to implement exception handling. Debuggers usually need to recover all register values for every frame whereas framehop only cares about return addresses. And exception handling needs the ability to call destructors, which is also a non-goal for framehop.
 //!
@@ -44,7 +52,7 @@
 //!  3. It uses binary search to find the correct unwind rule in all supported unwind information formats. For formats without an built-in index, it creates an index when the module is added.
 //!  4. It caches unwind rules based on address. In practice, the 509-slot cache achieves a hit rate of around 80% on complicated code like Firefox (with the cache being shared across all Firefox processes). When profiling simpler applications, the hit rate is likely much higher.
 //!
-//! Furthermore, adding a module is fast too because framehop only does minimal up-front parsing and processing - really, the only thing it does is to create the index of FDE offsets for `.eh_frame` / `.debug_frame`.
+//! Furthermore, adding a module is fast too because framehop only does minimal up-front parsing and processing - really, the only thing it does is to create the index of FDE offsets for `.eh_frame` / `.debug_frame`. For hot libraries, you can trade some of that speed for faster cache misses with `Module::with_precompiled_rules`, which translates the module's DWARF CFI, `.sframe`, `__unwind_info` or PE unwind information into unwind rules up front.
 //!
 //! ## Example
 //!
@@ -116,33 +124,74 @@
 extern crate alloc;
 
 mod add_signed;
+mod any_unwinder;
 mod arch;
+mod breakpad;
 mod cache;
 mod code_address;
+mod compression;
+mod concurrent_unwinder;
+mod diagnostics;
 mod display_utils;
 mod dwarf;
 mod error;
+mod exidx;
+mod frame_record;
+#[cfg(feature = "gnu-debugdata")]
+mod gnu_debugdata;
 mod instruction_analysis;
 #[cfg(feature = "macho")]
 mod macho;
+mod orc;
 #[cfg(feature = "pe")]
 mod pe;
 mod rule_cache;
+mod rule_table;
+mod sframe;
+mod signal_frame;
+mod stack_memory;
 mod unwind_result;
 mod unwind_rule;
 mod unwinder;
 
 /// Types for unwinding on the aarch64 CPU architecture.
 pub mod aarch64;
+/// Types for unwinding on the 32-bit ARM CPU architecture.
+pub mod arm;
+/// Types for unwinding on the 64-bit RISC-V CPU architecture.
+pub mod riscv64;
+/// Types for unwinding on the 32-bit x86 CPU architecture.
+pub mod x86;
 /// Types for unwinding on the x86_64 CPU architecture.
 pub mod x86_64;
 
-pub use cache::{AllocationPolicy, MayAllocateDuringUnwind, MustNotAllocateDuringUnwind};
+pub use any_unwinder::{AnyCache, AnyUnwindRegs, AnyUnwinder, CpuArch};
+pub use breakpad::BreakpadUnwinderError;
+pub use cache::{
+    AllocationPolicy, CacheAssociativity, CacheConfig, CacheKeyMode, MayAllocateDuringUnwind,
+    MustNotAllocateDuringUnwind,
+};
 pub use code_address::FrameAddress;
-pub use error::Error;
+pub use concurrent_unwinder::{ConcurrentUnwinder, UnwinderSnapshot};
+pub use diagnostics::{FrameDiagnostics, UnwindSource};
+pub use dwarf::{ConversionError, DwarfUnwinderError};
+pub use error::{Error, UnwinderError};
+pub use exidx::ExidxUnwinderError;
+pub use frame_record::{FrameRecord, FrameRegs, UnwindMethod};
+#[cfg(feature = "macho")]
+pub use macho::CompactUnwindInfoUnwinderError;
+pub use orc::OrcUnwinderError;
+#[cfg(feature = "pe")]
+pub use pe::PeUnwinderError;
 pub use rule_cache::CacheStats;
+pub use rule_table::{CompileRuleTableError, RuleTableError, SerializedRuleTable};
+pub use sframe::SFrameUnwinderError;
+pub use signal_frame::SignalFrameUnwinderError;
+pub use stack_memory::{
+    ReadStack, StackBounds, StackEnd, StackMemory, StackMemoryReader, StackRegions,
+};
 pub use unwinder::{
-    ExplicitModuleSectionInfo, Module, ModuleSectionInfo, UnwindIterator, Unwinder,
+    ExplicitModuleSectionInfo, Module, ModuleAddress, ModuleSectionInfo, UnwindIterator, Unwinder,
 };
 
 /// The unwinder cache for the native CPU architecture.
@@ -155,6 +204,36 @@ pub type UnwindRegsNative = aarch64::UnwindRegsAarch64;
 #[cfg(target_arch = "aarch64")]
 pub type UnwinderNative<D, P> = aarch64::UnwinderAarch64<D, P>;
 
+/// The unwinder cache for the native CPU architecture.
+#[cfg(target_arch = "arm")]
+pub type CacheNative<P> = arm::CacheArm<P>;
+/// The unwind registers type for the native CPU architecture.
+#[cfg(target_arch = "arm")]
+pub type UnwindRegsNative = arm::UnwindRegsArm;
+/// The unwinder type for the native CPU architecture.
+#[cfg(target_arch = "arm")]
+pub type UnwinderNative<D, P> = arm::UnwinderArm<D, P>;
+
+/// The unwinder cache for the native CPU architecture.
+#[cfg(target_arch = "riscv64")]
+pub type CacheNative<P> = riscv64::CacheRiscv64<P>;
+/// The unwind registers type for the native CPU architecture.
+#[cfg(target_arch = "riscv64")]
+pub type UnwindRegsNative = riscv64::UnwindRegsRiscv64;
+/// The unwinder type for the native CPU architecture.
+#[cfg(target_arch = "riscv64")]
+pub type UnwinderNative<D, P> = riscv64::UnwinderRiscv64<D, P>;
+
+/// The unwinder cache for the native CPU architecture.
+#[cfg(target_arch = "x86")]
+pub type CacheNative<P> = x86::CacheX86<P>;
+/// The unwind registers type for the native CPU architecture.
+#[cfg(target_arch = "x86")]
+pub type UnwindRegsNative = x86::UnwindRegsX86;
+/// The unwinder type for the native CPU architecture.
+#[cfg(target_arch = "x86")]
+pub type UnwinderNative<D, P> = x86::UnwinderX86<D, P>;
+
 /// The unwinder cache for the native CPU architecture.
 #[cfg(target_arch = "x86_64")]
 pub type CacheNative<P> = x86_64::CacheX86_64<P>;
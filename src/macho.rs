@@ -1,6 +1,10 @@
+use alloc::vec::Vec;
 use core::marker::PhantomData;
+use core::ops::Range;
 
 use crate::dwarf::DwarfUnwinderError;
+use crate::rule_table::{RuleTable, RuleTableBuilder};
+use crate::unwind_result::UnwindResult;
 use crate::{arch::Arch, unwind_rule::UnwindRule};
 use macho_unwind_info::UnwindInfo;
 
@@ -19,6 +23,11 @@ pub enum CompactUnwindInfoUnwinderError {
     StackSizeDoesNotFit,
     StubFunctionCannotBeCaller,
     InvalidFrameless,
+    CouldNotReadSigtrampContext(u64),
+    SigtrampContextNotFound,
+    ArmUnsupported,
+    Riscv64Unsupported,
+    X86Unsupported,
 }
 
 impl core::fmt::Display for CompactUnwindInfoUnwinderError {
@@ -37,6 +46,11 @@ impl core::fmt::Display for CompactUnwindInfoUnwinderError {
             Self::StackSizeDoesNotFit => write!(f, "Stack size does not fit into the rule representation"),
             Self::StubFunctionCannotBeCaller => write!(f, "A caller had its address in the __stubs section"),
             Self::InvalidFrameless => write!(f, "Encountered invalid unwind entry"),
+            Self::CouldNotReadSigtrampContext(addr) => write!(f, "Could not read the signal context of a _sigtramp frame at 0x{addr:x}"),
+            Self::SigtrampContextNotFound => write!(f, "Could not find the signal context of a _sigtramp frame"),
+            Self::ArmUnsupported => write!(f, "__unwind_info is not supported for 32-bit ARM"),
+            Self::Riscv64Unsupported => write!(f, "__unwind_info is not supported for RISC-V"),
+            Self::X86Unsupported => write!(f, "__unwind_info is not supported for 32-bit x86"),
         }
     }
 }
@@ -67,7 +81,14 @@ impl std::error::Error for CompactUnwindInfoUnwinderError {
 #[derive(Clone, Debug)]
 pub enum CuiUnwindResult<R: UnwindRule> {
     ExecRule(R),
+    /// Like `ExecRule`, but the rule was derived from the function's instructions rather
+    /// than from the compact unwind info opcode, because the pc is in a prologue or an
+    /// epilogue.
+    ExecRuleFromInstructionAnalysis(R),
     NeedDwarf(u32),
+    /// The address is the return address of the signal handler call in `_sigtramp`. The
+    /// interrupted registers need to be recovered from the signal context on the stack.
+    Sigtramp,
 }
 
 pub trait CompactUnwindInfoUnwinding: Arch {
@@ -81,6 +102,44 @@ pub trait CompactUnwindInfoUnwinding: Arch {
     fn rule_for_stub_helper(
         offset: u32,
     ) -> Result<CuiUnwindResult<Self::UnwindRule>, CompactUnwindInfoUnwinderError>;
+
+    /// Checks whether `return_address_offset` is right after the call to the signal
+    /// handler in `_sigtramp`, whose instructions are in `function_bytes`. The
+    /// `__unwind_info` entry of `_sigtramp` is not useful for unwinding.
+    fn is_sigtramp_return_address(function_bytes: &[u8], return_address_offset: usize) -> bool;
+
+    /// Restores the interrupted registers from the `ucontext_t` which the kernel pushed on
+    /// the stack before it called `_sigtramp`. `regs` are the registers at the return
+    /// address in `_sigtramp`.
+    fn unwind_sigtramp_frame<F>(
+        regs: &mut Self::UnwindRegs,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, CompactUnwindInfoUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>;
+}
+
+/// The offset of `uc_mcsize` in the 64-bit `ucontext_t`.
+pub const UCONTEXT_MCSIZE: u64 = 40;
+/// The offset of `uc_mcontext` in the 64-bit `ucontext_t`.
+pub const UCONTEXT_MCONTEXT: u64 = 48;
+/// The size of the 64-bit `siginfo_t`.
+pub const SIGINFO_SIZE: u64 = 104;
+
+/// Reads the signal context at `base + offset`.
+pub fn read_sigtramp_context<F>(
+    base: u64,
+    offset: u64,
+    read_stack: &mut F,
+) -> Result<u64, CompactUnwindInfoUnwinderError>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let address = base
+        .checked_add(offset)
+        .ok_or(CompactUnwindInfoUnwinderError::SigtrampContextNotFound)?;
+    read_stack(address)
+        .map_err(|_| CompactUnwindInfoUnwinderError::CouldNotReadSigtrampContext(address))
 }
 
 #[derive(Clone, Copy)]
@@ -103,6 +162,7 @@ pub struct CompactUnwindInfoUnwinder<'a, A: CompactUnwindInfoUnwinding> {
     text_bytes: Option<TextBytes<'a>>,
     stubs_range: (u32, u32),
     stub_helper_range: (u32, u32),
+    sigtramp_range: (u32, u32),
     _arch: PhantomData<A>,
 }
 
@@ -112,12 +172,14 @@ impl<'a, A: CompactUnwindInfoUnwinding> CompactUnwindInfoUnwinder<'a, A> {
         text_bytes: Option<TextBytes<'a>>,
         stubs_range: (u32, u32),
         stub_helper_range: (u32, u32),
+        sigtramp_range: (u32, u32),
     ) -> Self {
         Self {
             unwind_info_data,
             text_bytes,
             stubs_range,
             stub_helper_range,
+            sigtramp_range,
             _arch: PhantomData,
         }
     }
@@ -163,6 +225,12 @@ impl<'a, A: CompactUnwindInfoUnwinding> CompactUnwindInfoUnwinder<'a, A> {
                 lookup_address_relative_to_section,
             );
         }
+        if !is_first_frame
+            && self.sigtramp_range.0 <= rel_lookup_address
+            && rel_lookup_address < self.sigtramp_range.1
+        {
+            return Ok(CuiUnwindResult::Sigtramp);
+        }
         let function = match self.function_for_address(rel_lookup_address) {
             Ok(f) => f,
             Err(CompactUnwindInfoUnwinderError::AddressOutsideRange(_)) if is_first_frame => {
@@ -183,19 +251,15 @@ impl<'a, A: CompactUnwindInfoUnwinding> CompactUnwindInfoUnwinder<'a, A> {
         }
         let address_offset_within_function =
             usize::try_from(rel_lookup_address - function.start_address).unwrap();
-        let function_bytes = self.text_bytes.and_then(|text_bytes| {
-            let TextBytes {
-                offset_from_base_address,
-                bytes,
-            } = text_bytes;
-            let function_start_relative_to_text = function
-                .start_address
-                .checked_sub(offset_from_base_address)?
-                as usize;
-            let function_end_relative_to_text =
-                function.end_address.checked_sub(offset_from_base_address)? as usize;
-            bytes.get(function_start_relative_to_text..function_end_relative_to_text)
-        });
+        let function_bytes = self.function_bytes(&function);
+        if let Some(function_bytes) = function_bytes {
+            // The lookup address of a return address is one byte before it.
+            if !is_first_frame
+                && A::is_sigtramp_return_address(function_bytes, address_offset_within_function + 1)
+            {
+                return Ok(CuiUnwindResult::Sigtramp);
+            }
+        }
         <A as CompactUnwindInfoUnwinding>::unwind_frame(
             function,
             is_first_frame,
@@ -203,4 +267,79 @@ impl<'a, A: CompactUnwindInfoUnwinding> CompactUnwindInfoUnwinder<'a, A> {
             function_bytes,
         )
     }
+
+    fn function_bytes(&self, function: &macho_unwind_info::Function) -> Option<&'a [u8]> {
+        let TextBytes {
+            offset_from_base_address,
+            bytes,
+        } = self.text_bytes?;
+        let function_start_relative_to_text = function
+            .start_address
+            .checked_sub(offset_from_base_address)?
+            as usize;
+        let function_end_relative_to_text =
+            function.end_address.checked_sub(offset_from_base_address)? as usize;
+        bytes.get(function_start_relative_to_text..function_end_relative_to_text)
+    }
+
+    /// Translates the opcodes of all functions into a table of unwind rules for return
+    /// addresses. Functions whose opcode refers to DWARF CFI get their rules from
+    /// `dwarf_rules`. Stub functions, the signal handler call in `_sigtramp`, and opcodes
+    /// which can't be expressed as a rule get no rule.
+    pub fn compile_rule_table(
+        &self,
+        dwarf_rules: Option<&RuleTable>,
+    ) -> Result<RuleTable, CompactUnwindInfoUnwinderError> {
+        let unwind_info = UnwindInfo::parse(self.unwind_info_data)?;
+        let mut functions = unwind_info.functions();
+        let mut builder = RuleTableBuilder::new();
+        let mut dwarf_ranges = Vec::new();
+        while let Some(function) = functions.next()? {
+            let function_range = function.start_address..function.end_address;
+            let function_bytes = self.function_bytes(&function);
+            // For frames other than the first one, the rule doesn't depend on the offset in
+            // the function.
+            let rule = match A::unwind_frame(function, false, 0, function_bytes) {
+                Ok(CuiUnwindResult::ExecRule(rule)) => Some(rule.pack()),
+                Ok(CuiUnwindResult::NeedDwarf(_)) => {
+                    dwarf_ranges.push(function_range.clone());
+                    None
+                }
+                Err(
+                    err @ (CompactUnwindInfoUnwinderError::ArmUnsupported
+                    | CompactUnwindInfoUnwinderError::Riscv64Unsupported
+                    | CompactUnwindInfoUnwinderError::X86Unsupported),
+                ) => return Err(err),
+                _ => None,
+            };
+            builder.push(function_range.start, rule);
+            if let Some(function_bytes) = function_bytes {
+                for return_address_offset in 1..=function_bytes.len() {
+                    if A::is_sigtramp_return_address(function_bytes, return_address_offset) {
+                        // The lookup address of a return address is one byte before it.
+                        let return_address = function_range.start + return_address_offset as u32;
+                        builder.push(return_address - 1, None);
+                        builder.push(return_address, rule);
+                    }
+                }
+            }
+            builder.push(function_range.end, None);
+        }
+        let mut table = builder.finish();
+        if let Some(dwarf_rules) = dwarf_rules {
+            table = table.overlay(dwarf_rules, &dwarf_ranges);
+        }
+
+        let mut stub_ranges: Vec<Range<u32>> = [
+            self.stubs_range,
+            self.stub_helper_range,
+            self.sigtramp_range,
+        ]
+        .into_iter()
+        .map(|(start, end)| start..end)
+        .filter(|range| !range.is_empty())
+        .collect();
+        stub_ranges.sort_by_key(|range| range.start);
+        Ok(table.overlay(&RuleTableBuilder::new().finish(), &stub_ranges))
+    }
 }
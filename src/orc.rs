@@ -0,0 +1,342 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::add_signed::checked_add_signed;
+use crate::exidx::DataAtSvmaRange;
+use crate::{arch::Arch, unwind_result::UnwindResult};
+
+/// The size of an entry in `.orc_unwind`.
+const ORC_ENTRY_SIZE: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrcUnwinderError {
+    AddressOutsideRange(u32),
+    EntryOutOfBounds(u32),
+    UnsupportedSpRegister(u8),
+    UnsupportedBpRegister(u8),
+    UnknownEntryType(u8),
+    CouldNotReadStack(u64),
+    IntegerOverflow,
+    DidNotAdvance,
+    StackPointerMovedBackwards,
+    UnsupportedArch,
+}
+
+impl core::fmt::Display for OrcUnwinderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::AddressOutsideRange(addr) => write!(
+                f,
+                "Address 0x{addr:x} outside of the range covered by .orc_unwind_ip"
+            ),
+            Self::EntryOutOfBounds(index) => {
+                write!(f, ".orc_unwind entry {index} extends past the section end")
+            }
+            Self::UnsupportedSpRegister(reg) => {
+                write!(f, "Cannot compute the CFA from ORC register {reg}")
+            }
+            Self::UnsupportedBpRegister(reg) => {
+                write!(f, "Cannot restore bp relative to ORC register {reg}")
+            }
+            Self::UnknownEntryType(entry_type) => {
+                write!(f, "Unknown ORC entry type {entry_type}")
+            }
+            Self::CouldNotReadStack(addr) => {
+                write!(f, "Could not read stack memory at 0x{addr:x}")
+            }
+            Self::IntegerOverflow => write!(f, "CFA computation overflowed"),
+            Self::DidNotAdvance => write!(f, "Did not advance"),
+            Self::StackPointerMovedBackwards => write!(f, "Stack pointer moved backwards"),
+            Self::UnsupportedArch => {
+                write!(f, "ORC unwinding is only supported on x86_64")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OrcUnwinderError {}
+
+pub trait OrcUnwinding: Arch {
+    fn unwind_frame<F>(
+        _entry: OrcEntry,
+        _regs: &mut Self::UnwindRegs,
+        _is_first_frame: bool,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, OrcUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(OrcUnwinderError::UnsupportedArch)
+    }
+}
+
+/// The layout of the flags in an `.orc_unwind` entry. The entry type encoding changed
+/// in Linux 6.4, which is also when the `.orc_header` section was introduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrcFormat {
+    /// Before Linux 6.4: 2-bit type (call, regs, iret regs), followed by an "end" bit.
+    /// Entries with an undefined sp register have no unwind information.
+    V1,
+    /// Linux 6.4 and newer: 3-bit type (undefined, end of stack, call, regs, partial
+    /// regs), followed by a "signal" bit.
+    V2,
+}
+
+/// The registers which ORC entries can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrcReg {
+    Undefined,
+    PrevSp,
+    Dx,
+    Di,
+    Bp,
+    Sp,
+    R10,
+    R13,
+    BpIndirect,
+    SpIndirect,
+    Unknown(u8),
+}
+
+impl OrcReg {
+    fn parse(reg: u8) -> Self {
+        match reg {
+            0 => Self::Undefined,
+            1 => Self::PrevSp,
+            2 => Self::Dx,
+            3 => Self::Di,
+            4 => Self::Bp,
+            5 => Self::Sp,
+            6 => Self::R10,
+            7 => Self::R13,
+            8 => Self::BpIndirect,
+            9 => Self::SpIndirect,
+            _ => Self::Unknown(reg),
+        }
+    }
+
+    pub fn raw(self) -> u8 {
+        match self {
+            Self::Undefined => 0,
+            Self::PrevSp => 1,
+            Self::Dx => 2,
+            Self::Di => 3,
+            Self::Bp => 4,
+            Self::Sp => 5,
+            Self::R10 => 6,
+            Self::R13 => 7,
+            Self::BpIndirect => 8,
+            Self::SpIndirect => 9,
+            Self::Unknown(reg) => reg,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrcEntryType {
+    /// No unwind information, e.g. for gaps between functions. The kernel's own unwinder
+    /// stops here.
+    Undefined,
+    /// The outermost frame of a kernel stack.
+    EndOfStack,
+    /// A regular function frame: the return address is at CFA - 8.
+    Call,
+    /// CFA points to a full `struct pt_regs`, e.g. at an interrupt or syscall entry.
+    Regs,
+    /// CFA points to the hardware IRET frame at the end of `struct pt_regs`.
+    RegsPartial,
+}
+
+/// A decoded `.orc_unwind` entry.
+///
+/// The "CFA" here is the location that `sp_reg + sp_offset` points to: the caller's stack
+/// pointer for `Call` entries, or the start of the saved register frame for `Regs` and
+/// `RegsPartial` entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrcEntry {
+    pub sp_offset: i16,
+    pub bp_offset: i16,
+    pub sp_reg: OrcReg,
+    pub bp_reg: OrcReg,
+    pub entry_type: OrcEntryType,
+}
+
+impl OrcEntry {
+    pub fn parse(data: &[u8], format: OrcFormat) -> Option<Self> {
+        let data = data.get(..ORC_ENTRY_SIZE)?;
+        let sp_offset = i16::from_le_bytes([data[0], data[1]]);
+        let bp_offset = i16::from_le_bytes([data[2], data[3]]);
+        let flags = u16::from_le_bytes([data[4], data[5]]);
+        let sp_reg = OrcReg::parse((flags & 0xf) as u8);
+        let bp_reg = OrcReg::parse(((flags >> 4) & 0xf) as u8);
+        let entry_type = match format {
+            OrcFormat::V1 => {
+                if flags & (1 << 10) != 0 {
+                    OrcEntryType::EndOfStack
+                } else if sp_reg == OrcReg::Undefined {
+                    OrcEntryType::Undefined
+                } else {
+                    match (flags >> 8) & 0x3 {
+                        0 => OrcEntryType::Call,
+                        1 => OrcEntryType::Regs,
+                        // ORC_TYPE_REGS_IRET, same as ORC_TYPE_REGS_PARTIAL in V2.
+                        2 => OrcEntryType::RegsPartial,
+                        _ => return None,
+                    }
+                }
+            }
+            OrcFormat::V2 => match (flags >> 8) & 0x7 {
+                0 => OrcEntryType::Undefined,
+                1 => OrcEntryType::EndOfStack,
+                2 => OrcEntryType::Call,
+                3 => OrcEntryType::Regs,
+                4 => OrcEntryType::RegsPartial,
+                _ => return None,
+            },
+        };
+        Some(Self {
+            sp_offset,
+            bp_offset,
+            sp_reg,
+            bp_reg,
+            entry_type,
+        })
+    }
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Option<i32> {
+    let bytes = data.get(offset..)?.get(..4)?;
+    Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Returns the SVMA of the instruction described by the `.orc_unwind_ip` entry with the
+/// given index. Each entry is a 32-bit offset relative to the entry's own address.
+fn ip_svma(orc_unwind_ip: &[u8], orc_unwind_ip_svma: u64, index: usize) -> Option<u64> {
+    let offset = read_i32(orc_unwind_ip, index * 4)?;
+    checked_add_signed(orc_unwind_ip_svma + index as u64 * 4, i64::from(offset))
+}
+
+/// The entries in vmlinux are sorted at build time since Linux 5.6. Older kernels sort
+/// them at boot, so their vmlinux files contain unsorted tables. The same is true for
+/// kernel module images whose relocations were already applied, because the kernel
+/// sorts the entries of loadable modules when loading them. For unsorted tables, we
+/// create a sorted list of entry indexes when the module is added.
+pub struct OrcIndex {
+    sorted_entry_indexes: Vec<u32>,
+}
+
+impl OrcIndex {
+    /// Returns `None` if the table is already sorted and doesn't need an index.
+    pub fn new_if_unsorted(
+        orc_unwind_ip: &DataAtSvmaRange<impl core::ops::Deref<Target = [u8]>>,
+    ) -> Option<Self> {
+        let data = &orc_unwind_ip.data[..];
+        let svma = orc_unwind_ip.svma_range.start;
+        let count = data.len() / 4;
+        let ips: Vec<u64> = (0..count)
+            .map(|index| ip_svma(data, svma, index).unwrap_or(0))
+            .collect();
+        if ips.windows(2).all(|w| w[0] <= w[1]) {
+            return None;
+        }
+        let mut sorted_entry_indexes: Vec<u32> = (0..count as u32).collect();
+        sorted_entry_indexes.sort_by_key(|index| ips[*index as usize]);
+        Some(Self {
+            sorted_entry_indexes,
+        })
+    }
+}
+
+/// Looks up ORC entries, using the Linux kernel's `.orc_unwind_ip` and `.orc_unwind`
+/// sections.
+///
+/// The `.orc_lookup` section is not used: it is only filled in by the kernel at boot, so
+/// it doesn't contain anything useful in the `vmlinux` file. Instead, we do a binary
+/// search in `.orc_unwind_ip`.
+pub struct OrcUnwinder<'a, A: OrcUnwinding> {
+    orc_unwind_ip: &'a [u8],
+    orc_unwind_ip_svma: u64,
+    orc_unwind: &'a [u8],
+    index: Option<&'a OrcIndex>,
+    format: OrcFormat,
+    base_svma: u64,
+    _arch: PhantomData<A>,
+}
+
+impl<'a, A: OrcUnwinding> OrcUnwinder<'a, A> {
+    pub fn new<D: core::ops::Deref<Target = [u8]>>(
+        orc_unwind_ip: &'a DataAtSvmaRange<D>,
+        orc_unwind: &'a D,
+        index: Option<&'a OrcIndex>,
+        format: OrcFormat,
+        base_svma: u64,
+    ) -> Self {
+        Self {
+            orc_unwind_ip: &orc_unwind_ip.data[..],
+            orc_unwind_ip_svma: orc_unwind_ip.svma_range.start,
+            orc_unwind: &orc_unwind[..],
+            index,
+            format,
+            base_svma,
+            _arch: PhantomData,
+        }
+    }
+
+    fn entry_count(&self) -> usize {
+        (self.orc_unwind_ip.len() / 4).min(self.orc_unwind.len() / ORC_ENTRY_SIZE)
+    }
+
+    /// Maps the n-th entry in address order to the entry index in the sections.
+    fn entry_index(&self, n: usize) -> usize {
+        match self.index {
+            Some(index) => index.sorted_entry_indexes[n] as usize,
+            None => n,
+        }
+    }
+
+    /// Returns the entry which covers the address. Every entry covers the range up to the
+    /// next entry's address.
+    pub fn entry_for_address(&self, rel_lookup_address: u32) -> Result<OrcEntry, OrcUnwinderError> {
+        let outside = OrcUnwinderError::AddressOutsideRange(rel_lookup_address);
+        let lookup_svma = self.base_svma + u64::from(rel_lookup_address);
+        let mut low = 0;
+        let mut high = self.entry_count();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let ip = ip_svma(
+                self.orc_unwind_ip,
+                self.orc_unwind_ip_svma,
+                self.entry_index(mid),
+            )
+            .ok_or(outside)?;
+            if ip <= lookup_svma {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let index = self.entry_index(low.checked_sub(1).ok_or(outside)?);
+        let entry_data = self
+            .orc_unwind
+            .get(index * ORC_ENTRY_SIZE..)
+            .ok_or(OrcUnwinderError::EntryOutOfBounds(index as u32))?;
+        OrcEntry::parse(entry_data, self.format).ok_or(OrcUnwinderError::UnknownEntryType(
+            entry_data.get(5).map_or(0, |flags| flags & 0x7),
+        ))
+    }
+
+    pub fn unwind_frame<F>(
+        &self,
+        rel_lookup_address: u32,
+        regs: &mut A::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<A::UnwindRule>, OrcUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let entry = self.entry_for_address(rel_lookup_address)?;
+        A::unwind_frame(entry, regs, is_first_frame, read_stack)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::rule_table::RuleTable;
 use crate::{arch::Arch, unwind_result::UnwindResult};
 use core::ops::Range;
 
@@ -7,7 +8,10 @@ pub enum PeUnwinderError {
     MissingInstructionData(u32),
     MissingStackData(Option<u64>),
     UnwindInfoParseError,
-    Aarch64Unsupported,
+    UnsupportedUnwindCode(u8),
+    ArmUnsupported,
+    Riscv64Unsupported,
+    X86Unsupported,
 }
 
 impl core::fmt::Display for PeUnwinderError {
@@ -27,7 +31,10 @@ impl core::fmt::Display for PeUnwinderError {
                 Ok(())
             }
             Self::UnwindInfoParseError => write!(f, "failed to parse UnwindInfo"),
-            Self::Aarch64Unsupported => write!(f, "AArch64 is not yet supported"),
+            Self::UnsupportedUnwindCode(code) => write!(f, "unsupported unwind code {code:#x}"),
+            Self::ArmUnsupported => write!(f, "32-bit ARM is not supported"),
+            Self::Riscv64Unsupported => write!(f, "RISC-V is not supported"),
+            Self::X86Unsupported => write!(f, "32-bit x86 is not supported"),
         }
     }
 }
@@ -97,4 +104,11 @@ pub trait PeUnwinding: Arch {
     where
         F: FnMut(u64) -> Result<u64, ()>,
         D: core::ops::Deref<Target = [u8]>;
+
+    /// Translates the unwind information of all functions into a table of unwind rules
+    /// for return addresses. Unwind information which can't be expressed as a rule gets
+    /// no rule.
+    fn compile_rule_table<D>(sections: PeSections<D>) -> Result<RuleTable, PeUnwinderError>
+    where
+        D: core::ops::Deref<Target = [u8]>;
 }
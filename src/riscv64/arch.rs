@@ -0,0 +1,32 @@
+use super::unwind_rule::UnwindRuleRiscv64;
+use super::unwindregs::UnwindRegsRiscv64;
+use crate::any_unwinder::CpuArch;
+use crate::arch::Arch;
+use crate::breakpad::BreakpadUnwinding;
+use crate::exidx::ExidxUnwinding;
+use crate::orc::OrcUnwinding;
+use crate::sframe::SFrameUnwinding;
+use crate::signal_frame::SignalFrameUnwinding;
+
+/// The 64-bit RISC-V CPU architecture (riscv64gc).
+pub struct ArchRiscv64;
+impl Arch for ArchRiscv64 {
+    const CPU_ARCH: CpuArch = CpuArch::Riscv64;
+    type UnwindRule = UnwindRuleRiscv64;
+    type UnwindRegs = UnwindRegsRiscv64;
+}
+
+// .ARM.exidx is only used on 32-bit ARM.
+impl ExidxUnwinding for ArchRiscv64 {}
+
+// .sframe is only emitted for x86_64 and aarch64.
+impl SFrameUnwinding for ArchRiscv64 {}
+
+// ORC is only used by the x86_64 Linux kernel.
+impl OrcUnwinding for ArchRiscv64 {}
+
+// Breakpad STACK CFI isn't supported on this architecture yet.
+impl BreakpadUnwinding for ArchRiscv64 {}
+
+// Signal frames are only recognized on x86_64 and aarch64 for now.
+impl SignalFrameUnwinding for ArchRiscv64 {}
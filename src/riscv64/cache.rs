@@ -0,0 +1,80 @@
+use super::unwind_rule::*;
+use crate::cache::*;
+
+/// The unwinder cache type for [`UnwinderRiscv64`](super::UnwinderRiscv64).
+pub struct CacheRiscv64<P: AllocationPolicy = MayAllocateDuringUnwind>(
+    pub Cache<UnwindRuleRiscv64, P>,
+);
+
+impl CacheRiscv64<MayAllocateDuringUnwind> {
+    /// Create a new cache.
+    pub fn new() -> Self {
+        Self(Cache::new())
+    }
+
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared(shared: &SharedCacheRiscv64) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
+}
+
+impl<P: AllocationPolicy> CacheRiscv64<P> {
+    /// Create a new cache.
+    pub fn new_in() -> Self {
+        Self(Cache::new())
+    }
+
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config_in(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared_in(shared: &SharedCacheRiscv64) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
+
+    /// Returns a snapshot of the cache usage statistics.
+    pub fn stats(&self) -> CacheStats {
+        self.0.rule_cache.stats()
+    }
+}
+
+impl<P: AllocationPolicy> Default for CacheRiscv64<P> {
+    fn default() -> Self {
+        Self::new_in()
+    }
+}
+
+/// Unwind rule storage for [`UnwinderRiscv64`](super::UnwinderRiscv64) which can be shared by the
+/// caches of multiple threads, so that an unwind rule which was found on one thread is a
+/// cache hit on all other threads. Create the per-thread caches with
+/// [`CacheRiscv64::new_shared`] or [`CacheRiscv64::new_shared_in`].
+///
+/// Lookups and insertions are lock-free and don't allocate, so the sharing caches can be
+/// used from signal handlers. Cloning gives you another handle to the same storage.
+#[derive(Clone, Default)]
+pub struct SharedCacheRiscv64(pub SharedCache<UnwindRuleRiscv64>);
+
+impl SharedCacheRiscv64 {
+    /// Create a new shared cache.
+    pub fn new() -> Self {
+        Self(SharedCache::new())
+    }
+
+    /// Create a new shared cache with the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(SharedCache::with_config(config))
+    }
+
+    /// Returns a snapshot of the cache usage statistics, summed up over all caches
+    /// which share these rules.
+    pub fn stats(&self) -> CacheStats {
+        self.0.stats()
+    }
+}
@@ -0,0 +1,196 @@
+use gimli::{
+    CfaRule, Encoding, EvaluationStorage, Reader, ReaderOffset, Register, RegisterRule, RiscV,
+    UnwindContextStorage, UnwindSection, UnwindTableRow,
+};
+
+use super::{arch::ArchRiscv64, unwind_rule::UnwindRuleRiscv64, unwindregs::UnwindRegsRiscv64};
+
+use crate::unwind_result::UnwindResult;
+
+use crate::dwarf::{
+    eval_cfa_rule, eval_register_rule, ConversionError, DwarfUnwindRegs, DwarfUnwinderError,
+    DwarfUnwinding,
+};
+
+impl DwarfUnwindRegs for UnwindRegsRiscv64 {
+    fn get(&self, register: Register) -> Option<u64> {
+        match register {
+            RiscV::SP => Some(self.sp()),
+            RiscV::S0 => Some(self.fp()),
+            RiscV::RA => Some(self.ra()),
+            _ => None,
+        }
+    }
+}
+
+impl DwarfUnwinding for ArchRiscv64 {
+    fn rule_for_row<O, UCS>(
+        unwind_info: &UnwindTableRow<O, UCS>,
+    ) -> Result<Self::UnwindRule, ConversionError>
+    where
+        O: ReaderOffset,
+        UCS: UnwindContextStorage<O>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let fp_rule = unwind_info.register(RiscV::S0);
+        let ra_rule = unwind_info.register(RiscV::RA);
+        translate_into_unwind_rule(cfa_rule, &fp_rule, &ra_rule)
+    }
+
+    fn unwind_frame<F, R, UCS, ES>(
+        section: &impl UnwindSection<R>,
+        unwind_info: &UnwindTableRow<R::Offset, UCS>,
+        encoding: Encoding,
+        regs: &mut Self::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, DwarfUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+        R: Reader,
+        UCS: UnwindContextStorage<R::Offset>,
+        ES: EvaluationStorage<R>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let fp_rule = unwind_info.register(RiscV::S0);
+        let ra_rule = unwind_info.register(RiscV::RA);
+
+        let cfa = eval_cfa_rule::<R, _, ES>(section, cfa_rule, encoding, regs)
+            .ok_or(DwarfUnwinderError::CouldNotRecoverCfa)?;
+
+        let ra = regs.ra();
+        let fp = regs.fp();
+        let sp = regs.sp();
+
+        let (fp, ra) = if !is_first_frame {
+            if cfa <= sp {
+                return Err(DwarfUnwinderError::StackPointerMovedBackwards);
+            }
+            let fp = eval_register_rule::<R, F, _, ES>(
+                section, fp_rule, cfa, encoding, fp, regs, read_stack,
+            )
+            .ok_or(DwarfUnwinderError::CouldNotRecoverFramePointer)?;
+            let ra = eval_register_rule::<R, F, _, ES>(
+                section, ra_rule, cfa, encoding, ra, regs, read_stack,
+            )
+            .ok_or(DwarfUnwinderError::CouldNotRecoverReturnAddress)?;
+            (fp, ra)
+        } else {
+            // For the first frame, be more lenient when encountering errors.
+            let fp = eval_register_rule::<R, F, _, ES>(
+                section, fp_rule, cfa, encoding, fp, regs, read_stack,
+            )
+            .unwrap_or(fp);
+            let ra = eval_register_rule::<R, F, _, ES>(
+                section, ra_rule, cfa, encoding, ra, regs, read_stack,
+            )
+            .unwrap_or(ra);
+            (fp, ra)
+        };
+
+        regs.set_fp(fp);
+        regs.set_sp(cfa);
+        regs.set_ra(ra);
+        regs.set_pc(ra);
+
+        Ok(UnwindResult::Uncacheable(ra))
+    }
+
+    fn rule_if_uncovered_by_fde() -> Self::UnwindRule {
+        UnwindRuleRiscv64::NoOpIfFirstFrameOtherwiseFp
+    }
+}
+
+fn register_rule_to_cfa_offset<RO: ReaderOffset>(
+    rule: &RegisterRule<RO>,
+) -> Result<Option<i64>, ConversionError> {
+    match *rule {
+        RegisterRule::Undefined | RegisterRule::SameValue => Ok(None),
+        RegisterRule::Offset(offset) => Ok(Some(offset)),
+        _ => Err(ConversionError::RegisterNotStoredRelativeToCfa),
+    }
+}
+
+fn translate_into_unwind_rule<RO: ReaderOffset>(
+    cfa_rule: &CfaRule<RO>,
+    fp_rule: &RegisterRule<RO>,
+    ra_rule: &RegisterRule<RO>,
+) -> Result<UnwindRuleRiscv64, ConversionError> {
+    match cfa_rule {
+        CfaRule::RegisterAndOffset { register, offset } => match *register {
+            RiscV::SP => {
+                // The psABI requires sp to be 16-byte aligned.
+                if offset % 16 != 0 {
+                    return Err(ConversionError::SpOffsetDoesNotFit);
+                }
+                let sp_offset_by_16 =
+                    u16::try_from(offset / 16).map_err(|_| ConversionError::SpOffsetDoesNotFit)?;
+                let ra_cfa_offset = register_rule_to_cfa_offset(ra_rule)?;
+                let fp_cfa_offset = register_rule_to_cfa_offset(fp_rule)?;
+                match (ra_cfa_offset, fp_cfa_offset) {
+                    (None, Some(_)) => Err(ConversionError::RestoringFpButNotLr),
+                    (None, None) => {
+                        if let RegisterRule::Undefined = ra_rule {
+                            // Either the function never returns, or the compiler omitted the
+                            // return address column when it meant "same value".
+                            Ok(
+                                UnwindRuleRiscv64::OffsetSpIfFirstFrameOtherwiseStackEndsHere {
+                                    sp_offset_by_16,
+                                },
+                            )
+                        } else {
+                            Ok(UnwindRuleRiscv64::OffsetSp { sp_offset_by_16 })
+                        }
+                    }
+                    (Some(ra_cfa_offset), None) => {
+                        let ra_storage_offset_from_sp_by_8 =
+                            i16::try_from((offset + ra_cfa_offset) / 8)
+                                .map_err(|_| ConversionError::LrStorageOffsetDoesNotFit)?;
+                        Ok(UnwindRuleRiscv64::OffsetSpAndRestoreRa {
+                            sp_offset_by_16,
+                            ra_storage_offset_from_sp_by_8,
+                        })
+                    }
+                    (Some(ra_cfa_offset), Some(fp_cfa_offset)) => {
+                        let ra_storage_offset_from_sp_by_8 =
+                            i16::try_from((offset + ra_cfa_offset) / 8)
+                                .map_err(|_| ConversionError::LrStorageOffsetDoesNotFit)?;
+                        let fp_storage_offset_from_sp_by_8 =
+                            i16::try_from((offset + fp_cfa_offset) / 8)
+                                .map_err(|_| ConversionError::FpStorageOffsetDoesNotFit)?;
+                        Ok(UnwindRuleRiscv64::OffsetSpAndRestoreFpAndRa {
+                            sp_offset_by_16,
+                            fp_storage_offset_from_sp_by_8,
+                            ra_storage_offset_from_sp_by_8,
+                        })
+                    }
+                }
+            }
+            RiscV::S0 => {
+                let ra_cfa_offset = register_rule_to_cfa_offset(ra_rule)?
+                    .ok_or(ConversionError::FramePointerRuleDoesNotRestoreLr)?;
+                let fp_cfa_offset = register_rule_to_cfa_offset(fp_rule)?
+                    .ok_or(ConversionError::FramePointerRuleDoesNotRestoreFp)?;
+                if *offset == 0 && fp_cfa_offset == -16 && ra_cfa_offset == -8 {
+                    Ok(UnwindRuleRiscv64::UseFramePointer)
+                } else {
+                    let sp_offset_from_fp_by_8 = i16::try_from(offset / 8)
+                        .map_err(|_| ConversionError::SpOffsetFromFpDoesNotFit)?;
+                    let ra_storage_offset_from_fp_by_8 =
+                        i16::try_from((offset + ra_cfa_offset) / 8)
+                            .map_err(|_| ConversionError::LrStorageOffsetDoesNotFit)?;
+                    let fp_storage_offset_from_fp_by_8 =
+                        i16::try_from((offset + fp_cfa_offset) / 8)
+                            .map_err(|_| ConversionError::FpStorageOffsetDoesNotFit)?;
+                    Ok(UnwindRuleRiscv64::UseFramepointerWithOffsets {
+                        sp_offset_from_fp_by_8,
+                        fp_storage_offset_from_fp_by_8,
+                        ra_storage_offset_from_fp_by_8,
+                    })
+                }
+            }
+            _ => Err(ConversionError::CfaIsOffsetFromUnknownRegister),
+        },
+        CfaRule::Expression(_) => Err(ConversionError::CfaIsExpression),
+    }
+}
@@ -0,0 +1,18 @@
+use super::arch::ArchRiscv64;
+use crate::instruction_analysis::InstructionAnalysis;
+
+impl InstructionAnalysis for ArchRiscv64 {
+    fn rule_from_prologue_analysis(
+        _text_bytes: &[u8],
+        _pc_offset: usize,
+    ) -> Option<Self::UnwindRule> {
+        None
+    }
+
+    fn rule_from_epilogue_analysis(
+        _text_bytes: &[u8],
+        _pc_offset: usize,
+    ) -> Option<Self::UnwindRule> {
+        None
+    }
+}
@@ -0,0 +1,37 @@
+use super::arch::ArchRiscv64;
+use super::unwind_rule::UnwindRuleRiscv64;
+use super::unwindregs::UnwindRegsRiscv64;
+use crate::macho::{CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding, CuiUnwindResult};
+use crate::unwind_result::UnwindResult;
+use macho_unwind_info::Function;
+
+impl CompactUnwindInfoUnwinding for ArchRiscv64 {
+    fn unwind_frame(
+        _function: Function,
+        _is_first_frame: bool,
+        _address_offset_within_function: usize,
+        _function_bytes: Option<&[u8]>,
+    ) -> Result<CuiUnwindResult<UnwindRuleRiscv64>, CompactUnwindInfoUnwinderError> {
+        Err(CompactUnwindInfoUnwinderError::Riscv64Unsupported)
+    }
+
+    fn rule_for_stub_helper(
+        _offset: u32,
+    ) -> Result<CuiUnwindResult<UnwindRuleRiscv64>, CompactUnwindInfoUnwinderError> {
+        Err(CompactUnwindInfoUnwinderError::Riscv64Unsupported)
+    }
+
+    fn is_sigtramp_return_address(_function_bytes: &[u8], _return_address_offset: usize) -> bool {
+        false
+    }
+
+    fn unwind_sigtramp_frame<F>(
+        _regs: &mut UnwindRegsRiscv64,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleRiscv64>, CompactUnwindInfoUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(CompactUnwindInfoUnwinderError::Riscv64Unsupported)
+    }
+}
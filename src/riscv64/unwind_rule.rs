@@ -0,0 +1,349 @@
+use super::unwindregs::UnwindRegsRiscv64;
+use crate::add_signed::checked_add_signed;
+use crate::error::Error;
+
+use crate::unwind_rule::{pack_rule, unpack_rule, UnwindRule};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnwindRuleRiscv64 {
+    /// (sp, fp, ra) = (sp, fp, ra)
+    /// Only possible for the first frame. Subsequent frames must get the
+    /// return address from somewhere other than the ra register to avoid
+    /// infinite loops.
+    NoOp,
+    /// (sp, fp, ra) = if is_first_frame (sp, fp, ra) else (fp, *(fp - 16), *(fp - 8))
+    /// Used as a fallback rule.
+    NoOpIfFirstFrameOtherwiseFp,
+    /// (sp, fp, ra) = (sp + 16x, fp, ra)
+    /// Only possible for the first frame. Subsequent frames must get the
+    /// return address from somewhere other than the ra register to avoid
+    /// infinite loops.
+    OffsetSp { sp_offset_by_16: u16 },
+    /// (sp, fp, ra) = (sp + 16x, fp, ra) if is_first_frame
+    /// This rule reflects an ambiguity in DWARF CFI information. When the
+    /// return address is "undefined" because it was omitted, it could mean
+    /// "same value", but this is only allowed for the first frame.
+    OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_16: u16 },
+    /// (sp, fp, ra) = (sp + 16x, fp, *(sp + 8y))
+    OffsetSpAndRestoreRa {
+        sp_offset_by_16: u16,
+        ra_storage_offset_from_sp_by_8: i16,
+    },
+    /// (sp, fp, ra) = (sp + 16x, *(sp + 8y), *(sp + 8z))
+    OffsetSpAndRestoreFpAndRa {
+        sp_offset_by_16: u16,
+        fp_storage_offset_from_sp_by_8: i16,
+        ra_storage_offset_from_sp_by_8: i16,
+    },
+    /// (sp, fp, ra) = (fp, *(fp - 16), *(fp - 8))
+    UseFramePointer,
+    /// (sp, fp, ra) = (fp + 8x, *(fp + 8y), *(fp + 8z))
+    UseFramepointerWithOffsets {
+        sp_offset_from_fp_by_8: i16,
+        fp_storage_offset_from_fp_by_8: i16,
+        ra_storage_offset_from_fp_by_8: i16,
+    },
+}
+
+impl UnwindRule for UnwindRuleRiscv64 {
+    type UnwindRegs = UnwindRegsRiscv64;
+
+    fn rule_for_stub_functions() -> Self {
+        UnwindRuleRiscv64::NoOp
+    }
+    fn rule_for_function_start() -> Self {
+        UnwindRuleRiscv64::NoOp
+    }
+    fn fallback_rule() -> Self {
+        UnwindRuleRiscv64::UseFramePointer
+    }
+
+    fn pack(self) -> u64 {
+        match self {
+            UnwindRuleRiscv64::NoOp => pack_rule(0, [0, 0, 0]),
+            UnwindRuleRiscv64::NoOpIfFirstFrameOtherwiseFp => pack_rule(1, [0, 0, 0]),
+            UnwindRuleRiscv64::OffsetSp { sp_offset_by_16 } => {
+                pack_rule(2, [sp_offset_by_16, 0, 0])
+            }
+            UnwindRuleRiscv64::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_16 } => {
+                pack_rule(3, [sp_offset_by_16, 0, 0])
+            }
+            UnwindRuleRiscv64::OffsetSpAndRestoreRa {
+                sp_offset_by_16,
+                ra_storage_offset_from_sp_by_8,
+            } => pack_rule(
+                4,
+                [sp_offset_by_16, ra_storage_offset_from_sp_by_8 as u16, 0],
+            ),
+            UnwindRuleRiscv64::OffsetSpAndRestoreFpAndRa {
+                sp_offset_by_16,
+                fp_storage_offset_from_sp_by_8,
+                ra_storage_offset_from_sp_by_8,
+            } => pack_rule(
+                5,
+                [
+                    sp_offset_by_16,
+                    fp_storage_offset_from_sp_by_8 as u16,
+                    ra_storage_offset_from_sp_by_8 as u16,
+                ],
+            ),
+            UnwindRuleRiscv64::UseFramePointer => pack_rule(6, [0, 0, 0]),
+            UnwindRuleRiscv64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8,
+                fp_storage_offset_from_fp_by_8,
+                ra_storage_offset_from_fp_by_8,
+            } => pack_rule(
+                7,
+                [
+                    sp_offset_from_fp_by_8 as u16,
+                    fp_storage_offset_from_fp_by_8 as u16,
+                    ra_storage_offset_from_fp_by_8 as u16,
+                ],
+            ),
+        }
+    }
+
+    fn unpack(bits: u64) -> Option<Self> {
+        let (tag, [a, b, c]) = unpack_rule(bits);
+        let rule = match tag {
+            0 => UnwindRuleRiscv64::NoOp,
+            1 => UnwindRuleRiscv64::NoOpIfFirstFrameOtherwiseFp,
+            2 => UnwindRuleRiscv64::OffsetSp { sp_offset_by_16: a },
+            3 => {
+                UnwindRuleRiscv64::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_16: a }
+            }
+            4 => UnwindRuleRiscv64::OffsetSpAndRestoreRa {
+                sp_offset_by_16: a,
+                ra_storage_offset_from_sp_by_8: b as i16,
+            },
+            5 => UnwindRuleRiscv64::OffsetSpAndRestoreFpAndRa {
+                sp_offset_by_16: a,
+                fp_storage_offset_from_sp_by_8: b as i16,
+                ra_storage_offset_from_sp_by_8: c as i16,
+            },
+            6 => UnwindRuleRiscv64::UseFramePointer,
+            7 => UnwindRuleRiscv64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8: a as i16,
+                fp_storage_offset_from_fp_by_8: b as i16,
+                ra_storage_offset_from_fp_by_8: c as i16,
+            },
+            _ => return None,
+        };
+        Some(rule)
+    }
+
+    fn exec<F>(
+        self,
+        is_first_frame: bool,
+        regs: &mut UnwindRegsRiscv64,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, Error>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let ra = regs.ra();
+        let sp = regs.sp();
+        let fp = regs.fp();
+
+        let (new_ra, new_sp, new_fp) = match self {
+            UnwindRuleRiscv64::NoOp => {
+                if !is_first_frame {
+                    return Err(Error::DidNotAdvance);
+                }
+                (ra, sp, fp)
+            }
+            UnwindRuleRiscv64::NoOpIfFirstFrameOtherwiseFp => {
+                if is_first_frame {
+                    (ra, sp, fp)
+                } else {
+                    let ra_location = fp.checked_sub(8).ok_or(Error::IntegerOverflow)?;
+                    let fp_location = fp.checked_sub(16).ok_or(Error::IntegerOverflow)?;
+                    let new_ra = read_stack(ra_location)
+                        .map_err(|_| Error::CouldNotReadStack(ra_location))?;
+                    let new_fp = read_stack(fp_location)
+                        .map_err(|_| Error::CouldNotReadStack(fp_location))?;
+                    if new_fp == 0 {
+                        return Ok(None);
+                    }
+                    if new_fp <= fp || fp <= sp {
+                        return Err(Error::FramepointerUnwindingMovedBackwards);
+                    }
+                    (new_ra, fp, new_fp)
+                }
+            }
+            UnwindRuleRiscv64::OffsetSpIfFirstFrameOtherwiseStackEndsHere { sp_offset_by_16 } => {
+                if !is_first_frame {
+                    return Ok(None);
+                }
+                let sp_offset = u64::from(sp_offset_by_16) * 16;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                (ra, new_sp, fp)
+            }
+            UnwindRuleRiscv64::OffsetSp { sp_offset_by_16 } => {
+                if !is_first_frame {
+                    return Err(Error::DidNotAdvance);
+                }
+                let sp_offset = u64::from(sp_offset_by_16) * 16;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                (ra, new_sp, fp)
+            }
+            UnwindRuleRiscv64::OffsetSpAndRestoreRa {
+                sp_offset_by_16,
+                ra_storage_offset_from_sp_by_8,
+            } => {
+                let sp_offset = u64::from(sp_offset_by_16) * 16;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                let ra_storage_offset = i64::from(ra_storage_offset_from_sp_by_8) * 8;
+                let ra_location =
+                    checked_add_signed(sp, ra_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let new_ra =
+                    read_stack(ra_location).map_err(|_| Error::CouldNotReadStack(ra_location))?;
+                (new_ra, new_sp, fp)
+            }
+            UnwindRuleRiscv64::OffsetSpAndRestoreFpAndRa {
+                sp_offset_by_16,
+                fp_storage_offset_from_sp_by_8,
+                ra_storage_offset_from_sp_by_8,
+            } => {
+                let sp_offset = u64::from(sp_offset_by_16) * 16;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                let ra_storage_offset = i64::from(ra_storage_offset_from_sp_by_8) * 8;
+                let ra_location =
+                    checked_add_signed(sp, ra_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let new_ra =
+                    read_stack(ra_location).map_err(|_| Error::CouldNotReadStack(ra_location))?;
+                let fp_storage_offset = i64::from(fp_storage_offset_from_sp_by_8) * 8;
+                let fp_location =
+                    checked_add_signed(sp, fp_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let new_fp =
+                    read_stack(fp_location).map_err(|_| Error::CouldNotReadStack(fp_location))?;
+                (new_ra, new_sp, new_fp)
+            }
+            UnwindRuleRiscv64::UseFramePointer => {
+                // Do a frame pointer stack walk. The RISC-V psABI frame record layout puts the
+                // return address and the caller's frame pointer right below the address that
+                // the frame pointer points to; the frame pointer is the stack pointer value
+                // from before the function was entered.
+                //
+                // Function prologue example:
+                // addi  sp, sp, -32   ; allocate stack space
+                // sd    ra, 24(sp)    ; store the return address at original sp - 8
+                // sd    s0, 16(sp)    ; store the caller's frame pointer at original sp - 16
+                // addi  s0, sp, 32    ; sets fp to the original sp
+                //
+                // So: *(fp - 16) is the caller's frame pointer, *(fp - 8) is the return address,
+                // and fp is the caller's stack pointer.
+                let ra_location = fp.checked_sub(8).ok_or(Error::IntegerOverflow)?;
+                let fp_location = fp.checked_sub(16).ok_or(Error::IntegerOverflow)?;
+                let new_ra =
+                    read_stack(ra_location).map_err(|_| Error::CouldNotReadStack(ra_location))?;
+                let new_fp =
+                    read_stack(fp_location).map_err(|_| Error::CouldNotReadStack(fp_location))?;
+                if new_fp == 0 {
+                    return Ok(None);
+                }
+                if new_fp <= fp || fp <= sp {
+                    return Err(Error::FramepointerUnwindingMovedBackwards);
+                }
+                (new_ra, fp, new_fp)
+            }
+            UnwindRuleRiscv64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8,
+                fp_storage_offset_from_fp_by_8,
+                ra_storage_offset_from_fp_by_8,
+            } => {
+                let sp_offset_from_fp = i64::from(sp_offset_from_fp_by_8) * 8;
+                let new_sp =
+                    checked_add_signed(fp, sp_offset_from_fp).ok_or(Error::IntegerOverflow)?;
+                let ra_storage_offset = i64::from(ra_storage_offset_from_fp_by_8) * 8;
+                let ra_location =
+                    checked_add_signed(fp, ra_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let new_ra =
+                    read_stack(ra_location).map_err(|_| Error::CouldNotReadStack(ra_location))?;
+                let fp_storage_offset = i64::from(fp_storage_offset_from_fp_by_8) * 8;
+                let fp_location =
+                    checked_add_signed(fp, fp_storage_offset).ok_or(Error::IntegerOverflow)?;
+                let new_fp =
+                    read_stack(fp_location).map_err(|_| Error::CouldNotReadStack(fp_location))?;
+
+                if new_fp == 0 {
+                    return Ok(None);
+                }
+                if new_fp <= fp || new_sp <= sp {
+                    return Err(Error::FramepointerUnwindingMovedBackwards);
+                }
+                (new_ra, new_sp, new_fp)
+            }
+        };
+        let return_address = new_ra;
+        if return_address == 0 {
+            return Ok(None);
+        }
+        if !is_first_frame && new_sp == sp {
+            return Err(Error::DidNotAdvance);
+        }
+        regs.set_pc(return_address);
+        regs.set_ra(new_ra);
+        regs.set_sp(new_sp);
+        regs.set_fp(new_fp);
+
+        Ok(Some(return_address))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let stack = [
+            1, 2, 3, 4, 0x50, 0x100200, 5, 6, 0x80, 0x100100, 7, 8, 9, 10, 0x0, 0x0,
+        ];
+        let mut read_stack = |addr| Ok(stack[(addr / 8) as usize]);
+        let mut regs = UnwindRegsRiscv64::new(0x100400, 0x100300, 0x10, 0x30);
+        let res = UnwindRuleRiscv64::NoOp.exec(true, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100300)));
+        assert_eq!(regs.pc(), 0x100300);
+        assert_eq!(regs.sp(), 0x10);
+        let res = UnwindRuleRiscv64::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100200)));
+        assert_eq!(regs.sp(), 0x30);
+        assert_eq!(regs.fp(), 0x50);
+        let res = UnwindRuleRiscv64::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100100)));
+        assert_eq!(regs.sp(), 0x50);
+        assert_eq!(regs.fp(), 0x80);
+        let res = UnwindRuleRiscv64::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(None));
+    }
+
+    #[test]
+    fn test_no_op_if_first_frame_otherwise_fp() {
+        let rule = UnwindRuleRiscv64::NoOpIfFirstFrameOtherwiseFp;
+        let stack = [
+            1, 2, 3, 4, 0x50, 0x100200, 5, 6, 0x80, 0x100100, 7, 8, 0x0, 0x0,
+        ];
+        let mut read_stack = |addr| Ok(stack[(addr / 8) as usize]);
+        let mut regs = UnwindRegsRiscv64::new(0x100400, 0x100300, 0x10, 0x30);
+        assert_eq!(
+            rule.exec(false, &mut regs, &mut read_stack),
+            Ok(Some(0x100200))
+        );
+        assert_eq!(regs.sp(), 0x30);
+        assert_eq!(regs.fp(), 0x50);
+
+        // A zero frame pointer ends the stack.
+        let mut regs = UnwindRegsRiscv64::new(0x100400, 0x100300, 0x50, 0x70);
+        assert_eq!(rule.exec(false, &mut regs, &mut read_stack), Ok(None));
+
+        // A frame pointer which points back into the current frame is corrupt.
+        let stack = [1, 2, 3, 4, 0x20, 0x100200];
+        let mut read_stack = |addr| Ok(stack[(addr / 8) as usize]);
+        let mut regs = UnwindRegsRiscv64::new(0x100400, 0x100300, 0x10, 0x30);
+        assert_eq!(
+            rule.exec(false, &mut regs, &mut read_stack),
+            Err(Error::FramepointerUnwindingMovedBackwards)
+        );
+    }
+}
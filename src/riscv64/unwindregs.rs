@@ -0,0 +1,90 @@
+use core::fmt::Debug;
+
+use crate::display_utils::HexNum;
+use crate::frame_record::FrameRegs;
+
+/// The registers used for unwinding on 64-bit RISC-V. We need pc, ra (x1), sp (x2)
+/// and fp (s0 / x8).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct UnwindRegsRiscv64 {
+    pc: u64,
+    ra: u64,
+    sp: u64,
+    fp: u64,
+}
+
+impl UnwindRegsRiscv64 {
+    /// Create a set of unwind register values.
+    pub fn new(pc: u64, ra: u64, sp: u64, fp: u64) -> Self {
+        Self { pc, ra, sp, fp }
+    }
+
+    /// Get the program counter value.
+    #[inline(always)]
+    pub fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    /// Set the program counter value.
+    #[inline(always)]
+    pub fn set_pc(&mut self, pc: u64) {
+        self.pc = pc
+    }
+
+    /// Get the return address register value (ra / x1).
+    #[inline(always)]
+    pub fn ra(&self) -> u64 {
+        self.ra
+    }
+
+    /// Set the return address register value (ra / x1).
+    #[inline(always)]
+    pub fn set_ra(&mut self, ra: u64) {
+        self.ra = ra
+    }
+
+    /// Get the stack pointer value (sp / x2).
+    #[inline(always)]
+    pub fn sp(&self) -> u64 {
+        self.sp
+    }
+
+    /// Set the stack pointer value (sp / x2).
+    #[inline(always)]
+    pub fn set_sp(&mut self, sp: u64) {
+        self.sp = sp
+    }
+
+    /// Get the frame pointer value (s0 / x8).
+    #[inline(always)]
+    pub fn fp(&self) -> u64 {
+        self.fp
+    }
+
+    /// Set the frame pointer value (s0 / x8).
+    #[inline(always)]
+    pub fn set_fp(&mut self, fp: u64) {
+        self.fp = fp
+    }
+}
+
+impl FrameRegs for UnwindRegsRiscv64 {
+    fn stack_pointer(&self) -> u64 {
+        self.sp()
+    }
+
+    fn frame_pointer(&self) -> u64 {
+        self.fp()
+    }
+}
+
+impl Debug for UnwindRegsRiscv64 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnwindRegsRiscv64")
+            .field("pc", &HexNum(self.pc))
+            .field("ra", &HexNum(self.ra))
+            .field("sp", &HexNum(self.sp))
+            .field("fp", &HexNum(self.fp))
+            .finish()
+    }
+}
@@ -1,59 +1,313 @@
 use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::{fence, AtomicU32, AtomicU8, AtomicUsize, Ordering};
 
 use crate::unwind_rule::UnwindRule;
+use crate::unwinder::generation_era;
 
-const CACHE_ENTRY_COUNT: usize = 509;
+const DEFAULT_ENTRY_COUNT: usize = 509;
+
+/// How many slots of the rule cache an address can be stored in. See [`CacheConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheAssociativity {
+    /// Each address has one slot. Addresses which map to the same slot evict each other.
+    #[default]
+    DirectMapped,
+    /// Each address can be stored in one of two slots. When both are taken, the least
+    /// recently used entry is replaced.
+    TwoWay,
+    /// Each address can be stored in one of four slots. When all are taken, the least
+    /// recently used entry is replaced.
+    FourWay,
+}
+
+impl CacheAssociativity {
+    fn ways(self) -> usize {
+        match self {
+            CacheAssociativity::DirectMapped => 1,
+            CacheAssociativity::TwoWay => 2,
+            CacheAssociativity::FourWay => 4,
+        }
+    }
+}
+
+/// What the rule cache uses to identify an address. See [`CacheConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheKeyMode {
+    /// The address in the process, together with a generation number of the module
+    /// which contains it. The generation changes when the module is added again, so
+    /// cached rules can only be found again by the unwinder which added the module,
+    /// and by its clones. Adding or removing other modules only invalidates the cached
+    /// rules for addresses outside of all modules.
+    #[default]
+    AbsoluteAddress,
+    /// The [cache key](crate::Module::with_cache_key) of the module which contains the
+    /// address, and the address relative to the module's base address. Cached rules can
+    /// be found by all unwinders which have a module with the same cache key, even if it
+    /// is mapped at a different address, for example when profiling many processes which
+    /// load the same library. Adding or removing other modules does not invalidate them.
+    ///
+    /// Each lookup needs to find the address's module first. Rules for addresses in
+    /// modules without a cache key, and for addresses outside of all modules, are not
+    /// cached in this mode.
+    ModuleRelative,
+}
+
+/// The size, the associativity and the keys of the rule cache. The default is a
+/// direct-mapped cache with 509 entries, keyed by absolute addresses.
+///
+/// A larger or set-associative cache helps if many different addresses are unwound
+/// repeatedly, for example when profiling many processes with the same cache. Use
+/// [`CacheStats`] to measure the difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// The number of unwind rules which the cache can hold. This is rounded up to a
+    /// multiple of the number of ways.
+    pub entry_count: usize,
+    /// How many slots each address can be stored in.
+    pub associativity: CacheAssociativity,
+    /// What the cache uses to identify an address.
+    pub key_mode: CacheKeyMode,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            entry_count: DEFAULT_ENTRY_COUNT,
+            associativity: CacheAssociativity::DirectMapped,
+            key_mode: CacheKeyMode::AbsoluteAddress,
+        }
+    }
+}
+
+/// The entries are grouped into sets of `ways` consecutive entries. An address can be
+/// stored in any entry of the set `address % set_count`.
+#[derive(Debug, Clone, Copy)]
+struct CacheLayout {
+    set_count: usize,
+    ways: usize,
+}
+
+impl CacheLayout {
+    fn new(config: CacheConfig) -> Self {
+        let ways = config.associativity.ways();
+        Self {
+            set_count: config.entry_count.div_ceil(ways).max(1),
+            ways,
+        }
+    }
+
+    fn entry_count(&self) -> usize {
+        self.set_count * self.ways
+    }
+
+    /// The number of sets which need an LRU order. Direct-mapped caches don't.
+    fn lru_count(&self) -> usize {
+        if self.ways > 1 {
+            self.set_count
+        } else {
+            0
+        }
+    }
+
+    /// Code addresses are often aligned, so the address is hashed before picking the set.
+    /// Otherwise, with an even set count, aligned addresses would only use some of the
+    /// sets. This is a multiplicative hash, which mixes all bits of the address into the
+    /// upper bits of the product.
+    fn set_for_address(&self, address: u64) -> usize {
+        let hash = address.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+        (hash % self.set_count as u64) as usize
+    }
+
+    /// Looks for `address` in `set`. On a miss, picks the way which a new entry should
+    /// be stored in: an empty way, else a way from different modules, else the least
+    /// recently used way.
+    fn search_set<R: UnwindRule, M: Copy + Eq>(
+        &self,
+        set: usize,
+        address: u64,
+        modules: M,
+        lru: LruOrder,
+        read: impl Fn(usize) -> Option<CacheEntry<R, M>>,
+    ) -> (LookupOutcome<R>, usize) {
+        let mut empty_way = None;
+        let mut wrong_modules_way = None;
+        for way in 0..self.ways {
+            match read(set * self.ways + way) {
+                None => {
+                    empty_way.get_or_insert(way);
+                }
+                Some(entry) if entry.modules != modules => {
+                    wrong_modules_way.get_or_insert(way);
+                }
+                Some(entry) if entry.address == address => {
+                    return (LookupOutcome::Hit(entry.unwind_rule), way);
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(way) = empty_way {
+            (LookupOutcome::MissEmptySlot, way)
+        } else if let Some(way) = wrong_modules_way {
+            (LookupOutcome::MissWrongModules, way)
+        } else {
+            (
+                LookupOutcome::MissWrongAddress,
+                lru.least_recently_used(self.ways),
+            )
+        }
+    }
+}
+
+/// The order in which the ways of a set were used, two bits per way, with the most
+/// recently used way in the lowest bits.
+#[derive(Debug, Clone, Copy)]
+struct LruOrder(u8);
+
+impl LruOrder {
+    const INITIAL: Self = Self(0b11_10_01_00);
+
+    fn way_at(self, position: usize) -> usize {
+        usize::from(self.0 >> (2 * position)) & 0b11
+    }
+
+    fn least_recently_used(self, ways: usize) -> usize {
+        self.way_at(ways - 1)
+    }
+
+    /// Makes `way` the most recently used way.
+    fn touch(self, way: usize, ways: usize) -> Self {
+        let mut order = way as u8;
+        let mut position = 1;
+        for other_way in (0..ways).map(|position| self.way_at(position)) {
+            if other_way != way {
+                order |= (other_way as u8) << (2 * position);
+                position += 1;
+            }
+        }
+        Self(order)
+    }
+}
 
 pub struct RuleCache<R: UnwindRule> {
-    entries: Box<[Option<CacheEntry<R>>; CACHE_ENTRY_COUNT]>,
+    storage: RuleCacheStorage<R>,
     stats: CacheStats,
 }
 
+enum RuleCacheStorage<R: UnwindRule> {
+    /// Keyed by absolute addresses and module generations. Only the low 16 bits of the
+    /// generations are stored; `era` holds the upper bits of the generations in the
+    /// cache.
+    Local {
+        cache: LocalRuleCache<R, u16>,
+        era: u64,
+    },
+    /// Keyed by relative addresses and module cache keys.
+    LocalModuleRelative(LocalRuleCache<R, u64>),
+    Shared(Arc<SharedRuleCache<R>>),
+}
+
 impl<R: UnwindRule> RuleCache<R> {
-    pub fn new() -> Self {
+    pub fn new(config: CacheConfig) -> Self {
+        let storage = match config.key_mode {
+            CacheKeyMode::AbsoluteAddress => RuleCacheStorage::Local {
+                cache: LocalRuleCache::new(config),
+                era: 0,
+            },
+            CacheKeyMode::ModuleRelative => {
+                RuleCacheStorage::LocalModuleRelative(LocalRuleCache::new(config))
+            }
+        };
         Self {
-            entries: Box::new([None; CACHE_ENTRY_COUNT]),
+            storage,
             stats: CacheStats::new(),
         }
     }
 
-    pub fn lookup(&mut self, address: u64, modules_generation: u16) -> CacheResult<R> {
-        let slot = (address % (CACHE_ENTRY_COUNT as u64)) as u16;
-        match &self.entries[slot as usize] {
-            None => {
-                self.stats.miss_empty_slot_count += 1;
-            }
-            Some(entry) => {
-                if entry.modules_generation == modules_generation {
-                    if entry.address == address {
-                        self.stats.hit_count += 1;
-                        return CacheResult::Hit(entry.unwind_rule);
-                    } else {
-                        self.stats.miss_wrong_address_count += 1;
-                    }
+    pub fn new_shared(shared: Arc<SharedRuleCache<R>>) -> Self {
+        Self {
+            storage: RuleCacheStorage::Shared(shared),
+            stats: CacheStats::new(),
+        }
+    }
+
+    pub fn key_mode(&self) -> CacheKeyMode {
+        match &self.storage {
+            RuleCacheStorage::Local { .. } => CacheKeyMode::AbsoluteAddress,
+            RuleCacheStorage::LocalModuleRelative(_) => CacheKeyMode::ModuleRelative,
+            RuleCacheStorage::Shared(shared) => shared.key_mode,
+        }
+    }
+
+    /// Looks up the rule for an address. In [`CacheKeyMode::AbsoluteAddress`] mode,
+    /// `address` is the absolute address and `modules` is the generation of the module
+    /// which contains it. In [`CacheKeyMode::ModuleRelative`] mode, `address` is the address
+    /// relative to the module's base address and `modules` is the module's cache key.
+    pub fn lookup(&mut self, address: u64, modules: u64) -> CacheResult<R> {
+        let (outcome, set, way) = match &mut self.storage {
+            RuleCacheStorage::Local { cache, era } => {
+                let generation_era = generation_era(modules);
+                if generation_era > *era {
+                    // The stored low bits could now collide with new generations.
+                    cache.clear();
+                    *era = generation_era;
+                }
+                if generation_era == *era {
+                    cache.lookup(address, modules as u16)
                 } else {
-                    self.stats.miss_wrong_modules_count += 1;
+                    // The unwinder's generations are from before the last flush. It
+                    // needs new generations before its rules can be cached again.
+                    self.stats.count(&LookupOutcome::<R>::MissWrongModules);
+                    return CacheResult::MissOutdatedGeneration;
                 }
             }
+            RuleCacheStorage::LocalModuleRelative(cache) => cache.lookup(address, modules),
+            RuleCacheStorage::Shared(shared) => shared.lookup(address, modules),
+        };
+        self.stats.count(&outcome);
+        if let RuleCacheStorage::Shared(shared) = &self.storage {
+            shared.stats.count(&outcome);
+        }
+        match outcome {
+            LookupOutcome::Hit(unwind_rule) => CacheResult::Hit(unwind_rule),
+            _ => CacheResult::Miss(CacheHandle {
+                set,
+                way,
+                address,
+                modules,
+            }),
         }
-        CacheResult::Miss(CacheHandle {
-            slot,
-            address,
-            modules_generation,
-        })
     }
 
     pub fn insert(&mut self, handle: CacheHandle, unwind_rule: R) {
         let CacheHandle {
-            slot,
+            set,
+            way,
             address,
-            modules_generation,
+            modules,
         } = handle;
-        self.entries[slot as usize] = Some(CacheEntry {
+        let entry = CacheEntry {
             address,
-            modules_generation,
+            modules,
             unwind_rule,
-        });
+        };
+        match &mut self.storage {
+            RuleCacheStorage::Local { cache, era } => {
+                if generation_era(modules) == *era {
+                    let entry = CacheEntry {
+                        address,
+                        modules: modules as u16,
+                        unwind_rule,
+                    };
+                    cache.insert(set, way, entry);
+                }
+            }
+            RuleCacheStorage::LocalModuleRelative(cache) => cache.insert(set, way, entry),
+            RuleCacheStorage::Shared(shared) => shared.insert(set, way, entry),
+        }
     }
 
     /// Returns a snapshot of the cache usage statistics.
@@ -62,26 +316,231 @@ impl<R: UnwindRule> RuleCache<R> {
     }
 }
 
+struct LocalRuleCache<R: UnwindRule, M> {
+    layout: CacheLayout,
+    entries: Box<[Option<CacheEntry<R, M>>]>,
+    lru: Box<[LruOrder]>,
+}
+
+impl<R: UnwindRule, M: Copy + Eq> LocalRuleCache<R, M> {
+    fn new(config: CacheConfig) -> Self {
+        let layout = CacheLayout::new(config);
+        Self {
+            layout,
+            entries: vec![None; layout.entry_count()].into_boxed_slice(),
+            lru: vec![LruOrder::INITIAL; layout.lru_count()].into_boxed_slice(),
+        }
+    }
+
+    fn lookup(&mut self, address: u64, modules: M) -> (LookupOutcome<R>, usize, usize) {
+        let set = self.layout.set_for_address(address);
+        let lru = self.lru.get(set).copied().unwrap_or(LruOrder::INITIAL);
+        let (outcome, way) = self
+            .layout
+            .search_set(set, address, modules, lru, |i| self.entries[i]);
+        if let LookupOutcome::Hit(_) = outcome {
+            self.touch(set, way);
+        }
+        (outcome, set, way)
+    }
+
+    fn clear(&mut self) {
+        self.entries.fill(None);
+        self.lru.fill(LruOrder::INITIAL);
+    }
+
+    fn insert(&mut self, set: usize, way: usize, entry: CacheEntry<R, M>) {
+        self.entries[set * self.layout.ways + way] = Some(entry);
+        self.touch(set, way);
+    }
+
+    fn touch(&mut self, set: usize, way: usize) {
+        if let Some(lru) = self.lru.get_mut(set) {
+            *lru = lru.touch(way, self.layout.ways);
+        }
+    }
+}
+
+/// The rule storage of a [`SharedCache`](crate::cache::SharedCache).
+///
+/// Each slot is protected by a sequence lock: a writer makes the sequence number odd
+/// while it writes, and readers discard what they read if the sequence number was odd
+/// or has changed. Neither readers nor writers ever wait: if a slot is being written,
+/// readers treat it as empty, and other writers skip it. This makes the cache usable
+/// from signal handlers, even if the signal interrupted a write on the same thread.
+///
+/// The LRU order of each set is updated without synchronization. Concurrent updates can
+/// get lost, which only makes the replacement choice less accurate.
+pub struct SharedRuleCache<R: UnwindRule> {
+    key_mode: CacheKeyMode,
+    layout: CacheLayout,
+    slots: Box<[SharedCacheSlot<R>]>,
+    lru: Box<[AtomicU8]>,
+    stats: AtomicCacheStats,
+}
+
+impl<R: UnwindRule> SharedRuleCache<R> {
+    pub fn new(config: CacheConfig) -> Self {
+        let layout = CacheLayout::new(config);
+        Self {
+            key_mode: config.key_mode,
+            layout,
+            slots: (0..layout.entry_count())
+                .map(|_| SharedCacheSlot::new())
+                .collect::<Vec<_>>()
+                .into_boxed_slice(),
+            lru: (0..layout.lru_count())
+                .map(|_| AtomicU8::new(LruOrder::INITIAL.0))
+                .collect::<Vec<_>>()
+                .into_boxed_slice(),
+            stats: AtomicCacheStats::default(),
+        }
+    }
+
+    /// Returns the usage statistics of all caches which share this rule cache.
+    pub fn stats(&self) -> CacheStats {
+        self.stats.load()
+    }
+
+    fn lookup(&self, address: u64, modules: u64) -> (LookupOutcome<R>, usize, usize) {
+        let set = self.layout.set_for_address(address);
+        let (outcome, way) =
+            self.layout
+                .search_set(set, address, modules, self.lru_order(set), |i| {
+                    self.slots[i].read()
+                });
+        if let LookupOutcome::Hit(_) = outcome {
+            self.touch(set, way);
+        }
+        (outcome, set, way)
+    }
+
+    fn insert(&self, set: usize, way: usize, entry: CacheEntry<R, u64>) {
+        self.slots[set * self.layout.ways + way].write(entry);
+        self.touch(set, way);
+    }
+
+    fn lru_order(&self, set: usize) -> LruOrder {
+        self.lru.get(set).map_or(LruOrder::INITIAL, |lru| {
+            LruOrder(lru.load(Ordering::Relaxed))
+        })
+    }
+
+    fn touch(&self, set: usize, way: usize) {
+        let Some(lru) = self.lru.get(set) else {
+            return;
+        };
+        let order = LruOrder(lru.load(Ordering::Relaxed));
+        // Avoid writing to the shared cache line if nothing changes.
+        if order.way_at(0) != way {
+            lru.store(order.touch(way, self.layout.ways).0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The fields are split into 32-bit halves so that this also works on targets without
+/// 64-bit atomics. The sequence lock makes sure that the halves are consistent.
+struct SharedCacheSlot<R: UnwindRule> {
+    /// 0 if the slot has never been written, odd while it's being written.
+    seq: AtomicU32,
+    modules: [AtomicU32; 2],
+    address: [AtomicU32; 2],
+    unwind_rule: [AtomicU32; 2],
+    _rule: PhantomData<fn() -> R>,
+}
+
+impl<R: UnwindRule> SharedCacheSlot<R> {
+    fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            modules: [AtomicU32::new(0), AtomicU32::new(0)],
+            address: [AtomicU32::new(0), AtomicU32::new(0)],
+            unwind_rule: [AtomicU32::new(0), AtomicU32::new(0)],
+            _rule: PhantomData,
+        }
+    }
+
+    fn read(&self) -> Option<CacheEntry<R, u64>> {
+        let seq = self.seq.load(Ordering::Acquire);
+        if seq == 0 || seq % 2 == 1 {
+            return None;
+        }
+        let modules = load_u64(&self.modules);
+        let address = load_u64(&self.address);
+        let unwind_rule = load_u64(&self.unwind_rule);
+        fence(Ordering::Acquire);
+        if self.seq.load(Ordering::Relaxed) != seq {
+            return None;
+        }
+        Some(CacheEntry {
+            address,
+            modules,
+            unwind_rule: R::unpack(unwind_rule)?,
+        })
+    }
+
+    fn write(&self, entry: CacheEntry<R, u64>) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        if seq % 2 == 1
+            || self
+                .seq
+                .compare_exchange(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+        {
+            // Another thread is writing to this slot.
+            return;
+        }
+        fence(Ordering::Release);
+        store_u64(&self.modules, entry.modules);
+        store_u64(&self.address, entry.address);
+        store_u64(&self.unwind_rule, entry.unwind_rule.pack());
+        // Skip 0 when wrapping around, because it means "never written".
+        let next_seq = match seq.wrapping_add(2) {
+            0 => 2,
+            next_seq => next_seq,
+        };
+        self.seq.store(next_seq, Ordering::Release);
+    }
+}
+
+fn load_u64(halves: &[AtomicU32; 2]) -> u64 {
+    u64::from(halves[0].load(Ordering::Relaxed))
+        | u64::from(halves[1].load(Ordering::Relaxed)) << 32
+}
+
+fn store_u64(halves: &[AtomicU32; 2], value: u64) {
+    halves[0].store(value as u32, Ordering::Relaxed);
+    halves[1].store((value >> 32) as u32, Ordering::Relaxed);
+}
+
+enum LookupOutcome<R> {
+    Hit(R),
+    MissEmptySlot,
+    MissWrongModules,
+    MissWrongAddress,
+}
+
 pub enum CacheResult<R: UnwindRule> {
     Miss(CacheHandle),
+    /// The generation is from an era before the cache's. The caller should renew its
+    /// generations; nothing can be inserted until then.
+    MissOutdatedGeneration,
     Hit(R),
 }
 
 pub struct CacheHandle {
-    slot: u16,
+    set: usize,
+    way: usize,
     address: u64,
-    modules_generation: u16,
+    modules: u64,
 }
 
-const _: () = assert!(
-    CACHE_ENTRY_COUNT as u64 <= u16::MAX as u64,
-    "u16 should be sufficient to store the cache slot index"
-);
-
+/// `modules` is the module's generation for absolute addresses, and the module's cache
+/// key for relative addresses.
 #[derive(Clone, Copy, Debug)]
-struct CacheEntry<R: UnwindRule> {
+struct CacheEntry<R: UnwindRule, M = u16> {
     address: u64,
-    modules_generation: u16,
+    modules: M,
     unwind_rule: R,
 }
 
@@ -90,13 +549,16 @@ struct CacheEntry<R: UnwindRule> {
 pub struct CacheStats {
     /// The number of successful cache hits.
     pub hit_count: u64,
-    /// The number of cache misses that were due to an empty slot.
+    /// The number of cache misses that were due to an empty slot. For a shared cache,
+    /// this includes slots which were being written by another thread at the time.
     pub miss_empty_slot_count: u64,
     /// The number of cache misses that were due to a filled slot whose module
-    /// generation didn't match the unwinder's current module generation.
-    /// (This means that either the unwinder's modules have changed since the
-    /// rule in this slot was stored, or the same cache is used with multiple
-    /// unwinders and the unwinders are stomping on each other's cache slots.)
+    /// generation didn't match the generation of the looked up address's module.
+    /// (This means that either the module has been replaced since the rule in
+    /// this slot was stored, or the same cache is used with multiple unwinders
+    /// and the unwinders are stomping on each other's cache slots.)
+    /// With [`CacheKeyMode::ModuleRelative`], these are misses due to a slot
+    /// which was filled for a different module.
     pub miss_wrong_modules_count: u64,
     /// The number of cache misses that were due to cache slot collisions of
     /// different addresses.
@@ -123,14 +585,283 @@ impl CacheStats {
     pub fn misses(&self) -> u64 {
         self.miss_empty_slot_count + self.miss_wrong_modules_count + self.miss_wrong_address_count
     }
+
+    fn count<R>(&mut self, outcome: &LookupOutcome<R>) {
+        match outcome {
+            LookupOutcome::Hit(_) => self.hit_count += 1,
+            LookupOutcome::MissEmptySlot => self.miss_empty_slot_count += 1,
+            LookupOutcome::MissWrongModules => self.miss_wrong_modules_count += 1,
+            LookupOutcome::MissWrongAddress => self.miss_wrong_address_count += 1,
+        }
+    }
+}
+
+/// The counters of [`CacheStats`], for a [`SharedRuleCache`] which is used by many threads.
+#[derive(Default)]
+struct AtomicCacheStats {
+    hit_count: AtomicUsize,
+    miss_empty_slot_count: AtomicUsize,
+    miss_wrong_modules_count: AtomicUsize,
+    miss_wrong_address_count: AtomicUsize,
+}
+
+impl AtomicCacheStats {
+    fn count<R>(&self, outcome: &LookupOutcome<R>) {
+        let counter = match outcome {
+            LookupOutcome::Hit(_) => &self.hit_count,
+            LookupOutcome::MissEmptySlot => &self.miss_empty_slot_count,
+            LookupOutcome::MissWrongModules => &self.miss_wrong_modules_count,
+            LookupOutcome::MissWrongAddress => &self.miss_wrong_address_count,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> CacheStats {
+        let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed) as u64;
+        CacheStats {
+            hit_count: load(&self.hit_count),
+            miss_empty_slot_count: load(&self.miss_empty_slot_count),
+            miss_wrong_modules_count: load(&self.miss_wrong_modules_count),
+            miss_wrong_address_count: load(&self.miss_wrong_address_count),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{aarch64::UnwindRuleAarch64, x86_64::UnwindRuleX86_64};
+    use crate::{arm::UnwindRuleArm, riscv64::UnwindRuleRiscv64, x86::UnwindRuleX86};
 
     use super::*;
 
+    fn assert_pack_roundtrip<R: UnwindRule + PartialEq>(rules: &[R]) {
+        for &rule in rules {
+            assert_eq!(R::unpack(rule.pack()), Some(rule));
+        }
+    }
+
+    #[test]
+    fn test_pack_roundtrip() {
+        assert_pack_roundtrip(&[
+            UnwindRuleX86_64::EndOfStack,
+            UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                sp_offset_by_8: 3,
+                bp_storage_offset_from_sp_by_8: -2,
+            },
+            UnwindRuleX86_64::OffsetSpAndPopRegisters {
+                sp_offset_by_8: u16::MAX,
+                register_count: 8,
+                encoded_registers_to_pop: 0x1234,
+            },
+        ]);
+        assert_pack_roundtrip(&[
+            UnwindRuleX86::JustReturnIfFirstFrameOtherwiseFp,
+            UnwindRuleX86::OffsetSpAndRestoreBp {
+                sp_offset_by_4: 5,
+                bp_storage_offset_from_sp_by_4: i16::MIN,
+            },
+        ]);
+        assert_pack_roundtrip(&[
+            UnwindRuleAarch64::NoOp,
+            UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                sp_offset_by_16: 4,
+                fp_storage_offset_from_sp_by_8: -4,
+                lr_storage_offset_from_fp_storage_by_8: 1,
+            },
+            UnwindRuleAarch64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8: 2,
+                fp_storage_offset_from_fp_by_8: i16::MAX,
+                lr_storage_offset_from_fp_storage_by_8: i8::MIN,
+            },
+            UnwindRuleAarch64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8: 2,
+                fp_storage_offset_from_fp_by_8: i16::MAX,
+                lr_storage_offset_from_fp_storage_by_8: 1,
+            },
+        ]);
+        assert_pack_roundtrip(&[
+            UnwindRuleArm::UseFramePointer,
+            UnwindRuleArm::UseR7WithOffsets {
+                sp_offset_from_r7_by_4: -8,
+                lr_storage_offset_from_new_sp_by_4: -1,
+                r7_storage_offset_from_new_sp_by_4: i8::MIN,
+                r11_storage_offset_from_new_sp_by_4: i8::MAX,
+            },
+        ]);
+        assert_pack_roundtrip(&[
+            UnwindRuleRiscv64::NoOpIfFirstFrameOtherwiseFp,
+            UnwindRuleRiscv64::UseFramepointerWithOffsets {
+                sp_offset_from_fp_by_8: -2,
+                fp_storage_offset_from_fp_by_8: -2,
+                ra_storage_offset_from_fp_by_8: -1,
+            },
+        ]);
+        assert_eq!(UnwindRuleX86_64::unpack(0xff), None);
+    }
+
+    fn lookup_or_insert<R: UnwindRule>(cache: &mut RuleCache<R>, address: u64, rule: R) -> bool {
+        match cache.lookup(address, 0) {
+            CacheResult::Hit(_) => true,
+            CacheResult::Miss(handle) => {
+                cache.insert(handle, rule);
+                false
+            }
+            CacheResult::MissOutdatedGeneration => false,
+        }
+    }
+
+    #[test]
+    fn test_lru_order() {
+        let order = LruOrder::INITIAL.touch(2, 4);
+        assert_eq!(
+            (0..4).map(|p| order.way_at(p)).collect::<Vec<_>>(),
+            [2, 0, 1, 3]
+        );
+        let order = order.touch(3, 4).touch(0, 4);
+        assert_eq!(
+            (0..4).map(|p| order.way_at(p)).collect::<Vec<_>>(),
+            [0, 3, 2, 1]
+        );
+        assert_eq!(order.least_recently_used(4), 1);
+        assert_eq!(LruOrder::INITIAL.touch(0, 2).least_recently_used(2), 1);
+        assert_eq!(LruOrder::INITIAL.touch(1, 2).least_recently_used(2), 0);
+    }
+
+    #[test]
+    fn test_set_associative() {
+        let rule = UnwindRuleX86_64::JustReturn;
+        // Returns three addresses which map to the same set.
+        let same_set = |config: CacheConfig| {
+            let layout = CacheLayout::new(config);
+            let set = layout.set_for_address(0x10);
+            let mut addresses = (0x10..).step_by(4);
+            let mut next = || {
+                addresses
+                    .find(|&address| layout.set_for_address(address) == set)
+                    .unwrap()
+            };
+            [next(), next(), next()]
+        };
+
+        // a, b and c all map to the same slot.
+        let config = CacheConfig {
+            entry_count: 4,
+            associativity: CacheAssociativity::DirectMapped,
+            ..Default::default()
+        };
+        let [a, b, _] = same_set(config);
+        let mut cache = RuleCache::new(config);
+        assert!(!lookup_or_insert(&mut cache, a, rule));
+        assert!(!lookup_or_insert(&mut cache, b, rule));
+        assert!(!lookup_or_insert(&mut cache, a, rule));
+        assert_eq!(cache.stats().miss_wrong_address_count, 2);
+
+        // With two ways, a, b and c map to the same set of two slots.
+        let config = CacheConfig {
+            entry_count: 8,
+            associativity: CacheAssociativity::TwoWay,
+            ..Default::default()
+        };
+        let [a, b, c] = same_set(config);
+        let mut cache = RuleCache::new(config);
+        assert!(!lookup_or_insert(&mut cache, a, rule));
+        assert!(!lookup_or_insert(&mut cache, b, rule));
+        assert!(lookup_or_insert(&mut cache, a, rule));
+        assert!(lookup_or_insert(&mut cache, b, rule));
+        // a is the least recently used entry, so c replaces it.
+        assert!(!lookup_or_insert(&mut cache, c, rule));
+        assert!(lookup_or_insert(&mut cache, b, rule));
+        assert!(!lookup_or_insert(&mut cache, a, rule));
+        let stats = cache.stats();
+        assert_eq!(stats.hits(), 3);
+        assert_eq!(stats.miss_empty_slot_count, 2);
+        assert_eq!(stats.miss_wrong_address_count, 2);
+    }
+
+    #[test]
+    fn test_set_occupancy_for_aligned_addresses() {
+        // As many 16-byte aligned addresses as there are sets. A plain modulo would put
+        // them into only 1/16 of the sets.
+        for (entry_count, associativity) in [
+            (512, CacheAssociativity::DirectMapped),
+            (1024, CacheAssociativity::TwoWay),
+            (2048, CacheAssociativity::FourWay),
+        ] {
+            let layout = CacheLayout::new(CacheConfig {
+                entry_count,
+                associativity,
+                ..Default::default()
+            });
+            let mut used = vec![false; layout.set_count];
+            for address in (0..layout.set_count as u64).map(|i| 0x7f00_0000_0000 + i * 16) {
+                used[layout.set_for_address(address)] = true;
+            }
+            let used_count = used.iter().filter(|used| **used).count();
+            assert!(used_count > layout.set_count / 2, "{used_count} sets used");
+        }
+    }
+
+    #[test]
+    fn test_generation_eras() {
+        let mut cache = RuleCache::new(CacheConfig::default());
+        let rule = UnwindRuleX86_64::JustReturn;
+        let old_generation = 5;
+        let new_generation = (1 << 16) | 5;
+
+        let CacheResult::Miss(handle) = cache.lookup(0x1234, old_generation) else {
+            panic!("expected a miss in an empty cache");
+        };
+        cache.insert(handle, rule);
+        assert!(matches!(
+            cache.lookup(0x1234, old_generation),
+            CacheResult::Hit(_)
+        ));
+
+        // Same low bits, but from a later era: the cache is flushed.
+        let CacheResult::Miss(handle) = cache.lookup(0x1234, new_generation) else {
+            panic!("expected a miss after the era changed");
+        };
+        cache.insert(handle, rule);
+        assert!(matches!(
+            cache.lookup(0x1234, new_generation),
+            CacheResult::Hit(_)
+        ));
+
+        // Generations from earlier eras need to be renewed before they can be cached.
+        assert!(matches!(
+            cache.lookup(0x1234, old_generation),
+            CacheResult::MissOutdatedGeneration
+        ));
+        assert!(matches!(
+            cache.lookup(0x1234, new_generation),
+            CacheResult::Hit(_)
+        ));
+        assert_eq!(cache.stats().hits(), 3);
+        assert_eq!(cache.stats().miss_wrong_modules_count, 1);
+    }
+
+    #[test]
+    fn test_shared_rule_cache() {
+        let shared = Arc::new(SharedRuleCache::new(CacheConfig::default()));
+        let mut cache1 = RuleCache::new_shared(shared.clone());
+        let mut cache2 = RuleCache::new_shared(shared.clone());
+        let rule = UnwindRuleX86_64::OffsetSp { sp_offset_by_8: 2 };
+
+        let CacheResult::Miss(handle) = cache1.lookup(0x1234, 7) else {
+            panic!("expected a miss in an empty cache");
+        };
+        cache1.insert(handle, rule);
+        assert!(matches!(cache2.lookup(0x1234, 7), CacheResult::Hit(r) if r == rule));
+        assert!(matches!(cache2.lookup(0x1234, 8), CacheResult::Miss(_)));
+
+        assert_eq!(cache1.stats().total(), 1);
+        assert_eq!(cache2.stats().hits(), 1);
+        let stats = shared.stats();
+        assert_eq!(stats.hit_count, 1);
+        assert_eq!(stats.miss_empty_slot_count, 1);
+        assert_eq!(stats.miss_wrong_modules_count, 1);
+    }
+
     // Ensure that the size of Option<CacheEntry<UnwindRuleX86_64>> doesn't change by accident.
     #[test]
     fn test_cache_entry_size() {
@@ -140,7 +871,7 @@ mod tests {
         );
         assert_eq!(
             core::mem::size_of::<Option<CacheEntry<UnwindRuleAarch64>>>(),
-            24 // <-- larger than we'd like
+            16
         );
     }
 }
@@ -0,0 +1,412 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::any_unwinder::CpuArch;
+use crate::unwind_rule::UnwindRule;
+use crate::ModuleSectionInfo;
+
+/// Marks ranges which have no precompiled rule. Packed rules never have all bits set,
+/// because no arch uses the variant tag `0xff`.
+const NO_RULE: u64 = u64::MAX;
+
+/// A sorted table of unwind rules for the addresses of a module, compiled ahead of time
+/// from the module's unwind information. See [`Module::with_precompiled_rules`].
+///
+/// Each entry applies from its relative address up to the next entry's address. Ranges
+/// without a rule, for example because the CFI row could not be translated into a rule,
+/// are looked up in the module's unwind information as usual.
+///
+/// The rules are stored [packed](UnwindRule::pack), so that the table does not depend on
+/// the arch's rule type.
+///
+/// [`Module::with_precompiled_rules`]: crate::Module::with_precompiled_rules
+pub struct RuleTable {
+    /// Sorted, with the same length as `rules`.
+    range_starts: Box<[u32]>,
+    rules: Box<[u64]>,
+}
+
+impl RuleTable {
+    /// Returns the rule for the address, if the table has one.
+    pub fn lookup<R: UnwindRule>(&self, relative_address: u32) -> Option<R> {
+        R::unpack(self.packed_rule(relative_address)?)
+    }
+
+    fn packed_rule(&self, relative_address: u32) -> Option<u64> {
+        let i = match self.range_starts.binary_search(&relative_address) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        match self.rules[i] {
+            NO_RULE => None,
+            bits => Some(bits),
+        }
+    }
+
+    /// Returns a table with the rules of `top` for the addresses in `ranges`, and the
+    /// rules of `self` everywhere else. `ranges` must be sorted and must not overlap.
+    pub fn overlay(&self, top: &RuleTable, ranges: &[Range<u32>]) -> RuleTable {
+        let mut boundaries: Vec<u32> = self
+            .range_starts
+            .iter()
+            .chain(top.range_starts.iter())
+            .copied()
+            .chain(ranges.iter().flat_map(|range| [range.start, range.end]))
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        let mut builder = RuleTableBuilder::new();
+        for address in boundaries {
+            let i = ranges.partition_point(|range| range.end <= address);
+            let table = match ranges.get(i).is_some_and(|range| range.start <= address) {
+                true => top,
+                false => self,
+            };
+            builder.push(address, table.packed_rule(address));
+        }
+        builder.finish()
+    }
+}
+
+impl RuleTable {
+    /// Serializes the table in the format which is read by [`RuleTableData`].
+    pub fn serialize(&self, arch: CpuArch, base_svma: u64, address_size: u8) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_SIZE + ENTRY_SIZE * self.rules.len());
+        data.extend_from_slice(&MAGIC);
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        data.push(arch_code(arch));
+        data.push(address_size);
+        data.extend_from_slice(&base_svma.to_le_bytes());
+        data.extend_from_slice(&(self.rules.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        for (range_start, rule) in self.range_starts.iter().zip(self.rules.iter()) {
+            data.extend_from_slice(&range_start.to_le_bytes());
+            data.extend_from_slice(&rule.to_le_bytes());
+        }
+        data
+    }
+}
+
+/// Builds a [`RuleTable`] from ranges which are pushed in ascending order of their
+/// start address.
+#[derive(Default)]
+pub struct RuleTableBuilder {
+    entries: Vec<(u32, u64)>,
+}
+
+impl RuleTableBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a range which extends until the start of the next range. `rule` is `None`
+    /// for ranges which should be looked up in the module's unwind information.
+    ///
+    /// If the range starts before ranges which were pushed earlier, it replaces them.
+    /// This happens if the unwind information has overlapping entries; the one with the
+    /// higher start address wins, like in a lookup in the unwind information itself.
+    pub fn push(&mut self, range_start: u32, rule: Option<u64>) {
+        while self
+            .entries
+            .last()
+            .is_some_and(|&(start, _)| start >= range_start)
+        {
+            self.entries.pop();
+        }
+        let rule = rule.unwrap_or(NO_RULE);
+        // Merge with the previous range if it has the same rule.
+        if self.entries.last().map_or(NO_RULE, |&(_, rule)| rule) != rule {
+            self.entries.push((range_start, rule));
+        }
+    }
+
+    pub fn finish(self) -> RuleTable {
+        let (range_starts, rules): (Vec<u32>, Vec<u64>) = self.entries.into_iter().unzip();
+        RuleTable {
+            range_starts: range_starts.into_boxed_slice(),
+            rules: rules.into_boxed_slice(),
+        }
+    }
+}
+
+/// The pseudo section under which [`SerializedRuleTable`] hands its data to the module.
+pub(crate) const RULE_TABLE_SECTION: &[u8] = b".framehop_rule_table";
+
+const MAGIC: [u8; 4] = *b"FHRT";
+const FORMAT_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 24;
+const ENTRY_SIZE: usize = 12;
+
+/// The serialized format of a [`RuleTable`]. All integers are little-endian.
+///
+/// The header is 24 bytes:
+///
+/// | Offset | Type    | Contents                                                    |
+/// |--------|---------|-------------------------------------------------------------|
+/// | 0      | [u8; 4] | The magic bytes `FHRT`                                      |
+/// | 4      | u16     | The format version, currently 1                             |
+/// | 6      | u8      | The CPU arch: 1 x86_64, 2 aarch64, 3 arm, 4 x86, 5 riscv64  |
+/// | 7      | u8      | The module's address size in bytes                          |
+/// | 8      | u64     | The module's base SVMA                                      |
+/// | 16     | u32     | The number of entries                                       |
+/// | 20     | u32     | Reserved, zero                                              |
+///
+/// It is followed by the entries, 12 bytes each and sorted by address: the relative
+/// address at which the entry's range starts as a u32, and the [packed](UnwindRule::pack)
+/// rule as a u64, or `u64::MAX` for ranges without a rule.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleTableData<'a> {
+    arch: CpuArch,
+    address_size: u8,
+    base_svma: u64,
+    entries: &'a [u8],
+}
+
+impl<'a> RuleTableData<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, RuleTableError> {
+        let header = data.get(..HEADER_SIZE).ok_or(RuleTableError::TooShort)?;
+        if header[0..4] != MAGIC {
+            return Err(RuleTableError::BadMagic);
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != FORMAT_VERSION {
+            return Err(RuleTableError::UnsupportedVersion(version));
+        }
+        let arch = arch_from_code(header[6]).ok_or(RuleTableError::UnknownArch(header[6]))?;
+        let base_svma = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let entry_count = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let entries = usize::try_from(entry_count)
+            .ok()
+            .and_then(|entry_count| entry_count.checked_mul(ENTRY_SIZE))
+            .and_then(|len| data.get(HEADER_SIZE..)?.get(..len))
+            .ok_or(RuleTableError::TooShort)?;
+        Ok(Self {
+            arch,
+            address_size: header[7],
+            base_svma,
+            entries,
+        })
+    }
+
+    pub fn arch(&self) -> CpuArch {
+        self.arch
+    }
+
+    /// Returns the rule for the address, if the table has one.
+    pub fn lookup<R: UnwindRule>(&self, relative_address: u32) -> Option<R> {
+        let entry_count = self.entries.len() / ENTRY_SIZE;
+        let range_start = |i: usize| {
+            let entry = &self.entries[i * ENTRY_SIZE..];
+            u32::from_le_bytes(entry[..4].try_into().unwrap())
+        };
+        // The number of entries which start at or before the address.
+        let (mut low, mut high) = (0, entry_count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if range_start(mid) <= relative_address {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let entry = &self.entries[low.checked_sub(1)? * ENTRY_SIZE..];
+        match u64::from_le_bytes(entry[4..12].try_into().unwrap()) {
+            NO_RULE => None,
+            bits => R::unpack(bits),
+        }
+    }
+}
+
+fn arch_code(arch: CpuArch) -> u8 {
+    match arch {
+        CpuArch::X86_64 => 1,
+        CpuArch::Aarch64 => 2,
+        CpuArch::Arm => 3,
+        CpuArch::X86 => 4,
+        CpuArch::Riscv64 => 5,
+    }
+}
+
+fn arch_from_code(code: u8) -> Option<CpuArch> {
+    Some(match code {
+        1 => CpuArch::X86_64,
+        2 => CpuArch::Aarch64,
+        3 => CpuArch::Arm,
+        4 => CpuArch::X86,
+        5 => CpuArch::Riscv64,
+        _ => return None,
+    })
+}
+
+/// The error type for [`SerializedRuleTable::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleTableError {
+    /// The data ends before the end of the header or of the entries.
+    TooShort,
+    /// The data does not start with the magic bytes.
+    BadMagic,
+    /// The table was written in a format version which this version of framehop does
+    /// not support.
+    UnsupportedVersion(u16),
+    /// The table is for an unknown CPU architecture.
+    UnknownArch(u8),
+}
+
+impl core::fmt::Display for RuleTableError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "The rule table data is truncated"),
+            Self::BadMagic => write!(f, "The data is not a serialized rule table"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported rule table format version {version}")
+            }
+            Self::UnknownArch(code) => write!(f, "Unknown CPU architecture {code}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RuleTableError {}
+
+/// Why a module's unwind information could not be compiled into a table of unwind
+/// rules by [`Module::serialize_rule_table`](crate::Module::serialize_rule_table) or
+/// [`Module::precompile_rules`](crate::Module::precompile_rules).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileRuleTableError {
+    /// The module's kind of unwind information can't be compiled into unwind rules.
+    /// This is the case for `.ARM.exidx`, ORC tables and Breakpad symbol files, and for
+    /// modules without unwind information.
+    UnsupportedUnwindInfo,
+    /// The module's unwind information could not be parsed.
+    InvalidUnwindInfo,
+    /// The module was created from a serialized rule table for a different CPU
+    /// architecture.
+    ArchMismatch(CpuArch),
+    /// The module was created from a serialized rule table which is invalid.
+    InvalidRuleTable(RuleTableError),
+}
+
+impl core::fmt::Display for CompileRuleTableError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedUnwindInfo => write!(
+                f,
+                "The module's kind of unwind information can't be compiled into unwind rules"
+            ),
+            Self::InvalidUnwindInfo => {
+                write!(f, "The module's unwind information could not be parsed")
+            }
+            Self::ArchMismatch(arch) => {
+                write!(
+                    f,
+                    "The module's rule table is for a different CPU architecture, {arch:?}"
+                )
+            }
+            Self::InvalidRuleTable(err) => write!(f, "The module's rule table is invalid: {err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompileRuleTableError {}
+
+/// Unwind information for a module which was serialized with
+/// [`Module::serialize_rule_table`](crate::Module::serialize_rule_table), for unwinding
+/// without the module's binary. Pass it to [`Module::new`](crate::Module::new) in place
+/// of the module's sections.
+///
+/// The rule table is only used by unwinders for the CPU architecture that it was
+/// serialized for. The data is not copied: lookups do a binary search right inside it.
+pub struct SerializedRuleTable<D> {
+    data: Option<D>,
+    address_size: u8,
+    base_svma: u64,
+}
+
+impl<D: core::ops::Deref<Target = [u8]>> SerializedRuleTable<D> {
+    /// Checks the header of the serialized table.
+    pub fn new(data: D) -> Result<Self, RuleTableError> {
+        let table = RuleTableData::parse(&data)?;
+        let (address_size, base_svma) = (table.address_size, table.base_svma);
+        Ok(Self {
+            data: Some(data),
+            address_size,
+            base_svma,
+        })
+    }
+}
+
+impl<D> ModuleSectionInfo<D> for SerializedRuleTable<D> {
+    fn base_svma(&self) -> u64 {
+        self.base_svma
+    }
+
+    fn address_size(&self) -> u8 {
+        self.address_size
+    }
+
+    fn section_svma_range(&mut self, _name: &[u8]) -> Option<Range<u64>> {
+        None
+    }
+
+    fn section_data(&mut self, name: &[u8]) -> Option<D> {
+        if name == RULE_TABLE_SECTION {
+            self.data.take()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x86_64::UnwindRuleX86_64;
+
+    #[test]
+    fn test_rule_table_builder() {
+        let a = UnwindRuleX86_64::JustReturn;
+        let b = UnwindRuleX86_64::OffsetSp { sp_offset_by_8: 2 };
+        let mut builder = RuleTableBuilder::new();
+        builder.push(0x100, Some(a.pack()));
+        builder.push(0x110, Some(a.pack()));
+        builder.push(0x120, Some(b.pack()));
+        builder.push(0x200, None);
+        // An entry which overlaps the previous one replaces the rest of it.
+        builder.push(0x180, Some(a.pack()));
+        builder.push(0x190, None);
+        let table = builder.finish();
+
+        assert_eq!(table.range_starts[..], [0x100, 0x120, 0x180, 0x190]);
+        assert_eq!(table.lookup::<UnwindRuleX86_64>(0xff), None);
+        assert_eq!(table.lookup(0x115), Some(a));
+        assert_eq!(table.lookup(0x17f), Some(b));
+        assert_eq!(table.lookup(0x185), Some(a));
+        assert_eq!(table.lookup::<UnwindRuleX86_64>(0x1a0), None);
+    }
+
+    #[test]
+    fn test_rule_table_overlay() {
+        let a = UnwindRuleX86_64::JustReturn;
+        let b = UnwindRuleX86_64::OffsetSp { sp_offset_by_8: 2 };
+        let mut builder = RuleTableBuilder::new();
+        builder.push(0x100, Some(a.pack()));
+        builder.push(0x200, None);
+        let bottom = builder.finish();
+        let mut builder = RuleTableBuilder::new();
+        builder.push(0x120, Some(b.pack()));
+        builder.push(0x130, None);
+        let top = builder.finish();
+        let table = bottom.overlay(&top, &[0x110..0x140, 0x1f0..0x210]);
+
+        assert_eq!(table.lookup(0x10f), Some(a));
+        assert_eq!(table.lookup::<UnwindRuleX86_64>(0x110), None);
+        assert_eq!(table.lookup(0x125), Some(b));
+        assert_eq!(table.lookup::<UnwindRuleX86_64>(0x135), None);
+        assert_eq!(table.lookup(0x140), Some(a));
+        assert_eq!(table.lookup::<UnwindRuleX86_64>(0x1f0), None);
+        assert_eq!(table.lookup::<UnwindRuleX86_64>(0x220), None);
+    }
+}
@@ -0,0 +1,665 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use crate::add_signed::checked_add_signed;
+use crate::exidx::DataAtSvmaRange;
+use crate::rule_table::{RuleTable, RuleTableBuilder};
+use crate::unwind_rule::UnwindRule;
+use crate::{arch::Arch, unwind_result::UnwindResult};
+
+const SFRAME_MAGIC: u16 = 0xdee2;
+const SFRAME_HEADER_LEN: usize = 28;
+
+const SFRAME_F_FDE_SORTED: u8 = 0x1;
+const SFRAME_F_FDE_FUNC_START_PCREL: u8 = 0x4;
+
+const SFRAME_FDE_TYPE_PCMASK: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SFrameUnwinderError {
+    InvalidHeader,
+    UnsupportedVersion(u8),
+    UnsupportedAbi(u8),
+    AddressNotCovered(u32),
+    FdeOutOfBounds(u32),
+    FreOutOfBounds(u32),
+    UnknownFreType(u8),
+    UnknownFreOffsetSize(u8),
+    MissingCfaOffset,
+    CouldNotReadStack(u64),
+    IntegerOverflow,
+    DidNotAdvance,
+    StackPointerMovedBackwards,
+    UnsupportedArch,
+}
+
+impl core::fmt::Display for SFrameUnwinderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "The .sframe header is invalid"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported .sframe version {version}")
+            }
+            Self::UnsupportedAbi(abi) => write!(f, "Unsupported .sframe ABI/arch {abi}"),
+            Self::AddressNotCovered(addr) => {
+                write!(f, "No .sframe FDE covers the address 0x{addr:x}")
+            }
+            Self::FdeOutOfBounds(index) => {
+                write!(f, ".sframe FDE {index} extends past the section end")
+            }
+            Self::FreOutOfBounds(offset) => write!(
+                f,
+                ".sframe FRE at offset 0x{offset:x} extends past the section end"
+            ),
+            Self::UnknownFreType(fre_type) => write!(f, "Unknown .sframe FRE type {fre_type}"),
+            Self::UnknownFreOffsetSize(size) => {
+                write!(f, "Unknown .sframe FRE offset size {size}")
+            }
+            Self::MissingCfaOffset => write!(f, "The .sframe FRE has no CFA offset"),
+            Self::CouldNotReadStack(addr) => {
+                write!(f, "Could not read stack memory at 0x{addr:x}")
+            }
+            Self::IntegerOverflow => write!(f, "CFA computation overflowed"),
+            Self::DidNotAdvance => write!(f, "Did not advance"),
+            Self::StackPointerMovedBackwards => write!(f, "Stack pointer moved backwards"),
+            Self::UnsupportedArch => {
+                write!(
+                    f,
+                    ".sframe unwinding is only supported on x86_64 and aarch64"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SFrameUnwinderError {}
+
+/// The ABI / architecture identifier from the `.sframe` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SFrameAbi {
+    Aarch64Be = 1,
+    Aarch64Le = 2,
+    Amd64Le = 3,
+}
+
+impl SFrameAbi {
+    fn parse(abi: u8) -> Option<Self> {
+        match abi {
+            1 => Some(Self::Aarch64Be),
+            2 => Some(Self::Aarch64Le),
+            3 => Some(Self::Amd64Le),
+            _ => None,
+        }
+    }
+}
+
+/// The register from which the CFA is computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SFrameCfaBase {
+    Sp,
+    Fp,
+}
+
+/// The unwind information of a single SFrame row entry (FRE), with the ABI's fixed
+/// offsets already applied. All offsets are relative to the CFA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SFrameRow {
+    /// CFA = cfa_base + cfa_offset
+    pub cfa_base: SFrameCfaBase,
+    pub cfa_offset: i32,
+    /// The return address is stored at CFA + ra_offset. `None` means that the return
+    /// address has not been saved, i.e. it is still in the link register.
+    pub ra_offset: Option<i32>,
+    /// The caller's frame pointer is stored at CFA + fp_offset. `None` means that the
+    /// frame pointer has not been modified.
+    pub fp_offset: Option<i32>,
+    /// Whether the saved return address is signed with pointer authentication.
+    pub ra_mangled: bool,
+}
+
+pub trait SFrameUnwinding: Arch {
+    /// Translates the row into an unwind rule, if it can be expressed as one.
+    fn rule_for_row(
+        _row: &SFrameRow,
+        _abi: SFrameAbi,
+    ) -> Result<Option<Self::UnwindRule>, SFrameUnwinderError> {
+        Err(SFrameUnwinderError::UnsupportedArch)
+    }
+
+    fn unwind_frame<F>(
+        _row: SFrameRow,
+        _abi: SFrameAbi,
+        _regs: &mut Self::UnwindRegs,
+        _is_first_frame: bool,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, SFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(SFrameUnwinderError::UnsupportedArch)
+    }
+}
+
+/// The parsed fixed-size `.sframe` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SFrameHeader {
+    big_endian: bool,
+    version: u8,
+    flags: u8,
+    abi: SFrameAbi,
+    cfa_fixed_fp_offset: i8,
+    cfa_fixed_ra_offset: i8,
+    num_fdes: u32,
+    fde_start: usize,
+    fre_start: usize,
+}
+
+impl SFrameHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, SFrameUnwinderError> {
+        let big_endian = match data.get(..2) {
+            Some(magic) if u16::from_le_bytes([magic[0], magic[1]]) == SFRAME_MAGIC => false,
+            Some(magic) if u16::from_be_bytes([magic[0], magic[1]]) == SFRAME_MAGIC => true,
+            _ => return Err(SFrameUnwinderError::InvalidHeader),
+        };
+        let reader = Reader { data, big_endian };
+        let header = data
+            .get(..SFRAME_HEADER_LEN)
+            .ok_or(SFrameUnwinderError::InvalidHeader)?;
+        let version = header[2];
+        if version != 1 && version != 2 {
+            return Err(SFrameUnwinderError::UnsupportedVersion(version));
+        }
+        let abi =
+            SFrameAbi::parse(header[4]).ok_or(SFrameUnwinderError::UnsupportedAbi(header[4]))?;
+        let header_len = SFRAME_HEADER_LEN + usize::from(header[7]);
+        let invalid = SFrameUnwinderError::InvalidHeader;
+        let num_fdes = reader.u32(8).ok_or(invalid)?;
+        let fde_start = header_len + reader.u32(20).ok_or(invalid)? as usize;
+        let fre_start = header_len + reader.u32(24).ok_or(invalid)? as usize;
+        if fde_start > data.len() || fre_start > data.len() {
+            return Err(invalid);
+        }
+        Ok(Self {
+            big_endian,
+            version,
+            flags: header[3],
+            abi,
+            cfa_fixed_fp_offset: header[5] as i8,
+            cfa_fixed_ra_offset: header[6] as i8,
+            num_fdes,
+            fde_start,
+            fre_start,
+        })
+    }
+
+    fn fde_len(&self) -> usize {
+        match self.version {
+            1 => 17,
+            _ => 20,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        let bytes = self.data.get(offset..)?.get(..N)?;
+        let mut array: [u8; N] = bytes.try_into().ok()?;
+        if !self.big_endian {
+            array.reverse();
+        }
+        Some(array)
+    }
+
+    fn u8(&self, offset: usize) -> Option<u8> {
+        self.data.get(offset).copied()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        self.bytes(offset).map(u16::from_be_bytes)
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        self.bytes(offset).map(u32::from_be_bytes)
+    }
+
+    /// Reads an unsigned integer of the given size (1, 2 or 4 bytes).
+    fn uint(&self, offset: usize, size: usize) -> Option<u32> {
+        match size {
+            1 => self.u8(offset).map(u32::from),
+            2 => self.u16(offset).map(u32::from),
+            _ => self.u32(offset),
+        }
+    }
+
+    /// Reads a signed integer of the given size (1, 2 or 4 bytes).
+    fn int(&self, offset: usize, size: usize) -> Option<i32> {
+        match size {
+            1 => self.u8(offset).map(|v| i32::from(v as i8)),
+            2 => self.u16(offset).map(|v| i32::from(v as i16)),
+            _ => self.u32(offset).map(|v| v as i32),
+        }
+    }
+}
+
+/// A function descriptor entry.
+#[derive(Clone, Copy)]
+struct Fde {
+    start_svma: u64,
+    size: u32,
+    fre_offset: u32,
+    fre_count: u32,
+    fre_addr_size: usize,
+    pc_mask_rep_size: Option<u8>,
+}
+
+/// A frame row entry, whose offsets are only read when the row is needed.
+struct Fre {
+    /// The offset of the row's first address from the function start.
+    start: u32,
+    info: u8,
+    offsets_start: usize,
+    offset_size: usize,
+    offset_count: usize,
+}
+
+impl Fre {
+    /// The offset of the next FRE in the section.
+    fn end(&self) -> usize {
+        self.offsets_start + self.offset_size * self.offset_count
+    }
+}
+
+/// Does the lookup in the `.sframe` section. The FDE index is binary searched in place,
+/// nothing is copied or indexed when the module is added.
+pub struct SFrameUnwinder<'a, A: SFrameUnwinding> {
+    reader: Reader<'a>,
+    header: SFrameHeader,
+    sframe_svma: u64,
+    base_svma: u64,
+    _arch: PhantomData<A>,
+}
+
+impl<'a, A: SFrameUnwinding> SFrameUnwinder<'a, A> {
+    pub fn new<D: core::ops::Deref<Target = [u8]>>(
+        sframe: &'a DataAtSvmaRange<D>,
+        base_svma: u64,
+    ) -> Result<Self, SFrameUnwinderError> {
+        let data = &sframe.data[..];
+        let header = SFrameHeader::parse(data)?;
+        Ok(Self {
+            reader: Reader {
+                data,
+                big_endian: header.big_endian,
+            },
+            header,
+            sframe_svma: sframe.svma_range.start,
+            base_svma,
+            _arch: PhantomData,
+        })
+    }
+
+    fn fde(&self, index: u32) -> Result<Fde, SFrameUnwinderError> {
+        let out_of_bounds = SFrameUnwinderError::FdeOutOfBounds(index);
+        let offset = self.header.fde_start + index as usize * self.header.fde_len();
+        let r = &self.reader;
+        let start = r.u32(offset).ok_or(out_of_bounds)? as i32;
+        let size = r.u32(offset + 4).ok_or(out_of_bounds)?;
+        let fre_offset = r.u32(offset + 8).ok_or(out_of_bounds)?;
+        let fre_count = r.u32(offset + 12).ok_or(out_of_bounds)?;
+        let info = r.u8(offset + 16).ok_or(out_of_bounds)?;
+        // The function start address is relative to the start of the section, or, if
+        // the corresponding flag is set, to the address of the field itself.
+        let start_base = if self.header.flags & SFRAME_F_FDE_FUNC_START_PCREL != 0 {
+            self.sframe_svma + offset as u64
+        } else {
+            self.sframe_svma
+        };
+        let start_svma = checked_add_signed(start_base, i64::from(start))
+            .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+        let fre_addr_size = match info & 0xf {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            fre_type => return Err(SFrameUnwinderError::UnknownFreType(fre_type)),
+        };
+        let pc_mask_rep_size = if (info >> 4) & 1 == SFRAME_FDE_TYPE_PCMASK {
+            // Version 1 has no repetition size field; the PLT entries it was designed
+            // for are 16 bytes in size.
+            match self.header.version {
+                1 => Some(16),
+                _ => Some(r.u8(offset + 17).ok_or(out_of_bounds)?),
+            }
+        } else {
+            None
+        };
+        Ok(Fde {
+            start_svma,
+            size,
+            fre_offset,
+            fre_count,
+            fre_addr_size,
+            pc_mask_rep_size,
+        })
+    }
+
+    /// Find the FDE of the function covering the address.
+    fn fde_for_svma(&self, lookup_svma: u64) -> Result<Option<Fde>, SFrameUnwinderError> {
+        let num_fdes = self.header.num_fdes;
+        let index = if self.header.flags & SFRAME_F_FDE_SORTED != 0 {
+            let mut low = 0;
+            let mut high = num_fdes;
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if self.fde(mid)?.start_svma <= lookup_svma {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            match low.checked_sub(1) {
+                Some(index) => index,
+                None => return Ok(None),
+            }
+        } else {
+            let mut found = None;
+            for index in 0..num_fdes {
+                let fde = self.fde(index)?;
+                if fde.start_svma <= lookup_svma
+                    && lookup_svma - fde.start_svma < u64::from(fde.size)
+                {
+                    found = Some(index);
+                    break;
+                }
+            }
+            match found {
+                Some(index) => index,
+                None => return Ok(None),
+            }
+        };
+        let fde = self.fde(index)?;
+        if lookup_svma - fde.start_svma >= u64::from(fde.size) {
+            return Ok(None);
+        }
+        Ok(Some(fde))
+    }
+
+    /// Returns the row covering the address, or `AddressNotCovered` if no FDE covers it.
+    pub fn row_for_address(
+        &self,
+        rel_lookup_address: u32,
+    ) -> Result<SFrameRow, SFrameUnwinderError> {
+        let not_covered = SFrameUnwinderError::AddressNotCovered(rel_lookup_address);
+        let lookup_svma = self.base_svma + u64::from(rel_lookup_address);
+        let fde = self.fde_for_svma(lookup_svma)?.ok_or(not_covered)?;
+        let mut pc_offset = (lookup_svma - fde.start_svma) as u32;
+        if let Some(rep_size) = fde.pc_mask_rep_size {
+            if rep_size != 0 {
+                pc_offset %= u32::from(rep_size);
+            }
+        }
+
+        // The FREs are sorted by start address. Find the last one which starts at or
+        // before pc_offset.
+        let mut offset = self.header.fre_start + fde.fre_offset as usize;
+        let mut found = None;
+        for _ in 0..fde.fre_count {
+            let fre = self.fre(&fde, offset)?;
+            if fre.start > pc_offset {
+                break;
+            }
+            offset = fre.end();
+            found = Some(fre);
+        }
+        self.row(&found.ok_or(not_covered)?)
+    }
+
+    fn fre(&self, fde: &Fde, offset: usize) -> Result<Fre, SFrameUnwinderError> {
+        let r = &self.reader;
+        let out_of_bounds = SFrameUnwinderError::FreOutOfBounds(offset as u32);
+        let start = r.uint(offset, fde.fre_addr_size).ok_or(out_of_bounds)?;
+        let info = r.u8(offset + fde.fre_addr_size).ok_or(out_of_bounds)?;
+        let offset_size = match (info >> 5) & 0x3 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            size => return Err(SFrameUnwinderError::UnknownFreOffsetSize(size)),
+        };
+        Ok(Fre {
+            start,
+            info,
+            offsets_start: offset + fde.fre_addr_size + 1,
+            offset_size,
+            offset_count: usize::from((info >> 1) & 0xf),
+        })
+    }
+
+    fn row(&self, fre: &Fre) -> Result<SFrameRow, SFrameUnwinderError> {
+        let r = &self.reader;
+        let Fre {
+            info,
+            offsets_start,
+            offset_size,
+            offset_count,
+            ..
+        } = *fre;
+        let out_of_bounds = SFrameUnwinderError::FreOutOfBounds(offsets_start as u32);
+        let mut offsets = (0..offset_count).map(|i| {
+            r.int(offsets_start + i * offset_size, offset_size)
+                .ok_or(out_of_bounds)
+        });
+
+        let cfa_offset = offsets
+            .next()
+            .ok_or(SFrameUnwinderError::MissingCfaOffset)??;
+        let ra_offset = match self.header.cfa_fixed_ra_offset {
+            0 => offsets.next().transpose()?,
+            fixed => Some(i32::from(fixed)),
+        };
+        let fp_offset = match self.header.cfa_fixed_fp_offset {
+            0 => offsets.next().transpose()?,
+            fixed => Some(i32::from(fixed)),
+        };
+        Ok(SFrameRow {
+            cfa_base: if info & 1 != 0 {
+                SFrameCfaBase::Sp
+            } else {
+                SFrameCfaBase::Fp
+            },
+            cfa_offset,
+            // An RA offset of zero is padding, used when only the frame pointer was saved.
+            ra_offset: ra_offset.filter(|offset| *offset != 0),
+            fp_offset,
+            ra_mangled: info & 0x80 != 0,
+        })
+    }
+
+    pub fn unwind_frame<F>(
+        &self,
+        rel_lookup_address: u32,
+        regs: &mut A::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<A::UnwindRule>, SFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let row = self.row_for_address(rel_lookup_address)?;
+        A::unwind_frame(row, self.header.abi, regs, is_first_frame, read_stack)
+    }
+
+    /// Translates the rows of all functions into a table of unwind rules. Also returns
+    /// the sorted address ranges of the functions, which are the addresses that the
+    /// table covers. Rows which can't be expressed as a rule get no rule, and neither
+    /// do functions with repetitive blocks, such as PLT entries.
+    pub fn compile_rule_table(&self) -> Result<(RuleTable, Vec<Range<u32>>), SFrameUnwinderError> {
+        let mut fdes = (0..self.header.num_fdes)
+            .map(|index| self.fde(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        fdes.sort_by_key(|fde| fde.start_svma);
+
+        let mut builder = RuleTableBuilder::new();
+        let mut ranges: Vec<Range<u32>> = Vec::new();
+        for fde in fdes {
+            let start = fde
+                .start_svma
+                .checked_sub(self.base_svma)
+                .and_then(|start| u32::try_from(start).ok())
+                .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+            let end = start
+                .checked_add(fde.size)
+                .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+            builder.push(start, None);
+            if fde.pc_mask_rep_size.is_none() {
+                let mut offset = self.header.fre_start + fde.fre_offset as usize;
+                for _ in 0..fde.fre_count {
+                    let Ok(fre) = self.fre(&fde, offset) else {
+                        // Leave the entire function to the lookup in the section.
+                        builder.push(start, None);
+                        break;
+                    };
+                    offset = fre.end();
+                    let Some(address) = start.checked_add(fre.start).filter(|a| *a < end) else {
+                        break;
+                    };
+                    let rule = match self.row(&fre) {
+                        Ok(row) => A::rule_for_row(&row, self.header.abi)?,
+                        Err(_) => None,
+                    };
+                    builder.push(address, rule.map(UnwindRule::pack));
+                }
+            }
+            builder.push(end, None);
+            // Like in the builder, a function which overlaps the previous one replaces the
+            // rest of it.
+            if let Some(previous) = ranges.last_mut() {
+                previous.end = previous.end.min(start);
+            }
+            ranges.push(start..end);
+        }
+        Ok((builder.finish(), ranges))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aarch64::{ArchAarch64, UnwindRegsAarch64, UnwindRuleAarch64};
+
+    /// Builds a little-endian version 2 aarch64 section with a single function at 0x1000
+    /// with the following rows:
+    ///  - 0x1000: CFA = sp + 0
+    ///  - 0x1004: CFA = sp + 32, fp at CFA - 32, lr at CFA - 24
+    ///  - 0x1008: CFA = fp + 32, fp at CFA - 32, lr at CFA - 24
+    fn aarch64_section(sframe_svma: u64) -> Vec<u8> {
+        let mut data = vec![0xe2, 0xde, 2, SFRAME_F_FDE_SORTED, 2, 0, 0, 0];
+        data.extend_from_slice(&1u32.to_le_bytes()); // num_fdes
+        data.extend_from_slice(&3u32.to_le_bytes()); // num_fres
+        data.extend_from_slice(&0u32.to_le_bytes()); // fre_len, unused
+        data.extend_from_slice(&0u32.to_le_bytes()); // fdeoff
+        data.extend_from_slice(&20u32.to_le_bytes()); // freoff
+        let start = (0x1000 - sframe_svma as i64) as i32;
+        data.extend_from_slice(&start.to_le_bytes());
+        data.extend_from_slice(&0x20u32.to_le_bytes()); // size
+        data.extend_from_slice(&0u32.to_le_bytes()); // start_fre_off
+        data.extend_from_slice(&3u32.to_le_bytes()); // num_fres
+        data.extend_from_slice(&[0, 0, 0, 0]); // info, rep_size, padding
+        data.extend_from_slice(&[0x00, 0b0000_0011, 0]);
+        data.extend_from_slice(&[0x04, 0b0000_0111, 32, (-24i8) as u8, (-32i8) as u8]);
+        data.extend_from_slice(&[0x08, 0b0000_0110, 32, (-24i8) as u8, (-32i8) as u8]);
+        data
+    }
+
+    #[test]
+    fn test_aarch64_rows() {
+        let sframe_svma = 0x3000;
+        let sframe = DataAtSvmaRange {
+            data: &aarch64_section(sframe_svma)[..],
+            svma_range: sframe_svma..sframe_svma + 0x100,
+        };
+        let unwinder = SFrameUnwinder::<ArchAarch64>::new(&sframe, 0).unwrap();
+        assert_eq!(
+            unwinder.row_for_address(0x1006),
+            Ok(SFrameRow {
+                cfa_base: SFrameCfaBase::Sp,
+                cfa_offset: 32,
+                ra_offset: Some(-24),
+                fp_offset: Some(-32),
+                ra_mangled: false,
+            })
+        );
+        assert_eq!(
+            unwinder.row_for_address(0x1020),
+            Err(SFrameUnwinderError::AddressNotCovered(0x1020))
+        );
+        assert_eq!(
+            unwinder.row_for_address(0xffc),
+            Err(SFrameUnwinderError::AddressNotCovered(0xffc))
+        );
+
+        let mut read_stack = |_| Err(());
+        let mut regs = UnwindRegsAarch64::new(0x1234, 0x100, 0x200);
+        let mut unwind = |address| unwinder.unwind_frame(address, &mut regs, true, &mut read_stack);
+        assert_eq!(
+            unwind(0x1000),
+            Ok(UnwindResult::ExecRule(UnwindRuleAarch64::OffsetSp {
+                sp_offset_by_16: 0
+            }))
+        );
+        assert_eq!(
+            unwind(0x1004),
+            Ok(UnwindResult::ExecRule(
+                UnwindRuleAarch64::OffsetSpAndRestoreFpAndLr {
+                    sp_offset_by_16: 2,
+                    fp_storage_offset_from_sp_by_8: 0,
+                    lr_storage_offset_from_fp_storage_by_8: 1,
+                }
+            ))
+        );
+        assert_eq!(
+            unwind(0x101c),
+            Ok(UnwindResult::ExecRule(
+                UnwindRuleAarch64::UseFramepointerWithOffsets {
+                    sp_offset_from_fp_by_8: 4,
+                    fp_storage_offset_from_fp_by_8: 0,
+                    lr_storage_offset_from_fp_storage_by_8: 1,
+                }
+            ))
+        );
+    }
+    #[test]
+    fn test_aarch64_rule_table() {
+        let sframe_svma = 0x3000;
+        let sframe = DataAtSvmaRange {
+            data: &aarch64_section(sframe_svma)[..],
+            svma_range: sframe_svma..sframe_svma + 0x100,
+        };
+        let unwinder = SFrameUnwinder::<ArchAarch64>::new(&sframe, 0).unwrap();
+        let (table, ranges) = unwinder.compile_rule_table().unwrap();
+        assert_eq!(ranges, vec![0x1000..0x1020]);
+        for address in 0xff0..0x1030 {
+            let mut regs = UnwindRegsAarch64::new(0x1234, 0x100, 0x200);
+            let rule = match unwinder.unwind_frame(address, &mut regs, false, &mut |_| Err(())) {
+                Ok(UnwindResult::ExecRule(rule)) => Some(rule),
+                _ => None,
+            };
+            assert_eq!(table.lookup::<UnwindRuleAarch64>(address), rule);
+        }
+
+        // The rows can't be translated into rules for another arch.
+        let unwinder = SFrameUnwinder::<crate::x86_64::ArchX86_64>::new(&sframe, 0).unwrap();
+        assert_eq!(
+            unwinder.compile_rule_table().err(),
+            Some(SFrameUnwinderError::UnsupportedAbi(
+                SFrameAbi::Aarch64Le as u8
+            ))
+        );
+    }
+}
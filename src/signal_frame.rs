@@ -0,0 +1,88 @@
+use crate::{arch::Arch, unwind_result::UnwindResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalFrameUnwinderError {
+    CouldNotReadStack(u64),
+    IntegerOverflow,
+    DidNotAdvance,
+    UnsupportedArch,
+}
+
+impl core::fmt::Display for SignalFrameUnwinderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CouldNotReadStack(addr) => {
+                write!(f, "Could not read stack memory at 0x{addr:x}")
+            }
+            Self::IntegerOverflow => write!(f, "Signal frame address computation overflowed"),
+            Self::DidNotAdvance => write!(f, "Did not advance"),
+            Self::UnsupportedArch => write!(
+                f,
+                "Signal frame unwinding is only supported on x86_64 and aarch64"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SignalFrameUnwinderError {}
+
+/// Unwinding through the frames of signal return trampolines, i.e. the code which a
+/// signal handler returns to, and which calls `rt_sigreturn`. The kernel saves the
+/// interrupted registers on the stack before it calls the signal handler, and the stack
+/// pointer points at them while the trampoline runs.
+///
+/// The recovered pc is returned as an instruction pointer, not as a return address: the
+/// interrupted frame is looked up at pc itself and unwound like the first frame of a
+/// stack, because the signal can arrive anywhere, including in a function prologue or
+/// epilogue.
+pub trait SignalFrameUnwinding: Arch {
+    /// The instructions of the Linux `rt_sigreturn` trampoline (`__restore_rt` in libc,
+    /// `__kernel_rt_sigreturn` in the vDSO). This is used to recognize the trampoline
+    /// when it has no CFI, which is the case for musl.
+    const LINUX_SIGRETURN_TRAMPOLINE: &'static [u8] = &[];
+
+    /// The offset of the system call instruction in `LINUX_SIGRETURN_TRAMPOLINE`.
+    const LINUX_SIGRETURN_SYSCALL_OFFSET: usize = 0;
+
+    /// Restores the interrupted registers from the `ucontext_t` which the Linux kernel
+    /// pushed on the stack. `regs` are the registers at the trampoline.
+    fn unwind_linux_signal_frame<F>(
+        _regs: &mut Self::UnwindRegs,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, SignalFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(SignalFrameUnwinderError::UnsupportedArch)
+    }
+}
+
+/// Returns whether the instruction at `offset` in `text` is one of the two
+/// instructions of the Linux `rt_sigreturn` trampoline.
+pub fn is_linux_sigreturn_trampoline<A: SignalFrameUnwinding>(text: &[u8], offset: usize) -> bool {
+    let trampoline = A::LINUX_SIGRETURN_TRAMPOLINE;
+    if trampoline.is_empty() {
+        return false;
+    }
+    let matches_at = |start: usize| text.get(start..).is_some_and(|t| t.starts_with(trampoline));
+    matches_at(offset)
+        || offset
+            .checked_sub(A::LINUX_SIGRETURN_SYSCALL_OFFSET)
+            .is_some_and(matches_at)
+}
+
+/// Reads the stack at `base + offset`.
+pub fn read_at_offset<F>(
+    base: u64,
+    offset: u64,
+    read_stack: &mut F,
+) -> Result<u64, SignalFrameUnwinderError>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let address = base
+        .checked_add(offset)
+        .ok_or(SignalFrameUnwinderError::IntegerOverflow)?;
+    read_stack(address).map_err(|_| SignalFrameUnwinderError::CouldNotReadStack(address))
+}
@@ -0,0 +1,277 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, Range};
+
+use crate::error::Error;
+
+/// Reads words from the stack of the unwound thread. This is what the unwinder's
+/// `read_stack` argument needs to implement.
+///
+/// It is implemented for closures of the type `FnMut(u64) -> Result<u64, ()>`, for
+/// [`StackRegions`], and for any other [`StackMemory`] wrapped in a [`StackMemoryReader`].
+pub trait ReadStack {
+    /// Reads the word at `address`. On 32-bit architectures, only the lower 32 bits of
+    /// the returned value are used.
+    ///
+    /// This has the same signature as a `read_stack` closure.
+    #[allow(clippy::result_unit_err)]
+    fn read_stack(&mut self, address: u64) -> Result<u64, ()>;
+
+    /// Reads the word at `address` for an architecture whose stack words are `word_size`
+    /// bytes long, i.e. 8 on 64-bit architectures and 4 on 32-bit architectures. This is
+    /// what the unwinder calls.
+    ///
+    /// The default implementation calls [`read_stack`](ReadStack::read_stack). Stack
+    /// memory which knows which bytes are available overrides it, so that only the
+    /// word's bytes need to be available.
+    #[allow(clippy::result_unit_err)]
+    fn read_stack_word(&mut self, address: u64, _word_size: u8) -> Result<u64, ()> {
+        self.read_stack(address)
+    }
+}
+
+impl<F> ReadStack for F
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    fn read_stack(&mut self, address: u64) -> Result<u64, ()> {
+        self(address)
+    }
+}
+
+/// Stack memory which can be accessed as byte slices, for example copies of the stack
+/// that were taken when the thread was sampled.
+///
+/// This is an alternative to a `read_stack` closure which reads one word at a time, for
+/// when the stack bytes are already available.
+pub trait StackMemory {
+    /// Returns the bytes at `range`, or `None` if not all of them are available.
+    fn bytes(&self, range: Range<u64>) -> Option<&[u8]>;
+}
+
+impl<S: StackMemory + ?Sized> StackMemory for &S {
+    fn bytes(&self, range: Range<u64>) -> Option<&[u8]> {
+        (**self).bytes(range)
+    }
+}
+
+/// The address range of the `word_size`-byte word at `address`.
+fn word_range(address: u64, word_size: u8) -> Result<Range<u64>, ()> {
+    Ok(address..address.checked_add(word_size.into()).ok_or(())?)
+}
+
+/// Converts the bytes of a little-endian 4-byte or 8-byte word.
+fn word_from_le_bytes(bytes: &[u8]) -> Result<u64, ()> {
+    match bytes.len() {
+        4 => Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| ())?).into()),
+        8 => Ok(u64::from_le_bytes(bytes.try_into().map_err(|_| ())?)),
+        _ => Err(()),
+    }
+}
+
+/// Makes a [`StackMemory`] usable as the unwinder's `read_stack` argument.
+///
+/// Words are read as little-endian values. [`read_stack`](ReadStack::read_stack) reads
+/// 8-byte words.
+pub struct StackMemoryReader<S>(pub S);
+
+impl<S: StackMemory> ReadStack for StackMemoryReader<S> {
+    fn read_stack(&mut self, address: u64) -> Result<u64, ()> {
+        self.read_stack_word(address, 8)
+    }
+
+    fn read_stack_word(&mut self, address: u64, word_size: u8) -> Result<u64, ()> {
+        word_from_le_bytes(self.0.bytes(word_range(address, word_size)?).ok_or(())?)
+    }
+}
+
+/// [`StackMemory`] made of one or more copied regions of the stack, for example the
+/// bytes of a `PERF_SAMPLE_STACK_USER` sample from Linux perf.
+///
+/// Reads which are not entirely inside one region fail. Words are read as little-endian
+/// values, and [`read_stack`](ReadStack::read_stack) reads 8-byte words.
+#[derive(Debug, Clone)]
+pub struct StackRegions<D> {
+    /// Sorted by start address.
+    regions: Vec<(u64, D)>,
+    /// The index of the region of the last word read. Consecutive reads are usually in
+    /// the same region, so it's checked before searching all regions.
+    last_region: usize,
+}
+
+impl<D> Default for StackRegions<D> {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            last_region: 0,
+        }
+    }
+}
+
+impl<D: Deref<Target = [u8]>> StackRegions<D> {
+    /// Creates an empty set of regions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a set with the single region `bytes`, which were copied from the stack at
+    /// `start_address`.
+    ///
+    /// For a `PERF_SAMPLE_STACK_USER` sample, `start_address` is the value of the stack
+    /// pointer register in the sample, and `bytes` are the first `dyn_size` bytes of the
+    /// stack data.
+    pub fn with_region(start_address: u64, bytes: D) -> Self {
+        let mut regions = Self::new();
+        regions.add_region(start_address, bytes);
+        regions
+    }
+
+    /// Adds `bytes`, which were copied from the stack at `start_address`. Regions should
+    /// not overlap.
+    pub fn add_region(&mut self, start_address: u64, bytes: D) {
+        let index = self
+            .regions
+            .partition_point(|(start, _)| *start <= start_address);
+        self.regions.insert(index, (start_address, bytes));
+    }
+
+    /// The index of the last region which starts at or before `address`.
+    fn region_index(&self, address: u64) -> Option<usize> {
+        self.regions
+            .partition_point(|(start, _)| *start <= address)
+            .checked_sub(1)
+    }
+
+    /// The bytes at `range`, if they are all in the region at `index`.
+    fn region_bytes(&self, index: usize, range: &Range<u64>) -> Option<&[u8]> {
+        let (start, bytes) = self.regions.get(index)?;
+        let offset = usize::try_from(range.start.checked_sub(*start)?).ok()?;
+        let len = usize::try_from(range.end.checked_sub(range.start)?).ok()?;
+        bytes.get(offset..offset.checked_add(len)?)
+    }
+}
+
+impl<D: Deref<Target = [u8]>> StackMemory for StackRegions<D> {
+    fn bytes(&self, range: Range<u64>) -> Option<&[u8]> {
+        self.region_bytes(self.region_index(range.start)?, &range)
+    }
+}
+
+impl<D: Deref<Target = [u8]>> ReadStack for StackRegions<D> {
+    fn read_stack(&mut self, address: u64) -> Result<u64, ()> {
+        self.read_stack_word(address, 8)
+    }
+
+    fn read_stack_word(&mut self, address: u64, word_size: u8) -> Result<u64, ()> {
+        let range = word_range(address, word_size)?;
+        if self.region_bytes(self.last_region, &range).is_none() {
+            self.last_region = self.region_index(address).ok_or(())?;
+        }
+        word_from_le_bytes(self.region_bytes(self.last_region, &range).ok_or(())?)
+    }
+}
+
+/// What is known about the extent of the stack of the unwound thread. See
+/// [`UnwindIterator::with_stack_bounds`](crate::UnwindIterator::with_stack_bounds).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackBounds {
+    /// The address range of the thread's stack, for example from the `[stack]` mapping in
+    /// `/proc/<pid>/maps` or from `pthread_attr_getstack`. Frame records are never read
+    /// from outside of it.
+    pub thread_stack: Range<u64>,
+    /// The address range of the stack bytes that were copied when the thread was sampled,
+    /// if `read_stack` reads from a copy. For a `PERF_SAMPLE_STACK_USER` sample, this is
+    /// `sp..sp + dyn_size`. Reads outside of it are not passed to `read_stack`.
+    pub copied: Option<Range<u64>>,
+}
+
+/// How the unwinding of a stack ended. See
+/// [`UnwindIterator::stack_end`](crate::UnwindIterator::stack_end).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackEnd {
+    /// The root function of the stack was found.
+    Complete,
+    /// The stack continues beyond the memory which could be read, for example because
+    /// the copy of the stack was too small.
+    CutOff,
+    /// The unwinder went off the rails: it tried to read from outside the thread's
+    /// stack, or the recovered registers made no sense.
+    Corrupted,
+}
+
+impl StackEnd {
+    /// Classifies the error with which unwinding ended. Without bounds, every failed read
+    /// counts as the end of the available memory.
+    pub(crate) fn for_error(error: Error, bounds: Option<&StackBounds>) -> Self {
+        match error {
+            Error::CouldNotReadStack(address)
+                if bounds.is_none_or(|bounds| bounds.thread_stack.contains(&address)) =>
+            {
+                StackEnd::CutOff
+            }
+            _ => StackEnd::Corrupted,
+        }
+    }
+}
+
+/// Wraps `read_stack` so that reads outside of the stack bounds fail.
+pub(crate) struct BoundedReadStack<'a, F> {
+    pub read_stack: &'a mut F,
+    pub bounds: Option<&'a StackBounds>,
+}
+
+impl<F> BoundedReadStack<'_, F> {
+    fn is_in_bounds(&self, address: u64) -> bool {
+        self.bounds.is_none_or(|bounds| {
+            bounds.thread_stack.contains(&address)
+                && bounds
+                    .copied
+                    .as_ref()
+                    .is_none_or(|copied| copied.contains(&address))
+        })
+    }
+}
+
+impl<F: ReadStack> ReadStack for BoundedReadStack<'_, F> {
+    fn read_stack(&mut self, address: u64) -> Result<u64, ()> {
+        if !self.is_in_bounds(address) {
+            return Err(());
+        }
+        self.read_stack.read_stack(address)
+    }
+
+    fn read_stack_word(&mut self, address: u64, word_size: u8) -> Result<u64, ()> {
+        if !self.is_in_bounds(address) {
+            return Err(());
+        }
+        self.read_stack.read_stack_word(address, word_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_regions() {
+        let low: Vec<u8> = (0..16).collect();
+        let high = [0xaa; 12];
+        let mut regions = StackRegions::new();
+        regions.add_region(0x2000, &high[..]);
+        regions.add_region(0x1000, &low[..]);
+
+        assert_eq!(regions.bytes(0x1004..0x1008), Some(&[4, 5, 6, 7][..]));
+        assert_eq!(regions.bytes(0x100c..0x1014), None);
+        assert_eq!(regions.bytes(0xffc..0x1004), None);
+        assert_eq!(regions.read_stack(0x1008), Ok(0x0f0e0d0c0b0a0908));
+        assert_eq!(regions.read_stack(0x2000), Ok(0xaaaaaaaaaaaaaaaa));
+        // Only 4 bytes are left at the end of the region. That's not enough for a 64-bit
+        // word, but enough for a 32-bit word.
+        assert_eq!(regions.read_stack(0x2008), Err(()));
+        assert_eq!(regions.read_stack_word(0x2008, 8), Err(()));
+        assert_eq!(regions.read_stack_word(0x2008, 4), Ok(0xaaaaaaaa));
+        assert_eq!(regions.read_stack_word(0x200c, 4), Err(()));
+        assert_eq!(regions.read_stack(0x3000), Err(()));
+        // Back to the first region, after reading from the second one.
+        assert_eq!(regions.read_stack_word(0x1000, 4), Ok(0x03020100));
+    }
+}
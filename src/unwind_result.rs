@@ -1,5 +1,8 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnwindResult<R> {
     ExecRule(R),
     Uncacheable(u64),
+    /// The frame was interrupted by a signal, and this is its instruction pointer rather
+    /// than a return address. Not cached either.
+    InstructionPointer(u64),
 }
@@ -15,4 +15,30 @@ pub trait UnwindRule: Copy + core::fmt::Debug {
     fn rule_for_stub_functions() -> Self;
     fn rule_for_function_start() -> Self;
     fn fallback_rule() -> Self;
+
+    /// Packs the rule into a `u64`, so that it can be stored in an atomic cache slot.
+    ///
+    /// The packed rules are also stored in serialized rule tables, so the packed
+    /// representation of existing rules must not change without bumping the version of
+    /// the rule table format.
+    fn pack(self) -> u64;
+
+    /// The inverse of `pack`. Returns `None` for bits which don't describe a rule.
+    fn unpack(bits: u64) -> Option<Self>;
+}
+
+/// Packs a rule's variant tag and up to three 16-bit fields into a `u64`.
+pub fn pack_rule(tag: u8, fields: [u16; 3]) -> u64 {
+    u64::from(tag)
+        | u64::from(fields[0]) << 8
+        | u64::from(fields[1]) << 24
+        | u64::from(fields[2]) << 40
+}
+
+/// The inverse of [`pack_rule`].
+pub fn unpack_rule(bits: u64) -> (u8, [u16; 3]) {
+    (
+        bits as u8,
+        [(bits >> 8) as u16, (bits >> 24) as u16, (bits >> 40) as u16],
+    )
 }
@@ -1,29 +1,51 @@
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use fallible_iterator::FallibleIterator;
-use gimli::{EndianSlice, LittleEndian};
+use gimli::{DebugFrame, EhFrame, EndianSlice, LittleEndian};
 
+use crate::aarch64::ArchAarch64;
+use crate::any_unwinder::CpuArch;
 use crate::arch::Arch;
-use crate::cache::{AllocationPolicy, Cache};
-use crate::dwarf::{DwarfCfiIndex, DwarfUnwinder, DwarfUnwinding, UnwindSectionType};
+use crate::arm::ArchArm;
+use crate::breakpad::{BreakpadIndex, BreakpadUnwinder, BreakpadUnwinding};
+use crate::cache::{AllocationPolicy, Cache, CacheKeyMode};
+use crate::compression::{debug_frame_data, MaybeDecompressed};
+use crate::diagnostics::{FrameDiagnostics, UnwindSource};
+use crate::dwarf::{
+    compile_rule_table, DwarfCfiIndex, DwarfUnwinder, DwarfUnwinding, UnwindSectionType,
+};
 use crate::error::{Error, UnwinderError};
+use crate::exidx::{DataAtSvmaRange, ExidxUnwinder, ExidxUnwinderError, ExidxUnwinding};
+use crate::frame_record::{FrameRecord, FrameRegs, UnwindMethod};
 use crate::instruction_analysis::InstructionAnalysis;
 
 #[cfg(feature = "macho")]
 use crate::macho::{
-    CompactUnwindInfoUnwinder, CompactUnwindInfoUnwinding, CuiUnwindResult, TextBytes,
+    CompactUnwindInfoUnwinder, CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding,
+    CuiUnwindResult, TextBytes,
 };
+use crate::orc::{OrcFormat, OrcIndex, OrcUnwinder, OrcUnwinding};
 #[cfg(feature = "pe")]
-use crate::pe::{DataAtRvaRange, PeUnwinding};
+use crate::pe::{DataAtRvaRange, PeUnwinderError, PeUnwinding};
+use crate::riscv64::ArchRiscv64;
 use crate::rule_cache::CacheResult;
+use crate::rule_table::{
+    CompileRuleTableError, RuleTable, RuleTableBuilder, RuleTableData, RULE_TABLE_SECTION,
+};
+use crate::sframe::{SFrameHeader, SFrameUnwinder, SFrameUnwinderError, SFrameUnwinding};
+use crate::signal_frame::{is_linux_sigreturn_trampoline, SignalFrameUnwinding};
+use crate::stack_memory::{BoundedReadStack, ReadStack, StackBounds, StackEnd};
 use crate::unwind_result::UnwindResult;
 use crate::unwind_rule::UnwindRule;
+use crate::x86::ArchX86;
+use crate::x86_64::ArchX86_64;
 use crate::FrameAddress;
 
 use core::marker::PhantomData;
 use core::ops::{Deref, Range};
-use core::sync::atomic::{AtomicU16, Ordering};
+use core::sync::atomic::{AtomicU64, Ordering};
 
 /// Unwinder is the trait that each CPU architecture's concrete unwinder type implements.
 /// This trait's methods are what let you do the actual unwinding.
@@ -60,8 +82,32 @@ pub trait Unwinder: Clone {
     /// to make an educated guess at a pointer authentication mask for Aarch64 return addresses.
     fn max_known_code_address(&self) -> u64;
 
+    /// Returns the module whose address range contains `address`, along with the address
+    /// relative to the module's base address and the corresponding SVMA. This can be used
+    /// to symbolicate the frames found during unwinding.
+    ///
+    /// For return addresses, you usually want to look up
+    /// [`FrameAddress::address_for_lookup`] so that the address is inside the call
+    /// instruction.
+    fn module_for_address(&self, address: u64) -> Option<ModuleAddress<'_, Self::Module>>;
+
+    /// Returns an iterator over all modules that have been added, ordered by the start
+    /// address of their address range.
+    fn modules(&self) -> core::slice::Iter<'_, Self::Module>;
+
     /// Unwind a single frame, to recover return address and caller register values.
     /// This is the main entry point for unwinding.
+    ///
+    /// If the frame is a signal handler frame, the returned address is the instruction
+    /// pointer of the code which was interrupted by the signal, not a return address. Use
+    /// [`unwind_frame_with_method`](Unwinder::unwind_frame_with_method) and
+    /// [`UnwindMethod::is_signal_frame`] to tell the two apart, and pass the address to
+    /// the next call as a [`FrameAddress::InstructionPointer`]. [`UnwindIterator`] does
+    /// this for you.
+    ///
+    /// `read_stack` reads from the stack of the unwound thread. It can be a closure
+    /// which reads one word at a time, or copies of the stack in a
+    /// [`StackRegions`](crate::StackRegions). See [`ReadStack`].
     fn unwind_frame<F>(
         &self,
         address: FrameAddress,
@@ -70,7 +116,37 @@ pub trait Unwinder: Clone {
         read_stack: &mut F,
     ) -> Result<Option<u64>, Error>
     where
-        F: FnMut(u64) -> Result<u64, ()>;
+        F: ReadStack;
+
+    /// Like [`unwind_frame`](Unwinder::unwind_frame), but also returns whether the unwind
+    /// rule came from the cache, or else which unwind information was used. Unlike
+    /// [`unwind_frame_with_diagnostics`](Unwinder::unwind_frame_with_diagnostics), this
+    /// uses the rule cache and is as fast as `unwind_frame`.
+    fn unwind_frame_with_method<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut Self::UnwindRegs,
+        cache: &mut Self::Cache,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, UnwindMethod)
+    where
+        F: ReadStack;
+
+    /// Like [`unwind_frame`](Unwinder::unwind_frame), but also reports which unwind
+    /// information was used for the frame, and why the unwinder fell back to frame
+    /// pointers, if it did.
+    ///
+    /// This is meant for investigating bad stacks. It bypasses the rule cache, so it's
+    /// slower than `unwind_frame`. The cache is still used for other scratch state.
+    fn unwind_frame_with_diagnostics<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut Self::UnwindRegs,
+        cache: &mut Self::Cache,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, FrameDiagnostics)
+    where
+        F: ReadStack;
 
     /// Return an iterator that unwinds frame by frame until the end of the stack is found.
     fn iter_frames<'u, 'c, 'r, F>(
@@ -81,16 +157,38 @@ pub trait Unwinder: Clone {
         read_stack: &'r mut F,
     ) -> UnwindIterator<'u, 'c, 'r, Self, F>
     where
-        F: FnMut(u64) -> Result<u64, ()>,
+        F: ReadStack,
     {
         UnwindIterator::new(self, pc, regs, cache, read_stack)
     }
 }
 
+/// The module that contains a code address, and where in the module the address is.
+/// Returned by [`Unwinder::module_for_address`].
+pub struct ModuleAddress<'a, M> {
+    /// The module. Use [`Module::name`], [`Module::avma_range`] and [`Module::base_avma`]
+    /// to find the module's file and mapping.
+    pub module: &'a M,
+    /// The address relative to the module's base address, i.e. `address - base_avma`.
+    pub relative_address: u64,
+    /// The address in the module's own address space ("stated virtual memory address"),
+    /// i.e. the address as it appears in the binary and in its debug info.
+    pub svma: u64,
+}
+
+impl<M> Clone for ModuleAddress<'_, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for ModuleAddress<'_, M> {}
+
 /// An iterator for unwinding the entire stack, starting from the initial register values.
 ///
 /// The first yielded frame is the instruction pointer. Subsequent addresses are return
-/// addresses.
+/// addresses, except for the frames which were interrupted by a signal, whose
+/// instruction pointers were recovered from the signal frame.
 ///
 /// This iterator attempts to detect if stack unwinding completed successfully, or if the
 /// stack was truncated prematurely. If it thinks that it successfully found the root
@@ -100,17 +198,24 @@ pub trait Unwinder: Clone {
 /// this iterator as a `FallibleIterator`, because you might lose the entire stack if the
 /// last iteration returns `Err(...)`.
 ///
+/// If you know the bounds of the thread's stack, pass them to
+/// [`with_stack_bounds`](UnwindIterator::with_stack_bounds). Once the iterator has
+/// completed, [`stack_end`](UnwindIterator::stack_end) tells you whether the stack was
+/// complete, cut off, or corrupted.
+///
 /// Lifetimes:
 ///
 ///  - `'u`: The lifetime of the [`Unwinder`].
 ///  - `'c`: The lifetime of the unwinder cache.
 ///  - `'r`: The lifetime of the exclusive access to the `read_stack` callback.
-pub struct UnwindIterator<'u, 'c, 'r, U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> {
+pub struct UnwindIterator<'u, 'c, 'r, U: Unwinder, F: ReadStack> {
     unwinder: &'u U,
     state: UnwindIteratorState,
     regs: U::UnwindRegs,
     cache: &'c mut U::Cache,
     read_stack: &'r mut F,
+    bounds: Option<StackBounds>,
+    end: Option<StackEnd>,
 }
 
 enum UnwindIteratorState {
@@ -119,7 +224,7 @@ enum UnwindIteratorState {
     Done,
 }
 
-impl<'u, 'c, 'r, U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> UnwindIterator<'u, 'c, 'r, U, F> {
+impl<'u, 'c, 'r, U: Unwinder, F: ReadStack> UnwindIterator<'u, 'c, 'r, U, F> {
     /// Create a new iterator. You'd usually use [`Unwinder::iter_frames`] instead.
     pub fn new(
         unwinder: &'u U,
@@ -134,15 +239,38 @@ impl<'u, 'c, 'r, U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> UnwindIterator<'
             regs,
             cache,
             read_stack,
+            bounds: None,
+            end: None,
         }
     }
+
+    /// Restricts the unwinding to the thread's stack. Frame records outside of
+    /// `bounds.thread_stack`, and stack memory outside of `bounds.copied`, are not read.
+    /// This also makes [`stack_end`](UnwindIterator::stack_end) more accurate.
+    pub fn with_stack_bounds(mut self, bounds: StackBounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Returns how the stack ended, once the iterator has returned `Ok(None)` or
+    /// `Err(...)`. Returns `None` before that.
+    ///
+    /// Without [stack bounds](UnwindIterator::with_stack_bounds), any stack memory
+    /// which could not be read is taken to be the end of the available memory, so the
+    /// stack counts as [`CutOff`](StackEnd::CutOff) rather than
+    /// [`Corrupted`](StackEnd::Corrupted).
+    pub fn stack_end(&self) -> Option<StackEnd> {
+        self.end
+    }
 }
 
-impl<U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> UnwindIterator<'_, '_, '_, U, F> {
+impl<U: Unwinder, F: ReadStack> UnwindIterator<'_, '_, '_, U, F> {
     /// Yield the next frame in the stack.
     ///
     /// The first frame is `Ok(Some(FrameAddress::InstructionPointer(...)))`.
-    /// Subsequent frames are `Ok(Some(FrameAddress::ReturnAddress(...)))`.
+    /// Subsequent frames are `Ok(Some(FrameAddress::ReturnAddress(...)))`, or
+    /// `Ok(Some(FrameAddress::InstructionPointer(...)))` for frames which were
+    /// interrupted by a signal.
     ///
     /// If a root function has been reached, this iterator completes with `Ok(None)`.
     /// Otherwise it completes with `Err(...)`, usually indicating that a certain stack
@@ -154,30 +282,121 @@ impl<U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> UnwindIterator<'_, '_, '_, U
                 self.state = UnwindIteratorState::Unwinding(FrameAddress::InstructionPointer(pc));
                 return Ok(Some(FrameAddress::InstructionPointer(pc)));
             }
-            UnwindIteratorState::Unwinding(address) => {
-                self.unwinder
-                    .unwind_frame(address, &mut self.regs, self.cache, self.read_stack)?
-            }
+            UnwindIteratorState::Unwinding(address) => self.unwinder.unwind_frame_with_method(
+                address,
+                &mut self.regs,
+                self.cache,
+                &mut BoundedReadStack {
+                    read_stack: self.read_stack,
+                    bounds: self.bounds.as_ref(),
+                },
+            ),
             UnwindIteratorState::Done => return Ok(None),
         };
+        let (next, method) = next;
+        self.advance(next, method.is_signal_frame())
+    }
+
+    /// Like [`next`](UnwindIterator::next), but also returns how the previous frame was
+    /// unwound to find this one. See [`Unwinder::unwind_frame_with_diagnostics`].
+    ///
+    /// The diagnostics are `None` for the first frame, which is the instruction pointer,
+    /// and once the iterator has completed.
+    pub fn next_with_diagnostics(
+        &mut self,
+    ) -> (
+        Result<Option<FrameAddress>, Error>,
+        Option<FrameDiagnostics>,
+    ) {
+        let (next, diagnostics) = match self.state {
+            UnwindIteratorState::Initial(pc) => {
+                self.state = UnwindIteratorState::Unwinding(FrameAddress::InstructionPointer(pc));
+                return (Ok(Some(FrameAddress::InstructionPointer(pc))), None);
+            }
+            UnwindIteratorState::Unwinding(address) => self.unwinder.unwind_frame_with_diagnostics(
+                address,
+                &mut self.regs,
+                self.cache,
+                &mut BoundedReadStack {
+                    read_stack: self.read_stack,
+                    bounds: self.bounds.as_ref(),
+                },
+            ),
+            UnwindIteratorState::Done => return (Ok(None), None),
+        };
+        let is_signal_frame = diagnostics.source == UnwindSource::SignalFrame;
+        (self.advance(next, is_signal_frame), Some(diagnostics))
+    }
+
+    /// Turns the result of unwinding a frame into the next frame's address. After a
+    /// signal handler frame, the address is the interrupted instruction pointer.
+    fn advance(
+        &mut self,
+        next: Result<Option<u64>, Error>,
+        is_signal_frame: bool,
+    ) -> Result<Option<FrameAddress>, Error> {
+        let next = next.and_then(|next| match next {
+            Some(pc) if is_signal_frame => Ok(Some(FrameAddress::InstructionPointer(pc))),
+            Some(return_address) => FrameAddress::from_return_address(return_address)
+                .map(Some)
+                .ok_or(Error::ReturnAddressIsNull),
+            None => Ok(None),
+        });
         match next {
-            Some(return_address) => {
-                let return_address = FrameAddress::from_return_address(return_address)
-                    .ok_or(Error::ReturnAddressIsNull)?;
-                self.state = UnwindIteratorState::Unwinding(return_address);
-                Ok(Some(return_address))
+            Ok(Some(address)) => {
+                self.state = UnwindIteratorState::Unwinding(address);
             }
-            None => {
+            Ok(None) => {
                 self.state = UnwindIteratorState::Done;
-                Ok(None)
+                self.end = Some(StackEnd::Complete);
             }
+            Err(error) => self.end = Some(StackEnd::for_error(error, self.bounds.as_ref())),
         }
+        next
     }
 }
 
-impl<U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> FallibleIterator
-    for UnwindIterator<'_, '_, '_, U, F>
+impl<U: Unwinder, F: ReadStack> UnwindIterator<'_, '_, '_, U, F>
+where
+    U::UnwindRegs: FrameRegs,
 {
+    /// Like [`next`](UnwindIterator::next), but yields a [`FrameRecord`] with the stack
+    /// pointer and the frame pointer of the frame, and how it was found.
+    ///
+    /// This does not allocate, and it uses the rule cache just like `next`.
+    pub fn next_record(&mut self) -> Result<Option<FrameRecord>, Error> {
+        let (next, method) = match self.state {
+            UnwindIteratorState::Initial(pc) => {
+                let address = FrameAddress::InstructionPointer(pc);
+                self.state = UnwindIteratorState::Unwinding(address);
+                return Ok(Some(self.record(address, UnwindMethod::InitialRegisters)));
+            }
+            UnwindIteratorState::Unwinding(address) => self.unwinder.unwind_frame_with_method(
+                address,
+                &mut self.regs,
+                self.cache,
+                &mut BoundedReadStack {
+                    read_stack: self.read_stack,
+                    bounds: self.bounds.as_ref(),
+                },
+            ),
+            UnwindIteratorState::Done => return Ok(None),
+        };
+        let address = self.advance(next, method.is_signal_frame())?;
+        Ok(address.map(|address| self.record(address, method)))
+    }
+
+    fn record(&self, address: FrameAddress, method: UnwindMethod) -> FrameRecord {
+        FrameRecord {
+            address,
+            sp: self.regs.stack_pointer(),
+            fp: self.regs.frame_pointer(),
+            method,
+        }
+    }
+}
+
+impl<U: Unwinder, F: ReadStack> FallibleIterator for UnwindIterator<'_, '_, '_, U, F> {
     type Item = FrameAddress;
     type Error = Error;
 
@@ -187,42 +406,67 @@ impl<U: Unwinder, F: FnMut(u64) -> Result<u64, ()>> FallibleIterator
 }
 
 /// This global generation counter makes it so that the cache can be shared
-/// between multiple unwinders.
-/// This is a u16, so if you make it wrap around by adding / removing modules
-/// more than 65535 times, then you risk collisions in the cache; meaning:
-/// unwinding might not work properly if an old unwind rule was found in the
-/// cache for the same address and the same (pre-wraparound) modules_generation.
-static GLOBAL_MODULES_GENERATION: AtomicU16 = AtomicU16::new(0);
-
-fn next_global_modules_generation() -> u16 {
-    GLOBAL_MODULES_GENERATION.fetch_add(1, Ordering::Relaxed)
+/// between multiple unwinders: each module, and the addresses outside of an
+/// unwinder's modules, are tagged with generations from this counter, and
+/// cached rules are only found again with the same generation.
+///
+/// Local caches only store the low 16 bits of the generation, and flush their
+/// entries when they see generations with higher upper bits. An unwinder whose
+/// generations are from an earlier era than the cache's gets new ones, see
+/// [`generation_era`].
+static GLOBAL_MODULES_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The number of generations which share the same upper bits.
+const GENERATIONS_PER_ERA: u64 = 1 << 16;
+
+/// Returns the first of `count` consecutive generations which are in the same era.
+/// `count` must be at most [`GENERATIONS_PER_ERA`].
+fn next_global_modules_generations(count: u64) -> u64 {
+    loop {
+        let first = GLOBAL_MODULES_GENERATION.fetch_add(count, Ordering::Relaxed);
+        if generation_era(first) == generation_era(first + count - 1) {
+            return first;
+        }
+    }
+}
+
+/// The upper bits of a generation. All generations which are used by an unwinder
+/// are in the same era, so that they can be told apart by their lower 16 bits.
+pub(crate) fn generation_era(generation: u64) -> u64 {
+    generation / GENERATIONS_PER_ERA
 }
 
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "macho", feature = "pe"))] {
         pub trait Unwinding:
-            Arch + DwarfUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding + PeUnwinding {}
-        impl<T: Arch + DwarfUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding + PeUnwinding>
+            Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding + PeUnwinding {}
+        impl<T: Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding + PeUnwinding>
             Unwinding for T {}
     } else if #[cfg(feature = "macho")] {
         pub trait Unwinding:
-            Arch + DwarfUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding {}
-        impl<T: Arch + DwarfUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding> Unwinding for T {}
+            Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding {}
+        impl<T: Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis + CompactUnwindInfoUnwinding> Unwinding for T {}
     } else if #[cfg(feature = "pe")] {
         pub trait Unwinding:
-            Arch + DwarfUnwinding + InstructionAnalysis  + PeUnwinding {}
-        impl<T: Arch + DwarfUnwinding + InstructionAnalysis + PeUnwinding> Unwinding for T {}
+            Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis  + PeUnwinding {}
+        impl<T: Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis + PeUnwinding> Unwinding for T {}
     } else {
-        pub trait Unwinding: Arch + DwarfUnwinding + InstructionAnalysis {}
-        impl<T: Arch + DwarfUnwinding + InstructionAnalysis> Unwinding for T {}
+        pub trait Unwinding: Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis {}
+        impl<T: Arch + BreakpadUnwinding + DwarfUnwinding + ExidxUnwinding + OrcUnwinding + SFrameUnwinding + SignalFrameUnwinding + InstructionAnalysis> Unwinding for T {}
     }
 }
 
 pub struct UnwinderInternal<D, A, P> {
     /// sorted by avma_range.start
     modules: Vec<Module<D>>,
-    /// Incremented every time modules is changed.
-    modules_generation: u16,
+    /// The cache generation for addresses outside of all modules. Every added module
+    /// can cover addresses whose fallback rules were cached, so this changes whenever
+    /// a module is added. Removing a module doesn't invalidate any cached rules: the
+    /// addresses of the removed module were cached with the module's generation.
+    ///
+    /// This and the modules' generations are renewed through `&self` when a cache has
+    /// moved on to a later era, see `renew_generations`.
+    gap_generation: AtomicU64,
     _arch: PhantomData<A>,
     _allocation_policy: PhantomData<P>,
 }
@@ -237,7 +481,7 @@ impl<D, A, P> Clone for UnwinderInternal<D, A, P> {
     fn clone(&self) -> Self {
         Self {
             modules: self.modules.clone(),
-            modules_generation: self.modules_generation,
+            gap_generation: AtomicU64::new(self.gap_generation.load(Ordering::Relaxed)),
             _arch: PhantomData,
             _allocation_policy: PhantomData,
         }
@@ -248,7 +492,7 @@ impl<D, A, P> UnwinderInternal<D, A, P> {
     pub fn new() -> Self {
         Self {
             modules: Vec::new(),
-            modules_generation: next_global_modules_generation(),
+            gap_generation: AtomicU64::new(next_global_modules_generations(1)),
             _arch: PhantomData,
             _allocation_policy: PhantomData,
         }
@@ -256,7 +500,18 @@ impl<D, A, P> UnwinderInternal<D, A, P> {
 }
 
 impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInternal<D, A, P> {
-    pub fn add_module(&mut self, module: Module<D>) {
+    pub fn add_module(&mut self, mut module: Module<D>) {
+        let generation = next_global_modules_generations(2);
+        let same_era = generation_era(generation) == generation_era(*self.gap_generation.get_mut());
+        *module.generation.get_mut() = generation;
+        *self.gap_generation.get_mut() = generation + 1;
+        if module.precompile_rules && !module.has_precompiled_rules(A::CPU_ARCH) {
+            module.rule_table = module
+                .unwind_data
+                .compile_rule_table::<A>(module.base_svma, module.address_size, None)
+                .ok()
+                .map(|table| (A::CPU_ARCH, Arc::new(table)));
+        }
         let insertion_index = match self
             .modules
             .binary_search_by_key(&module.avma_range.start, |module| module.avma_range.start)
@@ -265,7 +520,34 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
             Err(i) => i,
         };
         self.modules.insert(insertion_index, module);
-        self.modules_generation = next_global_modules_generation();
+        if !same_era {
+            // Start over in the new era, so that the local caches can keep telling our
+            // generations apart.
+            self.renew_generations();
+        }
+    }
+
+    /// Gives all modules, and the addresses outside of them, new generations in the
+    /// current era. This invalidates all cached rules for this unwinder.
+    ///
+    /// This is done when the unwinder crosses into a new era, and when a local cache
+    /// which has already moved on to a later era sees one of our generations. Without
+    /// it, such a cache could never store our rules again.
+    ///
+    /// Concurrent calls can leave the modules with generations from different calls.
+    /// That only causes cache misses: every generation is still only used by this
+    /// unwinder (and its clones), and caches only compare generations from their era.
+    fn renew_generations(&self) {
+        // In the unlikely case of more modules than generations per era, modules
+        // share generations, which is fine because their addresses don't overlap.
+        let count = (self.modules.len() as u64 + 1).min(GENERATIONS_PER_ERA);
+        let first = next_global_modules_generations(count);
+        for (i, module) in self.modules.iter().enumerate() {
+            let generation = first + i as u64 % (count - 1);
+            module.generation.store(generation, Ordering::Relaxed);
+        }
+        self.gap_generation
+            .store(first + count - 1, Ordering::Relaxed);
     }
 
     pub fn remove_module(&mut self, module_address_range_start: u64) {
@@ -276,7 +558,6 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
             })
         {
             self.modules.remove(index);
-            self.modules_generation = next_global_modules_generation();
         };
     }
 
@@ -284,7 +565,27 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
         self.modules.last().map_or(0, |m| m.avma_range.end)
     }
 
+    pub fn modules(&self) -> core::slice::Iter<'_, Module<D>> {
+        self.modules.iter()
+    }
+
+    pub fn module_for_address(&self, address: u64) -> Option<ModuleAddress<'_, Module<D>>> {
+        let (module_index, relative_address) = self.find_module(address)?;
+        let module = &self.modules[module_index];
+        Some(ModuleAddress {
+            module,
+            relative_address,
+            svma: module.base_svma.wrapping_add(relative_address),
+        })
+    }
+
     fn find_module_for_address(&self, address: u64) -> Option<(usize, u32)> {
+        let (module_index, relative_address) = self.find_module(address)?;
+        let relative_address = u32::try_from(relative_address).ok()?;
+        Some((module_index, relative_address))
+    }
+
+    fn find_module(&self, address: u64) -> Option<(usize, u64)> {
         let (module_index, module) = match self
             .modules
             .binary_search_by_key(&address, |m| m.avma_range.start)
@@ -308,136 +609,210 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
             // Invalid base address
             return None;
         }
-        let relative_address = u32::try_from(address - module.base_avma).ok()?;
-        Some((module_index, relative_address))
+        Some((module_index, address - module.base_avma))
     }
 
-    fn with_cache<F, G>(
+    pub fn unwind_frame<F>(
         &self,
         address: FrameAddress,
         regs: &mut A::UnwindRegs,
         cache: &mut Cache<A::UnwindRule, P>,
         read_stack: &mut F,
-        callback: G,
     ) -> Result<Option<u64>, Error>
     where
-        F: FnMut(u64) -> Result<u64, ()>,
-        G: FnOnce(
-            &Module<D>,
-            FrameAddress,
-            u32,
-            &mut A::UnwindRegs,
-            &mut Cache<A::UnwindRule, P>,
-            &mut F,
-        ) -> Result<UnwindResult<A::UnwindRule>, UnwinderError>,
+        F: ReadStack,
     {
+        self.unwind_frame_with_method(address, regs, cache, read_stack)
+            .0
+    }
+
+    pub fn unwind_frame_with_method<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut A::UnwindRegs,
+        cache: &mut Cache<A::UnwindRule, P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, UnwindMethod)
+    where
+        F: ReadStack,
+    {
+        let read_stack = &mut |address| read_stack.read_stack_word(address, A::WORD_SIZE);
         let lookup_address = address.address_for_lookup();
         let is_first_frame = !address.is_return_address();
-        let cache_handle = match cache
-            .rule_cache
-            .lookup(lookup_address, self.modules_generation)
-        {
-            CacheResult::Hit(unwind_rule) => {
-                return unwind_rule.exec(is_first_frame, regs, read_stack);
+        let cache_key = match cache.rule_cache.key_mode() {
+            CacheKeyMode::AbsoluteAddress => {
+                let generation = match self.find_module(lookup_address) {
+                    Some((module_index, _)) => &self.modules[module_index].generation,
+                    None => &self.gap_generation,
+                };
+                let generation = generation.load(Ordering::Relaxed);
+                Some((lookup_address, generation))
+            }
+            CacheKeyMode::ModuleRelative => {
+                self.find_module(lookup_address)
+                    .and_then(|(module_index, relative_address)| {
+                        Some((relative_address, self.modules[module_index].cache_key?))
+                    })
             }
-            CacheResult::Miss(handle) => handle,
         };
-
-        let unwind_rule = match self.find_module_for_address(lookup_address) {
-            None => A::UnwindRule::fallback_rule(),
-            Some((module_index, relative_lookup_address)) => {
-                let module = &self.modules[module_index];
-                match callback(
-                    module,
-                    address,
-                    relative_lookup_address,
-                    regs,
-                    cache,
-                    read_stack,
-                ) {
-                    Ok(UnwindResult::ExecRule(rule)) => rule,
-                    Ok(UnwindResult::Uncacheable(return_address)) => {
-                        return Ok(Some(return_address))
-                    }
-                    Err(_err) => {
-                        // eprintln!("Unwinder error: {}", err);
-                        A::UnwindRule::fallback_rule()
-                    }
+        let cache_handle =
+            match cache_key.map(|(address, modules)| cache.rule_cache.lookup(address, modules)) {
+                Some(CacheResult::Hit(unwind_rule)) => {
+                    let result = unwind_rule.exec(is_first_frame, regs, read_stack);
+                    return (result, UnwindMethod::Cached);
                 }
-            }
+                Some(CacheResult::Miss(handle)) => Some(handle),
+                Some(CacheResult::MissOutdatedGeneration) => {
+                    self.renew_generations();
+                    None
+                }
+                None => None,
+            };
+
+        let mut diagnostics = FrameDiagnostics::new();
+        let unwind_result =
+            self.unwind_result_for_address(address, regs, cache, read_stack, &mut diagnostics);
+        let method = UnwindMethod::Uncached(diagnostics.source);
+        let unwind_rule = match unwind_result {
+            UnwindResult::ExecRule(rule) => rule,
+            UnwindResult::Uncacheable(return_address) => return (Ok(Some(return_address)), method),
+            UnwindResult::InstructionPointer(pc) => return (Ok(Some(pc)), method),
         };
-        cache.rule_cache.insert(cache_handle, unwind_rule);
-        unwind_rule.exec(is_first_frame, regs, read_stack)
+        if let Some(cache_handle) = cache_handle {
+            cache.rule_cache.insert(cache_handle, unwind_rule);
+        }
+        (unwind_rule.exec(is_first_frame, regs, read_stack), method)
     }
 
-    pub fn unwind_frame<F>(
+    /// Like `unwind_frame`, but also reports how the frame was unwound. This does not
+    /// use the rule cache, so that the diagnostics always reflect the module's unwind
+    /// information.
+    pub fn unwind_frame_with_diagnostics<F>(
         &self,
         address: FrameAddress,
         regs: &mut A::UnwindRegs,
         cache: &mut Cache<A::UnwindRule, P>,
         read_stack: &mut F,
-    ) -> Result<Option<u64>, Error>
+    ) -> (Result<Option<u64>, Error>, FrameDiagnostics)
+    where
+        F: ReadStack,
+    {
+        let read_stack = &mut |address| read_stack.read_stack_word(address, A::WORD_SIZE);
+        let is_first_frame = !address.is_return_address();
+        let mut diagnostics = FrameDiagnostics::new();
+        let result = match self.unwind_result_for_address(
+            address,
+            regs,
+            cache,
+            read_stack,
+            &mut diagnostics,
+        ) {
+            UnwindResult::ExecRule(rule) => rule.exec(is_first_frame, regs, read_stack),
+            UnwindResult::Uncacheable(return_address) => Ok(Some(return_address)),
+            UnwindResult::InstructionPointer(pc) => Ok(Some(pc)),
+        };
+        (result, diagnostics)
+    }
+
+    /// Finds the unwind rule for the address from the unwind information of the module
+    /// that contains it. Falls back to the arch's fallback rule if there is no such
+    /// module or if its unwind information could not be used.
+    fn unwind_result_for_address<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut A::UnwindRegs,
+        cache: &mut Cache<A::UnwindRule, P>,
+        read_stack: &mut F,
+        diagnostics: &mut FrameDiagnostics,
+    ) -> UnwindResult<A::UnwindRule>
     where
         F: FnMut(u64) -> Result<u64, ()>,
     {
-        self.with_cache(address, regs, cache, read_stack, Self::unwind_frame_impl)
+        let Some((module_index, relative_lookup_address)) =
+            self.find_module_for_address(address.address_for_lookup())
+        else {
+            diagnostics.source = UnwindSource::Fallback;
+            return UnwindResult::ExecRule(A::UnwindRule::fallback_rule());
+        };
+        let module = &self.modules[module_index];
+        match Self::unwind_frame_with_data(
+            module,
+            &module.unwind_data,
+            address,
+            relative_lookup_address,
+            regs,
+            cache,
+            read_stack,
+            diagnostics,
+        ) {
+            Ok(Some(unwind_result)) => unwind_result,
+            Ok(None) => UnwindResult::ExecRule(A::rule_if_uncovered_by_fde()),
+            Err(err) => {
+                diagnostics.source = UnwindSource::Fallback;
+                diagnostics.error = Some(err);
+                UnwindResult::ExecRule(A::UnwindRule::fallback_rule())
+            }
+        }
     }
 
-    fn unwind_frame_impl<F>(
+    /// Finds the unwind rule for the address in the given unwind data of the module.
+    /// Returns `Ok(None)` if the DWARF CFI has no FDE which covers the address.
+    #[allow(clippy::too_many_arguments)]
+    fn unwind_frame_with_data<F>(
         module: &Module<D>,
+        unwind_data: &ModuleUnwindDataInternal<D>,
         address: FrameAddress,
         rel_lookup_address: u32,
         regs: &mut A::UnwindRegs,
         cache: &mut Cache<A::UnwindRule, P>,
         read_stack: &mut F,
-    ) -> Result<UnwindResult<A::UnwindRule>, UnwinderError>
+        diagnostics: &mut FrameDiagnostics,
+    ) -> Result<Option<UnwindResult<A::UnwindRule>>, UnwinderError>
     where
         F: FnMut(u64) -> Result<u64, ()>,
     {
         let is_first_frame = !address.is_return_address();
-        let unwind_result = match &*module.unwind_data {
+        let unwind_result = match unwind_data {
             #[cfg(feature = "macho")]
             ModuleUnwindDataInternal::CompactUnwindInfoAndEhFrame {
                 unwind_info,
                 eh_frame,
                 stubs_svma: stubs,
                 stub_helper_svma: stub_helper,
+                sigtramp_svma: sigtramp,
                 base_addresses,
                 text_data,
             } => {
                 // eprintln!("unwinding with cui and eh_frame in module {}", module.name);
-                let text_bytes = text_data.as_ref().and_then(|data| {
-                    let offset_from_base =
-                        u32::try_from(data.svma_range.start.checked_sub(module.base_svma)?).ok()?;
-                    Some(TextBytes::new(offset_from_base, &data.bytes[..]))
-                });
-                let stubs_range = if let Some(stubs_range) = stubs {
-                    (
-                        (stubs_range.start - module.base_svma) as u32,
-                        (stubs_range.end - module.base_svma) as u32,
-                    )
-                } else {
-                    (0, 0)
-                };
-                let stub_helper_range = if let Some(stub_helper_range) = stub_helper {
-                    (
-                        (stub_helper_range.start - module.base_svma) as u32,
-                        (stub_helper_range.end - module.base_svma) as u32,
-                    )
-                } else {
-                    (0, 0)
-                };
-                let mut unwinder = CompactUnwindInfoUnwinder::<A>::new(
-                    &unwind_info[..],
-                    text_bytes,
-                    stubs_range,
-                    stub_helper_range,
+                // The precompiled rules are for return addresses. In the first frame, the
+                // unwinder may need to analyze the function's prologue or epilogue.
+                if !is_first_frame {
+                    if let Some(rule) = module.precompiled_rule::<A>(rel_lookup_address) {
+                        diagnostics.source = UnwindSource::PrecompiledRules;
+                        return Ok(Some(UnwindResult::ExecRule(rule)));
+                    }
+                }
+                diagnostics.source = UnwindSource::CompactUnwindInfo;
+                let mut unwinder = compact_unwind_info_unwinder::<A, D>(
+                    unwind_info,
+                    text_data,
+                    [stubs, stub_helper, sigtramp],
+                    module.base_svma,
                 );
 
                 let unwind_result = unwinder.unwind_frame(rel_lookup_address, is_first_frame)?;
                 match unwind_result {
                     CuiUnwindResult::ExecRule(rule) => UnwindResult::ExecRule(rule),
+                    CuiUnwindResult::ExecRuleFromInstructionAnalysis(rule) => {
+                        diagnostics.source = UnwindSource::InstructionAnalysis;
+                        UnwindResult::ExecRule(rule)
+                    }
+                    CuiUnwindResult::Sigtramp => {
+                        diagnostics.source = UnwindSource::SignalFrame;
+                        A::unwind_sigtramp_frame(regs, read_stack)?
+                    }
                     CuiUnwindResult::NeedDwarf(fde_offset) => {
+                        diagnostics.source = UnwindSource::CompactUnwindInfoEhFrame;
                         let eh_frame_data =
                             eh_frame.as_deref().ok_or(UnwinderError::NoDwarfData)?;
                         let mut dwarf_unwinder = DwarfUnwinder::<_, A, _>::new(
@@ -447,6 +822,7 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                             &mut cache.gimli_unwind_context,
                             base_addresses.clone(),
                             module.base_svma,
+                            module.address_size,
                         );
                         dwarf_unwinder.unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
                             regs,
@@ -454,6 +830,7 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                             rel_lookup_address,
                             fde_offset,
                             read_stack,
+                            &mut diagnostics.conversion_error,
                         )?
                     }
                 }
@@ -463,6 +840,11 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                 eh_frame,
                 base_addresses,
             } => {
+                if let Some(rule) = module.precompiled_rule::<A>(rel_lookup_address) {
+                    diagnostics.source = UnwindSource::PrecompiledRules;
+                    return Ok(Some(UnwindResult::ExecRule(rule)));
+                }
+                diagnostics.source = UnwindSource::EhFrameHdr;
                 let eh_frame_hdr_data = &eh_frame_hdr[..];
                 let mut dwarf_unwinder = DwarfUnwinder::<_, A, _>::new(
                     EndianSlice::new(eh_frame, LittleEndian),
@@ -471,23 +853,33 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                     &mut cache.gimli_unwind_context,
                     base_addresses.clone(),
                     module.base_svma,
+                    module.address_size,
                 );
                 let fde_offset = dwarf_unwinder
                     .get_fde_offset_for_relative_address(rel_lookup_address)
                     .ok_or(UnwinderError::EhFrameHdrCouldNotFindAddress)?;
-                dwarf_unwinder.unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
+                match dwarf_unwinder.try_unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
                     regs,
                     is_first_frame,
                     rel_lookup_address,
                     fde_offset,
                     read_stack,
-                )?
+                    &mut diagnostics.conversion_error,
+                )? {
+                    Some(unwind_result) => unwind_result,
+                    None => return Ok(None),
+                }
             }
             ModuleUnwindDataInternal::DwarfCfiIndexAndEhFrame {
                 index,
                 eh_frame,
                 base_addresses,
             } => {
+                if let Some(rule) = module.precompiled_rule::<A>(rel_lookup_address) {
+                    diagnostics.source = UnwindSource::PrecompiledRules;
+                    return Ok(Some(UnwindResult::ExecRule(rule)));
+                }
+                diagnostics.source = UnwindSource::EhFrameDwarfCfiIndex;
                 let mut dwarf_unwinder = DwarfUnwinder::<_, A, _>::new(
                     EndianSlice::new(eh_frame, LittleEndian),
                     UnwindSectionType::EhFrame,
@@ -495,23 +887,33 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                     &mut cache.gimli_unwind_context,
                     base_addresses.clone(),
                     module.base_svma,
+                    module.address_size,
                 );
                 let fde_offset = index
                     .fde_offset_for_relative_address(rel_lookup_address)
                     .ok_or(UnwinderError::DwarfCfiIndexCouldNotFindAddress)?;
-                dwarf_unwinder.unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
+                match dwarf_unwinder.try_unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
                     regs,
                     is_first_frame,
                     rel_lookup_address,
                     fde_offset,
                     read_stack,
-                )?
+                    &mut diagnostics.conversion_error,
+                )? {
+                    Some(unwind_result) => unwind_result,
+                    None => return Ok(None),
+                }
             }
             ModuleUnwindDataInternal::DwarfCfiIndexAndDebugFrame {
                 index,
                 debug_frame,
                 base_addresses,
             } => {
+                if let Some(rule) = module.precompiled_rule::<A>(rel_lookup_address) {
+                    diagnostics.source = UnwindSource::PrecompiledRules;
+                    return Ok(Some(UnwindResult::ExecRule(rule)));
+                }
+                diagnostics.source = UnwindSource::DebugFrame;
                 let mut dwarf_unwinder = DwarfUnwinder::<_, A, _>::new(
                     EndianSlice::new(debug_frame, LittleEndian),
                     UnwindSectionType::DebugFrame,
@@ -519,17 +921,22 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                     &mut cache.gimli_unwind_context,
                     base_addresses.clone(),
                     module.base_svma,
+                    module.address_size,
                 );
                 let fde_offset = index
                     .fde_offset_for_relative_address(rel_lookup_address)
                     .ok_or(UnwinderError::DwarfCfiIndexCouldNotFindAddress)?;
-                dwarf_unwinder.unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
+                match dwarf_unwinder.try_unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
                     regs,
                     is_first_frame,
                     rel_lookup_address,
                     fde_offset,
                     read_stack,
-                )?
+                    &mut diagnostics.conversion_error,
+                )? {
+                    Some(unwind_result) => unwind_result,
+                    None => return Ok(None),
+                }
             }
             #[cfg(feature = "pe")]
             ModuleUnwindDataInternal::PeUnwindInfo {
@@ -537,24 +944,253 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
                 rdata,
                 xdata,
                 text,
-            } => <A as PeUnwinding>::unwind_frame(
-                crate::pe::PeSections {
-                    pdata,
-                    rdata: rdata.as_ref(),
-                    xdata: xdata.as_ref(),
-                    text: text.as_ref(),
-                },
-                rel_lookup_address,
-                regs,
-                is_first_frame,
-                read_stack,
-            )?,
+            } => {
+                // The precompiled rules are for return addresses, see above.
+                if !is_first_frame {
+                    if let Some(rule) = module.precompiled_rule::<A>(rel_lookup_address) {
+                        diagnostics.source = UnwindSource::PrecompiledRules;
+                        return Ok(Some(UnwindResult::ExecRule(rule)));
+                    }
+                }
+                diagnostics.source = UnwindSource::Pe;
+                <A as PeUnwinding>::unwind_frame(
+                    crate::pe::PeSections {
+                        pdata,
+                        rdata: rdata.as_ref(),
+                        xdata: xdata.as_ref(),
+                        text: text.as_ref(),
+                    },
+                    rel_lookup_address,
+                    regs,
+                    is_first_frame,
+                    read_stack,
+                )?
+            }
+            ModuleUnwindDataInternal::ArmExidx {
+                exidx,
+                extab,
+                debug_frame,
+                base_addresses,
+            } => {
+                diagnostics.source = UnwindSource::ArmExidx;
+                let unwinder = ExidxUnwinder::<A>::new(exidx, extab.as_ref(), module.base_svma);
+                match unwinder.unwind_frame(rel_lookup_address, regs, is_first_frame, read_stack) {
+                    Ok(unwind_result) => unwind_result,
+                    Err(
+                        err @ (ExidxUnwinderError::AddressOutsideRange(_)
+                        | ExidxUnwinderError::CantUnwind),
+                    ) => {
+                        // .ARM.exidx has no information for this address. Some toolchains
+                        // still emit .debug_frame information for such functions.
+                        let Some((index, debug_frame)) = debug_frame else {
+                            return Err(err.into());
+                        };
+                        diagnostics.source = UnwindSource::DebugFrame;
+                        let mut dwarf_unwinder = DwarfUnwinder::<_, A, _>::new(
+                            EndianSlice::new(debug_frame, LittleEndian),
+                            UnwindSectionType::DebugFrame,
+                            None,
+                            &mut cache.gimli_unwind_context,
+                            base_addresses.clone(),
+                            module.base_svma,
+                            module.address_size,
+                        );
+                        let fde_offset = index
+                            .fde_offset_for_relative_address(rel_lookup_address)
+                            .ok_or(UnwinderError::DwarfCfiIndexCouldNotFindAddress)?;
+                        match dwarf_unwinder
+                            .try_unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
+                                regs,
+                                is_first_frame,
+                                rel_lookup_address,
+                                fde_offset,
+                                read_stack,
+                                &mut diagnostics.conversion_error,
+                            )? {
+                            Some(unwind_result) => unwind_result,
+                            None => return Ok(None),
+                        }
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            ModuleUnwindDataInternal::Orc {
+                orc_unwind_ip,
+                orc_unwind,
+                index,
+                format,
+            } => {
+                diagnostics.source = UnwindSource::Orc;
+                OrcUnwinder::<A>::new(
+                    orc_unwind_ip,
+                    orc_unwind,
+                    index.as_ref(),
+                    *format,
+                    module.base_svma,
+                )
+                .unwind_frame(
+                    rel_lookup_address,
+                    regs,
+                    is_first_frame,
+                    read_stack,
+                )?
+            }
+            ModuleUnwindDataInternal::SFrame { sframe, fallback } => {
+                if let Some(rule) = module.precompiled_rule::<A>(rel_lookup_address) {
+                    diagnostics.source = UnwindSource::PrecompiledRules;
+                    return Ok(Some(UnwindResult::ExecRule(rule)));
+                }
+                diagnostics.source = UnwindSource::SFrame;
+                let unwind_result =
+                    SFrameUnwinder::<A>::new(sframe, module.base_svma).and_then(|unwinder| {
+                        unwinder.unwind_frame(rel_lookup_address, regs, is_first_frame, read_stack)
+                    });
+                match unwind_result {
+                    Ok(unwind_result) => unwind_result,
+                    Err(
+                        SFrameUnwinderError::AddressNotCovered(_)
+                        | SFrameUnwinderError::UnsupportedAbi(_)
+                        | SFrameUnwinderError::UnsupportedArch,
+                    ) => {
+                        // .sframe only covers functions with a simple frame layout. Use the
+                        // DWARF CFI for everything else.
+                        return Self::unwind_frame_with_data(
+                            module,
+                            fallback,
+                            address,
+                            rel_lookup_address,
+                            regs,
+                            cache,
+                            read_stack,
+                            diagnostics,
+                        );
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            #[cfg(feature = "gnu-debugdata")]
+            ModuleUnwindDataInternal::GnuDebugdata {
+                outer,
+                index,
+                debug_frame,
+                base_addresses,
+            } => {
+                // Use the outer unwind data if it covers the address. The MiniDebugInfo
+                // .debug_frame usually only contains the functions which are missing from
+                // it.
+                let outer_result = Self::unwind_frame_with_data(
+                    module,
+                    outer,
+                    address,
+                    rel_lookup_address,
+                    regs,
+                    cache,
+                    read_stack,
+                    diagnostics,
+                );
+                if let Ok(Some(unwind_result)) = outer_result {
+                    return Ok(Some(unwind_result));
+                }
+                let Some(fde_offset) = index.fde_offset_for_relative_address(rel_lookup_address)
+                else {
+                    return outer_result;
+                };
+                let outer_diagnostics = core::mem::replace(
+                    diagnostics,
+                    FrameDiagnostics::with_source(UnwindSource::GnuDebugdata),
+                );
+                let mut dwarf_unwinder = DwarfUnwinder::<_, A, _>::new(
+                    EndianSlice::new(debug_frame, LittleEndian),
+                    UnwindSectionType::DebugFrame,
+                    None,
+                    &mut cache.gimli_unwind_context,
+                    base_addresses.clone(),
+                    module.base_svma,
+                    module.address_size,
+                );
+                match dwarf_unwinder.try_unwind_frame_with_fde::<_, P::GimliEvaluationStorage<_>>(
+                    regs,
+                    is_first_frame,
+                    rel_lookup_address,
+                    fde_offset,
+                    read_stack,
+                    &mut diagnostics.conversion_error,
+                ) {
+                    Ok(Some(unwind_result)) => return Ok(Some(unwind_result)),
+                    // The .debug_frame didn't help either. Report what the outer unwind
+                    // data found, which covers most of the module.
+                    Ok(None) | Err(_) => {
+                        *diagnostics = outer_diagnostics;
+                        return outer_result;
+                    }
+                }
+            }
+            ModuleUnwindDataInternal::BreakpadSym { sym, index } => {
+                diagnostics.source = UnwindSource::Breakpad;
+                let unwinder = BreakpadUnwinder::<A>::new(sym, index);
+                unwinder.unwind_frame(rel_lookup_address, regs, is_first_frame, read_stack)?
+            }
+            ModuleUnwindDataInternal::WithSigreturnTrampoline { trampoline, inner } => {
+                // Signal return trampolines are recognized by their instructions, because
+                // some of them (in musl and in the vDSO) have no CFI. Return addresses are
+                // looked up at address - 1, but the signal handler returns to the first
+                // instruction of the trampoline, so we check the unadjusted address.
+                let rel_address = u64::from(rel_lookup_address)
+                    + (address.address() - address.address_for_lookup());
+                let trampoline_offset = (module.base_svma + rel_address)
+                    .checked_sub(trampoline.svma_range.start)
+                    .and_then(|offset| usize::try_from(offset).ok());
+                if let Some(trampoline_offset) = trampoline_offset {
+                    if is_linux_sigreturn_trampoline::<A>(&trampoline.data, trampoline_offset) {
+                        diagnostics.source = UnwindSource::SignalFrame;
+                        return Ok(Some(A::unwind_linux_signal_frame(regs, read_stack)?));
+                    }
+                }
+                return Self::unwind_frame_with_data(
+                    module,
+                    inner,
+                    address,
+                    rel_lookup_address,
+                    regs,
+                    cache,
+                    read_stack,
+                    diagnostics,
+                );
+            }
+            ModuleUnwindDataInternal::RuleTable { data } => {
+                diagnostics.source = UnwindSource::SerializedRuleTable;
+                let table = RuleTableData::parse(data).map_err(UnwinderError::RuleTable)?;
+                if table.arch() != A::CPU_ARCH {
+                    return Err(UnwinderError::RuleTableArchMismatch(table.arch()));
+                }
+                let rule = table
+                    .lookup(rel_lookup_address)
+                    .ok_or(UnwinderError::RuleTableCouldNotFindAddress)?;
+                UnwindResult::ExecRule(rule)
+            }
             ModuleUnwindDataInternal::None => return Err(UnwinderError::NoModuleUnwindData),
         };
-        Ok(unwind_result)
+        Ok(Some(unwind_result))
     }
 }
 
+/// Finds the instructions of a Linux signal return trampoline in `text`. The module's
+/// architecture isn't known yet when the module is created, so this looks for the
+/// trampolines of all architectures which have one.
+fn find_linux_sigreturn_trampoline(text: &[u8]) -> Option<Range<usize>> {
+    [
+        ArchX86_64::LINUX_SIGRETURN_TRAMPOLINE,
+        ArchAarch64::LINUX_SIGRETURN_TRAMPOLINE,
+    ]
+    .into_iter()
+    .find_map(|trampoline| {
+        let start = text
+            .windows(trampoline.len())
+            .position(|window| window == trampoline)?;
+        Some(start..start + trampoline.len())
+    })
+}
+
 /// The unwind data that should be used when unwinding addresses inside this module.
 /// Unwind data describes how to recover register values of the caller frame.
 ///
@@ -570,13 +1206,15 @@ impl<D: Deref<Target = [u8]>, A: Unwinding, P: AllocationPolicy> UnwinderInterna
 enum ModuleUnwindDataInternal<D> {
     /// Used on macOS, with mach-O binaries. Compact unwind info is in the `__unwind_info`
     /// section and is sometimes supplemented with DWARF CFI information in the `__eh_frame`
-    /// section. `__stubs` and `__stub_helper` ranges are used by the unwinder.
+    /// section. `__stubs` and `__stub_helper` ranges are used by the unwinder, and so is
+    /// the range of the `_sigtramp` function, if known.
     #[cfg(feature = "macho")]
     CompactUnwindInfoAndEhFrame {
         unwind_info: D,
         eh_frame: Option<D>,
         stubs_svma: Option<Range<u64>>,
         stub_helper_svma: Option<Range<u64>>,
+        sigtramp_svma: Option<Range<u64>>,
         base_addresses: crate::dwarf::BaseAddresses,
         text_data: Option<TextByteData<D>>,
     },
@@ -600,7 +1238,7 @@ enum ModuleUnwindDataInternal<D> {
     /// data type is added.
     DwarfCfiIndexAndDebugFrame {
         index: DwarfCfiIndex,
-        debug_frame: D,
+        debug_frame: MaybeDecompressed<D>,
         base_addresses: crate::dwarf::BaseAddresses,
     },
     /// Used with PE binaries (Windows).
@@ -611,6 +1249,67 @@ enum ModuleUnwindDataInternal<D> {
         xdata: Option<DataAtRvaRange<D>>,
         text: Option<DataAtRvaRange<D>>,
     },
+    /// Used with 32-bit ARM ELF binaries (Linux and Android), in the `.ARM.exidx` and
+    /// `.ARM.extab` sections. Contains a sorted table with ARM EHABI unwind instructions.
+    /// If `.debug_frame` is present, it is used for addresses which are not covered by
+    /// `.ARM.exidx`.
+    ArmExidx {
+        exidx: DataAtSvmaRange<D>,
+        extab: Option<DataAtSvmaRange<D>>,
+        debug_frame: Option<(DwarfCfiIndex, MaybeDecompressed<D>)>,
+        base_addresses: crate::dwarf::BaseAddresses,
+    },
+    /// Used with the Linux kernel (`vmlinux` and kernel modules) on x86_64, in the
+    /// `.orc_unwind_ip` and `.orc_unwind` sections. `.orc_unwind_ip` contains the sorted
+    /// instruction addresses and `.orc_unwind` the corresponding unwind entries. If the
+    /// addresses are not sorted, which is the case in the vmlinux files of kernels older
+    /// than 5.6, we create an index when the module is added.
+    ///
+    /// The addresses in `.orc_unwind_ip` need to be relocated already. This isn't the
+    /// case in kernel module `.ko` files, which have a `.rela.orc_unwind_ip` section;
+    /// their ORC tables are not used.
+    Orc {
+        orc_unwind_ip: DataAtSvmaRange<D>,
+        orc_unwind: D,
+        index: Option<OrcIndex>,
+        format: OrcFormat,
+    },
+    /// Used with ELF binaries (Linux and friends) which have an `.sframe` section. SFrame
+    /// only describes the CFA, return address and frame pointer, which is all we need, and
+    /// its FDE table is sorted so that it can be searched without building an index.
+    /// Addresses which are not covered by `.sframe` use the DWARF CFI in `fallback`.
+    SFrame {
+        sframe: DataAtSvmaRange<D>,
+        fallback: Box<ModuleUnwindDataInternal<D>>,
+    },
+    /// Used with ELF binaries which carry MiniDebugInfo, an xz-compressed ELF image in
+    /// the `.gnu_debugdata` section. The `.debug_frame` of that image is decompressed when
+    /// the module is added. The unwind data in `outer` is used first, and the
+    /// `.debug_frame` is used for the addresses which `outer` doesn't cover or fails to
+    /// unwind.
+    #[cfg(feature = "gnu-debugdata")]
+    GnuDebugdata {
+        outer: Box<ModuleUnwindDataInternal<D>>,
+        index: DwarfCfiIndex,
+        debug_frame: Vec<u8>,
+        base_addresses: crate::dwarf::BaseAddresses,
+    },
+    /// Used for modules whose unwind information comes from a Breakpad symbol file, in
+    /// its `STACK CFI` and `STACK WIN` records. We index the records when the module is
+    /// added.
+    BreakpadSym { sym: D, index: BreakpadIndex },
+    /// Used with ELF binaries which contain a Linux signal return trampoline, i.e. libc
+    /// and the vDSO. `trampoline` has a copy of the instruction bytes of `__restore_rt` or
+    /// `__kernel_rt_sigreturn`, which are used to recognize the trampoline, and the unwind
+    /// data in `inner` is used for all other addresses.
+    WithSigreturnTrampoline {
+        trampoline: DataAtSvmaRange<Vec<u8>>,
+        inner: Box<ModuleUnwindDataInternal<D>>,
+    },
+    /// Used for modules whose unwind information comes from a
+    /// [`SerializedRuleTable`](crate::SerializedRuleTable). The rules are looked up right
+    /// inside the serialized data.
+    RuleTable { data: D },
     /// No unwind information is used. Unwinding in this module will use a fallback rule
     /// (usually frame pointer unwinding).
     None,
@@ -618,13 +1317,258 @@ enum ModuleUnwindDataInternal<D> {
 
 impl<D: Deref<Target = [u8]>> ModuleUnwindDataInternal<D> {
     fn new(section_info: &mut impl ModuleSectionInfo<D>) -> Self {
+        let unwind_data = Self::new_from_unwind_sections(section_info);
+        #[cfg(feature = "gnu-debugdata")]
+        let unwind_data = match section_info.section_data(b".gnu_debugdata") {
+            Some(gnu_debugdata) => unwind_data.with_gnu_debugdata(&gnu_debugdata, section_info),
+            None => unwind_data,
+        };
+        unwind_data.with_sigreturn_trampoline(section_info)
+    }
+
+    /// Compiles the module's unwind information into a [`RuleTable`] with the rules for
+    /// return addresses. Fails for kinds of unwind information which can't be expressed
+    /// as unwind rules, or if the unwind information could not be parsed.
+    fn compile_rule_table<A: Unwinding>(
+        &self,
+        base_svma: u64,
+        address_size: u8,
+        uncovered_rule: Option<A::UnwindRule>,
+    ) -> Result<RuleTable, CompileRuleTableError> {
+        match self {
+            #[cfg(feature = "macho")]
+            ModuleUnwindDataInternal::CompactUnwindInfoAndEhFrame {
+                unwind_info,
+                eh_frame,
+                stubs_svma,
+                stub_helper_svma,
+                sigtramp_svma,
+                base_addresses,
+                text_data,
+            } => {
+                // The FDE which an opcode refers to is the one which covers the function,
+                // so the rules for those functions can be taken from the compiled
+                // __eh_frame.
+                let dwarf_rules = eh_frame.as_ref().and_then(|eh_frame| {
+                    let mut eh_frame = EhFrame::from(EndianSlice::new(eh_frame, LittleEndian));
+                    eh_frame.set_address_size(address_size);
+                    compile_rule_table::<A, _, _>(
+                        eh_frame,
+                        base_addresses,
+                        base_svma,
+                        uncovered_rule,
+                    )
+                    .ok()
+                });
+                compact_unwind_info_unwinder::<A, D>(
+                    unwind_info,
+                    text_data,
+                    [stubs_svma, stub_helper_svma, sigtramp_svma],
+                    base_svma,
+                )
+                .compile_rule_table(dwarf_rules.as_ref())
+                .map_err(|err| match err {
+                    CompactUnwindInfoUnwinderError::ArmUnsupported
+                    | CompactUnwindInfoUnwinderError::Riscv64Unsupported
+                    | CompactUnwindInfoUnwinderError::X86Unsupported => {
+                        CompileRuleTableError::UnsupportedUnwindInfo
+                    }
+                    _ => CompileRuleTableError::InvalidUnwindInfo,
+                })
+            }
+            ModuleUnwindDataInternal::EhFrameHdrAndEhFrame {
+                eh_frame,
+                base_addresses,
+                ..
+            }
+            | ModuleUnwindDataInternal::DwarfCfiIndexAndEhFrame {
+                eh_frame,
+                base_addresses,
+                ..
+            } => {
+                let mut eh_frame = EhFrame::from(EndianSlice::new(eh_frame, LittleEndian));
+                eh_frame.set_address_size(address_size);
+                compile_rule_table::<A, _, _>(eh_frame, base_addresses, base_svma, uncovered_rule)
+                    .map_err(|_| CompileRuleTableError::InvalidUnwindInfo)
+            }
+            ModuleUnwindDataInternal::DwarfCfiIndexAndDebugFrame {
+                debug_frame,
+                base_addresses,
+                ..
+            } => {
+                let mut debug_frame = DebugFrame::from(EndianSlice::new(debug_frame, LittleEndian));
+                debug_frame.set_address_size(address_size);
+                compile_rule_table::<A, _, _>(
+                    debug_frame,
+                    base_addresses,
+                    base_svma,
+                    uncovered_rule,
+                )
+                .map_err(|_| CompileRuleTableError::InvalidUnwindInfo)
+            }
+            #[cfg(feature = "pe")]
+            ModuleUnwindDataInternal::PeUnwindInfo {
+                pdata,
+                rdata,
+                xdata,
+                text,
+            } => <A as PeUnwinding>::compile_rule_table(crate::pe::PeSections {
+                pdata,
+                rdata: rdata.as_ref(),
+                xdata: xdata.as_ref(),
+                text: text.as_ref(),
+            })
+            .map_err(|err| match err {
+                PeUnwinderError::ArmUnsupported
+                | PeUnwinderError::Riscv64Unsupported
+                | PeUnwinderError::X86Unsupported => CompileRuleTableError::UnsupportedUnwindInfo,
+                _ => CompileRuleTableError::InvalidUnwindInfo,
+            }),
+            ModuleUnwindDataInternal::SFrame { sframe, fallback } => {
+                let fallback_rules =
+                    match fallback.compile_rule_table::<A>(base_svma, address_size, uncovered_rule)
+                    {
+                        Err(CompileRuleTableError::UnsupportedUnwindInfo) => {
+                            RuleTableBuilder::new().finish()
+                        }
+                        result => result?,
+                    };
+                // The .sframe rows are preferred for the functions that they cover, unless
+                // they are for a different arch.
+                let sframe_rules = SFrameUnwinder::<A>::new(sframe, base_svma)
+                    .and_then(|unwinder| unwinder.compile_rule_table());
+                match sframe_rules {
+                    Ok((sframe_rules, ranges)) => {
+                        Ok(fallback_rules.overlay(&sframe_rules, &ranges))
+                    }
+                    Err(
+                        SFrameUnwinderError::UnsupportedAbi(_)
+                        | SFrameUnwinderError::UnsupportedArch,
+                    ) => Ok(fallback_rules),
+                    Err(_) => Err(CompileRuleTableError::InvalidUnwindInfo),
+                }
+            }
+            #[cfg(feature = "gnu-debugdata")]
+            ModuleUnwindDataInternal::GnuDebugdata { outer, .. } => {
+                outer.compile_rule_table::<A>(base_svma, address_size, uncovered_rule)
+            }
+            ModuleUnwindDataInternal::WithSigreturnTrampoline { trampoline, inner } => {
+                let rules =
+                    inner.compile_rule_table::<A>(base_svma, address_size, uncovered_rule)?;
+                // Signal frames can't be unwound with a rule. The lookup address of the
+                // trampoline's return address is one byte before the trampoline.
+                let relative_range = |svma_range: &Range<u64>| {
+                    let start = svma_range.start.checked_sub(base_svma)?.checked_sub(1)?;
+                    let end = svma_range.end.checked_sub(base_svma)?;
+                    Some(u32::try_from(start).ok()?..u32::try_from(end).ok()?)
+                };
+                Ok(match relative_range(&trampoline.svma_range) {
+                    Some(range) => rules.overlay(&RuleTableBuilder::new().finish(), &[range]),
+                    None => rules,
+                })
+            }
+            _ => Err(CompileRuleTableError::UnsupportedUnwindInfo),
+        }
+    }
+
+    /// Wraps the unwind data so that the Linux signal return trampoline can be recognized,
+    /// if the module has one. Only the trampoline's bytes are kept, not the entire `.text`
+    /// section.
+    fn with_sigreturn_trampoline(self, section_info: &mut impl ModuleSectionInfo<D>) -> Self {
+        match self {
+            // Mach-O and PE modules don't contain Linux signal return trampolines. Neither
+            // does the Linux kernel.
+            #[cfg(feature = "macho")]
+            ModuleUnwindDataInternal::CompactUnwindInfoAndEhFrame { .. } => return self,
+            #[cfg(feature = "pe")]
+            ModuleUnwindDataInternal::PeUnwindInfo { .. } => return self,
+            ModuleUnwindDataInternal::Orc { .. } => return self,
+            _ => {}
+        }
+        let symbol_svma_range = section_info
+            .symbol_svma_range(b"__restore_rt")
+            .or_else(|| section_info.symbol_svma_range(b"__kernel_rt_sigreturn"));
+        let (Some(text_svma_range), Some(text)) = (
+            section_info.section_svma_range(b".text"),
+            section_info.section_data(b".text"),
+        ) else {
+            return self;
+        };
+        let svma_range = match symbol_svma_range {
+            Some(mut svma_range) => {
+                if svma_range.is_empty() {
+                    // The symbol has no size. The trampoline's two instructions are
+                    // shorter than this.
+                    svma_range.end = svma_range.start.saturating_add(16);
+                }
+                svma_range
+            }
+            None => {
+                // musl's __restore_rt is a hidden symbol, which only exists in .symtab and
+                // is gone from a stripped libc.so. Look for its instructions instead.
+                let Some(range) = find_linux_sigreturn_trampoline(&text) else {
+                    return self;
+                };
+                text_svma_range.start + range.start as u64..text_svma_range.start + range.end as u64
+            }
+        };
+        let start = svma_range.start.max(text_svma_range.start);
+        let end = svma_range.end.min(text_svma_range.end);
+        let bytes = usize::try_from(start - text_svma_range.start)
+            .ok()
+            .zip(usize::try_from(end.saturating_sub(text_svma_range.start)).ok())
+            .and_then(|(start, end)| text.get(start..end));
+        let Some(bytes) = bytes.filter(|bytes| !bytes.is_empty()) else {
+            return self;
+        };
+        ModuleUnwindDataInternal::WithSigreturnTrampoline {
+            trampoline: DataAtSvmaRange {
+                data: bytes.to_vec(),
+                svma_range: start..end,
+            },
+            inner: Box::new(self),
+        }
+    }
+
+    /// Wraps the unwind data so that the `.debug_frame` from the MiniDebugInfo in
+    /// `.gnu_debugdata` is used for addresses which the outer unwind data doesn't cover or
+    /// fails to unwind.
+    #[cfg(feature = "gnu-debugdata")]
+    fn with_gnu_debugdata(
+        self,
+        gnu_debugdata: &[u8],
+        section_info: &mut impl ModuleSectionInfo<D>,
+    ) -> Self {
+        let Some(debug_frame) = crate::gnu_debugdata::debug_frame_from_gnu_debugdata(gnu_debugdata)
+        else {
+            return self;
+        };
+        // The MiniDebugInfo image has the same section addresses as the module itself,
+        // so the outer module's sections can be used for the base addresses.
+        match DwarfCfiIndex::try_new_debug_frame(&debug_frame, section_info) {
+            Ok(index) => ModuleUnwindDataInternal::GnuDebugdata {
+                outer: Box::new(self),
+                index,
+                debug_frame,
+                base_addresses: crate::dwarf::base_addresses_for_sections(section_info),
+            },
+            Err(_) => self,
+        }
+    }
+
+    fn new_from_unwind_sections(section_info: &mut impl ModuleSectionInfo<D>) -> Self {
         use crate::dwarf::base_addresses_for_sections;
 
+        if let Some(data) = section_info.section_data(RULE_TABLE_SECTION) {
+            return ModuleUnwindDataInternal::RuleTable { data };
+        }
+
         #[cfg(feature = "macho")]
         if let Some(unwind_info) = section_info.section_data(b"__unwind_info") {
             let eh_frame = section_info.section_data(b"__eh_frame");
             let stubs = section_info.section_svma_range(b"__stubs");
             let stub_helper = section_info.section_svma_range(b"__stub_helper");
+            let sigtramp = section_info.symbol_svma_range(b"_sigtramp");
             // Get the bytes of the executable code (instructions).
             //
             // In mach-O objects, executable code is stored in the `__TEXT` segment, which contains
@@ -649,6 +1593,7 @@ impl<D: Deref<Target = [u8]>> ModuleUnwindDataInternal<D> {
                 eh_frame,
                 stubs_svma: stubs,
                 stub_helper_svma: stub_helper,
+                sigtramp_svma: sigtramp,
                 base_addresses: base_addresses_for_sections(section_info),
                 text_data,
             };
@@ -674,6 +1619,81 @@ impl<D: Deref<Target = [u8]>> ModuleUnwindDataInternal<D> {
             };
         }
 
+        if let Some(exidx) = section_info.section_data(b".ARM.exidx") {
+            if let Some(exidx_svma) = section_info.section_svma_range(b".ARM.exidx") {
+                let extab = match (
+                    section_info.section_data(b".ARM.extab"),
+                    section_info.section_svma_range(b".ARM.extab"),
+                ) {
+                    (Some(data), Some(svma_range)) => Some(DataAtSvmaRange { data, svma_range }),
+                    _ => None,
+                };
+                let debug_frame = debug_frame_data(section_info).and_then(|data| {
+                    let index = DwarfCfiIndex::try_new_debug_frame(&data, section_info).ok()?;
+                    Some((index, data))
+                });
+                return ModuleUnwindDataInternal::ArmExidx {
+                    exidx: DataAtSvmaRange {
+                        data: exidx,
+                        svma_range: exidx_svma,
+                    },
+                    extab,
+                    debug_frame,
+                    base_addresses: base_addresses_for_sections(section_info),
+                };
+            }
+        }
+
+        // In relocatable objects, i.e. the `.ko` files of kernel modules, the entries of
+        // .orc_unwind_ip are only filled in by relocations when the module is loaded. We
+        // don't apply relocations, so the ORC tables of such files are not used.
+        let orc_is_relocated = section_info
+            .section_svma_range(b".rela.orc_unwind_ip")
+            .is_none();
+        if let Some(orc_unwind_ip) = orc_is_relocated
+            .then(|| section_info.section_data(b".orc_unwind_ip"))
+            .flatten()
+        {
+            if let (Some(svma_range), Some(orc_unwind)) = (
+                section_info.section_svma_range(b".orc_unwind_ip"),
+                section_info.section_data(b".orc_unwind"),
+            ) {
+                let orc_unwind_ip = DataAtSvmaRange {
+                    data: orc_unwind_ip,
+                    svma_range,
+                };
+                // .orc_header was added in Linux 6.4, together with the new entry layout.
+                let format = match section_info.section_data(b".orc_header") {
+                    Some(_) => OrcFormat::V2,
+                    None => OrcFormat::V1,
+                };
+                return ModuleUnwindDataInternal::Orc {
+                    index: OrcIndex::new_if_unsorted(&orc_unwind_ip),
+                    orc_unwind_ip,
+                    orc_unwind,
+                    format,
+                };
+            }
+        }
+
+        let sframe = section_info.section_data(b".sframe").and_then(|data| {
+            let svma_range = section_info.section_svma_range(b".sframe")?;
+            SFrameHeader::parse(&data).ok()?;
+            Some(DataAtSvmaRange { data, svma_range })
+        });
+        let dwarf = Self::new_dwarf(section_info);
+        match sframe {
+            Some(sframe) => ModuleUnwindDataInternal::SFrame {
+                sframe,
+                fallback: Box::new(dwarf),
+            },
+            None => dwarf,
+        }
+    }
+
+    fn new_dwarf(section_info: &mut impl ModuleSectionInfo<D>) -> Self {
+        use crate::dwarf::base_addresses_for_sections;
+
         if let Some(eh_frame) = section_info
             .section_data(b".eh_frame")
             .or_else(|| section_info.section_data(b"__eh_frame"))
@@ -697,7 +1717,7 @@ impl<D: Deref<Target = [u8]>> ModuleUnwindDataInternal<D> {
                     Err(_) => ModuleUnwindDataInternal::None,
                 }
             }
-        } else if let Some(debug_frame) = section_info.section_data(b".debug_frame") {
+        } else if let Some(debug_frame) = debug_frame_data(section_info) {
             match DwarfCfiIndex::try_new_debug_frame(&debug_frame, section_info) {
                 Ok(index) => ModuleUnwindDataInternal::DwarfCfiIndexAndDebugFrame {
                     index,
@@ -735,6 +1755,35 @@ struct TextByteData<D> {
     pub svma_range: Range<u64>,
 }
 
+/// Creates the unwinder for a module's `__unwind_info`. `ranges` are the SVMA ranges of
+/// `__stubs`, `__stub_helper` and `_sigtramp`.
+#[cfg(feature = "macho")]
+fn compact_unwind_info_unwinder<'a, A: CompactUnwindInfoUnwinding, D: Deref<Target = [u8]>>(
+    unwind_info: &'a D,
+    text_data: &'a Option<TextByteData<D>>,
+    ranges: [&Option<Range<u64>>; 3],
+    base_svma: u64,
+) -> CompactUnwindInfoUnwinder<'a, A> {
+    let text_bytes = text_data.as_ref().and_then(|data| {
+        let offset_from_base = u32::try_from(data.svma_range.start.checked_sub(base_svma)?).ok()?;
+        Some(TextBytes::new(offset_from_base, &data.bytes[..]))
+    });
+    let [stubs_range, stub_helper_range, sigtramp_range] = ranges.map(|range| match range {
+        Some(range) => (
+            (range.start - base_svma) as u32,
+            (range.end - base_svma) as u32,
+        ),
+        None => (0, 0),
+    });
+    CompactUnwindInfoUnwinder::new(
+        &unwind_info[..],
+        text_bytes,
+        stubs_range,
+        stub_helper_range,
+        sigtramp_range,
+    )
+}
+
 /// Information about a module that is loaded in a process. You might know this under a
 /// different name, for example: (Shared) library, binary image, DSO ("Dynamic shared object")
 ///
@@ -749,8 +1798,7 @@ struct TextByteData<D> {
 ///    a file or a different process, for example. It just needs to provide a slice of
 ///    bytes via its `Deref` implementation.
 pub struct Module<D> {
-    /// The name or file path of the module. Unused, it's just there for easier debugging.
-    #[allow(unused)]
+    /// The name or file path of the module.
     name: String,
     /// The address range where this module is mapped into the process.
     avma_range: Range<u64>,
@@ -759,8 +1807,21 @@ pub struct Module<D> {
     base_avma: u64,
     /// The base address of this module, according to the module.
     base_svma: u64,
+    /// The size of a code address in this module, in bytes.
+    address_size: u8,
     /// The unwind data that should be used for unwinding addresses from this module.
     unwind_data: Arc<ModuleUnwindDataInternal<D>>,
+    /// Identifies the module's unwind information across unwinders, for caches with
+    /// [`CacheKeyMode::ModuleRelative`].
+    cache_key: Option<u64>,
+    /// Identifies this module's rules in caches with [`CacheKeyMode::AbsoluteAddress`].
+    /// Assigned by the unwinder when the module is added.
+    generation: AtomicU64,
+    /// Whether `rule_table` should be compiled when the module is added.
+    precompile_rules: bool,
+    /// The rules which were compiled from the module's unwind information, and the arch
+    /// which they were compiled for.
+    rule_table: Option<(CpuArch, Arc<RuleTable>)>,
 }
 
 impl<D> Clone for Module<D> {
@@ -770,7 +1831,12 @@ impl<D> Clone for Module<D> {
             avma_range: self.avma_range.clone(),
             base_avma: self.base_avma,
             base_svma: self.base_svma,
+            address_size: self.address_size,
             unwind_data: self.unwind_data.clone(),
+            cache_key: self.cache_key,
+            generation: AtomicU64::new(self.generation.load(Ordering::Relaxed)),
+            precompile_rules: self.precompile_rules,
+            rule_table: self.rule_table.clone(),
         }
     }
 }
@@ -795,6 +1861,14 @@ pub trait ModuleSectionInfo<D> {
     /// This is used to convert between SVMAs and relative addresses.
     fn base_svma(&self) -> u64;
 
+    /// Return the size of a code address in this module, in bytes: 8 for 64-bit modules
+    /// and 4 for 32-bit modules.
+    ///
+    /// This is used when parsing DWARF CFI, which can contain pointer-sized values.
+    fn address_size(&self) -> u8 {
+        8
+    }
+
     /// Get the given section's memory range, as stated in the module.
     fn section_svma_range(&mut self, name: &[u8]) -> Option<Range<u64>>;
 
@@ -810,6 +1884,19 @@ pub trait ModuleSectionInfo<D> {
     fn segment_data(&mut self, _name: &[u8]) -> Option<D> {
         None
     }
+
+    /// Get the given symbol's memory range, as stated in the module.
+    ///
+    /// This is only called for functions which need special treatment during unwinding,
+    /// i.e. `_sigtramp` in macOS's `libsystem_platform.dylib`, and the Linux signal
+    /// return trampolines `__restore_rt` in libc and `__kernel_rt_sigreturn` in the vDSO.
+    /// Framehop also recognizes `_sigtramp` by its instructions, if the text bytes are
+    /// available. The Linux trampolines are recognized by their instructions within the
+    /// symbol range, which needs the `.text` data. If neither symbol is available, the
+    /// trampoline's instructions are searched for in `.text` when the module is created.
+    fn symbol_svma_range(&mut self, _name: &[u8]) -> Option<Range<u64>> {
+        None
+    }
 }
 
 /// Explicit addresses and data of various sections in the module. This implements
@@ -838,7 +1925,8 @@ pub struct ExplicitModuleSectionInfo<D> {
     /// The data of the `__text` or `.text` section. This is where most of the compiled code is
     /// stored. For mach-O binaries, this does not need to be supplied if `text_segment` is supplied.
     ///
-    /// This is used to handle function prologues and epilogues in some cases.
+    /// This is used to handle function prologues and epilogues in some cases, and to
+    /// recognize Linux signal return trampolines, see `rt_sigreturn_svma`.
     pub text: Option<D>,
     /// The address range of the mach-O `__stubs` section. Contains small pieces of
     /// executable code for calling imported functions. Code inside this section is not
@@ -876,10 +1964,57 @@ pub struct ExplicitModuleSectionInfo<D> {
     pub eh_frame_hdr: Option<D>,
     /// The data of the `.debug_frame` section. The related address range is not needed.
     pub debug_frame: Option<D>,
+    /// The data of the `.gnu_debugdata` section (MiniDebugInfo). This is only used if the
+    /// `gnu-debugdata` feature is enabled.
+    pub gnu_debugdata: Option<D>,
     /// The address range of the `__TEXT` segment of mach-O binaries, if available.
     pub text_segment_svma: Option<Range<u64>>,
     /// The data of the `__TEXT` segment of mach-O binaries, if available.
     pub text_segment: Option<D>,
+    /// The address range of the `_sigtramp` function in macOS's `libsystem_platform.dylib`,
+    /// if known. Unwinding through `_sigtramp` recovers the registers of the code which was
+    /// interrupted by the signal. `_sigtramp` is also recognized without this range if
+    /// the text bytes are supplied.
+    pub sigtramp_svma: Option<Range<u64>>,
+    /// The address range of the Linux signal return trampoline, i.e. of the `__restore_rt`
+    /// function in libc or of `__kernel_rt_sigreturn` in the vDSO, if the module has one.
+    /// The trampoline's instructions are then copied from `text`, so that unwinding
+    /// through signal handler frames works even if the trampoline has no CFI, like in
+    /// musl. If this is `None`, `text` is searched for the trampoline's instructions
+    /// instead. The `.text` data is not kept for modules without a trampoline.
+    pub rt_sigreturn_svma: Option<Range<u64>>,
+    /// The address range of the `.ARM.exidx` section of 32-bit ARM ELF binaries. This is
+    /// needed to resolve the relative offsets inside the section.
+    pub arm_exidx_svma: Option<Range<u64>>,
+    /// The data of the `.ARM.exidx` section of 32-bit ARM ELF binaries.
+    pub arm_exidx: Option<D>,
+    /// The address range of the `.ARM.extab` section of 32-bit ARM ELF binaries. This is
+    /// needed to resolve the relative offsets in `.ARM.exidx`.
+    pub arm_extab_svma: Option<Range<u64>>,
+    /// The data of the `.ARM.extab` section of 32-bit ARM ELF binaries.
+    pub arm_extab: Option<D>,
+    /// The address range of the `.sframe` section of ELF binaries. This is needed to
+    /// resolve the function start addresses, which are relative to the section.
+    pub sframe_svma: Option<Range<u64>>,
+    /// The data of the `.sframe` section of ELF binaries.
+    pub sframe: Option<D>,
+    /// The address range of the `.orc_unwind_ip` section of the Linux kernel. This is
+    /// needed to resolve the relative addresses inside the section.
+    ///
+    /// The ORC sections need to come from `vmlinux`, or from a kernel module whose
+    /// relocations have been applied, for example the tables of a loaded module in kernel
+    /// memory. In kernel module `.ko` files, the entries of `.orc_unwind_ip` are filled in
+    /// by relocations when the module is loaded, and framehop doesn't apply them.
+    pub orc_unwind_ip_svma: Option<Range<u64>>,
+    /// The data of the `.orc_unwind_ip` section of the Linux kernel.
+    pub orc_unwind_ip: Option<D>,
+    /// The data of the `.orc_unwind` section of the Linux kernel.
+    pub orc_unwind: Option<D>,
+    /// The data of the `.orc_header` section of the Linux kernel. Only its presence
+    /// matters: it identifies the ORC entry layout used since Linux 6.4.
+    pub orc_header: Option<D>,
+    /// The size of a code address, in bytes. `None` means 8, i.e. a 64-bit module.
+    pub address_size: Option<u8>,
 }
 
 impl<D> ModuleSectionInfo<D> for ExplicitModuleSectionInfo<D>
@@ -890,6 +2025,10 @@ where
         self.base_svma
     }
 
+    fn address_size(&self) -> u8 {
+        self.address_size.unwrap_or(8)
+    }
+
     fn section_svma_range(&mut self, name: &[u8]) -> Option<Range<u64>> {
         match name {
             b"__text" | b".text" => self.text_svma.clone(),
@@ -898,6 +2037,10 @@ where
             b"__eh_frame" | b".eh_frame" => self.eh_frame_svma.clone(),
             b"__eh_frame_hdr" | b".eh_frame_hdr" => self.eh_frame_hdr_svma.clone(),
             b"__got" | b".got" => self.got_svma.clone(),
+            b".ARM.exidx" => self.arm_exidx_svma.clone(),
+            b".ARM.extab" => self.arm_extab_svma.clone(),
+            b".sframe" => self.sframe_svma.clone(),
+            b".orc_unwind_ip" => self.orc_unwind_ip_svma.clone(),
             _ => None,
         }
     }
@@ -908,6 +2051,13 @@ where
             b"__eh_frame" | b".eh_frame" => self.eh_frame.take(),
             b"__eh_frame_hdr" | b".eh_frame_hdr" => self.eh_frame_hdr.take(),
             b"__debug_frame" | b".debug_frame" => self.debug_frame.take(),
+            b".gnu_debugdata" => self.gnu_debugdata.take(),
+            b".ARM.exidx" => self.arm_exidx.take(),
+            b".ARM.extab" => self.arm_extab.take(),
+            b".sframe" => self.sframe.take(),
+            b".orc_unwind_ip" => self.orc_unwind_ip.take(),
+            b".orc_unwind" => self.orc_unwind.take(),
+            b".orc_header" => self.orc_header.take(),
             _ => None,
         }
     }
@@ -923,6 +2073,13 @@ where
             _ => None,
         }
     }
+    fn symbol_svma_range(&mut self, name: &[u8]) -> Option<Range<u64>> {
+        match name {
+            b"_sigtramp" => self.sigtramp_svma.clone(),
+            b"__restore_rt" | b"__kernel_rt_sigreturn" => self.rt_sigreturn_svma.clone(),
+            _ => None,
+        }
+    }
 }
 
 impl<D: Deref<Target = [u8]>> Module<D> {
@@ -939,7 +2096,38 @@ impl<D: Deref<Target = [u8]>> Module<D> {
             avma_range,
             base_avma,
             base_svma: section_info.base_svma(),
+            address_size: section_info.address_size(),
             unwind_data: Arc::new(unwind_data),
+            cache_key: None,
+            generation: AtomicU64::new(0),
+            precompile_rules: false,
+            rule_table: None,
+        }
+    }
+
+    /// Creates a module whose unwind information comes from a Breakpad symbol file
+    /// (`.sym`), for example when processing minidumps without the original binaries.
+    ///
+    /// The `STACK CFI` and `STACK WIN` records in `sym` are used for unwinding. Their
+    /// addresses are relative to `base_avma`.
+    pub fn new_from_breakpad_sym(
+        name: String,
+        avma_range: core::ops::Range<u64>,
+        base_avma: u64,
+        sym: D,
+    ) -> Self {
+        let index = BreakpadIndex::new(&sym);
+        Self {
+            name,
+            avma_range,
+            base_avma,
+            base_svma: 0,
+            address_size: index.address_size(),
+            unwind_data: Arc::new(ModuleUnwindDataInternal::BreakpadSym { sym, index }),
+            cache_key: None,
+            generation: AtomicU64::new(0),
+            precompile_rules: false,
+            rule_table: None,
         }
     }
 
@@ -951,7 +2139,143 @@ impl<D: Deref<Target = [u8]>> Module<D> {
         self.base_avma
     }
 
+    /// The base address of this module in its own address space. Addresses relative to
+    /// this are the same as addresses relative to `base_avma`.
+    pub fn base_svma(&self) -> u64 {
+        self.base_svma
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Sets a key which identifies this module's unwind information, so that unwind
+    /// rules for its addresses can be shared between unwinders with caches which use
+    /// [`CacheKeyMode::ModuleRelative`](crate::CacheKeyMode::ModuleRelative). For
+    /// example, this lets the unwinders for multiple processes which load the same
+    /// library share cached rules, even if the library is mapped at different addresses.
+    ///
+    /// Modules with the same key must have the same unwind information. A good key is
+    /// a hash of the module's build ID or debug ID.
+    pub fn with_cache_key(mut self, cache_key: u64) -> Self {
+        self.cache_key = Some(cache_key);
+        self
+    }
+
+    /// The key which was set with [`with_cache_key`](Module::with_cache_key).
+    pub fn cache_key(&self) -> Option<u64> {
+        self.cache_key
+    }
+
+    /// Compile the module's unwind information into a sorted table of unwind rules when
+    /// the module is added to an unwinder.
+    ///
+    /// This makes adding the module slower, but cache misses for addresses in the module
+    /// then only need a binary search in the table, instead of evaluating the unwind
+    /// information. The same kinds of unwind information as for
+    /// [`serialize_rule_table`](Module::serialize_rule_table) are supported. For
+    /// `__unwind_info` and PE unwind information, the table is only used for return
+    /// addresses. Entries which can't be expressed as unwind rules are still evaluated
+    /// when they're needed.
+    ///
+    /// If the unwind information can't be compiled, the module is unwound as if this
+    /// hadn't been called. Use [`precompile_rules`](Module::precompile_rules) to find out
+    /// whether it worked.
+    pub fn with_precompiled_rules(mut self) -> Self {
+        self.precompile_rules = true;
+        self
+    }
+
+    /// Compile the module's unwind information into a sorted table of unwind rules for
+    /// the CPU architecture `arch` right away, like
+    /// [`with_precompiled_rules`](Module::with_precompiled_rules) does when the module is
+    /// added to an unwinder.
+    ///
+    /// The table is used by unwinders for `arch`. Fails with
+    /// [`CompileRuleTableError::UnsupportedUnwindInfo`] for `.ARM.exidx`, ORC tables and
+    /// Breakpad symbol files, and if the unwind information could not be parsed. Modules
+    /// which were created from a [`SerializedRuleTable`](crate::SerializedRuleTable) for
+    /// `arch` already are a table of unwind rules, and always succeed.
+    pub fn precompile_rules(&mut self, arch: CpuArch) -> Result<(), CompileRuleTableError> {
+        if let ModuleUnwindDataInternal::RuleTable { data } = &*self.unwind_data {
+            let table =
+                RuleTableData::parse(data).map_err(CompileRuleTableError::InvalidRuleTable)?;
+            if table.arch() != arch {
+                return Err(CompileRuleTableError::ArchMismatch(table.arch()));
+            }
+            return Ok(());
+        }
+        let table = match arch {
+            CpuArch::X86_64 => self.compile_rule_table::<ArchX86_64>(None),
+            CpuArch::Aarch64 => self.compile_rule_table::<ArchAarch64>(None),
+            CpuArch::Arm => self.compile_rule_table::<ArchArm>(None),
+            CpuArch::X86 => self.compile_rule_table::<ArchX86>(None),
+            CpuArch::Riscv64 => self.compile_rule_table::<ArchRiscv64>(None),
+        }?;
+        self.rule_table = Some((arch, Arc::new(table)));
+        Ok(())
+    }
+
+    /// Serializes the module's unwind information as a table of unwind rules for the CPU
+    /// architecture `arch`, so that it can be used without the module's binary. Load the
+    /// table with [`SerializedRuleTable`](crate::SerializedRuleTable).
+    ///
+    /// This supports DWARF CFI from `.eh_frame` and `.debug_frame`, `.sframe`,
+    /// `__unwind_info` and PE unwind information, and fails with
+    /// [`CompileRuleTableError::UnsupportedUnwindInfo`] for `.ARM.exidx`, ORC tables and
+    /// Breakpad symbol files. For modules with `.gnu_debugdata`, the MiniDebugInfo
+    /// `.debug_frame` is not serialized.
+    ///
+    /// The table has the rules for return addresses. For `__unwind_info` and PE unwind
+    /// information, the unwinder usually analyzes the instructions of the first frame if
+    /// it is in a function prologue or epilogue; the table has the rule for the function
+    /// body there. Unwind information which can't be expressed as unwind rules, stub
+    /// functions and signal handler trampolines are not included in the table. The
+    /// unwinder uses its fallback rule for them, so it may get different results than
+    /// with the original module there.
+    ///
+    /// Modules which were created from a [`SerializedRuleTable`](crate::SerializedRuleTable)
+    /// for `arch` return a copy of the table.
+    pub fn serialize_rule_table(&self, arch: CpuArch) -> Result<Vec<u8>, CompileRuleTableError> {
+        if let ModuleUnwindDataInternal::RuleTable { data } = &*self.unwind_data {
+            let table =
+                RuleTableData::parse(data).map_err(CompileRuleTableError::InvalidRuleTable)?;
+            if table.arch() != arch {
+                return Err(CompileRuleTableError::ArchMismatch(table.arch()));
+            }
+            return Ok(data.to_vec());
+        }
+        let table = match arch {
+            CpuArch::X86_64 => self.complete_rule_table::<ArchX86_64>(),
+            CpuArch::Aarch64 => self.complete_rule_table::<ArchAarch64>(),
+            CpuArch::Arm => self.complete_rule_table::<ArchArm>(),
+            CpuArch::X86 => self.complete_rule_table::<ArchX86>(),
+            CpuArch::Riscv64 => self.complete_rule_table::<ArchRiscv64>(),
+        }?;
+        Ok(table.serialize(arch, self.base_svma, self.address_size))
+    }
+
+    /// Compiles a rule table which also covers the addresses after the end of each FDE.
+    fn complete_rule_table<A: Unwinding>(&self) -> Result<RuleTable, CompileRuleTableError> {
+        self.compile_rule_table::<A>(Some(A::rule_if_uncovered_by_fde()))
+    }
+
+    fn compile_rule_table<A: Unwinding>(
+        &self,
+        uncovered_rule: Option<A::UnwindRule>,
+    ) -> Result<RuleTable, CompileRuleTableError> {
+        self.unwind_data
+            .compile_rule_table::<A>(self.base_svma, self.address_size, uncovered_rule)
+    }
+
+    fn has_precompiled_rules(&self, arch: CpuArch) -> bool {
+        matches!(&self.rule_table, Some((table_arch, _)) if *table_arch == arch)
+    }
+
+    fn precompiled_rule<A: Unwinding>(&self, relative_address: u32) -> Option<A::UnwindRule> {
+        match &self.rule_table {
+            Some((arch, table)) if *arch == A::CPU_ARCH => table.lookup(relative_address),
+            _ => None,
+        }
+    }
 }
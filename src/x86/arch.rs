@@ -0,0 +1,28 @@
+use super::unwind_rule::UnwindRuleX86;
+use super::unwindregs::UnwindRegsX86;
+use crate::any_unwinder::CpuArch;
+use crate::arch::Arch;
+use crate::exidx::ExidxUnwinding;
+use crate::orc::OrcUnwinding;
+use crate::sframe::SFrameUnwinding;
+use crate::signal_frame::SignalFrameUnwinding;
+
+/// The 32-bit x86 CPU architecture (i386 / i686).
+pub struct ArchX86;
+impl Arch for ArchX86 {
+    const CPU_ARCH: CpuArch = CpuArch::X86;
+    type UnwindRule = UnwindRuleX86;
+    type UnwindRegs = UnwindRegsX86;
+}
+
+// .ARM.exidx is only used on 32-bit ARM.
+impl ExidxUnwinding for ArchX86 {}
+
+// .sframe is only emitted for x86_64 and aarch64.
+impl SFrameUnwinding for ArchX86 {}
+
+// ORC is only used by the x86_64 Linux kernel.
+impl OrcUnwinding for ArchX86 {}
+
+// Signal frames are only recognized on x86_64 and aarch64 for now.
+impl SignalFrameUnwinding for ArchX86 {}
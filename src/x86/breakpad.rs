@@ -0,0 +1,207 @@
+use super::{arch::ArchX86, unwind_rule::UnwindRuleX86, unwindregs::UnwindRegsX86};
+use crate::breakpad::{
+    evaluate, BreakpadRecord, BreakpadUnwinderError, BreakpadUnwinding, CfiRegisterNames, CfiRules,
+    SimpleCfaBase, SimpleCfiRow, Variables, WinRecord,
+};
+use crate::unwind_result::UnwindResult;
+
+const REGISTER_NAMES: CfiRegisterNames = CfiRegisterNames {
+    sp: "$esp",
+    fp: "$ebp",
+    ra: None,
+};
+
+/// The program for `FRAME_FPO` records: the return address is right above the saved
+/// registers and the locals.
+const FPO_PROGRAM: &str = "$eip .raSearchStart ^ = $esp .raSearchStart 4 + =";
+/// The program for `FRAME_FPO` records of functions which use ebp as a general purpose
+/// register. We assume that ebp was the first register saved by the prologue.
+const FPO_PROGRAM_RESTORE_BP: &str =
+    "$eip .raSearchStart ^ = $esp .raSearchStart 4 + = $ebp .raSearchStart 4 - ^ =";
+
+impl BreakpadUnwinding for ArchX86 {
+    fn unwind_frame<F>(
+        record: BreakpadRecord,
+        regs: &mut UnwindRegsX86,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86>, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let (new_ip, new_sp, new_bp) = match record {
+            BreakpadRecord::Cfi(rules) => {
+                if let Some(unwind_rule) = rules
+                    .simple_row(&REGISTER_NAMES)
+                    .and_then(|row| translate_into_unwind_rule(&row))
+                {
+                    return Ok(UnwindResult::ExecRule(unwind_rule));
+                }
+                evaluate_cfi_rules(&rules, regs, read_stack)?
+            }
+            BreakpadRecord::Win(record) => evaluate_win_record(&record, regs, read_stack)?,
+        };
+
+        let ip = regs.ip();
+        let sp = regs.sp();
+        if new_sp == sp && new_ip == ip {
+            return Err(BreakpadUnwinderError::DidNotAdvance);
+        }
+        if !is_first_frame && new_sp < sp {
+            return Err(BreakpadUnwinderError::StackPointerMovedBackwards);
+        }
+
+        regs.set_ip(new_ip);
+        regs.set_sp(new_sp);
+        regs.set_bp(new_bp);
+
+        Ok(UnwindResult::Uncacheable(u64::from(new_ip)))
+    }
+}
+
+fn register_variables(regs: &UnwindRegsX86) -> Result<Variables<'static>, BreakpadUnwinderError> {
+    let mut variables = Variables::new();
+    variables.set("$eip", u64::from(regs.ip()))?;
+    variables.set("$esp", u64::from(regs.sp()))?;
+    variables.set("$ebp", u64::from(regs.bp()))?;
+    Ok(variables)
+}
+
+fn evaluate_cfi_rules<F>(
+    rules: &CfiRules,
+    regs: &UnwindRegsX86,
+    read_stack: &mut F,
+) -> Result<(u32, u32, u32), BreakpadUnwinderError>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let mut variables = register_variables(regs)?;
+    let cfa = rules.evaluate_cfa(&mut variables, read_stack)?;
+    let return_address = rules
+        .evaluate_register(".ra", &mut variables, read_stack)?
+        .ok_or(BreakpadUnwinderError::MissingReturnAddressRule)?;
+    let new_sp = rules
+        .evaluate_register("$esp", &mut variables, read_stack)?
+        .unwrap_or(cfa);
+    let new_bp = rules
+        .evaluate_register("$ebp", &mut variables, read_stack)?
+        .map_or(regs.bp(), |bp| bp as u32);
+    Ok((return_address as u32, new_sp as u32, new_bp))
+}
+
+/// Runs the program string of a `STACK WIN` record, or the equivalent program for
+/// `FRAME_FPO` records, with the variables that Breakpad's stack walker provides.
+///
+/// Breakpad scans the stack for a plausible return address starting at `.raSearchStart`.
+/// We don't do any stack scanning, so `.raSearch` is the same as `.raSearchStart`.
+fn evaluate_win_record<F>(
+    record: &WinRecord,
+    regs: &UnwindRegsX86,
+    read_stack: &mut F,
+) -> Result<(u32, u32, u32), BreakpadUnwinderError>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let program = match record.program_string {
+        Some(program) => program,
+        None if record.allocates_base_pointer => FPO_PROGRAM_RESTORE_BP,
+        None => FPO_PROGRAM,
+    };
+    let mut variables = register_variables(regs)?;
+    let ra_search_start =
+        u64::from(regs.sp()) + u64::from(record.local_size) + u64::from(record.saved_register_size);
+    variables.set(".cbCalleeParams", 0)?;
+    variables.set(".cbSavedRegs", u64::from(record.saved_register_size))?;
+    variables.set(".cbLocals", u64::from(record.local_size))?;
+    variables.set(".cbParams", u64::from(record.parameter_size))?;
+    variables.set(".raSearchStart", ra_search_start)?;
+    variables.set(".raSearch", ra_search_start)?;
+
+    evaluate(program, &mut variables, read_stack)?;
+    let get = |name| variables.get(name).map(|value| value as u32);
+    let new_ip = get("$eip").ok_or(BreakpadUnwinderError::MissingReturnAddressRule)?;
+    let new_sp = get("$esp").ok_or(BreakpadUnwinderError::UndefinedVariable)?;
+    let new_bp = get("$ebp").ok_or(BreakpadUnwinderError::UndefinedVariable)?;
+    Ok((new_ip, new_sp, new_bp))
+}
+
+fn translate_into_unwind_rule(row: &SimpleCfiRow) -> Option<UnwindRuleX86> {
+    if row.ra_offset != Some(-4) {
+        return None;
+    }
+    match row.cfa_base {
+        SimpleCfaBase::Sp => {
+            if row.cfa_offset % 4 != 0 {
+                return None;
+            }
+            let sp_offset_by_4 = u16::try_from(row.cfa_offset / 4).ok()?;
+            match row.fp_offset {
+                None => Some(UnwindRuleX86::OffsetSp { sp_offset_by_4 }),
+                Some(bp_cfa_offset) => {
+                    let bp_storage_offset_from_sp_by_4 =
+                        i16::try_from(row.cfa_offset.checked_add(bp_cfa_offset)? / 4).ok()?;
+                    Some(UnwindRuleX86::OffsetSpAndRestoreBp {
+                        sp_offset_by_4,
+                        bp_storage_offset_from_sp_by_4,
+                    })
+                }
+            }
+        }
+        SimpleCfaBase::Fp => match (row.cfa_offset, row.fp_offset) {
+            (8, Some(-8)) => Some(UnwindRuleX86::UseFramePointer),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::breakpad::{BreakpadIndex, BreakpadUnwinder};
+
+    #[test]
+    fn test_stack_win() {
+        let sym = b"MODULE windows x86 0123456789ABCDEF1 test.pdb
+STACK WIN 4 1000 20 3 0 8 0 4 0 1 $T0 $ebp = $eip $T0 4 + ^ = $ebp $T0 ^ = $esp $T0 8 + =
+STACK WIN 0 2000 20 0 0 0 8 10 0 0 0
+STACK WIN 0 3000 20 0 0 0 8 10 0 0 1
+STACK CFI INIT 4000 10 .cfa: $esp 4 + .ra: .cfa -4 + ^
+";
+        let index = BreakpadIndex::new(sym);
+        assert_eq!(index.address_size(), 4);
+        let unwinder = BreakpadUnwinder::<ArchX86>::new(sym, &index);
+        let mut read_stack = |addr: u64| Ok(addr + 0x1000);
+        let mut unwind = |address, regs: &mut UnwindRegsX86| {
+            unwinder.unwind_frame(address, regs, false, &mut read_stack)
+        };
+
+        let mut regs = UnwindRegsX86::new(0x1010, 0x100, 0x200);
+        assert_eq!(
+            unwind(0x1010, &mut regs),
+            Ok(UnwindResult::Uncacheable(0x1204))
+        );
+        assert_eq!((regs.sp(), regs.bp()), (0x208, 0x1200));
+
+        // FPO: return address at sp + locals + saved registers.
+        let mut regs = UnwindRegsX86::new(0x2010, 0x100, 0x200);
+        assert_eq!(
+            unwind(0x2010, &mut regs),
+            Ok(UnwindResult::Uncacheable(0x1118))
+        );
+        assert_eq!((regs.sp(), regs.bp()), (0x11c, 0x200));
+
+        let mut regs = UnwindRegsX86::new(0x3010, 0x100, 0x200);
+        assert_eq!(
+            unwind(0x3010, &mut regs),
+            Ok(UnwindResult::Uncacheable(0x1118))
+        );
+        assert_eq!((regs.sp(), regs.bp()), (0x11c, 0x1114));
+
+        assert_eq!(
+            unwind(0x4000, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86::OffsetSp {
+                sp_offset_by_4: 1
+            }))
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use gimli::{
+    CfaRule, Encoding, EvaluationStorage, Reader, ReaderOffset, Register, RegisterRule,
+    UnwindContextStorage, UnwindSection, UnwindTableRow, X86,
+};
+
+use super::{arch::ArchX86, unwind_rule::UnwindRuleX86, unwindregs::UnwindRegsX86};
+use crate::dwarf::{
+    eval_cfa_rule, eval_register_rule, ConversionError, DwarfUnwindRegs, DwarfUnwinderError,
+    DwarfUnwinding,
+};
+use crate::unwind_result::UnwindResult;
+
+impl DwarfUnwindRegs for UnwindRegsX86 {
+    fn get(&self, register: Register) -> Option<u64> {
+        match register {
+            X86::RA => Some(u64::from(self.ip())),
+            X86::ESP => Some(u64::from(self.sp())),
+            X86::EBP => Some(u64::from(self.bp())),
+            _ => None,
+        }
+    }
+}
+
+impl DwarfUnwinding for ArchX86 {
+    fn rule_for_row<O, UCS>(
+        unwind_info: &UnwindTableRow<O, UCS>,
+    ) -> Result<Self::UnwindRule, ConversionError>
+    where
+        O: ReaderOffset,
+        UCS: UnwindContextStorage<O>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let bp_rule = unwind_info.register(X86::EBP);
+        let ra_rule = unwind_info.register(X86::RA);
+        translate_into_unwind_rule(cfa_rule, &bp_rule, &ra_rule)
+    }
+
+    fn unwind_frame<F, R, UCS, ES>(
+        section: &impl UnwindSection<R>,
+        unwind_info: &UnwindTableRow<R::Offset, UCS>,
+        encoding: Encoding,
+        regs: &mut Self::UnwindRegs,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<Self::UnwindRule>, DwarfUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+        R: Reader,
+        UCS: UnwindContextStorage<R::Offset>,
+        ES: EvaluationStorage<R>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let bp_rule = unwind_info.register(X86::EBP);
+        let ra_rule = unwind_info.register(X86::RA);
+
+        let cfa = eval_cfa_rule::<R, _, ES>(section, cfa_rule, encoding, regs)
+            .ok_or(DwarfUnwinderError::CouldNotRecoverCfa)?;
+        let cfa = u32::try_from(cfa).map_err(|_| DwarfUnwinderError::CouldNotRecoverCfa)?;
+
+        let ip = regs.ip();
+        let bp = regs.bp();
+        let sp = regs.sp();
+
+        let new_bp = eval_register_rule::<R, F, _, ES>(
+            section,
+            bp_rule,
+            u64::from(cfa),
+            encoding,
+            u64::from(bp),
+            regs,
+            read_stack,
+        )
+        .map_or(bp, |bp| bp as u32);
+
+        let return_address = match eval_register_rule::<R, F, _, ES>(
+            section,
+            ra_rule,
+            u64::from(cfa),
+            encoding,
+            u64::from(ip),
+            regs,
+            read_stack,
+        ) {
+            Some(ra) => ra as u32,
+            None => {
+                let ra_location = u64::from(
+                    cfa.checked_sub(4)
+                        .ok_or(DwarfUnwinderError::CouldNotRecoverReturnAddress)?,
+                );
+                read_stack(ra_location)
+                    .map_err(|_| DwarfUnwinderError::CouldNotRecoverReturnAddress)?
+                    as u32
+            }
+        };
+
+        if cfa == sp && return_address == ip {
+            return Err(DwarfUnwinderError::DidNotAdvance);
+        }
+        if !is_first_frame && cfa < regs.sp() {
+            return Err(DwarfUnwinderError::StackPointerMovedBackwards);
+        }
+
+        regs.set_ip(return_address);
+        regs.set_bp(new_bp);
+        regs.set_sp(cfa);
+
+        Ok(UnwindResult::Uncacheable(u64::from(return_address)))
+    }
+
+    fn rule_if_uncovered_by_fde() -> Self::UnwindRule {
+        UnwindRuleX86::JustReturnIfFirstFrameOtherwiseFp
+    }
+}
+
+fn register_rule_to_cfa_offset<RO: ReaderOffset>(
+    rule: &RegisterRule<RO>,
+) -> Result<Option<i64>, ConversionError> {
+    match *rule {
+        RegisterRule::Undefined | RegisterRule::SameValue => Ok(None),
+        RegisterRule::Offset(offset) => Ok(Some(offset)),
+        _ => Err(ConversionError::RegisterNotStoredRelativeToCfa),
+    }
+}
+
+fn translate_into_unwind_rule<RO: ReaderOffset>(
+    cfa_rule: &CfaRule<RO>,
+    bp_rule: &RegisterRule<RO>,
+    ra_rule: &RegisterRule<RO>,
+) -> Result<UnwindRuleX86, ConversionError> {
+    match ra_rule {
+        RegisterRule::Undefined => {
+            // No return address. This means that we've reached the end of the stack.
+            return Ok(UnwindRuleX86::EndOfStack);
+        }
+        RegisterRule::Offset(offset) if *offset == -4 => {
+            // This is normal case. Return address is [CFA-4].
+        }
+        RegisterRule::Offset(_) => {
+            // Unsupported, will have to use the slow path.
+            return Err(ConversionError::ReturnAddressRuleWithUnexpectedOffset);
+        }
+        _ => {
+            // Unsupported, will have to use the slow path.
+            return Err(ConversionError::ReturnAddressRuleWasWeird);
+        }
+    }
+
+    match cfa_rule {
+        CfaRule::RegisterAndOffset { register, offset } => match *register {
+            X86::ESP => {
+                if offset % 4 != 0 {
+                    return Err(ConversionError::SpOffsetDoesNotFit);
+                }
+                let sp_offset_by_4 =
+                    u16::try_from(offset / 4).map_err(|_| ConversionError::SpOffsetDoesNotFit)?;
+                let fp_cfa_offset = register_rule_to_cfa_offset(bp_rule)?;
+                match fp_cfa_offset {
+                    None => Ok(UnwindRuleX86::OffsetSp { sp_offset_by_4 }),
+                    Some(bp_cfa_offset) => {
+                        let bp_storage_offset_from_sp_by_4 =
+                            i16::try_from((offset + bp_cfa_offset) / 4)
+                                .map_err(|_| ConversionError::FpStorageOffsetDoesNotFit)?;
+                        Ok(UnwindRuleX86::OffsetSpAndRestoreBp {
+                            sp_offset_by_4,
+                            bp_storage_offset_from_sp_by_4,
+                        })
+                    }
+                }
+            }
+            X86::EBP => {
+                let bp_cfa_offset = register_rule_to_cfa_offset(bp_rule)?
+                    .ok_or(ConversionError::FramePointerRuleDoesNotRestoreBp)?;
+                if *offset == 8 && bp_cfa_offset == -8 {
+                    Ok(UnwindRuleX86::UseFramePointer)
+                } else {
+                    Err(ConversionError::FramePointerRuleHasStrangeBpOffset)
+                }
+            }
+            _ => Err(ConversionError::CfaIsOffsetFromUnknownRegister),
+        },
+        CfaRule::Expression(_) => Err(ConversionError::CfaIsExpression),
+    }
+}
@@ -0,0 +1,18 @@
+use super::arch::ArchX86;
+use crate::instruction_analysis::InstructionAnalysis;
+
+impl InstructionAnalysis for ArchX86 {
+    fn rule_from_prologue_analysis(
+        _text_bytes: &[u8],
+        _pc_offset: usize,
+    ) -> Option<Self::UnwindRule> {
+        None
+    }
+
+    fn rule_from_epilogue_analysis(
+        _text_bytes: &[u8],
+        _pc_offset: usize,
+    ) -> Option<Self::UnwindRule> {
+        None
+    }
+}
@@ -0,0 +1,37 @@
+use super::arch::ArchX86;
+use super::unwind_rule::UnwindRuleX86;
+use super::unwindregs::UnwindRegsX86;
+use crate::macho::{CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding, CuiUnwindResult};
+use crate::unwind_result::UnwindResult;
+use macho_unwind_info::Function;
+
+impl CompactUnwindInfoUnwinding for ArchX86 {
+    fn unwind_frame(
+        _function: Function,
+        _is_first_frame: bool,
+        _address_offset_within_function: usize,
+        _function_bytes: Option<&[u8]>,
+    ) -> Result<CuiUnwindResult<UnwindRuleX86>, CompactUnwindInfoUnwinderError> {
+        Err(CompactUnwindInfoUnwinderError::X86Unsupported)
+    }
+
+    fn rule_for_stub_helper(
+        _offset: u32,
+    ) -> Result<CuiUnwindResult<UnwindRuleX86>, CompactUnwindInfoUnwinderError> {
+        Err(CompactUnwindInfoUnwinderError::X86Unsupported)
+    }
+
+    fn is_sigtramp_return_address(_function_bytes: &[u8], _return_address_offset: usize) -> bool {
+        false
+    }
+
+    fn unwind_sigtramp_frame<F>(
+        _regs: &mut UnwindRegsX86,
+        _read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86>, CompactUnwindInfoUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        Err(CompactUnwindInfoUnwinderError::X86Unsupported)
+    }
+}
@@ -0,0 +1,226 @@
+use super::unwindregs::UnwindRegsX86;
+use crate::add_signed::checked_add_signed;
+use crate::error::Error;
+use crate::unwind_rule::{pack_rule, unpack_rule, UnwindRule};
+
+/// For all of these: return address is *(new_sp - 4)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnwindRuleX86 {
+    EndOfStack,
+    /// (sp, bp) = (sp + 4, bp)
+    JustReturn,
+    /// (sp, bp) = if is_first_frame (sp + 4, bp) else (bp + 8, *bp)
+    JustReturnIfFirstFrameOtherwiseFp,
+    /// (sp, bp) = (sp + 4x, bp)
+    OffsetSp {
+        sp_offset_by_4: u16,
+    },
+    /// (sp, bp) = (sp + 4x, *(sp + 4y))
+    OffsetSpAndRestoreBp {
+        sp_offset_by_4: u16,
+        bp_storage_offset_from_sp_by_4: i16,
+    },
+    /// (sp, bp) = (bp + 8, *bp)
+    UseFramePointer,
+}
+
+impl UnwindRule for UnwindRuleX86 {
+    type UnwindRegs = UnwindRegsX86;
+
+    fn rule_for_stub_functions() -> Self {
+        UnwindRuleX86::JustReturn
+    }
+    fn rule_for_function_start() -> Self {
+        UnwindRuleX86::JustReturn
+    }
+    fn fallback_rule() -> Self {
+        UnwindRuleX86::UseFramePointer
+    }
+
+    fn pack(self) -> u64 {
+        match self {
+            UnwindRuleX86::EndOfStack => pack_rule(0, [0, 0, 0]),
+            UnwindRuleX86::JustReturn => pack_rule(1, [0, 0, 0]),
+            UnwindRuleX86::JustReturnIfFirstFrameOtherwiseFp => pack_rule(2, [0, 0, 0]),
+            UnwindRuleX86::OffsetSp { sp_offset_by_4 } => pack_rule(3, [sp_offset_by_4, 0, 0]),
+            UnwindRuleX86::OffsetSpAndRestoreBp {
+                sp_offset_by_4,
+                bp_storage_offset_from_sp_by_4,
+            } => pack_rule(
+                4,
+                [sp_offset_by_4, bp_storage_offset_from_sp_by_4 as u16, 0],
+            ),
+            UnwindRuleX86::UseFramePointer => pack_rule(5, [0, 0, 0]),
+        }
+    }
+
+    fn unpack(bits: u64) -> Option<Self> {
+        let (tag, [a, b, _]) = unpack_rule(bits);
+        let rule = match tag {
+            0 => UnwindRuleX86::EndOfStack,
+            1 => UnwindRuleX86::JustReturn,
+            2 => UnwindRuleX86::JustReturnIfFirstFrameOtherwiseFp,
+            3 => UnwindRuleX86::OffsetSp { sp_offset_by_4: a },
+            4 => UnwindRuleX86::OffsetSpAndRestoreBp {
+                sp_offset_by_4: a,
+                bp_storage_offset_from_sp_by_4: b as i16,
+            },
+            5 => UnwindRuleX86::UseFramePointer,
+            _ => return None,
+        };
+        Some(rule)
+    }
+
+    fn exec<F>(
+        self,
+        is_first_frame: bool,
+        regs: &mut UnwindRegsX86,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, Error>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let sp = regs.sp();
+        let (new_sp, new_bp) = match self {
+            UnwindRuleX86::EndOfStack => return Ok(None),
+            UnwindRuleX86::JustReturn => {
+                let new_sp = sp.checked_add(4).ok_or(Error::IntegerOverflow)?;
+                (new_sp, regs.bp())
+            }
+            UnwindRuleX86::JustReturnIfFirstFrameOtherwiseFp => {
+                if is_first_frame {
+                    let new_sp = sp.checked_add(4).ok_or(Error::IntegerOverflow)?;
+                    (new_sp, regs.bp())
+                } else {
+                    match unwind_with_frame_pointer(regs, read_stack)? {
+                        Some(new_sp_and_bp) => new_sp_and_bp,
+                        None => return Ok(None),
+                    }
+                }
+            }
+            UnwindRuleX86::OffsetSp { sp_offset_by_4 } => {
+                let sp_offset = u32::from(sp_offset_by_4) * 4;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                (new_sp, regs.bp())
+            }
+            UnwindRuleX86::OffsetSpAndRestoreBp {
+                sp_offset_by_4,
+                bp_storage_offset_from_sp_by_4,
+            } => {
+                let sp_offset = u32::from(sp_offset_by_4) * 4;
+                let new_sp = sp.checked_add(sp_offset).ok_or(Error::IntegerOverflow)?;
+                let bp_storage_offset_from_sp = i32::from(bp_storage_offset_from_sp_by_4) * 4;
+                let bp_location = checked_add_signed(sp, bp_storage_offset_from_sp)
+                    .ok_or(Error::IntegerOverflow)?;
+                let new_bp = match read_stack(u64::from(bp_location)) {
+                    Ok(new_bp) => new_bp as u32,
+                    Err(()) if is_first_frame && bp_location < sp => {
+                        // Ignore errors when reading beyond the stack pointer in the first
+                        // frame, for the same reason as on x86_64: epilogues can leave
+                        // already-popped registers described as stored below the stack pointer.
+                        regs.bp()
+                    }
+                    Err(()) => return Err(Error::CouldNotReadStack(u64::from(bp_location))),
+                };
+                (new_sp, new_bp)
+            }
+            UnwindRuleX86::UseFramePointer => match unwind_with_frame_pointer(regs, read_stack)? {
+                Some(new_sp_and_bp) => new_sp_and_bp,
+                None => return Ok(None),
+            },
+        };
+        let return_address_location =
+            u64::from(new_sp.checked_sub(4).ok_or(Error::IntegerOverflow)?);
+        let return_address = read_stack(return_address_location)
+            .map_err(|_| Error::CouldNotReadStack(return_address_location))?
+            as u32;
+        if return_address == 0 {
+            return Ok(None);
+        }
+        if new_sp == sp && return_address == regs.ip() {
+            return Err(Error::DidNotAdvance);
+        }
+        regs.set_ip(return_address);
+        regs.set_sp(new_sp);
+        regs.set_bp(new_bp);
+        Ok(Some(u64::from(return_address)))
+    }
+}
+
+/// Do one step of a frame pointer stack walk. Returns the new (sp, bp), or `None` if
+/// the end of the frame pointer chain has been reached.
+///
+/// The i386 frame layout is the same as on x86_64, just with 4-byte slots:
+///
+/// ```text
+/// pushl  %ebp
+/// movl   %esp, %ebp
+/// ```
+///
+/// So *ebp is the caller's frame pointer, *(ebp + 4) is the return address, and the
+/// caller's stack pointer is ebp + 8.
+fn unwind_with_frame_pointer<F>(
+    regs: &UnwindRegsX86,
+    read_stack: &mut F,
+) -> Result<Option<(u32, u32)>, Error>
+where
+    F: FnMut(u64) -> Result<u64, ()>,
+{
+    let sp = regs.sp();
+    let bp = regs.bp();
+    if bp == 0 {
+        return Ok(None);
+    }
+    let new_sp = bp.checked_add(8).ok_or(Error::IntegerOverflow)?;
+    if new_sp <= sp {
+        return Err(Error::FramepointerUnwindingMovedBackwards);
+    }
+    let bp_location = u64::from(bp);
+    let new_bp = read_stack(bp_location).map_err(|_| Error::CouldNotReadStack(bp_location))?;
+    // As on x86_64, new_bp is left unchecked because the caller may be using ebp as a
+    // general purpose register.
+    Ok(Some((new_sp, new_bp as u32)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let stack = [
+            1, 2, 0x100300, 4, 0x20, 0x100200, 5, 6, 0x38, 0x100100, 7, 8, 9, 10, 0x0, 0x0,
+        ];
+        let mut read_stack = |addr| Ok(stack[(addr / 4) as usize]);
+        let mut regs = UnwindRegsX86::new(0x100400, 0x8, 0x10);
+        let res =
+            UnwindRuleX86::OffsetSp { sp_offset_by_4: 1 }.exec(true, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100300)));
+        assert_eq!(regs.ip(), 0x100300);
+        assert_eq!(regs.sp(), 0xc);
+        assert_eq!(regs.bp(), 0x10);
+        let res = UnwindRuleX86::UseFramePointer.exec(true, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100200)));
+        assert_eq!(regs.ip(), 0x100200);
+        assert_eq!(regs.sp(), 0x18);
+        assert_eq!(regs.bp(), 0x20);
+        let res = UnwindRuleX86::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(Some(0x100100)));
+        assert_eq!(regs.ip(), 0x100100);
+        assert_eq!(regs.sp(), 0x28);
+        assert_eq!(regs.bp(), 0x38);
+        let res = UnwindRuleX86::UseFramePointer.exec(false, &mut regs, &mut read_stack);
+        assert_eq!(res, Ok(None));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let stack = [1, 2, 0x100300, 4];
+        let mut read_stack = |addr| Ok(stack[(addr / 4) as usize]);
+        let mut regs = UnwindRegsX86::new(0x100400, u32::MAX / 4 * 4, u32::MAX);
+        let res = UnwindRuleX86::JustReturn.exec(true, &mut regs, &mut read_stack);
+        assert_eq!(res, Err(Error::IntegerOverflow));
+        let res = UnwindRuleX86::UseFramePointer.exec(true, &mut regs, &mut read_stack);
+        assert_eq!(res, Err(Error::IntegerOverflow));
+    }
+}
@@ -0,0 +1,102 @@
+use core::ops::Deref;
+
+use crate::{
+    unwinder::UnwinderInternal, AllocationPolicy, Error, FrameAddress, FrameDiagnostics,
+    MayAllocateDuringUnwind, Module, ModuleAddress, ReadStack, UnwindMethod, Unwinder,
+};
+
+use super::{ArchX86, CacheX86, UnwindRegsX86};
+
+/// The unwinder for the 32-bit x86 CPU architecture. Use the [`Unwinder`] trait for unwinding.
+///
+/// Type arguments:
+///
+///  - `D`: The type for unwind section data in the modules. See [`Module`].
+/// -  `P`: The [`AllocationPolicy`].
+pub struct UnwinderX86<D, P = MayAllocateDuringUnwind>(UnwinderInternal<D, ArchX86, P>);
+
+impl<D, P> Default for UnwinderX86<D, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D, P> Clone for UnwinderX86<D, P> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<D, P> UnwinderX86<D, P> {
+    /// Create an unwinder for a process.
+    pub fn new() -> Self {
+        Self(UnwinderInternal::new())
+    }
+}
+
+impl<D: Deref<Target = [u8]>, P: AllocationPolicy> Unwinder for UnwinderX86<D, P> {
+    type UnwindRegs = UnwindRegsX86;
+    type Cache = CacheX86<P>;
+    type Module = Module<D>;
+
+    fn add_module(&mut self, module: Module<D>) {
+        self.0.add_module(module);
+    }
+
+    fn remove_module(&mut self, module_address_range_start: u64) {
+        self.0.remove_module(module_address_range_start);
+    }
+
+    fn max_known_code_address(&self) -> u64 {
+        self.0.max_known_code_address()
+    }
+
+    fn module_for_address(&self, address: u64) -> Option<ModuleAddress<'_, Module<D>>> {
+        self.0.module_for_address(address)
+    }
+
+    fn modules(&self) -> core::slice::Iter<'_, Module<D>> {
+        self.0.modules()
+    }
+
+    fn unwind_frame<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsX86,
+        cache: &mut CacheX86<P>,
+        read_stack: &mut F,
+    ) -> Result<Option<u64>, Error>
+    where
+        F: ReadStack,
+    {
+        self.0.unwind_frame(address, regs, &mut cache.0, read_stack)
+    }
+
+    fn unwind_frame_with_method<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsX86,
+        cache: &mut CacheX86<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, UnwindMethod)
+    where
+        F: ReadStack,
+    {
+        self.0
+            .unwind_frame_with_method(address, regs, &mut cache.0, read_stack)
+    }
+
+    fn unwind_frame_with_diagnostics<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsX86,
+        cache: &mut CacheX86<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, FrameDiagnostics)
+    where
+        F: ReadStack,
+    {
+        self.0
+            .unwind_frame_with_diagnostics(address, regs, &mut cache.0, read_stack)
+    }
+}
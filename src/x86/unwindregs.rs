@@ -0,0 +1,79 @@
+use core::fmt::Debug;
+
+use crate::display_utils::HexNum;
+use crate::frame_record::FrameRegs;
+
+/// The registers used for unwinding on 32-bit x86. We need eip, esp and ebp.
+///
+/// The stack is read in 32-bit words: the `read_stack` callback is called with
+/// 4-byte aligned addresses and should return the 32-bit value at that address,
+/// zero-extended to `u64`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct UnwindRegsX86 {
+    ip: u32,
+    sp: u32,
+    bp: u32,
+}
+
+impl UnwindRegsX86 {
+    /// Create a set of unwind register values.
+    pub fn new(ip: u32, sp: u32, bp: u32) -> Self {
+        Self { ip, sp, bp }
+    }
+
+    /// Get the instruction pointer value (eip).
+    #[inline(always)]
+    pub fn ip(&self) -> u32 {
+        self.ip
+    }
+
+    /// Set the instruction pointer value (eip).
+    #[inline(always)]
+    pub fn set_ip(&mut self, ip: u32) {
+        self.ip = ip
+    }
+
+    /// Get the stack pointer value (esp).
+    #[inline(always)]
+    pub fn sp(&self) -> u32 {
+        self.sp
+    }
+
+    /// Set the stack pointer value (esp).
+    #[inline(always)]
+    pub fn set_sp(&mut self, sp: u32) {
+        self.sp = sp
+    }
+
+    /// Get the frame pointer value (ebp).
+    #[inline(always)]
+    pub fn bp(&self) -> u32 {
+        self.bp
+    }
+
+    /// Set the frame pointer value (ebp).
+    #[inline(always)]
+    pub fn set_bp(&mut self, bp: u32) {
+        self.bp = bp
+    }
+}
+
+impl FrameRegs for UnwindRegsX86 {
+    fn stack_pointer(&self) -> u64 {
+        u64::from(self.sp())
+    }
+
+    fn frame_pointer(&self) -> u64 {
+        u64::from(self.bp())
+    }
+}
+
+impl Debug for UnwindRegsX86 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnwindRegsX86")
+            .field("eip", &HexNum(self.ip))
+            .field("esp", &HexNum(self.sp))
+            .field("ebp", &HexNum(self.bp))
+            .finish()
+    }
+}
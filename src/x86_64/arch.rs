@@ -1,10 +1,16 @@
 use super::unwind_rule::UnwindRuleX86_64;
 use super::unwindregs::UnwindRegsX86_64;
+use crate::any_unwinder::CpuArch;
 use crate::arch::Arch;
+use crate::exidx::ExidxUnwinding;
 
 /// The x86_64 CPU architecture.
 pub struct ArchX86_64;
 impl Arch for ArchX86_64 {
+    const CPU_ARCH: CpuArch = CpuArch::X86_64;
     type UnwindRule = UnwindRuleX86_64;
     type UnwindRegs = UnwindRegsX86_64;
 }
+
+// .ARM.exidx is only used on 32-bit ARM.
+impl ExidxUnwinding for ArchX86_64 {}
@@ -0,0 +1,151 @@
+use super::{arch::ArchX86_64, unwind_rule::UnwindRuleX86_64, unwindregs::UnwindRegsX86_64};
+use crate::breakpad::{
+    BreakpadRecord, BreakpadUnwinderError, BreakpadUnwinding, CfiRegisterNames, SimpleCfaBase,
+    SimpleCfiRow, Variables,
+};
+use crate::unwind_result::UnwindResult;
+
+const REGISTER_NAMES: CfiRegisterNames = CfiRegisterNames {
+    sp: "$rsp",
+    fp: "$rbp",
+    ra: None,
+};
+
+impl BreakpadUnwinding for ArchX86_64 {
+    fn unwind_frame<F>(
+        record: BreakpadRecord,
+        regs: &mut UnwindRegsX86_64,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86_64>, BreakpadUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        let BreakpadRecord::Cfi(rules) = record else {
+            return Err(BreakpadUnwinderError::UnsupportedWinRecord);
+        };
+
+        if let Some(unwind_rule) = rules
+            .simple_row(&REGISTER_NAMES)
+            .and_then(|row| translate_into_unwind_rule(&row))
+        {
+            return Ok(UnwindResult::ExecRule(unwind_rule));
+        }
+
+        let ip = regs.ip();
+        let sp = regs.sp();
+        let bp = regs.bp();
+        let mut variables = Variables::new();
+        variables.set("$rip", ip)?;
+        variables.set("$rsp", sp)?;
+        variables.set("$rbp", bp)?;
+
+        let cfa = rules.evaluate_cfa(&mut variables, read_stack)?;
+        let return_address = rules
+            .evaluate_register(".ra", &mut variables, read_stack)?
+            .ok_or(BreakpadUnwinderError::MissingReturnAddressRule)?;
+        let new_sp = rules
+            .evaluate_register("$rsp", &mut variables, read_stack)?
+            .unwrap_or(cfa);
+        let new_bp = rules
+            .evaluate_register("$rbp", &mut variables, read_stack)?
+            .unwrap_or(bp);
+
+        if new_sp == sp && return_address == ip {
+            return Err(BreakpadUnwinderError::DidNotAdvance);
+        }
+        if !is_first_frame && new_sp < sp {
+            return Err(BreakpadUnwinderError::StackPointerMovedBackwards);
+        }
+
+        regs.set_ip(return_address);
+        regs.set_sp(new_sp);
+        regs.set_bp(new_bp);
+
+        Ok(UnwindResult::Uncacheable(return_address))
+    }
+}
+
+fn translate_into_unwind_rule(row: &SimpleCfiRow) -> Option<UnwindRuleX86_64> {
+    if row.ra_offset != Some(-8) {
+        return None;
+    }
+    match row.cfa_base {
+        SimpleCfaBase::Sp => {
+            if row.cfa_offset % 8 != 0 {
+                return None;
+            }
+            let sp_offset_by_8 = u16::try_from(row.cfa_offset / 8).ok()?;
+            match row.fp_offset {
+                None => Some(UnwindRuleX86_64::OffsetSp { sp_offset_by_8 }),
+                Some(bp_cfa_offset) => {
+                    let bp_storage_offset_from_sp_by_8 =
+                        i16::try_from(row.cfa_offset.checked_add(bp_cfa_offset)? / 8).ok()?;
+                    Some(UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                        sp_offset_by_8,
+                        bp_storage_offset_from_sp_by_8,
+                    })
+                }
+            }
+        }
+        SimpleCfaBase::Fp => match (row.cfa_offset, row.fp_offset) {
+            (16, Some(-16)) => Some(UnwindRuleX86_64::UseFramePointer),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::breakpad::{BreakpadIndex, BreakpadUnwinder};
+
+    #[test]
+    fn test_stack_cfi() {
+        let sym = b"MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+STACK CFI INIT 1000 40 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+STACK CFI 1001 .cfa: $rsp 16 + $rbp: .cfa -16 + ^
+STACK CFI 1004 .cfa: $rbp 16 +
+STACK CFI 1020 .cfa: $rbp 24 + .ra: .cfa 16 - ^
+";
+        let index = BreakpadIndex::new(sym);
+        let unwinder = BreakpadUnwinder::<ArchX86_64>::new(sym, &index);
+        let mut read_stack = |addr: u64| Ok(addr + 0x1000);
+        let mut regs = UnwindRegsX86_64::new(0x1000, 0x100, 0x200);
+        let mut unwind = |address, regs: &mut UnwindRegsX86_64| {
+            unwinder.unwind_frame(address, regs, false, &mut read_stack)
+        };
+        assert_eq!(
+            unwind(0x1000, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86_64::OffsetSp {
+                sp_offset_by_8: 1
+            }))
+        );
+        assert_eq!(
+            unwind(0x1002, &mut regs),
+            Ok(UnwindResult::ExecRule(
+                UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                    sp_offset_by_8: 2,
+                    bp_storage_offset_from_sp_by_8: 0,
+                }
+            ))
+        );
+        assert_eq!(
+            unwind(0x1010, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86_64::UseFramePointer))
+        );
+
+        // CFA = bp + 24, return address at CFA - 16, bp at CFA - 16.
+        assert_eq!(
+            unwind(0x1030, &mut regs),
+            Ok(UnwindResult::Uncacheable(0x200 + 8 + 0x1000))
+        );
+        assert_eq!(regs.sp(), 0x200 + 24);
+        assert_eq!(regs.bp(), 0x200 + 8 + 0x1000);
+
+        assert_eq!(
+            unwind(0x1040, &mut regs),
+            Err(BreakpadUnwinderError::AddressNotCovered(0x1040))
+        );
+    }
+}
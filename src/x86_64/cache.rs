@@ -11,6 +11,16 @@ impl CacheX86_64<MayAllocateDuringUnwind> {
     pub fn new() -> Self {
         Self(Cache::new())
     }
+
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared(shared: &SharedCacheX86_64) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
 }
 
 impl<P: AllocationPolicy> CacheX86_64<P> {
@@ -19,6 +29,16 @@ impl<P: AllocationPolicy> CacheX86_64<P> {
         Self(Cache::new())
     }
 
+    /// Create a new cache whose rule cache has the given size and associativity.
+    pub fn with_config_in(config: CacheConfig) -> Self {
+        Self(Cache::with_config(config))
+    }
+
+    /// Create a new cache which stores its unwind rules in `shared`.
+    pub fn new_shared_in(shared: &SharedCacheX86_64) -> Self {
+        Self(Cache::new_shared(&shared.0))
+    }
+
     /// Returns a snapshot of the cache usage statistics.
     pub fn stats(&self) -> CacheStats {
         self.0.rule_cache.stats()
@@ -30,3 +50,31 @@ impl<P: AllocationPolicy> Default for CacheX86_64<P> {
         Self::new_in()
     }
 }
+
+/// Unwind rule storage for [`UnwinderX86_64`](super::UnwinderX86_64) which can be shared by the
+/// caches of multiple threads, so that an unwind rule which was found on one thread is a
+/// cache hit on all other threads. Create the per-thread caches with
+/// [`CacheX86_64::new_shared`] or [`CacheX86_64::new_shared_in`].
+///
+/// Lookups and insertions are lock-free and don't allocate, so the sharing caches can be
+/// used from signal handlers. Cloning gives you another handle to the same storage.
+#[derive(Clone, Default)]
+pub struct SharedCacheX86_64(pub SharedCache<UnwindRuleX86_64>);
+
+impl SharedCacheX86_64 {
+    /// Create a new shared cache.
+    pub fn new() -> Self {
+        Self(SharedCache::new())
+    }
+
+    /// Create a new shared cache with the given size and associativity.
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(SharedCache::with_config(config))
+    }
+
+    /// Returns a snapshot of the cache usage statistics, summed up over all caches
+    /// which share these rules.
+    pub fn stats(&self) -> CacheStats {
+        self.0.stats()
+    }
+}
@@ -22,6 +22,19 @@ impl DwarfUnwindRegs for UnwindRegsX86_64 {
 }
 
 impl DwarfUnwinding for ArchX86_64 {
+    fn rule_for_row<O, UCS>(
+        unwind_info: &UnwindTableRow<O, UCS>,
+    ) -> Result<Self::UnwindRule, ConversionError>
+    where
+        O: ReaderOffset,
+        UCS: UnwindContextStorage<O>,
+    {
+        let cfa_rule = unwind_info.cfa();
+        let bp_rule = unwind_info.register(X86_64::RBP);
+        let ra_rule = unwind_info.register(X86_64::RA);
+        translate_into_unwind_rule(cfa_rule, &bp_rule, &ra_rule)
+    }
+
     fn unwind_frame<F, R, UCS, ES>(
         section: &impl UnwindSection<R>,
         unwind_info: &UnwindTableRow<R::Offset, UCS>,
@@ -40,14 +53,6 @@ impl DwarfUnwinding for ArchX86_64 {
         let bp_rule = unwind_info.register(X86_64::RBP);
         let ra_rule = unwind_info.register(X86_64::RA);
 
-        match translate_into_unwind_rule(cfa_rule, &bp_rule, &ra_rule) {
-            Ok(unwind_rule) => return Ok(UnwindResult::ExecRule(unwind_rule)),
-            Err(_err) => {
-                // Could not translate into a cacheable unwind rule. Fall back to the generic path.
-                // eprintln!("Unwind rule translation failed: {:?}", err);
-            }
-        }
-
         let cfa = eval_cfa_rule::<R, _, ES>(section, cfa_rule, encoding, regs)
             .ok_or(DwarfUnwinderError::CouldNotRecoverCfa)?;
 
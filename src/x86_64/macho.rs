@@ -1,10 +1,30 @@
 use super::arch::ArchX86_64;
 use super::unwind_rule::UnwindRuleX86_64;
+use super::unwindregs::UnwindRegsX86_64;
 use crate::instruction_analysis::InstructionAnalysis;
-use crate::macho::{CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding, CuiUnwindResult};
+use crate::macho::{
+    read_sigtramp_context, CompactUnwindInfoUnwinderError, CompactUnwindInfoUnwinding,
+    CuiUnwindResult, SIGINFO_SIZE, UCONTEXT_MCONTEXT, UCONTEXT_MCSIZE,
+};
+use crate::unwind_result::UnwindResult;
 use macho_unwind_info::opcodes::{OpcodeX86_64, RegisterNameX86_64};
 use macho_unwind_info::Function;
 
+/// `call *%rax`, which calls the signal handler in `_sigtramp`.
+const SIGTRAMP_CALL_HANDLER: [u8; 2] = [0xff, 0xd0];
+/// `decl ___in_sigtramp(%rip)`, in older versions of `_sigtramp`.
+const SIGTRAMP_DECL_IN_SIGTRAMP: [u8; 2] = [0xff, 0x0d];
+/// `movq %rbx, %rdi; movl $UC_FLAVOR, %esi`, the arguments for `__sigreturn`.
+const SIGTRAMP_SIGRETURN_ARGS: [u8; 8] = [0x48, 0x89, 0xdf, 0xbe, 0x1e, 0x00, 0x00, 0x00];
+
+/// The sizes of the different mcontext flavors: with the float state, the AVX state and
+/// the AVX-512 state, each with the regular and with the full thread state.
+const MCONTEXT_SIZES: [u64; 6] = [712, 1032, 2632, 744, 1064, 2664];
+/// Offsets of the registers we need in the mcontext, after the 16 byte exception state.
+const MCONTEXT_RBP: u64 = 16 + 6 * 8;
+const MCONTEXT_RSP: u64 = 16 + 7 * 8;
+const MCONTEXT_RIP: u64 = 16 + 16 * 8;
+
 impl CompactUnwindInfoUnwinding for ArchX86_64 {
     fn unwind_frame(
         function: Function,
@@ -24,13 +44,15 @@ impl CompactUnwindInfoUnwinding for ArchX86_64 {
                 ) {
                     // We are inside a prologue / epilogue. Ignore the opcode and use the rule from
                     // instruction analysis.
-                    return Ok(CuiUnwindResult::ExecRule(rule));
+                    return Ok(CuiUnwindResult::ExecRuleFromInstructionAnalysis(rule));
                 }
                 if opcode == OpcodeX86_64::Null
                     && function_bytes.starts_with(&[0x55, 0x48, 0x89, 0xe5])
                 {
                     // The function is uncovered but it has a `push rbp; mov rbp, rsp` prologue.
-                    return Ok(CuiUnwindResult::ExecRule(UnwindRuleX86_64::UseFramePointer));
+                    return Ok(CuiUnwindResult::ExecRuleFromInstructionAnalysis(
+                        UnwindRuleX86_64::UseFramePointer,
+                    ));
                 }
             }
             if opcode == OpcodeX86_64::Null {
@@ -168,4 +190,102 @@ impl CompactUnwindInfoUnwinding for ArchX86_64 {
         };
         Ok(CuiUnwindResult::ExecRule(rule))
     }
+
+    fn is_sigtramp_return_address(function_bytes: &[u8], return_address_offset: usize) -> bool {
+        let call_offset = match return_address_offset.checked_sub(SIGTRAMP_CALL_HANDLER.len()) {
+            Some(offset) => offset,
+            None => return false,
+        };
+        if function_bytes.get(call_offset..return_address_offset) != Some(&SIGTRAMP_CALL_HANDLER) {
+            return false;
+        }
+        let mut bytes_after_call = &function_bytes[return_address_offset..];
+        if bytes_after_call.starts_with(&SIGTRAMP_DECL_IN_SIGTRAMP) {
+            bytes_after_call = bytes_after_call.get(6..).unwrap_or_default();
+        }
+        bytes_after_call.starts_with(&SIGTRAMP_SIGRETURN_ARGS)
+    }
+
+    fn unwind_sigtramp_frame<F>(
+        regs: &mut UnwindRegsX86_64,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86_64>, CompactUnwindInfoUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        // _sigtramp keeps the ucontext pointer in rbx, which we don't track. But it sets up
+        // a frame pointer, and the kernel put the mcontext, the siginfo and the ucontext
+        // right above the return address slot at _sigtramp's entry, aligning the slot so
+        // that the stack is 16-byte aligned at the call. So the mcontext is at either
+        // rbp + 16 or rbp + 24. We pick the one which the ucontext points to.
+        let bp = regs.bp();
+        let mcontext = [16, 24]
+            .into_iter()
+            .filter_map(|offset| bp.checked_add(offset))
+            .find(|&mcontext| {
+                MCONTEXT_SIZES.iter().any(|&mcsize| {
+                    let Some(ucontext) = mcontext.checked_add(mcsize + SIGINFO_SIZE) else {
+                        return false;
+                    };
+                    let mut read = |offset| read_sigtramp_context(ucontext, offset, read_stack);
+                    read(UCONTEXT_MCONTEXT) == Ok(mcontext) && read(UCONTEXT_MCSIZE) == Ok(mcsize)
+                })
+            })
+            .ok_or(CompactUnwindInfoUnwinderError::SigtrampContextNotFound)?;
+
+        let new_bp = read_sigtramp_context(mcontext, MCONTEXT_RBP, read_stack)?;
+        let new_sp = read_sigtramp_context(mcontext, MCONTEXT_RSP, read_stack)?;
+        let new_ip = read_sigtramp_context(mcontext, MCONTEXT_RIP, read_stack)?;
+        regs.set_ip(new_ip);
+        regs.set_sp(new_sp);
+        regs.set_bp(new_bp);
+        Ok(UnwindResult::InstructionPointer(new_ip))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sigtramp_return_address() {
+        // push rbp; mov rbp, rsp; ...; call *%rax; movq %rbx, %rdi; movl $30, %esi; ...
+        let function_bytes = [
+            0x55, 0x48, 0x89, 0xe5, 0x48, 0x89, 0xf8, 0x48, 0x89, 0xc3, 0x4c, 0x89, 0xc3, 0x89,
+            0xd7, 0x48, 0x89, 0xce, 0x4c, 0x89, 0xc2, 0xff, 0xd0, 0x48, 0x89, 0xdf, 0xbe, 0x1e,
+            0x00, 0x00, 0x00, 0x4c, 0x89, 0xe2, 0xe8, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert!(ArchX86_64::is_sigtramp_return_address(&function_bytes, 23));
+        assert!(!ArchX86_64::is_sigtramp_return_address(&function_bytes, 22));
+        assert!(!ArchX86_64::is_sigtramp_return_address(&function_bytes, 0));
+        assert!(!ArchX86_64::is_sigtramp_return_address(
+            &function_bytes,
+            100
+        ));
+    }
+
+    #[test]
+    fn test_unwind_sigtramp_frame() {
+        // rbp is 0x100, the mcontext is at 0x118, the siginfo at 0x520 and the ucontext at
+        // 0x588.
+        let mut stack = [0u64; 0x100];
+        stack[(0x118 + MCONTEXT_RBP as usize) / 8] = 0x2000;
+        stack[(0x118 + MCONTEXT_RSP as usize) / 8] = 0x1800;
+        stack[(0x118 + MCONTEXT_RIP as usize) / 8] = 0x1234567;
+        stack[(0x588 + UCONTEXT_MCSIZE as usize) / 8] = 1032;
+        stack[(0x588 + UCONTEXT_MCONTEXT as usize) / 8] = 0x118;
+        let mut read_stack = |addr: u64| stack.get((addr / 8) as usize).copied().ok_or(());
+        let mut regs = UnwindRegsX86_64::new(0x7fff0000, 0x100, 0x100);
+        let res = ArchX86_64::unwind_sigtramp_frame(&mut regs, &mut read_stack);
+        assert_eq!(res, Ok(UnwindResult::InstructionPointer(0x1234567)));
+        assert_eq!(regs.sp(), 0x1800);
+        assert_eq!(regs.bp(), 0x2000);
+
+        let mut regs = UnwindRegsX86_64::new(0x7fff0000, 0x200, 0x200);
+        let res = ArchX86_64::unwind_sigtramp_frame(&mut regs, &mut read_stack);
+        assert_eq!(
+            res,
+            Err(CompactUnwindInfoUnwinderError::SigtrampContextNotFound)
+        );
+    }
 }
@@ -1,12 +1,16 @@
 mod arch;
+mod breakpad;
 mod cache;
 mod dwarf;
 mod instruction_analysis;
 #[cfg(feature = "macho")]
 mod macho;
+mod orc;
 #[cfg(feature = "pe")]
 mod pe;
 mod register_ordering;
+mod sframe;
+mod signal_frame;
 mod unwind_rule;
 mod unwinder;
 mod unwindregs;
@@ -0,0 +1,270 @@
+use super::{arch::ArchX86_64, unwind_rule::UnwindRuleX86_64, unwindregs::UnwindRegsX86_64};
+use crate::add_signed::checked_add_signed;
+use crate::orc::{OrcEntry, OrcEntryType, OrcReg, OrcUnwinderError, OrcUnwinding};
+use crate::unwind_result::UnwindResult;
+
+/// Offsets of the registers we need in `struct pt_regs`.
+const PT_REGS_BP: i16 = 4 * 8;
+const PT_REGS_IP: i16 = 16 * 8;
+const PT_REGS_SP: i16 = 19 * 8;
+
+/// Offsets of the registers we need in the hardware IRET frame.
+const IRET_FRAME_IP: i16 = 0;
+const IRET_FRAME_SP: i16 = 3 * 8;
+
+impl OrcUnwinding for ArchX86_64 {
+    fn unwind_frame<F>(
+        entry: OrcEntry,
+        regs: &mut UnwindRegsX86_64,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86_64>, OrcUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        if let Some(unwind_rule) = translate_into_unwind_rule(&entry) {
+            return Ok(UnwindResult::ExecRule(unwind_rule));
+        }
+
+        let ip = regs.ip();
+        let sp = regs.sp();
+        let bp = regs.bp();
+        let mut read =
+            |addr: u64| read_stack(addr).map_err(|_| OrcUnwinderError::CouldNotReadStack(addr));
+        let add = |base: u64, offset: i16| {
+            checked_add_signed(base, i64::from(offset)).ok_or(OrcUnwinderError::IntegerOverflow)
+        };
+
+        let cfa = match entry.sp_reg {
+            OrcReg::Sp => add(sp, entry.sp_offset)?,
+            OrcReg::Bp => add(bp, entry.sp_offset)?,
+            OrcReg::SpIndirect => add(read(sp)?, entry.sp_offset)?,
+            OrcReg::BpIndirect => read(add(bp, entry.sp_offset)?)?,
+            reg => return Err(OrcUnwinderError::UnsupportedSpRegister(reg.raw())),
+        };
+
+        let (new_ip, new_sp, new_bp) = match entry.entry_type {
+            OrcEntryType::Undefined | OrcEntryType::EndOfStack => {
+                unreachable!("handled by translate_into_unwind_rule")
+            }
+            OrcEntryType::Call => {
+                let return_address = read(
+                    cfa.checked_sub(8)
+                        .ok_or(OrcUnwinderError::IntegerOverflow)?,
+                )?;
+                let new_bp = match entry.bp_reg {
+                    OrcReg::Undefined => bp,
+                    OrcReg::PrevSp => read(add(cfa, entry.bp_offset)?)?,
+                    OrcReg::Bp => read(add(bp, entry.bp_offset)?)?,
+                    reg => return Err(OrcUnwinderError::UnsupportedBpRegister(reg.raw())),
+                };
+                (return_address, cfa, new_bp)
+            }
+            OrcEntryType::Regs => {
+                // Interrupt or syscall entry: the interrupted register values are in the
+                // pt_regs struct on the stack.
+                let new_ip = read(add(cfa, PT_REGS_IP)?)?;
+                let new_sp = read(add(cfa, PT_REGS_SP)?)?;
+                let new_bp = read(add(cfa, PT_REGS_BP)?)?;
+                (new_ip, new_sp, new_bp)
+            }
+            OrcEntryType::RegsPartial => {
+                // Only the IRET frame has been saved so far, bp has not been touched.
+                let new_ip = read(add(cfa, IRET_FRAME_IP)?)?;
+                let new_sp = read(add(cfa, IRET_FRAME_SP)?)?;
+                (new_ip, new_sp, bp)
+            }
+        };
+
+        if entry.entry_type == OrcEntryType::Call {
+            if new_sp == sp && new_ip == ip {
+                return Err(OrcUnwinderError::DidNotAdvance);
+            }
+            if !is_first_frame && new_sp < sp {
+                return Err(OrcUnwinderError::StackPointerMovedBackwards);
+            }
+        }
+
+        regs.set_ip(new_ip);
+        regs.set_sp(new_sp);
+        regs.set_bp(new_bp);
+
+        Ok(UnwindResult::Uncacheable(new_ip))
+    }
+}
+
+fn translate_into_unwind_rule(entry: &OrcEntry) -> Option<UnwindRuleX86_64> {
+    match entry.entry_type {
+        OrcEntryType::Undefined | OrcEntryType::EndOfStack => {
+            return Some(UnwindRuleX86_64::EndOfStack)
+        }
+        OrcEntryType::Call => {}
+        OrcEntryType::Regs | OrcEntryType::RegsPartial => return None,
+    }
+    match entry.sp_reg {
+        OrcReg::Sp => {
+            if entry.sp_offset % 8 != 0 {
+                return None;
+            }
+            let sp_offset_by_8 = u16::try_from(entry.sp_offset / 8).ok()?;
+            match entry.bp_reg {
+                OrcReg::Undefined => Some(UnwindRuleX86_64::OffsetSp { sp_offset_by_8 }),
+                OrcReg::PrevSp => {
+                    if entry.bp_offset % 8 != 0 {
+                        return None;
+                    }
+                    let bp_storage_offset_from_sp_by_8 =
+                        (entry.sp_offset.checked_add(entry.bp_offset)?) / 8;
+                    Some(UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                        sp_offset_by_8,
+                        bp_storage_offset_from_sp_by_8,
+                    })
+                }
+                _ => None,
+            }
+        }
+        OrcReg::Bp => match (entry.sp_offset, entry.bp_reg, entry.bp_offset) {
+            (16, OrcReg::PrevSp, -16) => Some(UnwindRuleX86_64::UseFramePointer),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::exidx::DataAtSvmaRange;
+    use crate::orc::{OrcFormat, OrcIndex, OrcUnwinder};
+
+    fn entry(sp_offset: i16, bp_offset: i16, sp_reg: u8, bp_reg: u8, entry_type: u8) -> [u8; 6] {
+        let flags = u16::from(sp_reg) | u16::from(bp_reg) << 4 | u16::from(entry_type) << 8;
+        let mut bytes = [0; 6];
+        bytes[..2].copy_from_slice(&sp_offset.to_le_bytes());
+        bytes[2..4].copy_from_slice(&bp_offset.to_le_bytes());
+        bytes[4..].copy_from_slice(&flags.to_le_bytes());
+        bytes
+    }
+
+    fn ip_table(ips: &[u64], svma: u64) -> Vec<u8> {
+        ips.iter()
+            .enumerate()
+            .flat_map(|(i, ip)| ((*ip as i64 - (svma + i as u64 * 4) as i64) as i32).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_orc_v2() {
+        let ip_svma = 0x5000;
+        let orc_unwind_ip = DataAtSvmaRange {
+            data: ip_table(&[0x1000, 0x1001, 0x1004, 0x1010, 0x1020, 0x1030], ip_svma),
+            svma_range: ip_svma..ip_svma + 24,
+        };
+        let orc_unwind: Vec<u8> = [
+            entry(8, 0, 5, 0, 2),    // sp + 8
+            entry(16, -16, 5, 1, 2), // sp + 16, bp at CFA - 16
+            entry(16, -16, 4, 1, 2), // bp + 16, bp at CFA - 16
+            entry(0, 0, 5, 0, 3),    // pt_regs at sp
+            entry(0, 0, 0, 0, 1),    // end of stack
+            entry(0, 0, 0, 0, 0),    // undefined
+        ]
+        .concat();
+        let unwinder =
+            OrcUnwinder::<ArchX86_64>::new(&orc_unwind_ip, &orc_unwind, None, OrcFormat::V2, 0);
+        let mut read_stack = |addr: u64| Ok(addr + 0x100);
+        let mut regs = UnwindRegsX86_64::new(0x1000, 0x100, 0x200);
+        let mut unwind = |address, regs: &mut UnwindRegsX86_64| {
+            unwinder.unwind_frame(address, regs, false, &mut read_stack)
+        };
+        assert_eq!(
+            unwind(0x1000, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86_64::OffsetSp {
+                sp_offset_by_8: 1
+            }))
+        );
+        assert_eq!(
+            unwind(0x1003, &mut regs),
+            Ok(UnwindResult::ExecRule(
+                UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                    sp_offset_by_8: 2,
+                    bp_storage_offset_from_sp_by_8: 0,
+                }
+            ))
+        );
+        assert_eq!(
+            unwind(0x100f, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86_64::UseFramePointer))
+        );
+        assert_eq!(
+            unwind(0x1020, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86_64::EndOfStack))
+        );
+        assert_eq!(
+            unwind(0x2000, &mut regs),
+            Ok(UnwindResult::ExecRule(UnwindRuleX86_64::EndOfStack))
+        );
+        assert_eq!(
+            unwind(0xfff, &mut regs),
+            Err(OrcUnwinderError::AddressOutsideRange(0xfff))
+        );
+
+        // The interrupted ip, sp and bp are read from pt_regs.
+        assert_eq!(
+            unwind(0x1010, &mut regs),
+            Ok(UnwindResult::Uncacheable(0x100 + 128 + 0x100))
+        );
+        assert_eq!(regs.sp(), 0x100 + 152 + 0x100);
+        assert_eq!(regs.bp(), 0x100 + 32 + 0x100);
+    }
+
+    #[test]
+    fn test_misaligned_bp_offset() {
+        let entry = OrcEntry {
+            sp_offset: 16,
+            bp_offset: -12,
+            sp_reg: OrcReg::Sp,
+            bp_reg: OrcReg::PrevSp,
+            entry_type: OrcEntryType::Call,
+        };
+        assert_eq!(translate_into_unwind_rule(&entry), None);
+    }
+
+    #[test]
+    fn test_orc_v1_unsorted() {
+        let ip_svma = 0x5000;
+        let orc_unwind_ip = DataAtSvmaRange {
+            data: ip_table(&[0x2000, 0x1000, 0x1008, 0x2010], ip_svma),
+            svma_range: ip_svma..ip_svma + 16,
+        };
+        let orc_unwind: Vec<u8> = [
+            entry(24, 0, 5, 0, 0),   // sp + 24
+            entry(8, 0, 5, 0, 0),    // sp + 8
+            entry(16, -16, 4, 1, 0), // bp + 16, bp at CFA - 16
+            entry(0, 0, 0, 0, 0),    // undefined
+        ]
+        .concat();
+        let index = OrcIndex::new_if_unsorted(&orc_unwind_ip);
+        assert!(index.is_some());
+        let unwinder = OrcUnwinder::<ArchX86_64>::new(
+            &orc_unwind_ip,
+            &orc_unwind,
+            index.as_ref(),
+            OrcFormat::V1,
+            0,
+        );
+        let entry_type = |address| unwinder.entry_for_address(address).map(|e| e.entry_type);
+        assert_eq!(
+            unwinder.entry_for_address(0x1004).map(|e| e.sp_offset),
+            Ok(8)
+        );
+        assert_eq!(
+            unwinder.entry_for_address(0x100c).map(|e| e.sp_reg),
+            Ok(OrcReg::Bp)
+        );
+        assert_eq!(
+            unwinder.entry_for_address(0x2008).map(|e| e.sp_offset),
+            Ok(24)
+        );
+        assert_eq!(entry_type(0x2010), Ok(OrcEntryType::Undefined));
+    }
+}
@@ -5,7 +5,9 @@ use super::{
 };
 use crate::arch::Arch;
 use crate::pe::{PeSections, PeUnwinderError, PeUnwinding};
+use crate::rule_table::{RuleTable, RuleTableBuilder};
 use crate::unwind_result::UnwindResult;
+use crate::unwind_rule::UnwindRule;
 use core::ops::ControlFlow;
 
 use alloc::vec::Vec;
@@ -93,6 +95,47 @@ impl From<&'_ UnwindOperation> for OffsetOrPop {
     }
 }
 
+/// Collects the unwind operations which apply at `offset` in the function: the
+/// operations of the function's own unwind info for the prolog instructions which have
+/// executed, followed by the operations of all chained unwind infos.
+fn unwind_operations_at_offset<D>(
+    sections: &PeSections<D>,
+    unwind_info: UnwindInfo,
+    offset: u32,
+) -> Result<Vec<UnwindOperation>, PeUnwinderError>
+where
+    D: core::ops::Deref<Target = [u8]>,
+{
+    // Get all chained UnwindInfo and resolve errors when collecting.
+    let chained_info = core::iter::successors(Some(Ok(unwind_info)), |info| {
+        let Ok(info) = info else {
+            return None;
+        };
+        if let Some(UnwindInfoTrailer::ChainedUnwindInfo { chained }) = info.trailer() {
+            let unwind_info_address = chained.unwind_info_address.get();
+            Some(
+                sections
+                    .unwind_info_memory_at_rva(unwind_info_address)
+                    .and_then(|data| {
+                        UnwindInfo::parse(data).ok_or(PeUnwinderError::UnwindInfoParseError)
+                    }),
+            )
+        } else {
+            None
+        }
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+    // Get all operations across chained UnwindInfo. The first should be filtered to only those
+    // operations which are before the offset in the function.
+    let operations = chained_info.into_iter().enumerate().flat_map(|(i, info)| {
+        info.unwind_operations()
+            .skip_while(move |(o, _)| i == 0 && *o as u32 > offset)
+            .map(|(_, op)| op)
+    });
+    Ok(operations.collect())
+}
+
 impl PeUnwinding for ArchX86_64 {
     fn unwind_frame<F, D>(
         sections: PeSections<D>,
@@ -168,38 +211,10 @@ impl PeUnwinding for ArchX86_64 {
             }
         }
 
-        // Get all chained UnwindInfo and resolve errors when collecting.
-        let chained_info = core::iter::successors(Some(Ok(unwind_info)), |info| {
-            let Ok(info) = info else {
-                return None;
-            };
-            if let Some(UnwindInfoTrailer::ChainedUnwindInfo { chained }) = info.trailer() {
-                let unwind_info_address = chained.unwind_info_address.get();
-                Some(
-                    sections
-                        .unwind_info_memory_at_rva(unwind_info_address)
-                        .and_then(|data| {
-                            UnwindInfo::parse(data).ok_or(PeUnwinderError::UnwindInfoParseError)
-                        }),
-                )
-            } else {
-                None
-            }
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-
-        // Get all operations across chained UnwindInfo. The first should be filtered to only those
-        // operations which are before the offset in the function.
-        let offset = address - function.begin_address.get();
-        let operations = chained_info.into_iter().enumerate().flat_map(|(i, info)| {
-            info.unwind_operations()
-                .skip_while(move |(o, _)| i == 0 && *o as u32 > offset)
-                .map(|(_, op)| op)
-        });
-
         // We need to collect operations to first check (without losing ownership) whether an
         // unwind rule can be returned.
-        let operations = operations.collect::<Vec<_>>();
+        let offset = address - function.begin_address.get();
+        let operations = unwind_operations_at_offset(&sections, unwind_info, offset)?;
         if let Some(rule) = UnwindRuleX86_64::for_sequence_of_offset_or_pop(operations.iter()) {
             return Ok(UnwindResult::ExecRule(rule));
         }
@@ -221,4 +236,43 @@ impl PeUnwinding for ArchX86_64 {
 
         Ok(UnwindResult::Uncacheable(ra))
     }
+
+    fn compile_rule_table<D>(sections: PeSections<D>) -> Result<RuleTable, PeUnwinderError>
+    where
+        D: core::ops::Deref<Target = [u8]>,
+    {
+        // Addresses which aren't covered by a function are in leaf functions.
+        let leaf_rule = Some(UnwindRuleX86_64::JustReturn.pack());
+        let mut builder = RuleTableBuilder::new();
+        builder.push(0, leaf_rule);
+        let entries = FunctionTableEntries::parse(sections.pdata);
+        for function in entries.functions().unwrap_or_default() {
+            let begin = function.begin_address.get();
+            builder.push(begin, None);
+            let unwind_info = sections
+                .unwind_info_memory_at_rva(function.unwind_info_address.get())
+                .ok()
+                .and_then(UnwindInfo::parse);
+            if let Some(unwind_info) = unwind_info {
+                // The rule changes after each prolog instruction.
+                let mut offsets: Vec<u32> = unwind_info
+                    .unwind_operations()
+                    .map(|(o, _)| u32::from(o))
+                    .chain([0])
+                    .collect();
+                offsets.sort_unstable();
+                offsets.dedup();
+                for offset in offsets {
+                    let rule = unwind_operations_at_offset(&sections, unwind_info, offset)
+                        .ok()
+                        .and_then(|operations| {
+                            UnwindRuleX86_64::for_sequence_of_offset_or_pop(operations.iter())
+                        });
+                    builder.push(begin.saturating_add(offset), rule.map(UnwindRule::pack));
+                }
+            }
+            builder.push(function.end_address.get(), leaf_rule);
+        }
+        Ok(builder.finish())
+    }
 }
@@ -0,0 +1,132 @@
+use super::{arch::ArchX86_64, unwind_rule::UnwindRuleX86_64, unwindregs::UnwindRegsX86_64};
+use crate::add_signed::checked_add_signed;
+use crate::sframe::{SFrameAbi, SFrameCfaBase, SFrameRow, SFrameUnwinderError, SFrameUnwinding};
+use crate::unwind_result::UnwindResult;
+
+impl SFrameUnwinding for ArchX86_64 {
+    fn rule_for_row(
+        row: &SFrameRow,
+        abi: SFrameAbi,
+    ) -> Result<Option<UnwindRuleX86_64>, SFrameUnwinderError> {
+        if abi != SFrameAbi::Amd64Le {
+            return Err(SFrameUnwinderError::UnsupportedAbi(abi as u8));
+        }
+        Ok(translate_into_unwind_rule(row))
+    }
+
+    fn unwind_frame<F>(
+        row: SFrameRow,
+        abi: SFrameAbi,
+        regs: &mut UnwindRegsX86_64,
+        is_first_frame: bool,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86_64>, SFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        if let Some(unwind_rule) = Self::rule_for_row(&row, abi)? {
+            return Ok(UnwindResult::ExecRule(unwind_rule));
+        }
+
+        let ip = regs.ip();
+        let sp = regs.sp();
+        let bp = regs.bp();
+
+        let cfa_base = match row.cfa_base {
+            SFrameCfaBase::Sp => sp,
+            SFrameCfaBase::Fp => bp,
+        };
+        let cfa = checked_add_signed(cfa_base, i64::from(row.cfa_offset))
+            .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+        let mut read_at_cfa_offset = |offset: i32| {
+            let location = checked_add_signed(cfa, i64::from(offset))
+                .ok_or(SFrameUnwinderError::IntegerOverflow)?;
+            read_stack(location).map_err(|_| SFrameUnwinderError::CouldNotReadStack(location))
+        };
+        // On x86_64, the return address is always saved at CFA - 8.
+        let return_address = read_at_cfa_offset(row.ra_offset.unwrap_or(-8))?;
+        let new_bp = match row.fp_offset {
+            Some(offset) => read_at_cfa_offset(offset)?,
+            None => bp,
+        };
+
+        if cfa == sp && return_address == ip {
+            return Err(SFrameUnwinderError::DidNotAdvance);
+        }
+        if !is_first_frame && cfa < sp {
+            return Err(SFrameUnwinderError::StackPointerMovedBackwards);
+        }
+
+        regs.set_ip(return_address);
+        regs.set_bp(new_bp);
+        regs.set_sp(cfa);
+
+        Ok(UnwindResult::Uncacheable(return_address))
+    }
+}
+
+fn translate_into_unwind_rule(row: &SFrameRow) -> Option<UnwindRuleX86_64> {
+    if row.ra_offset != Some(-8) {
+        return None;
+    }
+    match row.cfa_base {
+        SFrameCfaBase::Sp => {
+            if row.cfa_offset % 8 != 0 {
+                return None;
+            }
+            let sp_offset_by_8 = u16::try_from(row.cfa_offset / 8).ok()?;
+            match row.fp_offset {
+                None => Some(UnwindRuleX86_64::OffsetSp { sp_offset_by_8 }),
+                Some(bp_cfa_offset) => {
+                    if bp_cfa_offset % 8 != 0 {
+                        return None;
+                    }
+                    let bp_storage_offset = row.cfa_offset.checked_add(bp_cfa_offset)?;
+                    let bp_storage_offset_from_sp_by_8 =
+                        i16::try_from(bp_storage_offset / 8).ok()?;
+                    Some(UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                        sp_offset_by_8,
+                        bp_storage_offset_from_sp_by_8,
+                    })
+                }
+            }
+        }
+        SFrameCfaBase::Fp => match (row.cfa_offset, row.fp_offset) {
+            (16, Some(-16)) => Some(UnwindRuleX86_64::UseFramePointer),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row(cfa_offset: i32, fp_offset: Option<i32>) -> SFrameRow {
+        SFrameRow {
+            cfa_base: SFrameCfaBase::Sp,
+            cfa_offset,
+            ra_offset: Some(-8),
+            fp_offset,
+            ra_mangled: false,
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            translate_into_unwind_rule(&row(16, Some(-16))),
+            Some(UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                sp_offset_by_8: 2,
+                bp_storage_offset_from_sp_by_8: 0,
+            })
+        );
+        // A misaligned bp offset can't be expressed in 8-byte units.
+        assert_eq!(translate_into_unwind_rule(&row(16, Some(-12))), None);
+        // Offsets from a malformed section must not overflow.
+        assert_eq!(
+            translate_into_unwind_rule(&row(0x7ffffff8, Some(0x7ffffff8))),
+            None
+        );
+    }
+}
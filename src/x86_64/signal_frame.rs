@@ -0,0 +1,86 @@
+use super::{arch::ArchX86_64, unwind_rule::UnwindRuleX86_64, unwindregs::UnwindRegsX86_64};
+use crate::signal_frame::{read_at_offset, SignalFrameUnwinderError, SignalFrameUnwinding};
+use crate::unwind_result::UnwindResult;
+
+/// The offset of `uc_mcontext` in `ucontext_t`, after `uc_flags`, `uc_link` and
+/// `uc_stack`.
+const UCONTEXT_MCONTEXT: u64 = 40;
+/// Offsets of the registers we need in `struct sigcontext`.
+const SIGCONTEXT_RBP: u64 = 10 * 8;
+const SIGCONTEXT_RSP: u64 = 15 * 8;
+const SIGCONTEXT_RIP: u64 = 16 * 8;
+
+impl SignalFrameUnwinding for ArchX86_64 {
+    /// `mov $15, %rax; syscall`
+    const LINUX_SIGRETURN_TRAMPOLINE: &'static [u8] =
+        &[0x48, 0xc7, 0xc0, 0x0f, 0x00, 0x00, 0x00, 0x0f, 0x05];
+    const LINUX_SIGRETURN_SYSCALL_OFFSET: usize = 7;
+
+    fn unwind_linux_signal_frame<F>(
+        regs: &mut UnwindRegsX86_64,
+        read_stack: &mut F,
+    ) -> Result<UnwindResult<UnwindRuleX86_64>, SignalFrameUnwinderError>
+    where
+        F: FnMut(u64) -> Result<u64, ()>,
+    {
+        // The signal handler has returned to the trampoline, which popped the return
+        // address (`pretcode`) of `struct rt_sigframe`. sp points at the `ucontext_t`.
+        let sp = regs.sp();
+        let mcontext = UCONTEXT_MCONTEXT;
+        let new_bp = read_at_offset(sp, mcontext + SIGCONTEXT_RBP, read_stack)?;
+        let new_sp = read_at_offset(sp, mcontext + SIGCONTEXT_RSP, read_stack)?;
+        let new_ip = read_at_offset(sp, mcontext + SIGCONTEXT_RIP, read_stack)?;
+        if new_sp == sp && new_ip == regs.ip() {
+            return Err(SignalFrameUnwinderError::DidNotAdvance);
+        }
+
+        regs.set_ip(new_ip);
+        regs.set_sp(new_sp);
+        regs.set_bp(new_bp);
+
+        Ok(UnwindResult::InstructionPointer(new_ip))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signal_frame::is_linux_sigreturn_trampoline;
+
+    #[test]
+    fn test_trampoline() {
+        // __restore_rt in musl, preceded by a nop.
+        let text = [
+            0x90, 0x48, 0xc7, 0xc0, 0x0f, 0x00, 0x00, 0x00, 0x0f, 0x05, 0xc3,
+        ];
+        let is_trampoline = |offset| is_linux_sigreturn_trampoline::<ArchX86_64>(&text, offset);
+        assert!(!is_trampoline(0));
+        assert!(is_trampoline(1));
+        assert!(!is_trampoline(2));
+        assert!(is_trampoline(8));
+        assert!(!is_trampoline(10));
+        assert!(!is_trampoline(100));
+    }
+
+    #[test]
+    fn test_unwind_signal_frame() {
+        let mut stack = [0u64; 64];
+        stack[5 + 10] = 0x1234; // rbp
+        stack[5 + 15] = 0x8000; // rsp
+        stack[5 + 16] = 0x401000; // rip
+        let mut read_stack = |addr: u64| stack.get((addr / 8) as usize).copied().ok_or(());
+        let mut regs = UnwindRegsX86_64::new(0x7000, 0x0, 0x50);
+        let res = ArchX86_64::unwind_linux_signal_frame(&mut regs, &mut read_stack);
+        assert_eq!(res, Ok(UnwindResult::InstructionPointer(0x401000)));
+        assert_eq!(regs.ip(), 0x401000);
+        assert_eq!(regs.sp(), 0x8000);
+        assert_eq!(regs.bp(), 0x1234);
+
+        let mut regs = UnwindRegsX86_64::new(0x7000, 0x1000, 0x50);
+        let res = ArchX86_64::unwind_linux_signal_frame(&mut regs, &mut read_stack);
+        assert_eq!(
+            res,
+            Err(SignalFrameUnwinderError::CouldNotReadStack(0x1078))
+        );
+    }
+}
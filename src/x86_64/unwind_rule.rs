@@ -2,7 +2,7 @@ use super::register_ordering;
 use super::unwindregs::{Reg, UnwindRegsX86_64};
 use crate::add_signed::checked_add_signed;
 use crate::error::Error;
-use crate::unwind_rule::UnwindRule;
+use crate::unwind_rule::{pack_rule, unpack_rule, UnwindRule};
 use arrayvec::ArrayVec;
 
 /// For all of these: return address is *(new_sp - 8)
@@ -99,6 +99,57 @@ impl UnwindRule for UnwindRuleX86_64 {
         UnwindRuleX86_64::UseFramePointer
     }
 
+    fn pack(self) -> u64 {
+        match self {
+            UnwindRuleX86_64::EndOfStack => pack_rule(0, [0, 0, 0]),
+            UnwindRuleX86_64::JustReturn => pack_rule(1, [0, 0, 0]),
+            UnwindRuleX86_64::JustReturnIfFirstFrameOtherwiseFp => pack_rule(2, [0, 0, 0]),
+            UnwindRuleX86_64::OffsetSp { sp_offset_by_8 } => pack_rule(3, [sp_offset_by_8, 0, 0]),
+            UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                sp_offset_by_8,
+                bp_storage_offset_from_sp_by_8,
+            } => pack_rule(
+                4,
+                [sp_offset_by_8, bp_storage_offset_from_sp_by_8 as u16, 0],
+            ),
+            UnwindRuleX86_64::UseFramePointer => pack_rule(5, [0, 0, 0]),
+            UnwindRuleX86_64::OffsetSpAndPopRegisters {
+                sp_offset_by_8,
+                register_count,
+                encoded_registers_to_pop,
+            } => pack_rule(
+                6,
+                [
+                    sp_offset_by_8,
+                    u16::from(register_count),
+                    encoded_registers_to_pop,
+                ],
+            ),
+        }
+    }
+
+    fn unpack(bits: u64) -> Option<Self> {
+        let (tag, [a, b, c]) = unpack_rule(bits);
+        let rule = match tag {
+            0 => UnwindRuleX86_64::EndOfStack,
+            1 => UnwindRuleX86_64::JustReturn,
+            2 => UnwindRuleX86_64::JustReturnIfFirstFrameOtherwiseFp,
+            3 => UnwindRuleX86_64::OffsetSp { sp_offset_by_8: a },
+            4 => UnwindRuleX86_64::OffsetSpAndRestoreBp {
+                sp_offset_by_8: a,
+                bp_storage_offset_from_sp_by_8: b as i16,
+            },
+            5 => UnwindRuleX86_64::UseFramePointer,
+            6 => UnwindRuleX86_64::OffsetSpAndPopRegisters {
+                sp_offset_by_8: a,
+                register_count: u8::try_from(b).ok()?,
+                encoded_registers_to_pop: c,
+            },
+            _ => return None,
+        };
+        Some(rule)
+    }
+
     fn exec<F>(
         self,
         is_first_frame: bool,
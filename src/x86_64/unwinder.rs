@@ -4,9 +4,12 @@ use super::arch::ArchX86_64;
 use super::cache::CacheX86_64;
 use super::unwindregs::UnwindRegsX86_64;
 use crate::cache::{AllocationPolicy, MayAllocateDuringUnwind};
+use crate::diagnostics::FrameDiagnostics;
 use crate::error::Error;
+use crate::frame_record::UnwindMethod;
+use crate::stack_memory::ReadStack;
 use crate::unwinder::UnwinderInternal;
-use crate::unwinder::{Module, Unwinder};
+use crate::unwinder::{Module, ModuleAddress, Unwinder};
 use crate::FrameAddress;
 
 /// The unwinder for the x86_64 CPU architecture. Use the [`Unwinder`] trait for unwinding.
@@ -53,6 +56,14 @@ impl<D: Deref<Target = [u8]>, P: AllocationPolicy> Unwinder for UnwinderX86_64<D
         self.0.max_known_code_address()
     }
 
+    fn module_for_address(&self, address: u64) -> Option<ModuleAddress<'_, Module<D>>> {
+        self.0.module_for_address(address)
+    }
+
+    fn modules(&self) -> core::slice::Iter<'_, Module<D>> {
+        self.0.modules()
+    }
+
     fn unwind_frame<F>(
         &self,
         address: FrameAddress,
@@ -61,8 +72,36 @@ impl<D: Deref<Target = [u8]>, P: AllocationPolicy> Unwinder for UnwinderX86_64<D
         read_stack: &mut F,
     ) -> Result<Option<u64>, Error>
     where
-        F: FnMut(u64) -> Result<u64, ()>,
+        F: ReadStack,
     {
         self.0.unwind_frame(address, regs, &mut cache.0, read_stack)
     }
+
+    fn unwind_frame_with_method<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsX86_64,
+        cache: &mut CacheX86_64<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, UnwindMethod)
+    where
+        F: ReadStack,
+    {
+        self.0
+            .unwind_frame_with_method(address, regs, &mut cache.0, read_stack)
+    }
+
+    fn unwind_frame_with_diagnostics<F>(
+        &self,
+        address: FrameAddress,
+        regs: &mut UnwindRegsX86_64,
+        cache: &mut CacheX86_64<P>,
+        read_stack: &mut F,
+    ) -> (Result<Option<u64>, Error>, FrameDiagnostics)
+    where
+        F: ReadStack,
+    {
+        self.0
+            .unwind_frame_with_diagnostics(address, regs, &mut cache.0, read_stack)
+    }
 }
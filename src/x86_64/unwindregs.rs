@@ -1,6 +1,7 @@
 use core::fmt::Debug;
 
 use crate::display_utils::HexNum;
+use crate::frame_record::FrameRegs;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct UnwindRegsX86_64 {
@@ -77,6 +78,16 @@ impl UnwindRegsX86_64 {
     }
 }
 
+impl FrameRegs for UnwindRegsX86_64 {
+    fn stack_pointer(&self) -> u64 {
+        self.sp()
+    }
+
+    fn frame_pointer(&self) -> u64 {
+        self.bp()
+    }
+}
+
 impl Debug for UnwindRegsX86_64 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("UnwindRegsX86_64")
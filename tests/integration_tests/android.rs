@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use framehop::arm::*;
+use framehop::FrameAddress;
+use framehop::Unwinder;
+
+use super::common;
+
+#[test]
+fn test_arm_exidx() {
+    let mut cache = CacheArm::<_>::new();
+    let mut unwinder = UnwinderArm::new();
+    common::add_object(
+        &mut unwinder,
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/android/arm/nightly-libmozglue.so"),
+        0x1000000,
+    );
+
+    // 0x34cb4: @0xb0054
+    //   Compact model index: 1
+    //   0x97      vsp = r7
+    //   0x41      vsp = vsp - 8
+    //   0x84 0x0b pop {r4, r5, r7, r14}
+    //   0x00      vsp = vsp + 4
+    //   0xb0      finish
+    let stack = [1, 2, 3, 4, 0x30, 0x1234567, 5, 6, 7, 8, 9, 10, 11, 12];
+    let mut read_stack = |addr| stack.get((addr / 4) as usize).map(|&v| v as u64).ok_or(());
+    let mut regs = UnwindRegsArm::new(0x345, 0x4, 0x10, 0x678);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x1000000 + 0x34cc4).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x1234566)));
+    assert_eq!(regs.sp(), 0x1c);
+    assert_eq!(regs.r7(), 0x30);
+    assert_eq!(regs.r11(), 0x678);
+
+    // At the function start, the return address is still in lr.
+    let mut regs = UnwindRegsArm::new(0x1000101, 0x4, 0x10, 0x678);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1000000 + 0x34cb4),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x1000100)));
+    assert_eq!(regs.sp(), 0x4);
+}
+
+#[test]
+fn test_arm_extab_generic_personality() {
+    let mut cache = CacheArm::<_>::new();
+    let mut unwinder = UnwinderArm::new();
+    common::add_object(
+        &mut unwinder,
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/android/arm/nightly-libmozglue.so"),
+        0x1000000,
+    );
+
+    // 0x80024: @0xb1b74
+    //   Personality routine: 0x9f20d
+    // The .ARM.extab entry contains the words 0x7ffed699 (personality routine),
+    // 0x01974184 and 0x0bb0b0b0, i.e. one additional word and the instructions:
+    //   0x97      vsp = r7
+    //   0x41      vsp = vsp - 8
+    //   0x84 0x0b pop {r4, r5, r7, r14}
+    //   0xb0      finish
+    let stack = [1, 2, 3, 4, 0x30, 0x1234567, 5, 6, 7, 8, 9, 10, 11, 12];
+    let mut read_stack = |addr| stack.get((addr / 4) as usize).map(|&v| v as u64).ok_or(());
+    let mut regs = UnwindRegsArm::new(0x345, 0x4, 0x10, 0x678);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x1000000 + 0x80040).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x1234566)));
+    assert_eq!(regs.sp(), 0x18);
+    assert_eq!(regs.r7(), 0x30);
+}
@@ -0,0 +1,94 @@
+use fallible_iterator::FallibleIterator;
+use framehop::aarch64::UnwindRegsAarch64;
+use framehop::x86_64::UnwindRegsX86_64;
+use framehop::{
+    AnyCache, AnyUnwindRegs, AnyUnwinder, CpuArch, Error, FrameAddress, Module, UnwindMethod,
+    UnwindSource, Unwinder,
+};
+
+#[test]
+fn test_any_unwinder_x86_64() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let mut cache = AnyCache::new(CpuArch::X86_64);
+    let mut unwinder = AnyUnwinder::new(CpuArch::X86_64);
+    assert_eq!(unwinder.arch(), CpuArch::X86_64);
+    unwinder.add_module(Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    ));
+    assert_eq!(unwinder.modules().len(), 1);
+
+    // leaf returns to 0x2000010, which has no module and is unwound with the frame
+    // pointer: the caller's rbp 0 is stored at 0x20, the return address at 0x28.
+    let stack = [1, 2, 0x2000010, 3, 0, 0x3000010, 4, 5];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    let regs = AnyUnwindRegs::from(UnwindRegsX86_64::new(0x1001010, 0x10, 0x20));
+    let frames: Vec<_> = unwinder
+        .iter_frames(0x1001010, regs, &mut cache, &mut read_stack)
+        .collect()
+        .unwrap();
+    assert_eq!(
+        frames,
+        vec![
+            FrameAddress::from_instruction_pointer(0x1001010),
+            FrameAddress::from_return_address(0x2000010).unwrap(),
+            FrameAddress::from_return_address(0x3000010).unwrap(),
+        ]
+    );
+    assert_eq!(cache.stats().total(), 3);
+}
+
+#[test]
+fn test_any_unwinder_arch_mismatch() {
+    let unwinder: AnyUnwinder<Vec<u8>> = AnyUnwinder::new(CpuArch::Aarch64);
+    let stack = [1, 2, 3, 4];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    // The registers are for a different arch than the unwinder.
+    let mut cache = AnyCache::new(CpuArch::Aarch64);
+    let mut regs = AnyUnwindRegs::from(UnwindRegsX86_64::new(0x1001010, 0x18, 0x10));
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Err(Error::ArchitectureMismatch));
+
+    // The cache is for a different arch than the unwinder.
+    let mut cache = AnyCache::new(CpuArch::Riscv64);
+    let mut regs = AnyUnwindRegs::from(UnwindRegsAarch64::new(0x1001010, 0x18, 0x10));
+    assert_eq!(regs.arch(), CpuArch::Aarch64);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Err(Error::ArchitectureMismatch));
+
+    // No unwind information was used.
+    let (_, method) = unwinder.unwind_frame_with_method(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(
+        method,
+        UnwindMethod::Uncached(UnwindSource::ArchitectureMismatch)
+    );
+    let (_, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(diagnostics.source, UnwindSource::ArchitectureMismatch);
+}
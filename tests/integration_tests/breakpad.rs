@@ -0,0 +1,54 @@
+use framehop::x86_64::*;
+use framehop::{FrameAddress, Module, Unwinder};
+
+#[test]
+fn test_breakpad_sym_x86_64() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+INFO CODE_ID 67452301AB89EFCD
+FILE 0 test.c
+FUNC 1000 30 0 with_fp
+1000 30 3 0
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+STACK CFI 1001 .cfa: $rsp 16 + $rbp: .cfa -16 + ^
+STACK CFI 1004 .cfa: $rbp 16 +
+STACK CFI 102e .cfa: $rsp 8 +
+STACK CFI INIT 2000 20 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+STACK CFI 2004 .cfa: $rsp 40 + .ra: .cfa -8 + ^ $rbx: .cfa -16 + ^
+";
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    ));
+
+    // The caller's rbp 0x40 is stored at 0x10, the return address 0x123456 at 0x18.
+    let stack = [1, 2, 0x40, 0x123456, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    // with_fp, in the body: CFA=rbp+16, rbp=[CFA-16]
+    let mut regs = UnwindRegsX86_64::new(0x1001010, 0x8, 0x10);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x20);
+    assert_eq!(regs.bp(), 0x40);
+
+    // The second function: CFA=rsp+40
+    let mut regs = UnwindRegsX86_64::new(0x1002010, 0x0, 0x345);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x1002010).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(5)));
+    assert_eq!(regs.sp(), 40);
+    assert_eq!(regs.bp(), 0x345);
+}
@@ -0,0 +1,140 @@
+use framehop::x86_64::*;
+use framehop::{FrameAddress, Module, UnwindMethod, UnwindSource, Unwinder};
+
+/// A module whose function at 0x1000 has `sp_offset` bytes of stack above the
+/// return address.
+fn breakpad_module(base_avma: u64, sp_offset: u64) -> Module<Vec<u8>> {
+    let sym = format!(
+        "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp {} + .ra: .cfa -8 + ^
+",
+        sp_offset + 8
+    );
+    Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        base_avma..base_avma + 0x3000,
+        base_avma,
+        sym.into_bytes(),
+    )
+}
+
+fn unwind(
+    unwinder: &UnwinderX86_64<Vec<u8>>,
+    cache: &mut CacheX86_64,
+    address: u64,
+) -> (Option<u64>, UnwindMethod) {
+    let stack = [1, 2, 0x100, 0x200, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+    // bp points to a frame record with the return address 0x200.
+    let mut regs = UnwindRegsX86_64::new(address, 0x10, 0x10);
+    let (res, method) = unwinder.unwind_frame_with_method(
+        FrameAddress::from_instruction_pointer(address),
+        &mut regs,
+        cache,
+        &mut read_stack,
+    );
+    (res.unwrap(), method)
+}
+
+#[test]
+fn test_only_changed_modules_are_invalidated() {
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(breakpad_module(0x1000000, 0));
+
+    let breakpad = UnwindMethod::Uncached(UnwindSource::Breakpad);
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x1001010),
+        (Some(0x100), breakpad)
+    );
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x1001010),
+        (Some(0x100), UnwindMethod::Cached)
+    );
+
+    // Adding and removing other modules keeps the cached rule.
+    unwinder.add_module(breakpad_module(0x2000000, 0));
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x1001010),
+        (Some(0x100), UnwindMethod::Cached)
+    );
+    unwinder.remove_module(0x2000000);
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x1001010),
+        (Some(0x100), UnwindMethod::Cached)
+    );
+
+    // Replacing the module invalidates its rules.
+    unwinder.remove_module(0x1000000);
+    unwinder.add_module(breakpad_module(0x1000000, 8));
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x1001010),
+        (Some(0x200), breakpad)
+    );
+
+    // Rules for addresses outside of all modules are invalidated when a module is
+    // added, because the new module might cover them.
+    let fallback = UnwindMethod::Uncached(UnwindSource::Fallback);
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x3001010),
+        (Some(0x200), fallback)
+    );
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x3001010),
+        (Some(0x200), UnwindMethod::Cached)
+    );
+    unwinder.add_module(breakpad_module(0x3000000, 0));
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x3001010),
+        (Some(0x100), breakpad)
+    );
+
+    // Removing the module falls back to frame pointers again.
+    unwinder.remove_module(0x3000000);
+    assert_eq!(
+        unwind(&unwinder, &mut cache, 0x3001010),
+        (Some(0x200), fallback)
+    );
+}
+
+#[test]
+fn test_cache_invalidation_churn() {
+    let modules = [breakpad_module(0x1000000, 0), breakpad_module(0x1000000, 8)];
+    let expected = [Some(0x100), Some(0x200)];
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    let mut other_unwinder = UnwinderX86_64::new();
+    other_unwinder.add_module(breakpad_module(0x1000000, 0));
+
+    // Enough changes for the 16-bit generations which are stored in the cache to
+    // wrap around several times.
+    for i in 0..100_000 {
+        let which = i % 2;
+        unwinder.add_module(modules[which].clone());
+        assert_eq!(unwind(&unwinder, &mut cache, 0x1001010).0, expected[which]);
+        assert_eq!(
+            unwind(&unwinder, &mut cache, 0x1001010),
+            (expected[which], UnwindMethod::Cached)
+        );
+        if i % 1000 == 0 {
+            // Another unwinder which shares the cache must never see our rules.
+            assert_eq!(
+                unwind(&other_unwinder, &mut cache, 0x1001010).0,
+                Some(0x100)
+            );
+        }
+        unwinder.remove_module(0x1000000);
+    }
+
+    // The other unwinder's generations are from an earlier era than the cache's now. It
+    // gets new ones, and its rules are cached again.
+    assert_eq!(
+        unwind(&other_unwinder, &mut cache, 0x1001010).0,
+        Some(0x100)
+    );
+    assert_eq!(
+        unwind(&other_unwinder, &mut cache, 0x1001010),
+        (Some(0x100), UnwindMethod::Cached)
+    );
+}
@@ -1,4 +1,6 @@
-use std::{borrow::Cow, io::Read, ops::Range, path::Path};
+#[cfg(not(feature = "compression"))]
+use std::borrow::Cow;
+use std::{io::Read, ops::Range, path::Path};
 
 use object::{Object, ObjectSection, ObjectSegment};
 
@@ -8,6 +10,10 @@ pub fn add_object<U>(unwinder: &mut U, objpath: &Path, base_avma: u64)
 where
     U: Unwinder<Module = Module<Vec<u8>>>,
 {
+    unwinder.add_module(module_for_object(objpath, base_avma));
+}
+
+pub fn module_for_object(objpath: &Path, base_avma: u64) -> Module<Vec<u8>> {
     let mut buf = Vec::new();
     let mut file = std::fs::File::open(objpath).unwrap();
     file.read_to_end(&mut buf).unwrap();
@@ -21,6 +27,14 @@ where
             relative_address_base(&self.0)
         }
 
+        fn address_size(&self) -> u8 {
+            if self.0.is_64() {
+                8
+            } else {
+                4
+            }
+        }
+
         fn section_svma_range(&mut self, name: &[u8]) -> Option<Range<u64>> {
             let section = self.0.section_by_name_bytes(name)?;
             Some(section.address()..section.address() + section.size())
@@ -29,6 +43,9 @@ where
         fn section_data(&mut self, name: &[u8]) -> Option<Vec<u8>> {
             match self.0.section_by_name_bytes(name) {
                 Some(section) => section.data().ok().map(|data| data.to_owned()),
+                // With the compression feature, framehop finds and decompresses
+                // __zdebug_frame itself.
+                #[cfg(not(feature = "compression"))]
                 None if name == b".debug_frame" => {
                     let section = self.0.section_by_name_bytes(b"__zdebug_frame")?;
                     get_uncompressed_section_data(&section).map(|d| d.into_owned())
@@ -54,15 +71,15 @@ where
         }
     }
 
-    let module = framehop::Module::new(
+    framehop::Module::new(
         objpath.to_string_lossy().to_string(),
         base_avma..(base_avma + buf.len() as u64),
         base_avma,
         Module(file),
-    );
-    unwinder.add_module(module);
+    )
 }
 
+#[cfg(not(feature = "compression"))]
 fn get_uncompressed_section_data<'a>(
     section: &impl object::ObjectSection<'a>,
 ) -> Option<Cow<'a, [u8]>> {
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use framehop::x86_64::*;
+use framehop::{ConcurrentUnwinder, FrameAddress, Module, MustNotAllocateDuringUnwind, Unwinder};
+
+fn test_module() -> Module<Vec<u8>> {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    )
+}
+
+#[test]
+fn test_concurrent_unwinder_add_remove() {
+    let unwinder: ConcurrentUnwinder<UnwinderX86_64<Vec<u8>>> = ConcurrentUnwinder::default();
+    assert_eq!(unwinder.snapshot().modules().len(), 0);
+    unwinder.add_module(test_module());
+    let snapshot = unwinder.snapshot();
+    assert_eq!(snapshot.modules().len(), 1);
+    assert_eq!(snapshot.max_known_code_address(), 0x1003000);
+    // The snapshot must be dropped before the modules can be changed on this thread.
+    drop(snapshot);
+
+    unwinder.remove_module(0x1000000);
+    assert_eq!(unwinder.snapshot().modules().len(), 0);
+}
+
+#[test]
+fn test_concurrent_unwinder_threads() {
+    let unwinder: ConcurrentUnwinder<UnwinderX86_64<Vec<u8>, MustNotAllocateDuringUnwind>> =
+        ConcurrentUnwinder::default();
+
+    // With the module, leaf is unwound with its CFI and returns to 0x123456 at 0x10.
+    // Without it, the frame pointer is used, which finds 0x654321 at 0x28.
+    let stack = [1, 2, 0x123456, 3, 0, 0x654321, 4, 5];
+    let done = AtomicBool::new(false);
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let mut cache = CacheX86_64::new_in();
+                let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+                while !done.load(Ordering::Relaxed) {
+                    let mut regs = UnwindRegsX86_64::new(0x1001010, 0x10, 0x20);
+                    let res = unwinder.unwind_frame(
+                        FrameAddress::from_instruction_pointer(0x1001010),
+                        &mut regs,
+                        &mut cache,
+                        &mut read_stack,
+                    );
+                    assert!(
+                        res == Ok(Some(0x123456)) || res == Ok(Some(0x654321)),
+                        "unexpected result {res:?}"
+                    );
+                }
+            });
+        }
+        for _ in 0..1000 {
+            unwinder.add_module(test_module());
+            unwinder.remove_module(0x1000000);
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+    assert_eq!(unwinder.snapshot().modules().len(), 0);
+}
@@ -0,0 +1,127 @@
+use framehop::x86_64::*;
+use framehop::{
+    BreakpadUnwinderError, ConversionError, ExplicitModuleSectionInfo, FrameAddress, Module,
+    UnwindSource, Unwinder, UnwinderError,
+};
+
+#[test]
+fn test_diagnostics_breakpad_x86_64() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    ));
+
+    // The caller's rbp 0x40 is stored at 0x10, the return address 0x123456 at 0x18.
+    let stack = [1, 2, 0x40, 0x123456, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    let mut regs = UnwindRegsX86_64::new(0x1001010, 0x18, 0x10);
+    let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::Breakpad);
+    assert_eq!(diagnostics.error, None);
+
+    // The module does not cover this address, so we fall back to frame pointers.
+    let mut regs = UnwindRegsX86_64::new(0x1002010, 0x0, 0x10);
+    let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_return_address(0x1002010).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::Fallback);
+    assert_eq!(
+        diagnostics.error,
+        Some(UnwinderError::Breakpad(
+            BreakpadUnwinderError::AddressNotCovered(0x200f)
+        ))
+    );
+
+    // No module contains this address.
+    let mut regs = UnwindRegsX86_64::new(0x2000000, 0x0, 0x10);
+    let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_return_address(0x2000000).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::Fallback);
+    assert_eq!(diagnostics.error, None);
+}
+
+#[test]
+fn test_diagnostics_conversion_error_x86_64() {
+    #[rustfmt::skip]
+    let eh_frame = [
+        // CIE: code alignment 1, data alignment -8, return address in r16,
+        // CFA = rsp + 8, r16 at CFA - 8
+        0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x78, 0x10,
+        0x0c, 0x07, 0x08, 0x90, 0x01, 0x00, 0x00,
+        // FDE for 0x1000..0x1100: CFA = DW_OP_breg7 (rsp) 8
+        0x18, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00,
+        0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x0f, 0x02, 0x77, 0x08,
+    ];
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(Module::new(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        ExplicitModuleSectionInfo {
+            base_svma: 0,
+            text_svma: Some(0x1000..0x1100),
+            eh_frame_svma: Some(0x2000..0x2000 + eh_frame.len() as u64),
+            eh_frame: Some(eh_frame.to_vec()),
+            ..Default::default()
+        },
+    ));
+
+    let stack = [1, 2, 0x123456, 4, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    let mut regs = UnwindRegsX86_64::new(0x1001010, 0x10, 0x40);
+    let mut iter = unwinder.iter_frames(0x1001010, regs, &mut cache, &mut read_stack);
+    assert_eq!(
+        iter.next_with_diagnostics(),
+        (
+            Ok(Some(FrameAddress::from_instruction_pointer(0x1001010))),
+            None
+        )
+    );
+    let (res, diagnostics) = iter.next_with_diagnostics();
+    assert_eq!(res, Ok(FrameAddress::from_return_address(0x123456)));
+    let diagnostics = diagnostics.unwrap();
+    assert_eq!(diagnostics.source, UnwindSource::EhFrameDwarfCfiIndex);
+    assert_eq!(diagnostics.error, None);
+    assert_eq!(
+        diagnostics.conversion_error,
+        Some(ConversionError::CfaIsExpression)
+    );
+
+    // The diagnostics don't change the unwinding result.
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x18);
+}
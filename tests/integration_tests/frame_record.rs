@@ -0,0 +1,72 @@
+use framehop::x86_64::*;
+use framehop::{FrameAddress, FrameRecord, Module, UnwindMethod, UnwindSource, Unwinder};
+
+#[test]
+fn test_frame_records_x86_64() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    ));
+
+    // leaf returns to 0x2000010, which has no module and is unwound with the frame
+    // pointer: the caller's rbp 0 is stored at 0x20, the return address at 0x28.
+    let stack = [1, 2, 0x2000010, 3, 0, 0x3000010, 4, 5];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    let mut records = |cache: &mut CacheX86_64| {
+        let regs = UnwindRegsX86_64::new(0x1001010, 0x10, 0x20);
+        let mut iter = unwinder.iter_frames(0x1001010, regs, cache, &mut read_stack);
+        let mut records = Vec::new();
+        while let Some(record) = iter.next_record().unwrap() {
+            records.push(record);
+        }
+        records
+    };
+    let record = |address: u64, sp, fp, method| FrameRecord {
+        address: if address == 0x1001010 {
+            FrameAddress::from_instruction_pointer(address)
+        } else {
+            FrameAddress::from_return_address(address).unwrap()
+        },
+        sp,
+        fp,
+        method,
+    };
+
+    assert_eq!(
+        records(&mut cache),
+        vec![
+            record(0x1001010, 0x10, 0x20, UnwindMethod::InitialRegisters),
+            record(
+                0x2000010,
+                0x18,
+                0x20,
+                UnwindMethod::Uncached(UnwindSource::Breakpad)
+            ),
+            record(
+                0x3000010,
+                0x30,
+                0,
+                UnwindMethod::Uncached(UnwindSource::Fallback)
+            ),
+        ]
+    );
+
+    // The second time, both rules come from the cache.
+    assert_eq!(
+        records(&mut cache),
+        vec![
+            record(0x1001010, 0x10, 0x20, UnwindMethod::InitialRegisters),
+            record(0x2000010, 0x18, 0x20, UnwindMethod::Cached),
+            record(0x3000010, 0x30, 0, UnwindMethod::Cached),
+        ]
+    );
+}
@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use framehop::aarch64::*;
+use framehop::x86::*;
 use framehop::x86_64::*;
 use framehop::FrameAddress;
 use framehop::Unwinder;
@@ -534,3 +535,173 @@ fn test_root_func_aarch64_old_glibc() {
     );
     assert_eq!(res, Ok(None));
 }
+
+#[test]
+fn test_i686_dwarf() {
+    let mut cache = CacheX86::new();
+    let mut unwinder = UnwinderX86::new();
+    // Built with gcc -m32 -O1 -fno-omit-frame-pointer -shared -nostdlib -fPIC from:
+    // extern void callee(int *);
+    // int with_fp(int x) { int a[4] = {x, x, x, x}; callee(a); return a[0] + a[3]; }
+    common::add_object(
+        &mut unwinder,
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/linux/i686/libwithfp"),
+        0x1000000,
+    );
+
+    // The stack is read in 4-byte words.
+    // The caller's ebp 0x30 is stored at 0x10 and 0x18, the return address 0x123456
+    // at 0x14 and 0x1c.
+    let stack = [1, 2, 3, 4, 0x30, 0x123456, 0x30, 0x123456, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 4) as usize).cloned().ok_or(());
+
+    // with_fp, after the call to callee: CFA=ebp+8, ebp=[CFA-8], eip=[CFA-4]
+    let mut regs = UnwindRegsX86::new(0x1000000, 0x8, 0x18);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x100104a).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x20);
+    assert_eq!(regs.bp(), 0x30);
+
+    // with_fp, after push %ebp: CFA=esp+8, ebp=[CFA-8], eip=[CFA-4]
+    let mut regs = UnwindRegsX86::new(0x1001021, 0x10, 0x345);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001021),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x18);
+    assert_eq!(regs.bp(), 0x30);
+
+    // The PLT stubs use a CFA expression which checks whether the push has already
+    // happened: CFA=esp + 4 + (((eip & 0xf) >= 0xb) << 2)
+    for (sp, rel_pc) in [(0x14, 0x1016), (0x10, 0x101b)].iter() {
+        let mut regs = UnwindRegsX86::new(0x1000000 + rel_pc, *sp, 0x345);
+        let res = unwinder.unwind_frame(
+            FrameAddress::from_instruction_pointer(0x1000000 + u64::from(*rel_pc)),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        assert_eq!(res, Ok(Some(0x123456)));
+        assert_eq!(regs.sp(), 0x18);
+        assert_eq!(regs.bp(), 0x345);
+    }
+}
+
+#[test]
+fn test_sframe_x86_64() {
+    use object::{Object, ObjectSection};
+
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    // Built with gcc -O1 -fno-omit-frame-pointer -shared -nostdlib -fPIC -Wa,--gsframe from:
+    // extern void callee(long *);
+    // long with_fp(long x) { long a[4] = {x, x, x, x}; callee(a); return a[0] + a[3]; }
+    // long no_fp(long x) { long a[4] = {x, x, x, x}; callee(a); return a[0] + a[3]; }
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/linux/x86_64/sframe/libsframe");
+    let buf = std::fs::read(&path).unwrap();
+    let file = object::File::parse(&buf[..]).unwrap();
+    let section = file.section_by_name(".sframe").unwrap();
+    // Only supply .sframe, so that the DWARF CFI in .eh_frame can't be used.
+    let module = framehop::Module::new(
+        path.to_string_lossy().to_string(),
+        0x1000000..0x1000000 + buf.len() as u64,
+        0x1000000,
+        framehop::ExplicitModuleSectionInfo {
+            base_svma: 0,
+            sframe_svma: Some(section.address()..section.address() + section.size()),
+            sframe: Some(section.data().unwrap().to_owned()),
+            ..Default::default()
+        },
+    );
+    unwinder.add_module(module);
+
+    // The caller's rbp 0x40 is stored at 0x10, the return address 0x123456 at 0x18.
+    let stack = [1, 2, 0x40, 0x123456, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    // with_fp, after the call to callee: CFA=rbp+16, rbp=[CFA-16]
+    let mut regs = UnwindRegsX86_64::new(0x1000000, 0x8, 0x10);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x1001040).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x20);
+    assert_eq!(regs.bp(), 0x40);
+
+    // with_fp, after push %rbp: CFA=rsp+16, rbp=[CFA-16]
+    let mut regs = UnwindRegsX86_64::new(0x1001021, 0x10, 0x345);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001021),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x20);
+    assert_eq!(regs.bp(), 0x40);
+
+    // The PLT entries are described by a single PC mask FDE: CFA=rsp+8 before the push
+    // at offset 0xb into each entry, CFA=rsp+16 after it.
+    for (sp, rel_pc) in [(0x18, 0x1016), (0x10, 0x101b)].iter() {
+        let mut regs = UnwindRegsX86_64::new(0x1000000 + rel_pc, *sp, 0x345);
+        let res = unwinder.unwind_frame(
+            FrameAddress::from_instruction_pointer(0x1000000 + rel_pc),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        assert_eq!(res, Ok(Some(0x123456)));
+        assert_eq!(regs.sp(), 0x20);
+        assert_eq!(regs.bp(), 0x345);
+    }
+}
+
+#[cfg(feature = "gnu-debugdata")]
+#[test]
+fn test_gnu_debugdata() {
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    // with_eh_frame is described by .eh_frame. with_debug_frame was compiled with
+    // -fno-asynchronous-unwind-tables, and its .debug_frame was moved into the xz-compressed
+    // MiniDebugInfo in .gnu_debugdata:
+    // extern void callee(long *);
+    // long with_eh_frame(long x) { long a[4] = {x, x, x, x}; callee(a); return a[0] + a[3]; }
+    // long with_debug_frame(long x) { long a[4] = {x, x, x, x}; callee(a); return a[0] + a[3]; }
+    common::add_object(
+        &mut unwinder,
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/linux/x86_64/minidebuginfo/libminidebuginfo"),
+        0x1000000,
+    );
+
+    // Both functions allocate 0x28 bytes of stack, so CFA=rsp+48 in the body.
+    let stack = [1, 2, 3, 4, 5, 0x123456, 6, 7];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+    for (rel_ip, source) in [
+        (0x1030, framehop::UnwindSource::EhFrameDwarfCfiIndex),
+        (0x1060, framehop::UnwindSource::GnuDebugdata),
+    ] {
+        let mut regs = UnwindRegsX86_64::new(0x1000000 + rel_ip, 0x0, 0x345);
+        let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_return_address(0x1000000 + rel_ip).unwrap(),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        assert_eq!(res, Ok(Some(0x123456)));
+        assert_eq!(diagnostics.source, source);
+        assert_eq!(regs.sp(), 0x30);
+        assert_eq!(regs.bp(), 0x345);
+    }
+}
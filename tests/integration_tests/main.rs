@@ -1,3 +1,19 @@
+mod android;
+mod any_unwinder;
+mod breakpad;
+mod cache_invalidation;
 mod common;
+mod concurrent_unwinder;
+mod diagnostics;
+mod frame_record;
 mod linux;
 mod macos;
+mod module_lookup;
+mod module_relative_cache;
+mod orc;
+mod precompiled_rules;
+mod serialized_rule_table;
+mod shared_cache;
+mod signal_frame;
+mod stack_bounds;
+mod stack_memory;
@@ -0,0 +1,47 @@
+use framehop::x86_64::*;
+use framehop::{ExplicitModuleSectionInfo, FrameAddress, Module, Unwinder};
+
+#[test]
+fn test_module_for_address() {
+    let mut unwinder = UnwinderX86_64::<Vec<u8>>::new();
+    unwinder.add_module(Module::new(
+        "libb.so".to_string(),
+        0x7f0000200000..0x7f0000210000,
+        0x7f0000100000,
+        ExplicitModuleSectionInfo {
+            base_svma: 0x100000,
+            ..Default::default()
+        },
+    ));
+    unwinder.add_module(Module::new(
+        "liba.so".to_string(),
+        0x7f0000000000..0x7f0000010000,
+        0x7f0000000000,
+        ExplicitModuleSectionInfo::default(),
+    ));
+
+    let names: Vec<_> = unwinder.modules().map(|m| m.name()).collect();
+    assert_eq!(names, ["liba.so", "libb.so"]);
+
+    let lookup = unwinder.module_for_address(0x7f0000001234).unwrap();
+    assert_eq!(lookup.module.name(), "liba.so");
+    assert_eq!(lookup.relative_address, 0x1234);
+    assert_eq!(lookup.svma, 0x1234);
+
+    // The mapping starts after the base address.
+    let address = FrameAddress::from_return_address(0x7f0000201001).unwrap();
+    let lookup = unwinder
+        .module_for_address(address.address_for_lookup())
+        .unwrap();
+    assert_eq!(lookup.module.name(), "libb.so");
+    assert_eq!(lookup.module.avma_range(), 0x7f0000200000..0x7f0000210000);
+    assert_eq!(lookup.relative_address, 0x101000);
+    assert_eq!(lookup.svma, 0x201000);
+
+    assert!(unwinder.module_for_address(0x7f0000010000).is_none());
+    assert!(unwinder.module_for_address(0x1000).is_none());
+
+    unwinder.remove_module(0x7f0000000000);
+    assert!(unwinder.module_for_address(0x7f0000001234).is_none());
+    assert_eq!(unwinder.modules().len(), 1);
+}
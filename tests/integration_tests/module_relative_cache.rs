@@ -0,0 +1,77 @@
+use framehop::x86_64::*;
+use framehop::{
+    CacheConfig, CacheKeyMode, FrameAddress, Module, UnwindMethod, UnwindSource, Unwinder,
+};
+
+fn breakpad_module(base_avma: u64) -> Module<Vec<u8>> {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        base_avma..base_avma + 0x3000,
+        base_avma,
+        sym.as_bytes().to_vec(),
+    )
+}
+
+fn unwind_leaf(
+    unwinder: &UnwinderX86_64<Vec<u8>>,
+    cache: &mut CacheX86_64,
+    base_avma: u64,
+) -> UnwindMethod {
+    let stack = [1, 2, 0x123456, 3, 4, 5, 6, 7];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+    let mut regs = UnwindRegsX86_64::new(base_avma + 0x1010, 0x10, 0x20);
+    let (res, method) = unwinder.unwind_frame_with_method(
+        FrameAddress::from_instruction_pointer(base_avma + 0x1010),
+        &mut regs,
+        cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    method
+}
+
+#[test]
+fn test_module_relative_cache_keys() {
+    let mut cache = CacheX86_64::with_config(CacheConfig {
+        key_mode: CacheKeyMode::ModuleRelative,
+        ..Default::default()
+    });
+
+    // The same library is loaded at different addresses in two processes.
+    let mut unwinder1 = UnwinderX86_64::new();
+    unwinder1.add_module(breakpad_module(0x1000000).with_cache_key(0xabcd));
+    let mut unwinder2 = UnwinderX86_64::new();
+    unwinder2.add_module(breakpad_module(0x7f0000000).with_cache_key(0xabcd));
+
+    assert_eq!(
+        unwind_leaf(&unwinder1, &mut cache, 0x1000000),
+        UnwindMethod::Uncached(UnwindSource::Breakpad)
+    );
+    assert_eq!(
+        unwind_leaf(&unwinder2, &mut cache, 0x7f0000000),
+        UnwindMethod::Cached
+    );
+
+    // Adding modules doesn't invalidate the cached rules.
+    unwinder1.add_module(breakpad_module(0x2000000));
+    assert_eq!(
+        unwind_leaf(&unwinder1, &mut cache, 0x1000000),
+        UnwindMethod::Cached
+    );
+
+    // Rules for modules without a cache key are not cached.
+    assert_eq!(
+        unwind_leaf(&unwinder1, &mut cache, 0x2000000),
+        UnwindMethod::Uncached(UnwindSource::Breakpad)
+    );
+    assert_eq!(
+        unwind_leaf(&unwinder1, &mut cache, 0x2000000),
+        UnwindMethod::Uncached(UnwindSource::Breakpad)
+    );
+    assert_eq!(cache.stats().total(), 3);
+    assert_eq!(cache.stats().hits(), 2);
+}
@@ -0,0 +1,69 @@
+use std::ops::Range;
+
+use framehop::x86_64::*;
+use framehop::{FrameAddress, Module, ModuleSectionInfo, UnwindSource, Unwinder, UnwinderError};
+
+/// The sections of a kernel image or module with an ORC table for one function at
+/// 0x1000, whose CFA is sp + 8. `relocatable` adds the `.rela.orc_unwind_ip` section of a
+/// `.ko` file.
+struct KernelSections {
+    relocatable: bool,
+}
+
+impl ModuleSectionInfo<Vec<u8>> for KernelSections {
+    fn base_svma(&self) -> u64 {
+        0
+    }
+
+    fn section_svma_range(&mut self, name: &[u8]) -> Option<Range<u64>> {
+        match name {
+            b".orc_unwind_ip" => Some(0x5000..0x5004),
+            b".rela.orc_unwind_ip" if self.relocatable => Some(0..0x18),
+            _ => None,
+        }
+    }
+
+    fn section_data(&mut self, name: &[u8]) -> Option<Vec<u8>> {
+        match name {
+            // 0x1000, relative to the entry's address
+            b".orc_unwind_ip" => Some((-0x4000i32).to_le_bytes().to_vec()),
+            // sp + 8, in the layout from before Linux 6.4
+            b".orc_unwind" => Some(vec![8, 0, 0, 0, 5, 0]),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_orc_relocatable_object() {
+    let unwind = |relocatable| {
+        let mut unwinder = UnwinderX86_64::new();
+        unwinder.add_module(Module::new(
+            "kernel".to_string(),
+            0xffffffff81000000..0xffffffff81010000,
+            0xffffffff81000000,
+            KernelSections { relocatable },
+        ));
+        let stack = [0x123456u64, 2, 3, 4];
+        let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+        let mut regs = UnwindRegsX86_64::new(0xffffffff81001000, 0, 0x10);
+        let (_, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_instruction_pointer(0xffffffff81001000),
+            &mut regs,
+            &mut CacheX86_64::new(),
+            &mut read_stack,
+        );
+        (diagnostics.source, diagnostics.error)
+    };
+
+    assert_eq!(unwind(false), (UnwindSource::Orc, None));
+
+    // The table in a .ko file hasn't been relocated, so it's not used.
+    assert_eq!(
+        unwind(true),
+        (
+            UnwindSource::Fallback,
+            Some(UnwinderError::NoModuleUnwindData)
+        )
+    );
+}
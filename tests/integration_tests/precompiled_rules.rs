@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use object::{Object, ObjectSection};
+
+use framehop::aarch64::*;
+use framehop::x86_64::*;
+use framehop::{
+    CompileRuleTableError, ConversionError, CpuArch, ExplicitModuleSectionInfo, FrameAddress,
+    Module, UnwindSource, Unwinder,
+};
+
+use super::common;
+
+#[test]
+fn test_precompiled_rules_match_dwarf_aarch64() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/linux/aarch64/libc-2.31.so");
+    let data = std::fs::read(&path).unwrap();
+    let file = object::File::parse(&data[..]).unwrap();
+    let text = file.section_by_name(".text").unwrap();
+    let text_range = text.address()..text.address() + text.size();
+
+    let mut unwinder = UnwinderAarch64::new();
+    unwinder.add_module(common::module_for_object(&path, 0x10000000));
+    let mut precompiled_unwinder = UnwinderAarch64::new();
+    precompiled_unwinder
+        .add_module(common::module_for_object(&path, 0x10000000).with_precompiled_rules());
+
+    let mut cache = CacheAarch64::new();
+    let mut read_stack = |addr: u64| Ok(addr.wrapping_mul(3) & !0xf);
+    let mut precompiled_count = 0;
+    for address in text_range.step_by(16).map(|svma| 0x10000000 + svma) {
+        for address in [
+            FrameAddress::from_instruction_pointer(address),
+            FrameAddress::from_return_address(address).unwrap(),
+        ] {
+            let mut regs = UnwindRegsAarch64::new(0x20001000, 0x7fff1000, 0x7fff1100);
+            let mut precompiled_regs = regs;
+            let res = unwinder.unwind_frame(address, &mut regs, &mut cache, &mut read_stack);
+            let (precompiled_res, diagnostics) = precompiled_unwinder
+                .unwind_frame_with_diagnostics(
+                    address,
+                    &mut precompiled_regs,
+                    &mut cache,
+                    &mut read_stack,
+                );
+            assert_eq!(res, precompiled_res, "at {address:?}");
+            assert_eq!(regs, precompiled_regs, "at {address:?}");
+            if diagnostics.source == UnwindSource::PrecompiledRules {
+                precompiled_count += 1;
+            }
+        }
+    }
+    // Almost all of libc's CFI rows can be expressed as unwind rules.
+    assert!(precompiled_count > 50_000, "{precompiled_count}");
+}
+
+#[test]
+fn test_precompiled_rules_fall_back_to_dwarf() {
+    #[rustfmt::skip]
+    let eh_frame = [
+        // CIE: code alignment 1, data alignment -8, return address in r16,
+        // CFA = rsp + 8, r16 at CFA - 8
+        0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x78, 0x10,
+        0x0c, 0x07, 0x08, 0x90, 0x01, 0x00, 0x00,
+        // FDE for 0x1000..0x1100: CFA = rsp + 8 until 0x1010, then
+        // CFA = DW_OP_breg7 (rsp) 8
+        0x1c, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00,
+        0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x50, 0x0f, 0x02, 0x77, 0x08, 0x00, 0x00, 0x00,
+    ];
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(
+        Module::new(
+            "libtest.so".to_string(),
+            0x1000000..0x1003000,
+            0x1000000,
+            ExplicitModuleSectionInfo {
+                base_svma: 0,
+                text_svma: Some(0x1000..0x1100),
+                eh_frame_svma: Some(0x2000..0x2000 + eh_frame.len() as u64),
+                eh_frame: Some(eh_frame.to_vec()),
+                ..Default::default()
+            },
+        )
+        .with_precompiled_rules(),
+    );
+
+    let stack = [1, 2, 0x123456, 4, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    // The first row is a plain offset from rsp and is in the table.
+    let mut regs = UnwindRegsX86_64::new(0x1001008, 0x10, 0x40);
+    let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_instruction_pointer(0x1001008),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::PrecompiledRules);
+
+    // The second row's CFA is an expression, so it's evaluated from the CFI.
+    let mut regs = UnwindRegsX86_64::new(0x1001020, 0x10, 0x40);
+    let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_instruction_pointer(0x1001020),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::EhFrameDwarfCfiIndex);
+    assert_eq!(
+        diagnostics.conversion_error,
+        Some(ConversionError::CfaIsExpression)
+    );
+}
+
+#[test]
+fn test_precompiled_rules_match_compact_unwind_info_x86_64() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/macos/x86_64/nofp/libmozglue.dylib");
+    let data = std::fs::read(&path).unwrap();
+    let file = object::File::parse(&data[..]).unwrap();
+    let text = file.section_by_name("__text").unwrap();
+    let text_range = text.address()..text.address() + text.size();
+
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(common::module_for_object(&path, 0x10000000));
+    let mut module = common::module_for_object(&path, 0x10000000);
+    assert_eq!(module.precompile_rules(CpuArch::X86_64), Ok(()));
+    let mut precompiled_unwinder = UnwinderX86_64::new();
+    precompiled_unwinder.add_module(module);
+
+    let mut cache = CacheX86_64::new();
+    let mut read_stack = |addr: u64| Ok(addr.wrapping_mul(3) & !0xf);
+    let mut precompiled_count = 0;
+    for address in text_range.step_by(7).map(|svma| 0x10000000 + svma) {
+        for address in [
+            FrameAddress::from_instruction_pointer(address),
+            FrameAddress::from_return_address(address).unwrap(),
+        ] {
+            let mut regs = UnwindRegsX86_64::new(0x20001000, 0x7fff1000, 0x7fff1100);
+            let mut precompiled_regs = regs;
+            let res = unwinder.unwind_frame(address, &mut regs, &mut cache, &mut read_stack);
+            let (precompiled_res, diagnostics) = precompiled_unwinder
+                .unwind_frame_with_diagnostics(
+                    address,
+                    &mut precompiled_regs,
+                    &mut cache,
+                    &mut read_stack,
+                );
+            assert_eq!(res, precompiled_res, "at {address:?}");
+            assert_eq!(regs, precompiled_regs, "at {address:?}");
+            if diagnostics.source == UnwindSource::PrecompiledRules {
+                // The first frame may be in a prologue or epilogue, which the table
+                // doesn't know about.
+                assert!(
+                    matches!(address, FrameAddress::ReturnAddress(_)),
+                    "at {address:?}"
+                );
+                precompiled_count += 1;
+            }
+        }
+    }
+    assert!(precompiled_count > 50_000, "{precompiled_count}");
+}
+
+#[test]
+fn test_precompile_rules_errors() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let mut breakpad_module = Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    );
+    assert_eq!(
+        breakpad_module.precompile_rules(CpuArch::X86_64),
+        Err(CompileRuleTableError::UnsupportedUnwindInfo)
+    );
+
+    // The module is still unwound with its symbol file.
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(breakpad_module.with_precompiled_rules());
+    let stack = [1, 2, 0x123456, 4];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+    let mut regs = UnwindRegsX86_64::new(0x1001008, 0x10, 0x40);
+    let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_instruction_pointer(0x1001008),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::Breakpad);
+}
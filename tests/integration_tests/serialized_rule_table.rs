@@ -0,0 +1,284 @@
+use std::path::Path;
+
+use object::{Object, ObjectSection};
+
+use framehop::aarch64::*;
+use framehop::x86_64::*;
+use framehop::{
+    CompileRuleTableError, CpuArch, ExplicitModuleSectionInfo, FrameAddress, Module,
+    RuleTableError, SerializedRuleTable, UnwindSource, Unwinder, UnwinderError,
+};
+
+use super::common;
+
+#[rustfmt::skip]
+const EH_FRAME: [u8; 52] = [
+    // CIE: code alignment 1, data alignment -8, return address in r16,
+    // CFA = rsp + 8, r16 at CFA - 8
+    0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x78, 0x10,
+    0x0c, 0x07, 0x08, 0x90, 0x01, 0x00, 0x00,
+    // FDE for 0x1000..0x1100: CFA = rsp + 8 until 0x1010, then
+    // CFA = DW_OP_breg7 (rsp) 8
+    0x1c, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00,
+    0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x50, 0x0f, 0x02, 0x77, 0x08, 0x00, 0x00, 0x00,
+];
+
+fn eh_frame_module() -> Module<Vec<u8>> {
+    Module::new(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        ExplicitModuleSectionInfo {
+            base_svma: 0,
+            text_svma: Some(0x1000..0x1100),
+            eh_frame_svma: Some(0x2000..0x2000 + EH_FRAME.len() as u64),
+            eh_frame: Some(EH_FRAME.to_vec()),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn test_serialized_rule_table_format() {
+    let data = eh_frame_module()
+        .serialize_rule_table(CpuArch::X86_64)
+        .unwrap();
+    #[rustfmt::skip]
+    assert_eq!(
+        data,
+        [
+            // Magic, version 1, x86_64, 8-byte addresses
+            b'F', b'H', b'R', b'T', 0x01, 0x00, 0x01, 0x08,
+            // Base SVMA 0, 4 entries, reserved
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // 0x0: before the FDE, JustReturnIfFirstFrameOtherwiseFp
+            0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // 0x1000: OffsetSp { sp_offset_by_8: 1 }
+            0x00, 0x10, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // 0x1010: the CFA is an expression, no rule
+            0x10, 0x10, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            // 0x1100: after the FDE, JustReturnIfFirstFrameOtherwiseFp
+            0x00, 0x11, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]
+    );
+
+    // Breakpad symbol files can't be serialized.
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let breakpad_module = Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    );
+    assert_eq!(
+        breakpad_module.serialize_rule_table(CpuArch::X86_64),
+        Err(CompileRuleTableError::UnsupportedUnwindInfo)
+    );
+
+    let mut bad_magic = data.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(
+        SerializedRuleTable::new(bad_magic).err(),
+        Some(RuleTableError::BadMagic)
+    );
+    let mut bad_version = data.clone();
+    bad_version[4] = 2;
+    assert_eq!(
+        SerializedRuleTable::new(bad_version).err(),
+        Some(RuleTableError::UnsupportedVersion(2))
+    );
+    assert_eq!(
+        SerializedRuleTable::new(data[..data.len() - 1].to_vec()).err(),
+        Some(RuleTableError::TooShort)
+    );
+}
+
+#[test]
+fn test_serialized_rule_table_x86_64() {
+    let data = eh_frame_module()
+        .serialize_rule_table(CpuArch::X86_64)
+        .unwrap();
+    let module = Module::new(
+        "libtest.so".to_string(),
+        0x5000000..0x5003000,
+        0x5000000,
+        SerializedRuleTable::new(data.clone()).unwrap(),
+    );
+    // A loaded table serializes to the same bytes.
+    assert_eq!(module.serialize_rule_table(CpuArch::X86_64), Ok(data));
+    assert_eq!(
+        module.serialize_rule_table(CpuArch::Aarch64),
+        Err(CompileRuleTableError::ArchMismatch(CpuArch::X86_64))
+    );
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(module);
+
+    let stack = [1, 2, 0x123456, 4, 5, 6, 7, 8];
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+    let mut unwind = |address| {
+        let mut regs = UnwindRegsX86_64::new(address, 0x10, 0x40);
+        unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_instruction_pointer(address),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        )
+    };
+
+    let (res, diagnostics) = unwind(0x5001008);
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(diagnostics.source, UnwindSource::SerializedRuleTable);
+    assert_eq!(diagnostics.error, None);
+
+    // The row without a rule falls back to frame pointer unwinding.
+    let (_, diagnostics) = unwind(0x5001020);
+    assert_eq!(diagnostics.source, UnwindSource::Fallback);
+    assert_eq!(
+        diagnostics.error,
+        Some(UnwinderError::RuleTableCouldNotFindAddress)
+    );
+
+    // The table can't be used by an unwinder for a different arch.
+    let mut aarch64_unwinder = UnwinderAarch64::new();
+    aarch64_unwinder.add_module(Module::new(
+        "libtest.so".to_string(),
+        0x5000000..0x5003000,
+        0x5000000,
+        SerializedRuleTable::new(
+            eh_frame_module()
+                .serialize_rule_table(CpuArch::X86_64)
+                .unwrap(),
+        )
+        .unwrap(),
+    ));
+    let mut regs = UnwindRegsAarch64::new(0x5001008, 0x10, 0x40);
+    let (_, diagnostics) = aarch64_unwinder.unwind_frame_with_diagnostics(
+        FrameAddress::from_instruction_pointer(0x5001008),
+        &mut regs,
+        &mut CacheAarch64::new(),
+        &mut read_stack,
+    );
+    assert_eq!(
+        diagnostics.error,
+        Some(UnwinderError::RuleTableArchMismatch(CpuArch::X86_64))
+    );
+}
+
+#[test]
+fn test_serialized_rule_table_matches_dwarf_aarch64() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/linux/aarch64/libc-2.31.so");
+    let data = std::fs::read(&path).unwrap();
+    let file = object::File::parse(&data[..]).unwrap();
+    let text = file.section_by_name(".text").unwrap();
+    let text_range = text.address()..text.address() + text.size();
+
+    let module = common::module_for_object(&path, 0x10000000);
+    let table = module.serialize_rule_table(CpuArch::Aarch64).unwrap();
+    let mut unwinder = UnwinderAarch64::new();
+    unwinder.add_module(module);
+    // The offline module can be mapped at a different address.
+    let mut offline_unwinder = UnwinderAarch64::new();
+    offline_unwinder.add_module(Module::new(
+        "libc-2.31.so".to_string(),
+        0x30000000..0x30000000 + data.len() as u64,
+        0x30000000,
+        SerializedRuleTable::new(table).unwrap(),
+    ));
+
+    let mut cache = CacheAarch64::new();
+    let mut read_stack = |addr: u64| Ok(addr.wrapping_mul(3) & !0xf);
+    let mut compared_count = 0;
+    for svma in text_range.step_by(16) {
+        let mut regs = UnwindRegsAarch64::new(0x20001000, 0x7fff1000, 0x7fff1100);
+        let mut offline_regs = regs;
+        let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_return_address(0x10000000 + svma).unwrap(),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        let (offline_res, offline_diagnostics) = offline_unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_return_address(0x30000000 + svma).unwrap(),
+            &mut offline_regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        if diagnostics.source == UnwindSource::EhFrameHdr && diagnostics.conversion_error.is_none()
+        {
+            assert_eq!(
+                offline_diagnostics.source,
+                UnwindSource::SerializedRuleTable,
+                "at 0x{svma:x} {diagnostics:?} {offline_diagnostics:?}"
+            );
+            assert_eq!(res, offline_res, "at 0x{svma:x}");
+            assert_eq!(regs, offline_regs, "at 0x{svma:x}");
+            compared_count += 1;
+        }
+    }
+    assert!(compared_count > 50_000, "{compared_count}");
+}
+
+#[test]
+fn test_serialized_rule_table_matches_compact_unwind_info_x86_64() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/macos/x86_64/nofp/libmozglue.dylib");
+    let data = std::fs::read(&path).unwrap();
+    let file = object::File::parse(&data[..]).unwrap();
+    let text = file.section_by_name("__text").unwrap();
+    let text_range = text.address()..text.address() + text.size();
+
+    let module = common::module_for_object(&path, 0x10000000);
+    let table = module.serialize_rule_table(CpuArch::X86_64).unwrap();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(module);
+    let mut offline_unwinder = UnwinderX86_64::new();
+    offline_unwinder.add_module(Module::new(
+        "libmozglue.dylib".to_string(),
+        0x30000000..0x30000000 + data.len() as u64,
+        0x30000000,
+        SerializedRuleTable::new(table).unwrap(),
+    ));
+
+    let mut cache = CacheX86_64::new();
+    let mut read_stack = |addr: u64| Ok(addr.wrapping_mul(3) & !0xf);
+    let mut compared_count = 0;
+    for svma in text_range {
+        let mut regs = UnwindRegsX86_64::new(0x20001000, 0x7fff1000, 0x7fff1100);
+        let mut offline_regs = regs;
+        let (res, diagnostics) = unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_return_address(0x10000000 + svma).unwrap(),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        let (offline_res, offline_diagnostics) = offline_unwinder.unwind_frame_with_diagnostics(
+            FrameAddress::from_return_address(0x30000000 + svma).unwrap(),
+            &mut offline_regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        let from_unwind_info = match diagnostics.source {
+            UnwindSource::CompactUnwindInfo => true,
+            UnwindSource::CompactUnwindInfoEhFrame => diagnostics.conversion_error.is_none(),
+            _ => false,
+        };
+        if from_unwind_info && diagnostics.error.is_none() {
+            assert_eq!(
+                offline_diagnostics.source,
+                UnwindSource::SerializedRuleTable,
+                "at 0x{svma:x} {diagnostics:?} {offline_diagnostics:?}"
+            );
+            assert_eq!(res, offline_res, "at 0x{svma:x}");
+            assert_eq!(regs, offline_regs, "at 0x{svma:x}");
+            compared_count += 1;
+        }
+    }
+    assert!(compared_count > 400_000, "{compared_count}");
+}
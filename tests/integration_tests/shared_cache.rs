@@ -0,0 +1,56 @@
+use framehop::x86_64::*;
+use framehop::{FrameAddress, Module, Unwinder};
+
+#[test]
+fn test_shared_cache_threads() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    ));
+    let shared = SharedCacheX86_64::new();
+    let stack = [1, 2, 0x123456, 3, 4, 5, 6, 7];
+
+    // Warm up the shared cache on this thread.
+    let mut cache = CacheX86_64::new_shared(&shared);
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+    let mut regs = UnwindRegsX86_64::new(0x1001010, 0x10, 0x20);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_instruction_pointer(0x1001010),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let mut cache = CacheX86_64::new_shared(&shared);
+                let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+                for _ in 0..100 {
+                    let mut regs = UnwindRegsX86_64::new(0x1001010, 0x10, 0x20);
+                    let res = unwinder.unwind_frame(
+                        FrameAddress::from_instruction_pointer(0x1001010),
+                        &mut regs,
+                        &mut cache,
+                        &mut read_stack,
+                    );
+                    assert_eq!(res, Ok(Some(0x123456)));
+                }
+                // The rule was found by the main thread, so all lookups are hits.
+                assert_eq!(cache.stats().hits(), 100);
+            });
+        }
+    });
+
+    let stats = shared.stats();
+    assert_eq!(stats.total(), 401);
+    assert_eq!(stats.hits(), 400);
+}
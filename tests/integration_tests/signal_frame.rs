@@ -0,0 +1,132 @@
+use framehop::x86_64::*;
+use framehop::{ExplicitModuleSectionInfo, FrameAddress, Module, Unwinder};
+
+#[test]
+fn test_sigreturn_trampoline_without_cfi_x86_64() {
+    // A musl-style __restore_rt at 0x1010, without any CFI.
+    let mut text = vec![0xcc; 0x20];
+    text[0x10..0x19].copy_from_slice(&[0x48, 0xc7, 0xc0, 0x0f, 0x00, 0x00, 0x00, 0x0f, 0x05]);
+    let module = |text: &[u8], rt_sigreturn_svma| {
+        Module::new(
+            "libc.so".to_string(),
+            0x7f0000000000..0x7f0000002000,
+            0x7f0000000000,
+            ExplicitModuleSectionInfo {
+                base_svma: 0,
+                text_svma: Some(0x1000..0x1020),
+                text: Some(text.to_vec()),
+                rt_sigreturn_svma,
+                ..Default::default()
+            },
+        )
+    };
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(module(&text, Some(0x1010..0x1019)));
+
+    // The signal handler returned to __restore_rt, and sp points at the ucontext_t.
+    let mut stack = [0u64; 48];
+    stack[5 + 10] = 0x200; // rbp
+    stack[5 + 15] = 0x180; // rsp
+    stack[5 + 16] = 0x7f0000001004; // rip
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    for _ in 0..2 {
+        let mut regs = UnwindRegsX86_64::new(0x7f0000001010, 0x0, 0x10);
+        let res = unwinder.unwind_frame(
+            FrameAddress::from_return_address(0x7f0000001010).unwrap(),
+            &mut regs,
+            &mut cache,
+            &mut read_stack,
+        );
+        assert_eq!(res, Ok(Some(0x7f0000001004)));
+        assert_eq!(regs.sp(), 0x180);
+        assert_eq!(regs.bp(), 0x200);
+    }
+
+    // The interrupted frame is yielded as an instruction pointer, so that it's looked up
+    // at its address and unwound like a first frame.
+    let regs = UnwindRegsX86_64::new(0x7f0000001010, 0x0, 0x10);
+    let mut iter = unwinder.iter_frames(0x7f0000001010, regs, &mut cache, &mut read_stack);
+    assert_eq!(
+        iter.next(),
+        Ok(Some(FrameAddress::InstructionPointer(0x7f0000001010)))
+    );
+    assert_eq!(
+        iter.next(),
+        Ok(Some(FrameAddress::InstructionPointer(0x7f0000001004)))
+    );
+
+    // Without the symbol range, like in a stripped musl libc.so, the trampoline is found
+    // by its instructions.
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(module(&text, None));
+    let mut regs = UnwindRegsX86_64::new(0x7f0000001010, 0x0, 0x10);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x7f0000001010).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_eq!(res, Ok(Some(0x7f0000001004)));
+
+    // Other code is not mistaken for a trampoline.
+    let mut cache = CacheX86_64::new();
+    let mut unwinder = UnwinderX86_64::new();
+    text[0x17..0x19].copy_from_slice(&[0xcc, 0xcc]);
+    unwinder.add_module(module(&text, None));
+    let mut regs = UnwindRegsX86_64::new(0x7f0000001010, 0x0, 0x10);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x7f0000001010).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    assert_ne!(res, Ok(Some(0x7f0000001004)));
+}
+
+#[cfg(feature = "macho")]
+#[test]
+fn test_sigtramp_symbol_range_aarch64() {
+    use framehop::aarch64::*;
+
+    let mut cache = CacheAarch64::new();
+    let mut unwinder = UnwinderAarch64::new();
+    unwinder.add_module(Module::new(
+        "libsystem_platform.dylib".to_string(),
+        0x180000000..0x180010000,
+        0x180000000,
+        ExplicitModuleSectionInfo {
+            base_svma: 0x180000000,
+            unwind_info: Some(vec![]),
+            sigtramp_svma: Some(0x180004000..0x180004080),
+            ..Default::default()
+        },
+    ));
+
+    // _sigtramp's frame record is at 0x100. The kernel put the siginfo at 0x110, the
+    // ucontext at 0x178 and the mcontext at 0x1b0.
+    let mut stack = [0u64; 0x80];
+    stack[(0x178 + 40) / 8] = 808; // uc_mcsize
+    stack[(0x178 + 48) / 8] = 0x1b0; // uc_mcontext
+    stack[(0x1b0 + 16 + 29 * 8) / 8] = 0x400; // fp
+    stack[(0x1b0 + 16 + 30 * 8) / 8] = 0x100002000; // lr
+    stack[(0x1b0 + 16 + 31 * 8) / 8] = 0x300; // sp
+    stack[(0x1b0 + 16 + 32 * 8) / 8] = 0x100001000; // pc
+    let mut read_stack = |addr| stack.get((addr / 8) as usize).cloned().ok_or(());
+
+    let mut regs = UnwindRegsAarch64::new(0x180004040, 0xe0, 0x100);
+    let (res, method) = unwinder.unwind_frame_with_method(
+        FrameAddress::from_return_address(0x180004040).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut read_stack,
+    );
+    // The returned address is the interrupted pc, not a return address.
+    assert_eq!(res, Ok(Some(0x100001000)));
+    assert!(method.is_signal_frame());
+    assert_eq!(regs.lr(), 0x100002000);
+    assert_eq!(regs.sp(), 0x300);
+    assert_eq!(regs.fp(), 0x400);
+}
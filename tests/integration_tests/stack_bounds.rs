@@ -0,0 +1,94 @@
+use framehop::x86_64::*;
+use framehop::{FrameAddress, ReadStack, StackBounds, StackEnd, StackRegions, Unwinder};
+
+// The frames are unwound with the frame pointer. The first frame record at 0x1010 has
+// the caller's rbp 0x1020 and the return address 0x200000. The second one has the
+// return address 0x300000, and its rbp 0 ends the stack.
+const WORDS: [u64; 6] = [1, 2, 0x1020, 0x200000, 0, 0x300000];
+
+fn unwind<F: ReadStack>(
+    read_stack: &mut F,
+    bounds: Option<StackBounds>,
+) -> (Vec<FrameAddress>, Option<StackEnd>) {
+    let mut cache = CacheX86_64::new();
+    let unwinder: UnwinderX86_64<Vec<u8>> = UnwinderX86_64::new();
+    let regs = UnwindRegsX86_64::new(0x100000, 0x1000, 0x1010);
+    let mut iter = unwinder.iter_frames(0x100000, regs, &mut cache, read_stack);
+    if let Some(bounds) = bounds {
+        iter = iter.with_stack_bounds(bounds);
+    }
+    let mut frames = Vec::new();
+    while let Ok(Some(frame)) = iter.next() {
+        assert_eq!(iter.stack_end(), None);
+        frames.push(frame);
+    }
+    (frames, iter.stack_end())
+}
+
+fn bounds(copied_end: u64) -> Option<StackBounds> {
+    Some(StackBounds {
+        thread_stack: 0x800..0x2000,
+        copied: Some(0x1000..copied_end),
+    })
+}
+
+#[test]
+fn test_stack_end_complete() {
+    let stack: Vec<u8> = WORDS.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let mut regions = StackRegions::with_region(0x1000, &stack[..]);
+    let (frames, end) = unwind(&mut regions, bounds(0x1030));
+    assert_eq!(
+        frames,
+        vec![
+            FrameAddress::from_instruction_pointer(0x100000),
+            FrameAddress::from_return_address(0x200000).unwrap(),
+            FrameAddress::from_return_address(0x300000).unwrap(),
+        ]
+    );
+    assert_eq!(end, Some(StackEnd::Complete));
+}
+
+#[test]
+fn test_stack_end_cut_off() {
+    let stack: Vec<u8> = WORDS.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+    // The copy ends before the second frame record.
+    let mut regions = StackRegions::with_region(0x1000, &stack[..0x20]);
+    let (frames, end) = unwind(&mut regions, bounds(0x1020));
+    assert_eq!(frames.len(), 2);
+    assert_eq!(end, Some(StackEnd::CutOff));
+
+    // The same, with a callback which reads zeros outside of the copy. The copied range
+    // keeps the zeros from being taken for the end of the stack.
+    let mut read_stack = |addr: u64| match addr {
+        0x1000..0x1020 => Ok(WORDS[(addr - 0x1000) as usize / 8]),
+        _ => Ok(0),
+    };
+    let (frames, end) = unwind(&mut read_stack, bounds(0x1020));
+    assert_eq!(frames.len(), 2);
+    assert_eq!(end, Some(StackEnd::CutOff));
+
+    // Without bounds, failed reads count as the end of the available memory.
+    let (frames, end) = unwind(&mut regions, None);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(end, Some(StackEnd::CutOff));
+}
+
+#[test]
+fn test_stack_end_corrupted() {
+    // The saved rbp points outside of the thread's stack, into memory which could be
+    // read.
+    let mut words = WORDS;
+    words[2] = 0x5000;
+    let mut read_stack = |addr: u64| match addr {
+        0x1000..0x1030 => Ok(words[(addr - 0x1000) as usize / 8]),
+        _ => Ok(0x123456),
+    };
+    let bounds = Some(StackBounds {
+        thread_stack: 0x800..0x2000,
+        copied: None,
+    });
+    let (frames, end) = unwind(&mut read_stack, bounds);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(end, Some(StackEnd::Corrupted));
+}
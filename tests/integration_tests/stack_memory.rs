@@ -0,0 +1,99 @@
+use std::ops::Range;
+
+use framehop::x86::{CacheX86, UnwindRegsX86, UnwinderX86};
+use framehop::x86_64::*;
+use framehop::{
+    FrameAddress, Module, ReadStack, StackMemory, StackMemoryReader, StackRegions, Unwinder,
+};
+
+fn frames<F: ReadStack>(
+    unwinder: &UnwinderX86_64<Vec<u8>>,
+    read_stack: &mut F,
+) -> Vec<FrameAddress> {
+    let mut cache = CacheX86_64::new();
+    let regs = UnwindRegsX86_64::new(0x1001010, 0x1010, 0x1020);
+    let mut iter = unwinder.iter_frames(0x1001010, regs, &mut cache, read_stack);
+    let mut frames = Vec::new();
+    while let Ok(Some(frame)) = iter.next() {
+        frames.push(frame);
+    }
+    frames
+}
+
+#[test]
+fn test_stack_regions_x86_64() {
+    let sym = "MODULE Linux x86_64 0123456789ABCDEF0 libtest.so
+FUNC 1000 30 0 leaf
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+";
+    let mut unwinder = UnwinderX86_64::new();
+    unwinder.add_module(Module::new_from_breakpad_sym(
+        "libtest.so".to_string(),
+        0x1000000..0x1003000,
+        0x1000000,
+        sym.as_bytes().to_vec(),
+    ));
+
+    // leaf returns to 0x2000010, which is unwound with the frame pointer: the caller's
+    // rbp 0x1030 is stored at 0x1020, the return address at 0x1028. The next frame's
+    // rbp 0 at 0x1030 ends the stack.
+    let words: [u64; 6] = [0x2000010, 1, 0x1030, 0x3000010, 0, 0x4000010];
+    let stack: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let mut read_stack = |addr: u64| {
+        let index = addr.checked_sub(0x1010).ok_or(())? / 8;
+        words.get(index as usize).cloned().ok_or(())
+    };
+    let expected = frames(&unwinder, &mut read_stack);
+    assert_eq!(expected.len(), 4);
+
+    // A copy of the stack, starting at the stack pointer, like in a perf sample.
+    let mut regions = StackRegions::with_region(0x1010, &stack[..]);
+    assert_eq!(frames(&unwinder, &mut regions), expected);
+
+    // The stack split into two regions.
+    let mut regions = StackRegions::new();
+    regions.add_region(0x1030, &stack[0x20..]);
+    regions.add_region(0x1010, &stack[..0x20]);
+    assert_eq!(frames(&unwinder, &mut regions), expected);
+
+    // A truncated copy stops the unwinding where the copy ends, even if half of the
+    // next word is there.
+    let mut regions = StackRegions::with_region(0x1010, &stack[..0x1c]);
+    assert_eq!(frames(&unwinder, &mut regions), expected[..2]);
+
+    // Other stack memory can be used through a StackMemoryReader.
+    struct Words(Vec<u8>);
+    impl StackMemory for Words {
+        fn bytes(&self, range: Range<u64>) -> Option<&[u8]> {
+            let start = range.start.checked_sub(0x1010)? as usize;
+            let end = range.end.checked_sub(0x1010)? as usize;
+            self.0.get(start..end)
+        }
+    }
+    let mut reader = StackMemoryReader(Words(stack.clone()));
+    assert_eq!(frames(&unwinder, &mut reader), expected);
+}
+
+#[test]
+fn test_stack_regions_x86() {
+    let mut cache = CacheX86::new();
+    let unwinder: UnwinderX86<Vec<u8>> = UnwinderX86::new();
+
+    // The caller's ebp 0 is stored at 0x18, and the return address 0x123456 at 0x1c,
+    // in the last four bytes of the copy.
+    let stack: Vec<u8> = [1u32, 2, 0, 0x123456]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let mut regions = StackRegions::with_region(0x10, stack);
+    let mut regs = UnwindRegsX86::new(0x1000, 0x10, 0x18);
+    let res = unwinder.unwind_frame(
+        FrameAddress::from_return_address(0x1000).unwrap(),
+        &mut regs,
+        &mut cache,
+        &mut regions,
+    );
+    assert_eq!(res, Ok(Some(0x123456)));
+    assert_eq!(regs.sp(), 0x20);
+    assert_eq!(regs.bp(), 0);
+}